@@ -0,0 +1,184 @@
+//! Telling which browser, operating system, and kind of device a user agent string is from, which
+//! is what a web log's visitors are usually broken down by. Each user agent is slightly different
+//! (down to the browser's exact version), so grouping by them directly isn't much use.
+//!
+//! This goes by the tokens that browsers put in their user agents, in an order that sees through
+//! how they all claim to be each other (every Chrome also says it's Safari, and so on). It's not
+//! as thorough as a real user agent parser, but the big names are what matter for a chart.
+
+use merde::{CowStr, Value};
+
+use crate::schema::DataErr;
+
+/// How many of a key's values are looked at to tell if it holds user agents
+const AGENT_SAMPLE: usize = 100;
+
+/// How many of those have to be user agents for the key to be one
+const AGENT_SHARE: f64 = 0.9;
+
+/// Bots and tools that are known by name, by what's in their user agents
+const BOTS: &[(&str, &str)] = &[
+	("Googlebot", "Googlebot"),
+	("bingbot", "Bingbot"),
+	("DuckDuckBot", "DuckDuckBot"),
+	("YandexBot", "YandexBot"),
+	("Baiduspider", "Baiduspider"),
+	("Applebot", "Applebot"),
+	("facebookexternalhit", "Facebook"),
+	("Twitterbot", "Twitterbot"),
+	("GPTBot", "GPTBot"),
+	("curl/", "curl"),
+	("Wget/", "Wget"),
+	("python-requests/", "Python Requests"),
+	("Go-http-client/", "Go"),
+	("okhttp/", "OkHttp")
+];
+
+/// Words that give away other bots, in lowercase
+const BOT_WORDS: [&str; 4] = ["bot", "crawl", "spider", "slurp"];
+
+/// Browsers by their tokens, checked in order since most browsers' user agents name others too
+const BROWSERS: &[(&str, &str)] = &[
+	("Edg", "Edge"),
+	("OPR/", "Opera"),
+	("Opera", "Opera"),
+	("SamsungBrowser/", "Samsung Internet"),
+	("Vivaldi/", "Vivaldi"),
+	("YaBrowser/", "Yandex Browser"),
+	("Firefox/", "Firefox"),
+	("FxiOS/", "Firefox"),
+	("Chromium/", "Chromium"),
+	("CriOS/", "Chrome"),
+	("Chrome/", "Chrome"),
+	("Safari/", "Safari"),
+	("MSIE ", "Internet Explorer"),
+	("Trident/", "Internet Explorer")
+];
+
+/// Operating systems by their tokens, checked in order for the same reason
+const SYSTEMS: &[(&str, &str)] = &[
+	("Windows", "Windows"),
+	("iPhone", "iOS"),
+	("iPad", "iOS"),
+	("iPod", "iOS"),
+	("Android", "Android"),
+	("CrOS", "ChromeOS"),
+	("Mac OS X", "macOS"),
+	("Macintosh", "macOS"),
+	("Linux", "Linux")
+];
+
+/// What's said for a user agent's browser or system when it isn't one of the known ones
+const OTHER: &str = "Other";
+
+/// What a user agent says about where it's from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Agent {
+	pub browser: &'static str,
+	pub os: &'static str,
+	/// Desktop, Mobile, Tablet, or Bot
+	pub device: &'static str
+}
+
+/// What `agent` says about where it's from. Bots are named as their browser.
+pub fn parse(agent: &str) -> Agent {
+	let find = |tokens: &[(&str, &'static str)]| {
+		tokens
+			.iter()
+			.find(|(token, _)| agent.contains(token))
+			.map(|(_, name)| *name)
+	};
+	let os = find(SYSTEMS).unwrap_or(OTHER);
+
+	let lowercase = agent.to_lowercase();
+	let bot = find(BOTS).or_else(|| {
+		BOT_WORDS
+			.iter()
+			.any(|word| lowercase.contains(word))
+			.then_some("Other bot")
+	});
+	if let Some(bot) = bot {
+		return Agent {
+			browser: bot,
+			os,
+			device: "Bot"
+		};
+	}
+
+	// Android tablets leave out the `Mobile` that Android phones have
+	let device = match os {
+		_ if agent.contains("iPad") || agent.contains("Tablet") => "Tablet",
+		"Android" if !agent.contains("Mobile") => "Tablet",
+		_ if agent.contains("Mobi") || agent.contains("iPhone") || agent.contains("iPod") =>
+			"Mobile",
+		_ => "Desktop"
+	};
+	Agent {
+		browser: find(BROWSERS).unwrap_or(OTHER),
+		os,
+		device
+	}
+}
+
+/// If `s` looks like a user agent at all, since anything can be parsed as one
+fn is_agent(s: &str) -> bool {
+	s.starts_with("Mozilla/") || parse(s).device == "Bot"
+}
+
+/// If the string key `key` holds user agents in nearly all of the rows that have it
+pub(crate) fn is_agent_key(data: &[merde::Map], key: &str) -> bool {
+	let key = CowStr::from(key);
+	let (agents, values) = data
+		.iter()
+		.filter_map(|row| match row.get(&key)? {
+			Value::Str(s) => Some(is_agent(s)),
+			_ => None
+		})
+		.take(AGENT_SAMPLE)
+		.fold((0, 0), |(agents, values), agent| {
+			(agents + usize::from(agent), values + 1)
+		});
+	values > 0 && agents as f64 >= values as f64 * AGENT_SHARE
+}
+
+/// The keys that [`add_fields`] adds for `key`: its browser, its operating system, and its kind
+/// of device
+pub fn field_names(key: &str) -> [String; 3] {
+	[
+		format!("{key}_browser"),
+		format!("{key}_os"),
+		format!("{key}_device")
+	]
+}
+
+/// Adds the keys from [`field_names`] to each row, from its user agent in `key`. Rows without one
+/// have null for all of them.
+pub fn add_fields(data: &mut [merde::Map<'static>], key: &str) -> Result<(), DataErr> {
+	let names = field_names(key);
+	if let Some(taken) = names.iter().find(|name| {
+		data.iter()
+			.any(|row| row.contains_key(&name.as_str().into()))
+	}) {
+		return Err(DataErr::KeyExists(taken.clone()));
+	}
+	if !is_agent_key(data, key) {
+		return Err(DataErr::NotAgents(key.to_string()));
+	}
+
+	let [browser_key, os_key, device_key] = names.map(CowStr::from);
+	let key = CowStr::from(key);
+	for row in data {
+		let (browser, os, device) = match row.get(&key) {
+			Some(Value::Str(s)) => {
+				let agent = parse(s);
+				let value = |s: &'static str| Value::Str(CowStr::from(s));
+				(value(agent.browser), value(agent.os), value(agent.device))
+			}
+			_ => (Value::Null, Value::Null, Value::Null)
+		};
+		row.insert(browser_key.clone(), browser);
+		row.insert(os_key.clone(), os);
+		row.insert(device_key.clone(), device);
+	}
+	Ok(())
+}
@@ -0,0 +1,781 @@
+use std::{borrow::Cow, cmp::Ordering, ops::Deref};
+
+use fxhash::{FxHashMap, FxHashSet};
+use merde::{CowStr, IntoStatic, Value};
+use ordered_float::OrderedFloat;
+use rayon::prelude::*;
+
+use crate::{
+	bins, coloring,
+	columns::Columns,
+	diff,
+	index::{Indexes, Matches, intersect},
+	outliers::Fences,
+	settings::{Aggregation, BarOrder, Inclusion, Nans, Settings, TieBreak, ValueBound, YAxisKey}
+};
+
+/// What the bar that small groups are merged into is called (see [`Settings::merge_below`]), and
+/// the segment of stacked bars that the values without one of their own are
+pub const OTHER: &str = "Other";
+
+/// How many of the stack key's values get a segment of their own in stacked bars, which are the
+/// ones that make up the most of the bars overall
+pub const MAX_SEGMENTS: usize = 9;
+
+/// A bar in the chart, along with the formatted value of each x-axis key that it represents
+#[derive(Clone)]
+pub struct LabeledBar {
+	/// The full, multi-line description of what the bar represents
+	pub name: String,
+	/// How tall the bar is
+	pub value: f64,
+	pub labels: Vec<String>,
+	/// How many rows are in the bar (or in either of them, if it's being compared)
+	pub rows: usize,
+	/// How many of the bar's rows have outlying values of any of the keys in
+	/// [`Settings::outliers`]
+	pub outliers: usize,
+	/// The color that its settings' color rules give it, if any of them match it
+	pub color: Option<[u8; 3]>,
+	/// How tall the bar for the same category is among the rows that
+	/// [`Settings::compare`]'s bounds let through, if the bars are being compared
+	pub compared: Option<f64>,
+	/// What the bar's stacked out of, from the bottom up, if the bars are stacked (see
+	/// [`Settings::stack_by`])
+	pub segments: Vec<Segment>,
+	/// The labels filled into the settings' label template, if there is one
+	title: Option<String>
+}
+
+/// The part of a stacked bar that's made up of the rows with one of the values of the key that
+/// it's stacked by
+#[derive(Clone, Debug)]
+pub struct Segment {
+	pub label: String,
+	/// How tall the segment is, out of the whole bar's height
+	pub value: f64,
+	pub rows: usize
+}
+
+impl LabeledBar {
+	pub(crate) fn new(value: f64, labels: Vec<String>, x_axis: &[String]) -> Self {
+		let name = match labels.as_slice() {
+			[label] => label.clone(),
+			labels => labels
+				.iter()
+				.zip(x_axis)
+				.map(|(label, key)| format!("{key}: {label}"))
+				.collect::<Vec<_>>()
+				.join("\n")
+		};
+
+		Self {
+			name,
+			value,
+			labels,
+			rows: 0,
+			outliers: 0,
+			color: None,
+			compared: None,
+			segments: Vec::new(),
+			title: None
+		}
+	}
+
+	/// Names the bar by filling its labels into `template` (see [`Settings::label_template`])
+	pub(crate) fn titled(mut self, template: &str, x_axis: &[String]) -> Self {
+		let title = fill_template(template, &self.labels, x_axis);
+		self.name.clone_from(&title);
+		self.title = Some(title);
+		self
+	}
+
+	/// All the labels on a single line, for places where there's no room for the full name
+	pub fn short_name(&self) -> String {
+		self.title.clone().unwrap_or_else(|| self.labels.join(", "))
+	}
+}
+
+/// Replaces each `{key}` in `template` with that key's label. Anything else in braces is left as
+/// it is, since it's probably a key that isn't being grouped by at the moment.
+fn fill_template(template: &str, labels: &[String], x_axis: &[String]) -> String {
+	let mut out = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		let Some(len) = rest[start..].find('}') else {
+			break;
+		};
+		out.push_str(&rest[..start]);
+		let placeholder = &rest[start..=start + len];
+		let key = &placeholder[1..placeholder.len() - 1];
+		match x_axis.iter().position(|k| k == key) {
+			Some(idx) => out.push_str(&labels[idx]),
+			None => out.push_str(placeholder)
+		}
+		rest = &rest[start + len + 1..];
+	}
+	out.push_str(rest);
+	out
+}
+
+/// Each distinct combination of x-axis values among the rows that were grouped, along with what
+/// went into it
+pub type Groups = Vec<(Vec<Value<'static>>, Tally)>;
+
+/// What's been added up for a single group
+#[derive(Default, Clone, Debug)]
+pub struct Tally {
+	pub rows: usize,
+	/// The total of each row's [`bar_value`]
+	pub sum: f64,
+	/// How many of the rows had a value, which rows without the y-axis key don't
+	pub values: usize,
+	pub min: Option<f64>,
+	pub max: Option<f64>,
+	/// Every one of the values, but only if they're needed to find the median (see
+	/// [`keeps_values`])
+	pub kept: Vec<f64>,
+	/// How many of the rows have outlying values (see [`Settings::outliers`])
+	pub outliers: usize
+}
+
+impl Tally {
+	/// Adds a row, which has `value` if it has a [`bar_value`] at all. It's kept if `keep`.
+	pub fn add(&mut self, value: Option<f64>, keep: bool) {
+		self.rows += 1;
+		let Some(value) = value else {
+			return;
+		};
+		self.sum += value;
+		self.values += 1;
+		self.min = Some(self.min.map_or(value, |min| min.min(value)));
+		self.max = Some(self.max.map_or(value, |max| max.max(value)));
+		if keep {
+			self.kept.push(value);
+		}
+	}
+
+	pub fn merge(&mut self, other: Self) {
+		self.rows += other.rows;
+		self.sum += other.sum;
+		self.values += other.values;
+		self.min = match (self.min, other.min) {
+			(Some(a), Some(b)) => Some(a.min(b)),
+			(a, b) => a.or(b)
+		};
+		self.max = match (self.max, other.max) {
+			(Some(a), Some(b)) => Some(a.max(b)),
+			(a, b) => a.or(b)
+		};
+		self.kept.extend(other.kept);
+		self.outliers += other.outliers;
+	}
+
+	/// How tall the group's bar is. Groups without any values of the y-axis key are 0 tall,
+	/// whatever they're aggregated by.
+	pub fn height(&self, settings: &Settings) -> f64 {
+		match settings.y_axis {
+			YAxisKey::Count => self.sum,
+			YAxisKey::Rate(_) => self.sum / self.rows.max(1) as f64,
+			YAxisKey::Key(_) => match settings.aggregation {
+				Aggregation::Sum => self.sum,
+				Aggregation::Mean => self.sum / self.values.max(1) as f64,
+				Aggregation::Median => median(&self.kept).unwrap_or_default(),
+				Aggregation::Min => self.min.unwrap_or_default(),
+				Aggregation::Max => self.max.unwrap_or_default()
+			}
+		}
+	}
+}
+
+/// If every value has to be kept in each group's tally to make its bar
+pub fn keeps_values(settings: &Settings) -> bool {
+	matches!(settings.y_axis, YAxisKey::Key(_)) && settings.aggregation == Aggregation::Median
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+	let mut values = values.to_vec();
+	values.sort_unstable_by(f64::total_cmp);
+	let middle = values.len() / 2;
+	match values.len() {
+		0 => None,
+		len if len % 2 == 0 => Some((values[middle - 1] + values[middle]) / 2.),
+		_ => Some(values[middle])
+	}
+}
+
+/// The indices of the rows in `data` that aren't excluded by any of the `bounds`, and that the
+/// predicate and brushes (and the bars that have been drilled into) keep if there are any, cut
+/// down to a sample of them if one's being taken
+pub fn filter_rows(
+	data: &[merde::Map<'static>],
+	settings: &Settings,
+	columns: &mut Columns,
+	indexes: &mut Indexes
+) -> Vec<usize> {
+	let rows = filter_by_bounds(data, settings, columns, indexes);
+	let rows = match &settings.predicate {
+		// Running a script is much slower than checking a bound, so it's only done on whatever
+		// the bounds let through
+		Some(predicate) => rows
+			.into_par_iter()
+			.filter(|idx| predicate.keeps(&data[*idx]))
+			.collect(),
+		None => rows
+	};
+	let drilled = settings
+		.drill
+		.as_ref()
+		.and_then(|drill| drill.brush(settings));
+	let brushes = settings.brushes.iter().chain(&drilled).collect::<Vec<_>>();
+	let rows = match brushes.is_empty() {
+		true => rows,
+		false => rows
+			.into_par_iter()
+			.filter(|idx| brushes.iter().all(|brush| brush.keeps(&data[*idx])))
+			.collect()
+	};
+	match &settings.sample {
+		Some(sample) => sample.pick(data, rows, settings),
+		None => rows
+	}
+}
+
+fn filter_by_bounds(
+	data: &[merde::Map<'static>],
+	settings: &Settings,
+	columns: &mut Columns,
+	indexes: &mut Indexes
+) -> Vec<usize> {
+	let mut only: Option<Vec<usize>> = None;
+	let mut except = Vec::new();
+	let mut unindexed = Vec::new();
+	for (key, bound) in &settings.bounds {
+		match indexes.matches(data, key, bound) {
+			Some(Matches::Only(rows)) =>
+				only = Some(match only {
+					Some(only) => intersect(&only, &rows),
+					None => rows
+				}),
+			Some(Matches::Except(rows)) => except.push(rows),
+			None => unindexed.push((key, bound))
+		}
+	}
+
+	// If only some rows could possibly be in bounds, just check those instead of going through
+	// all of them
+	if let Some(only) = only {
+		return only
+			.into_par_iter()
+			.filter(|idx| !except.iter().any(|rows| rows.binary_search(idx).is_ok()))
+			.filter(|idx| !is_excluded(&data[*idx], &unindexed))
+			.collect();
+	}
+
+	let mut keep = vec![true; data.len()];
+	for idx in except.into_iter().flatten() {
+		keep[idx] = false;
+	}
+
+	// Whatever can be checked a column at a time is done next, and then the rest of the bounds
+	// are checked on each row that's left
+	let mut rest = Vec::new();
+	for (key, bound) in unindexed {
+		let checked = columns
+			.get(data, key)
+			.is_some_and(|column| column.retain(bound, &mut keep));
+		if !checked {
+			rest.push((key, bound));
+		}
+	}
+
+	keep.par_iter()
+		.enumerate()
+		.filter(|(idx, keep)| **keep && !is_excluded(&data[*idx], &rest))
+		.map(|(idx, _)| idx)
+		.collect()
+}
+
+/// How many rows are grouped at a time by [`group_rows_progressively`]
+pub const CHUNK_ROWS: usize = 250_000;
+
+/// Groups the given rows of `data` by their x-axis values
+pub fn group_rows(data: &[merde::Map<'static>], rows: &[usize], settings: &Settings) -> Groups {
+	group_rows_progressively(data, rows, settings, |_| ())
+}
+
+/// Groups the rows like [`group_rows`], but [`CHUNK_ROWS`] at a time, showing `partial` the groups
+/// of all the rows so far after each chunk but the last. There's only one chunk unless there are
+/// a lot of rows, which is when waiting on all of them would take a while.
+pub fn group_rows_progressively(
+	data: &[merde::Map<'static>],
+	rows: &[usize],
+	settings: &Settings,
+	mut partial: impl FnMut(&Groups)
+) -> Groups {
+	if settings.x_axis.is_empty() {
+		return Vec::new();
+	}
+
+	// Where the outliers start is decided by all of the data, so that it doesn't move as the
+	// rows are filtered
+	let fences = settings
+		.outliers
+		.iter()
+		.filter_map(|(key, test)| Some((CowStr::from(key.as_str()), test.fences(data, key)?)))
+		.collect::<Vec<_>>();
+
+	let mut totals = FxHashMap::<Vec<Value<'static>>, Tally>::default();
+	let mut chunks = rows.chunks(CHUNK_ROWS).peekable();
+	while let Some(chunk) = chunks.next() {
+		let sums = tally_rows(data, chunk, settings, &fences);
+		let last = chunks.peek().is_none();
+		if last && totals.is_empty() {
+			return sums;
+		}
+
+		for (group, tally) in sums {
+			totals.entry(group).or_default().merge(tally);
+		}
+		if !last {
+			partial(
+				&totals
+					.iter()
+					.map(|(group, tally)| (group.clone(), tally.clone()))
+					.collect()
+			);
+		}
+	}
+	totals.into_iter().collect()
+}
+
+/// Adds up which groups the given rows are in
+fn tally_rows(
+	data: &[merde::Map<'static>],
+	rows: &[usize],
+	settings: &Settings,
+	fences: &[(CowStr, Fences)]
+) -> Groups {
+	// Each thread adds up the groups in its share of the rows, and then those partial sums get
+	// merged together
+	let keep = keeps_values(settings);
+	let sums = rows
+		.par_iter()
+		.fold(FxHashMap::default, |mut sums, idx| {
+			let row = &data[*idx];
+			let value = bar_value(row, settings);
+			let outlying = fences
+				.iter()
+				.any(|(key, fences)| row.get(key).is_some_and(|val| fences.is_outlier(val)));
+			for group in groups_of(row, settings) {
+				let tally = sums.entry(group).or_insert_with(Tally::default);
+				tally.add(value, keep);
+				tally.outliers += usize::from(outlying);
+			}
+			sums
+		})
+		.reduce(FxHashMap::default, |a, b| {
+			// Merge the smaller map into the bigger one so there's less to move around
+			let (mut big, small) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+			for (group, tally) in small {
+				big.entry(group).or_insert_with(Tally::default).merge(tally);
+			}
+			big
+		});
+
+	sums.into_iter()
+		.map(|(group, tally)| {
+			let group = group
+				.into_iter()
+				.map(|val| val.into_owned().into_static())
+				.collect();
+			(group, tally)
+		})
+		.collect()
+}
+
+/// How much `row` adds to the bar it's in. Booleans count as 1 if they're true, and rows without
+/// a number (or boolean) for the y-axis key are still in a bar, they just don't have a value.
+pub fn bar_value(row: &merde::Map, settings: &Settings) -> Option<f64> {
+	match settings.y_axis {
+		YAxisKey::Count => Some(1.),
+		YAxisKey::Key(key) | YAxisKey::Rate(key) => match row.get(&key.into()) {
+			Some(Value::I64(i)) => Some(*i as f64),
+			Some(Value::U64(u)) => Some(*u as f64),
+			Some(Value::Float(f)) => Some(f.into_inner()),
+			Some(Value::Bool(b)) => Some(f64::from(u8::from(*b))),
+			_ => None
+		}
+	}
+}
+
+/// Turns each group into a bar, in the order that the settings ask for. Each bar is drawn at its
+/// index.
+pub fn make_bars(groups: &Groups, settings: &Settings) -> Vec<LabeledBar> {
+	let mut bars = label_groups(groups, settings);
+	sort_bars(&mut bars, settings);
+	bars
+}
+
+/// Turns each of the groups into a bar, in no particular order
+pub(crate) fn label_groups(groups: &Groups, settings: &Settings) -> Vec<LabeledBar> {
+	let stacked = settings
+		.stack_by
+		.as_ref()
+		.map(|key| unstack(groups, key, settings));
+	let groups = stacked.as_ref().map_or(groups, |(groups, _)| groups);
+	let (groups, mut other) = match settings.merge_below {
+		Some(fraction) => merge_small(groups, fraction, settings),
+		None => (groups.iter().collect(), None)
+	};
+
+	if let (Some(other), Some((_, parts))) = (&mut other, &stacked) {
+		let kept = groups
+			.iter()
+			.map(|(group, _)| group)
+			.collect::<FxHashSet<_>>();
+		let mut merged = FxHashMap::<&str, Tally>::default();
+		for (label, tally) in parts
+			.iter()
+			.filter(|(group, _)| !kept.contains(group))
+			.flat_map(|(_, parts)| parts)
+		{
+			merged.entry(label).or_default().merge(tally.clone());
+		}
+		other.segments = segments(
+			merged.iter().map(|(label, tally)| (*label, tally)),
+			other.value
+		);
+	}
+
+	let mut bars = groups
+		.into_par_iter()
+		.map(|(group, tally)| {
+			let labels = group
+				.iter()
+				.zip(&settings.x_axis)
+				.map(|(val, key)| settings.label_for(key, val))
+				.collect();
+			let mut bar = LabeledBar::new(tally.height(settings), labels, &settings.x_axis);
+			bar.rows = tally.rows;
+			bar.outliers = tally.outliers;
+			if let Some((_, parts)) = &stacked {
+				let parts = parts[group]
+					.iter()
+					.map(|(label, tally)| (label.as_str(), tally));
+				bar.segments = segments(parts, bar.value);
+			}
+			match &settings.label_template {
+				Some(template) => bar.titled(template, &settings.x_axis),
+				None => bar
+			}
+		})
+		.collect::<Vec<_>>();
+	bars.extend(other);
+	if stacked.is_some() {
+		rank_segments(&mut bars);
+	}
+	if !settings.color_rules.is_empty() {
+		for bar in &mut bars {
+			bar.color = coloring::color_of(&settings.color_rules, bar);
+		}
+	}
+	bars
+}
+
+/// The groups, and what each value of the stack key added up to in each of them
+type Unstacked = (Groups, FxHashMap<Vec<Value<'static>>, Vec<(String, Tally)>>);
+
+/// Merges together the groups that only differ by their value of `key`, which the bars are
+/// stacked by and so is the last value of each, keeping what each of its values added up to
+fn unstack(groups: &Groups, key: &str, settings: &Settings) -> Unstacked {
+	let mut merged = FxHashMap::<Vec<Value<'static>>, (Tally, Vec<(String, Tally)>)>::default();
+	for (group, tally) in groups {
+		let Some((value, rest)) = group.split_last() else {
+			continue;
+		};
+		let (total, parts) = merged.entry(rest.to_vec()).or_default();
+		total.merge(tally.clone());
+		parts.push((settings.label_for(key, value), tally.clone()));
+	}
+
+	let mut parts = FxHashMap::default();
+	let groups = merged
+		.into_iter()
+		.map(|(group, (total, stacked))| {
+			parts.insert(group.clone(), stacked);
+			(group, total)
+		})
+		.collect();
+	(groups, parts)
+}
+
+/// Splits a bar that's `height` tall into a segment for each of `parts`, by how much of the
+/// bar's total each of them makes up. That works out to exactly each part's own height when the
+/// bars are counts or sums, and to how much each part pulls the bar up otherwise.
+fn segments<'p>(parts: impl Iterator<Item = (&'p str, &'p Tally)>, height: f64) -> Vec<Segment> {
+	let parts = parts.collect::<Vec<_>>();
+	let total = parts.iter().map(|(_, tally)| tally.sum).sum::<f64>();
+	parts
+		.into_iter()
+		.map(|(label, tally)| Segment {
+			label: label.to_string(),
+			value: if total == 0. {
+				0.
+			} else {
+				height * tally.sum / total
+			},
+			rows: tally.rows
+		})
+		.collect()
+}
+
+/// Gives the [`MAX_SEGMENTS`] values that make up the most of `bars` overall a segment of their
+/// own in each of them, merges the rest into an [`OTHER`] segment on top, and puts every bar's
+/// segments in the same order, so that they can be told apart by color
+fn rank_segments(bars: &mut [LabeledBar]) {
+	let rank = legend(bars)
+		.into_iter()
+		.take(MAX_SEGMENTS)
+		.enumerate()
+		.map(|(idx, label)| (label, idx))
+		.collect::<FxHashMap<_, _>>();
+	for bar in bars {
+		let mut other = None::<Segment>;
+		let mut segments = Vec::with_capacity(bar.segments.len().min(MAX_SEGMENTS + 1));
+		for segment in std::mem::take(&mut bar.segments) {
+			if rank.contains_key(&segment.label) {
+				segments.push(segment);
+				continue;
+			}
+			let other = other.get_or_insert_with(|| Segment {
+				label: OTHER.to_string(),
+				value: 0.,
+				rows: 0
+			});
+			other.value += segment.value;
+			other.rows += segment.rows;
+		}
+		segments.sort_unstable_by_key(|segment| rank[&segment.label]);
+		segments.extend(other);
+		bar.segments = segments;
+	}
+}
+
+/// The labels of all of the segments that `bars` are stacked out of, from the one that makes up
+/// the most of them to the one that makes up the least, with [`OTHER`] last
+pub fn legend(bars: &[LabeledBar]) -> Vec<String> {
+	let mut totals = FxHashMap::<&str, f64>::default();
+	for segment in bars.iter().flat_map(|bar| &bar.segments) {
+		*totals.entry(&segment.label).or_default() += segment.value.abs();
+	}
+	let mut labels = totals.into_iter().collect::<Vec<_>>();
+	labels.sort_unstable_by(|(a, a_total), (b, b_total)| {
+		(*a == OTHER)
+			.cmp(&(*b == OTHER))
+			.then_with(|| OrderedFloat(*b_total).cmp(&OrderedFloat(*a_total)))
+			.then_with(|| a.cmp(b))
+	});
+	labels
+		.into_iter()
+		.map(|(label, _)| label.to_string())
+		.collect()
+}
+
+pub(crate) fn sort_bars(bars: &mut [LabeledBar], settings: &Settings) {
+	// The groups come out of the map in whatever order the threads happened to finish in, so ties
+	// are always broken to keep the chart the same from one rebuild to the next
+	bars.par_sort_unstable_by(|a, b| match settings.order {
+		BarOrder::Tallest => OrderedFloat(b.value)
+			.cmp(&OrderedFloat(a.value))
+			.then_with(|| break_tie(a, b, settings.tie_break)),
+		BarOrder::Labels => compare_labels(&a.labels, &b.labels)
+	});
+}
+
+/// Splits off the groups that make up less than `fraction` of the total into a single bar for
+/// all of them, if there's more than one
+fn merge_small<'g>(
+	groups: &'g Groups,
+	fraction: f64,
+	settings: &Settings
+) -> (Vec<&'g (Vec<Value<'static>>, Tally)>, Option<LabeledBar>) {
+	// A rate isn't a share of anything, so what a group contributes is how many rows it has
+	let size = |tally: &Tally| match settings.y_axis {
+		YAxisKey::Rate(_) => tally.rows as f64,
+		YAxisKey::Count | YAxisKey::Key(_) => tally.sum
+	};
+	let total = groups.iter().map(|(_, tally)| size(tally)).sum::<f64>();
+	let (small, kept) = groups
+		.iter()
+		.partition::<Vec<_>, _>(|(_, tally)| size(tally) < total * fraction);
+	if small.len() < 2 {
+		return (groups.iter().collect(), None);
+	}
+
+	let mut merged = Tally::default();
+	for (_, tally) in &small {
+		merged.merge(tally.clone());
+	}
+	let labels = vec![OTHER.to_string(); settings.x_axis.len()];
+	let mut other = LabeledBar::new(merged.height(settings), labels, &settings.x_axis);
+	other.name = format!("{OTHER} ({} groups)", small.len());
+	other.rows = merged.rows;
+	other.outliers = merged.outliers;
+	other.title = Some(OTHER.to_string());
+	(kept, Some(other))
+}
+
+/// Puts the `compared` bars next to the bars of the same category in `bars`. Categories that
+/// only one of them has are 0 in the other, and the pairs are put in order by whichever of the
+/// two is taller.
+pub fn pair(
+	bars: Vec<LabeledBar>,
+	compared: Vec<LabeledBar>,
+	settings: &Settings
+) -> Vec<LabeledBar> {
+	let mut compared = compared
+		.into_iter()
+		.map(|bar| (bar.labels.clone(), bar))
+		.collect::<FxHashMap<_, _>>();
+
+	let mut paired = bars
+		.into_iter()
+		.map(|mut bar| {
+			let other = compared.remove(&bar.labels);
+			bar.rows += other.as_ref().map_or(0, |other| other.rows);
+			bar.compared = Some(other.map_or(0., |other| other.value));
+			bar
+		})
+		.collect::<Vec<_>>();
+	paired.extend(compared.into_values().map(|mut bar| {
+		bar.compared = Some(std::mem::take(&mut bar.value));
+		bar
+	}));
+
+	// When they're charted as how much they changed, the ones that changed the most (either way)
+	// are the most interesting
+	let height = |bar: &LabeledBar| {
+		OrderedFloat(match settings.diff {
+			true => diff::change(bar).abs(),
+			false => bar.value.max(bar.compared.unwrap_or(0.))
+		})
+	};
+	paired.par_sort_unstable_by(|a, b| match settings.order {
+		BarOrder::Tallest => height(b)
+			.cmp(&height(a))
+			.then_with(|| break_tie(a, b, settings.tie_break)),
+		BarOrder::Labels => compare_labels(&a.labels, &b.labels)
+	});
+	paired
+}
+
+/// Orders two bars that are just as tall as each other
+fn break_tie(a: &LabeledBar, b: &LabeledBar, tie_break: TieBreak) -> Ordering {
+	match tie_break {
+		TieBreak::Alphabetical => a.labels.cmp(&b.labels),
+		TieBreak::Natural => compare_labels(&a.labels, &b.labels),
+		TieBreak::Rows => b.rows.cmp(&a.rows).then_with(|| a.labels.cmp(&b.labels))
+	}
+}
+
+/// Orders labels alphabetically, except for numbers, which are ordered by value so that e.g. `9`
+/// comes before `10`
+fn compare_labels(a: &[String], b: &[String]) -> Ordering {
+	a.iter()
+		.zip(b)
+		.map(|(a, b)| match (leading_number(a), leading_number(b)) {
+			(Some(a), Some(b)) => OrderedFloat(a).cmp(&OrderedFloat(b)),
+			_ => a.cmp(b)
+		})
+		.find(|ordering| ordering.is_ne())
+		.unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+/// The number that `label` is, or that it starts at if it names a bin (see [`bins::Bins::label`])
+fn leading_number(label: &str) -> Option<f64> {
+	label
+		.parse()
+		.ok()
+		.or_else(|| label.split_once(bins::SEPARATOR)?.0.parse().ok())
+}
+
+/// The values that decide which bars a row is counted towards (and which of their segments, if
+/// they're stacked). That's just one, unless it lists several things in a key that's split up
+/// (see [`Settings::delimiters`]), in which case it's one for each combination of them.
+pub fn groups_of<'v>(
+	row: &'v merde::Map<'v>,
+	settings: &'v Settings
+) -> Vec<Vec<Cow<'v, Value<'v>>>> {
+	let mut groups = vec![Vec::with_capacity(settings.x_axis.len() + 1)];
+	for key in settings.grouped_keys() {
+		// Rows that don't have the key go in the same bar as the ones where it's null
+		let value = row.get(&key.as_str().into()).unwrap_or(&Value::Null);
+		let values = match (value, settings.delimiters.get(key)) {
+			(Value::Str(s), Some(delimiter)) => split(s, delimiter),
+			_ => vec![settings.grouping_value(key, value)]
+		};
+
+		groups = match values.as_slice() {
+			[value] => {
+				for group in &mut groups {
+					group.push(value.clone());
+				}
+				groups
+			}
+			values => groups
+				.into_iter()
+				.flat_map(|group| {
+					values.iter().map(move |value| {
+						let mut group = group.clone();
+						group.push(value.clone());
+						group
+					})
+				})
+				.collect()
+		};
+	}
+	groups
+}
+
+/// Each different thing listed in `s`, with the whitespace around them trimmed. Rows that don't
+/// list anything are still counted, in the bar for an empty string.
+fn split<'v>(s: &'v str, delimiter: &str) -> Vec<Cow<'v, Value<'v>>> {
+	let mut parts = Vec::new();
+	for part in s.split(delimiter).map(str::trim) {
+		if !part.is_empty() && !parts.contains(&part) {
+			parts.push(part);
+		}
+	}
+	if parts.is_empty() {
+		parts.push("");
+	}
+	parts
+		.into_iter()
+		.map(|part| Cow::Owned(Value::Str(part.into())))
+		.collect()
+}
+
+/// If the row should be left out of the chart because of any of the given bounds
+pub fn is_excluded(row: &merde::Map, bounds: &[(&String, &ValueBound)]) -> bool {
+	bounds
+		.iter()
+		.map(|(key, bound)| (row.get(&key.as_str().into()).unwrap_or(&Value::Null), bound))
+		.any(|(field, bound)| match (field, bound) {
+			(Value::I64(val), ValueBound::I64(bound)) => bound.excludes(val),
+			(Value::U64(val), ValueBound::U64(bound)) => bound.excludes(val),
+			(Value::Float(val), ValueBound::F64(_, nans)) if val.is_nan() => *nans == Nans::Exclude,
+			(Value::Float(val), ValueBound::F64(bound, _)) => bound.excludes(&val.into_inner()),
+			(Value::Bool(val), ValueBound::Bool(bools)) => !bools.allows(Some(*val)),
+			(Value::Null, ValueBound::Bool(bools)) => !bools.allows(None),
+			(Value::Str(val), ValueBound::Str { include, values }) => match include {
+				Inclusion::Include => !values.iter().any(|s| s == val.deref()),
+				Inclusion::Exclude => values.iter().any(|s| s == val.deref())
+			},
+			(Value::Bytes(_), _) => false,
+			// Let's just say that having any other bound at all excludes nulls
+			(Value::Null, _) => true,
+			_ =>
+				unreachable!("The rest of the system should make sure we don't have this situation"),
+		})
+}
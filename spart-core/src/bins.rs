@@ -0,0 +1,199 @@
+//! Bucketing numeric values into bins that are all just as wide, so that a continuous key (like a
+//! price or a duration) can be charted as a histogram instead of with a bar for every distinct
+//! value it has. Rows are grouped by where the bin they're in starts, and the bars are named by
+//! the range of values each covers.
+
+use merde::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::format::NumberFormat;
+
+/// What's put between where a bin starts and where it ends in its name
+pub const SEPARATOR: char = '–';
+
+/// How the values of a key are bucketed
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "Unchecked")]
+pub struct Bins {
+	/// Where the first bin starts. Values below it still go in bins, which just start further
+	/// down.
+	pub start: f64,
+	/// The biggest value that the bins were made to cover, if they were made to cover some
+	/// values, which goes in the last bin instead of starting one of its own
+	pub end: Option<f64>,
+	pub width: f64
+}
+
+/// [`Bins`] that were read in from somewhere (like a shared view), before they've been checked
+#[derive(Deserialize)]
+struct Unchecked {
+	start: f64,
+	end: Option<f64>,
+	width: f64
+}
+
+impl TryFrom<Unchecked> for Bins {
+	type Error = String;
+
+	fn try_from(bins: Unchecked) -> Result<Self, Self::Error> {
+		Self::new(bins.start, bins.end, bins.width).ok_or_else(|| {
+			format!(
+				"bins can't start at {} and be {} wide",
+				bins.start, bins.width
+			)
+		})
+	}
+}
+
+impl Bins {
+	/// Bins that are `width` wide from `start`, unless they wouldn't cover anything (because
+	/// `width` isn't a finite number above 0, or `start` isn't finite)
+	pub fn new(start: f64, end: Option<f64>, width: f64) -> Option<Self> {
+		(start.is_finite() && width.is_finite() && width > 0.).then_some(Self { start, end, width })
+	}
+
+	/// `count` bins from `min` to `max`
+	pub fn covering(min: f64, max: f64, count: usize) -> Option<Self> {
+		let width = (max - min) / count.max(1) as f64;
+		// A key that's the same in every row still needs somewhere to put it
+		Self::new(min, Some(max), if width == 0. { 1. } else { width })
+	}
+
+	/// Bins that are `width` wide, lined up so that they start at multiples of it (so that bins
+	/// of 10 go 0–10, 10–20, and so on, wherever the values start)
+	pub fn aligned(width: f64) -> Option<Self> {
+		Self::new(0., None, width)
+	}
+
+	/// How many bins it takes to cover `min` to `max`
+	pub fn count(&self, min: f64, max: f64) -> usize {
+		let first = self.bin_of(min);
+		let last = self.bin_of(max);
+		((last - first) / self.width).round() as usize + 1
+	}
+
+	/// Where the bin that `value` goes in starts
+	pub fn bin_of(&self, value: f64) -> f64 {
+		let edges = (value - self.start) / self.width;
+		let mut idx = edges.floor();
+		// The biggest value that the bins were made to cover goes in the last of them, which it's
+		// the top of, rather than in one of its own
+		if self.end == Some(value) && (edges - edges.round()).abs() < 1e-9 && edges.round() > 0. {
+			idx = edges.round() - 1.;
+		}
+		tidy(self.start + idx * self.width)
+	}
+
+	/// The value to group `value` by, if it's a number
+	pub fn grouping_value(&self, value: &Value) -> Option<Value<'static>> {
+		let value = match value {
+			Value::I64(i) => *i as f64,
+			Value::U64(u) => *u as f64,
+			Value::Float(f) => f.into_inner(),
+			_ => return None
+		};
+		Some(Value::Float(self.bin_of(value).into()))
+	}
+
+	/// The name of the bin that starts at `start`. Its edges are only shown as precisely as it
+	/// takes to tell them apart from the next bin's, since the bins that cover some values start
+	/// wherever the smallest of them is.
+	pub fn label(&self, start: f64, format: NumberFormat) -> String {
+		let precision = 10f64.powi(self.width.log10().floor() as i32 - 2);
+		let [start, end] =
+			[start, start + self.width].map(|edge| tidy((edge / precision).round() * precision));
+		format!("{}{SEPARATOR}{}", format.format(start), format.format(end))
+	}
+}
+
+/// Rounds away the error that adding up widths leaves behind (so that a bin starts at 0.3, not
+/// 0.30000000000000004)
+fn tidy(value: f64) -> f64 {
+	if value == 0. || !value.is_finite() {
+		return value;
+	}
+	let digits = 12 - value.abs().log10().ceil() as i32;
+	let scale = 10f64.powi(digits.clamp(-300, 300));
+	(value * scale).round() / scale
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn edges_of_covering_bins() {
+		let bins = Bins::covering(0., 10., 5).unwrap();
+		assert_eq!(bins.width, 2.);
+		assert_eq!(bins.bin_of(0.), 0.);
+		assert_eq!(bins.bin_of(1.99), 0.);
+		// Edges start the bin above them
+		assert_eq!(bins.bin_of(2.), 2.);
+		// Except for the biggest value, which is the top of the last bin
+		assert_eq!(bins.bin_of(10.), 8.);
+		assert_eq!(bins.count(0., 10.), 5);
+	}
+
+	#[test]
+	fn uneven_widths() {
+		// 0.3 / 3 isn't exactly 0.1, and the bins shouldn't start at 0.30000000000000004
+		let bins = Bins::covering(0., 0.3, 3).unwrap();
+		assert_eq!(bins.bin_of(0.25), 0.2);
+		assert_eq!(bins.bin_of(0.3), 0.2);
+		assert_eq!(bins.count(0., 0.3), 3);
+	}
+
+	#[test]
+	fn single_value() {
+		let bins = Bins::covering(4., 4., 10).unwrap();
+		assert_eq!(bins.width, 1.);
+		assert_eq!(bins.bin_of(4.), 4.);
+		assert_eq!(bins.count(4., 4.), 1);
+	}
+
+	#[test]
+	fn unusable_widths() {
+		for width in [0., -5., f64::INFINITY, f64::NAN] {
+			assert_eq!(Bins::aligned(width), None);
+		}
+		assert_eq!(Bins::covering(0., f64::INFINITY, 10), None);
+		// Including ones that come from a shared view
+		let bins = r#"{"start": 0, "end": null, "width": -1}"#;
+		assert!(serde_json::from_str::<Bins>(bins).is_err());
+	}
+
+	#[test]
+	fn aligned_bins() {
+		let bins = Bins::aligned(10.).unwrap();
+		assert_eq!(bins.bin_of(0.), 0.);
+		assert_eq!(bins.bin_of(10.), 10.);
+		assert_eq!(bins.bin_of(-0.5), -10.);
+		assert_eq!(bins.count(3., 27.), 3);
+	}
+
+	#[test]
+	fn grouping_values() {
+		let bins = Bins::aligned(5.).unwrap();
+		assert_eq!(
+			bins.grouping_value(&Value::I64(7)),
+			Some(Value::Float(5.0.into()))
+		);
+		assert_eq!(
+			bins.grouping_value(&Value::U64(15)),
+			Some(Value::Float(15.0.into()))
+		);
+		assert_eq!(bins.grouping_value(&Value::Str("7".into())), None);
+		assert_eq!(bins.grouping_value(&Value::Null), None);
+	}
+
+	#[test]
+	fn labels() {
+		let bins = Bins::aligned(5.).unwrap();
+		assert_eq!(bins.label(5., NumberFormat::Plain), "5–10");
+		let bins = Bins::covering(0.123456, 1.123456, 4).unwrap();
+		assert_eq!(
+			bins.label(bins.bin_of(0.5), NumberFormat::Plain),
+			"0.373–0.623"
+		);
+	}
+}
@@ -0,0 +1,76 @@
+//! Linking charts of the same data together, so that picking a bar in one of them narrows the
+//! others down to just that bar's rows. A [`Brush`] remembers how the chart it came from groups its
+//! rows, so it keeps exactly the rows that went into the bar, even when they were grouped by a date
+//! format or split up into lists.
+
+use crate::{
+	bars::{LabeledBar, groups_of},
+	settings::Settings
+};
+
+/// A bar that's been picked in another chart
+#[derive(Clone)]
+pub struct Brush {
+	labels: Vec<String>,
+	/// Just what of the other chart's settings decides which bar a row is in and what it's
+	/// labelled
+	settings: Settings<'static>
+}
+
+impl Brush {
+	/// Keeps the rows that are in `bar`, which is one of the bars that `settings` made
+	pub fn new(bar: &LabeledBar, settings: &Settings) -> Self {
+		Self::of(&settings.x_axis, bar.labels.clone(), settings)
+	}
+
+	/// Keeps the rows whose values of `keys` would be labelled `labels` by `settings`
+	pub(crate) fn of(keys: &[String], labels: Vec<String>, settings: &Settings) -> Self {
+		Self {
+			labels,
+			settings: Settings {
+				x_axis: keys.to_vec(),
+				date_formats: settings.date_formats.clone(),
+				bins: settings.bins.clone(),
+				delimiters: settings.delimiters.clone(),
+				formats: settings.formats.clone(),
+				byte_encodings: settings.byte_encodings.clone(),
+				..Settings::default()
+			}
+		}
+	}
+
+	/// If `row` is one of the rows in the bar
+	pub fn keeps(&self, row: &merde::Map) -> bool {
+		groups_of(row, &self.settings).iter().any(|group| {
+			group
+				.iter()
+				.zip(&self.settings.x_axis)
+				.zip(&self.labels)
+				.all(|((value, key), label)| self.settings.label_for(key, value) == *label)
+		})
+	}
+
+	/// What the bar's called, for saying what the rows have been narrowed down to
+	pub fn describe(&self) -> String {
+		self.settings
+			.x_axis
+			.iter()
+			.zip(&self.labels)
+			.map(|(key, label)| format!("{key} = {label}"))
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+}
+
+impl PartialEq for Brush {
+	fn eq(&self, other: &Self) -> bool {
+		let (a, b) = (&self.settings, &other.settings);
+		self.labels == other.labels
+			&& a.x_axis == b.x_axis
+			&& a.date_formats == b.date_formats
+			&& a.bins == b.bins
+			&& a.delimiters == b.delimiters
+			&& a.formats == b.formats
+			&& a.byte_encodings == b.byte_encodings
+	}
+}
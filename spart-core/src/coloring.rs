@@ -0,0 +1,154 @@
+//! Rules that color some of the bars differently, to draw attention to the ones that matter. A
+//! rule looks like `condition -> color` (or with `→`), where the condition is one of:
+//!
+//! - `value > N` (or `>=`, `<`, `<=`), comparing the bar's height against a number
+//! - `name contains TEXT`, for bars whose name has `TEXT` in it (ignoring case), which can be
+//!   quoted like `'error'`
+//!
+//! and the color is either one of [`NAMED_COLORS`] or a hex code like `#ff8800`. The first rule
+//! that a bar matches decides its color.
+
+use serde::{Deserialize, Serialize};
+
+use crate::bars::LabeledBar;
+
+/// The colors that can be given by name, which are the same ones the rest of the charts use
+pub const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+	("red", [225, 87, 89]),
+	("orange", [242, 142, 43]),
+	("yellow", [237, 201, 72]),
+	("green", [89, 161, 79]),
+	("teal", [118, 183, 178]),
+	("blue", [78, 121, 167]),
+	("purple", [176, 122, 161]),
+	("pink", [255, 157, 167]),
+	("brown", [156, 117, 95]),
+	("gray", [186, 176, 172])
+];
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ColorRuleErr {
+	#[error("Rules need an arrow (-> or →) between the condition and the color")]
+	NoArrow,
+	#[error(
+		"'{0}' isn't a condition (expected something like 'value > 100' or 'name contains error')"
+	)]
+	InvalidCondition(String),
+	#[error("'{0}' isn't a color (expected a name like 'red' or a hex code like '#ff8800')")]
+	InvalidColor(String)
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ColorRule {
+	pub condition: Condition,
+	pub color: [u8; 3]
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+	Above(f64),
+	AtLeast(f64),
+	Below(f64),
+	AtMost(f64),
+	/// Always lowercase, since it's matched without caring about case
+	NameContains(String)
+}
+
+impl Condition {
+	pub fn matches(&self, bar: &LabeledBar) -> bool {
+		match self {
+			Self::Above(n) => bar.value > *n,
+			Self::AtLeast(n) => bar.value >= *n,
+			Self::Below(n) => bar.value < *n,
+			Self::AtMost(n) => bar.value <= *n,
+			Self::NameContains(text) => bar.name.to_lowercase().contains(text)
+		}
+	}
+}
+
+impl std::fmt::Display for ColorRule {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.condition {
+			Condition::Above(n) => write!(f, "value > {n}")?,
+			Condition::AtLeast(n) => write!(f, "value >= {n}")?,
+			Condition::Below(n) => write!(f, "value < {n}")?,
+			Condition::AtMost(n) => write!(f, "value <= {n}")?,
+			Condition::NameContains(text) => write!(f, "name contains '{text}'")?
+		}
+		let [r, g, b] = self.color;
+		match NAMED_COLORS.iter().find(|(_, color)| *color == self.color) {
+			Some((name, _)) => write!(f, " -> {name}"),
+			None => write!(f, " -> #{r:02x}{g:02x}{b:02x}")
+		}
+	}
+}
+
+impl std::str::FromStr for ColorRule {
+	type Err = ColorRuleErr;
+
+	fn from_str(rule: &str) -> Result<Self, Self::Err> {
+		let (condition, color) = rule
+			.split_once("->")
+			.or_else(|| rule.split_once('→'))
+			.ok_or(ColorRuleErr::NoArrow)?;
+		Ok(Self {
+			condition: parse_condition(condition.trim())?,
+			color: parse_color(color.trim())?
+		})
+	}
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, ColorRuleErr> {
+	let invalid = || ColorRuleErr::InvalidCondition(condition.to_string());
+
+	if let Some(text) = condition.strip_prefix("name contains") {
+		let text = text.trim();
+		let text = ["'", "\""]
+			.iter()
+			.find_map(|quote| text.strip_prefix(quote)?.strip_suffix(quote))
+			.unwrap_or(text);
+		if text.is_empty() {
+			return Err(invalid());
+		}
+		return Ok(Condition::NameContains(text.to_lowercase()));
+	}
+
+	let comparison = condition.strip_prefix("value").ok_or_else(invalid)?.trim();
+	// Longer operators come first so that `>=` isn't mistaken for `>` followed by `=N`
+	let ops = [
+		(">=", Condition::AtLeast as fn(f64) -> Condition),
+		("<=", Condition::AtMost),
+		(">", Condition::Above),
+		("<", Condition::Below)
+	];
+	ops.iter()
+		.find_map(|(op, make)| Some(make(comparison.strip_prefix(op)?.trim().parse().ok()?)))
+		.ok_or_else(invalid)
+}
+
+fn parse_color(color: &str) -> Result<[u8; 3], ColorRuleErr> {
+	let invalid = || ColorRuleErr::InvalidColor(color.to_string());
+
+	if let Some((_, rgb)) = NAMED_COLORS
+		.iter()
+		.find(|(name, _)| name.eq_ignore_ascii_case(color))
+	{
+		return Ok(*rgb);
+	}
+
+	let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+	if hex.len() != 6 || !hex.is_ascii() {
+		return Err(invalid());
+	}
+	let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| invalid());
+	Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// The color that the first of `rules` that `bar` matches gives it, if it matches any
+pub fn color_of(rules: &[ColorRule], bar: &LabeledBar) -> Option<[u8; 3]> {
+	rules
+		.iter()
+		.find(|rule| rule.condition.matches(bar))
+		.map(|rule| rule.color)
+}
@@ -0,0 +1,160 @@
+//! The values of a numeric key can be pulled out of the rows into a plain array, which can then be
+//! filtered many values at a time instead of digging through each row's map. They're only pulled
+//! out once a key is actually bounded, since most keys never will be.
+
+use fxhash::FxHashMap;
+use merde::{Value, ValueType};
+use ordered_float::OrderedFloat;
+use rayon::prelude::*;
+
+use crate::settings::{Bound, Inclusion, Nans, ValueBound};
+
+/// How many values are checked at once. Keeping this fixed lets the compiler turn each chunk into
+/// a handful of SIMD instructions.
+const LANES: usize = 16;
+/// How many values each thread checks in one go
+const CHUNK: usize = LANES * 4096;
+
+/// Columns that have been pulled out of some data so far
+#[derive(Default)]
+pub struct Columns(FxHashMap<String, Option<NumericColumn>>);
+
+impl Columns {
+	/// The column for `key`, pulling it out of `data` if it hasn't been already. Returns `None`
+	/// if `key` can't be made into a column.
+	pub fn get(&mut self, data: &[merde::Map], key: &str) -> Option<&NumericColumn> {
+		self.0
+			.entry(key.to_string())
+			.or_insert_with(|| NumericColumn::extract(data, key))
+			.as_ref()
+	}
+}
+
+pub struct NumericColumn {
+	values: Values,
+	/// Which rows have a null for this key. The value in `values` for those rows is meaningless.
+	nulls: Vec<bool>
+}
+
+enum Values {
+	I64(Vec<i64>),
+	U64(Vec<u64>),
+	F64(Vec<f64>)
+}
+
+impl NumericColumn {
+	/// Returns `None` if `key` isn't numeric. Rows that don't have it are treated as if it were
+	/// null there.
+	fn extract(data: &[merde::Map], key: &str) -> Option<Self> {
+		let key = key.into();
+		let ty = data
+			.iter()
+			.filter_map(|row| row.get(&key))
+			.map(Value::value_type)
+			.find(|ty| *ty != ValueType::Null)?;
+
+		let mut nulls = Vec::with_capacity(data.len());
+		macro_rules! extract {
+			($variant:ident => $column:ident, $get:expr) => {{
+				let mut values = Vec::with_capacity(data.len());
+				for row in data {
+					match row.get(&key).unwrap_or(&Value::Null) {
+						Value::$variant(val) => {
+							values.push($get(val));
+							nulls.push(false);
+						}
+						Value::Null => {
+							values.push(Default::default());
+							nulls.push(true);
+						}
+						_ => return None
+					}
+				}
+				Values::$column(values)
+			}};
+		}
+
+		let values = match ty {
+			ValueType::I64 => extract!(I64 => I64, |val: &i64| *val),
+			ValueType::U64 => extract!(U64 => U64, |val: &u64| *val),
+			ValueType::Float => extract!(Float => F64, |val: &OrderedFloat<f64>| val.0),
+			_ => return None
+		};
+
+		Some(Self { values, nulls })
+	}
+
+	/// Clears `keep` for each row whose value is excluded by `bound`. Returns false (without
+	/// touching `keep`) if `bound` isn't something that can be checked against this column.
+	pub fn retain(&self, bound: &ValueBound, keep: &mut [bool]) -> bool {
+		match (&self.values, bound) {
+			(Values::I64(values), ValueBound::I64(bound)) =>
+				retain_within(values, bound, keep, |_| false, false),
+			(Values::U64(values), ValueBound::U64(bound)) =>
+				retain_within(values, bound, keep, |_| false, false),
+			(Values::F64(values), ValueBound::F64(bound, nans)) =>
+				retain_within(values, bound, keep, f64::is_nan, *nans == Nans::Include),
+			_ => return false
+		}
+
+		// Having any bound at all excludes nulls, same as when checking rows one by one
+		retain_where(&self.nulls, keep, |null| !null);
+		true
+	}
+}
+
+/// Clears `keep` for each value outside of `bound`. Values that `is_nan` are kept only if
+/// `keep_nans` is set, whatever the bound is.
+fn retain_within<T>(
+	values: &[T],
+	bound: &Bound<T>,
+	keep: &mut [bool],
+	is_nan: impl Fn(T) -> bool + Sync,
+	keep_nans: bool
+) where
+	T: Copy + PartialOrd + Send + Sync
+{
+	match bound {
+		// `&` instead of `&&` so there's no branch to get in the way of vectorizing
+		Bound::Range(range) => retain_where(values, keep, |val| {
+			let nan = is_nan(val);
+			(nan & keep_nans) | (!nan & (range.start <= val) & (val < range.end))
+		}),
+		Bound::Specifics {
+			include,
+			values: specifics
+		} => {
+			let include = *include == Inclusion::Include;
+			retain_where(values, keep, |val| {
+				let nan = is_nan(val);
+				let listed = specifics.iter().fold(false, |found, s| found | (*s == val));
+				(nan & keep_nans) | (!nan & (listed == include))
+			});
+		}
+	}
+}
+
+/// Clears `keep[i]` wherever `pred(values[i])` is false
+fn retain_where<T: Copy + Send + Sync>(
+	values: &[T],
+	keep: &mut [bool],
+	pred: impl Fn(T) -> bool + Sync
+) {
+	values
+		.par_chunks(CHUNK)
+		.zip(keep.par_chunks_mut(CHUNK))
+		.for_each(|(values, keep)| {
+			let mut values = values.chunks_exact(LANES);
+			let mut keeps = keep.chunks_exact_mut(LANES);
+
+			for (values, keep) in (&mut values).zip(&mut keeps) {
+				for (keep, val) in keep.iter_mut().zip(values) {
+					*keep &= pred(*val);
+				}
+			}
+
+			for (keep, val) in keeps.into_remainder().iter_mut().zip(values.remainder()) {
+				*keep &= pred(*val);
+			}
+		});
+}
@@ -0,0 +1,117 @@
+//! Album covers from the [Cover Art Archive](https://coverartarchive.org), which are found by
+//! looking the album up on MusicBrainz first. Covers are kept on disk (and so are the albums that
+//! don't have one), so each album's only ever looked up once.
+
+use std::{cell::Cell, io::Read, path::PathBuf, thread, time::Instant};
+
+use ureq::Agent;
+
+use crate::{
+	enrich::{self, EnrichErr, Lookup},
+	musicbrainz::MusicBrainz
+};
+
+const API_URL: &str = "https://coverartarchive.org/release-group";
+
+const NAME: &str = "the cover art archive";
+
+/// How big a cover can be before it's not worth reading the rest of it, since the thumbnails that
+/// are asked for are only meant to be a few dozen KB
+const MAX_COVER_LEN: u64 = 4 * 1024 * 1024;
+
+pub struct CoverArt {
+	musicbrainz: MusicBrainz,
+	agent: Agent,
+	dir: Option<PathBuf>,
+	last_search: Cell<Option<Instant>>
+}
+
+impl CoverArt {
+	pub fn new() -> Self {
+		Self {
+			musicbrainz: MusicBrainz::new(),
+			agent: enrich::agent(),
+			dir: enrich::cache_dir().map(|dir| dir.join("covers")),
+			last_search: Cell::new(None)
+		}
+	}
+
+	/// The front cover of `album` by `artist` (usually a JPEG, 250 pixels across), or `None` if
+	/// there isn't one that can be found
+	pub fn front(&self, artist: &str, album: &str) -> Result<Option<Vec<u8>>, EnrichErr> {
+		let path = self
+			.dir
+			.as_ref()
+			.map(|dir| dir.join(format!("{:016x}", fxhash::hash64(&(artist, album)))));
+		if let Some(cached) = path.as_ref().and_then(|path| std::fs::read(path).ok()) {
+			// An empty file means that we've already found out there's no cover
+			return Ok((!cached.is_empty()).then_some(cached));
+		}
+
+		// Only MusicBrainz is rate-limited, the archive itself doesn't mind
+		if let Some(wait) = self
+			.last_search
+			.get()
+			.and_then(|last| MusicBrainz::INTERVAL.checked_sub(last.elapsed()))
+		{
+			thread::sleep(wait);
+		}
+		self.last_search.set(Some(Instant::now()));
+
+		let cover = match self.musicbrainz.release_group(artist, album)? {
+			Some(mbid) => self.download(&mbid)?,
+			None => None
+		};
+
+		if let Some(path) = path {
+			let err = |source| EnrichErr::Cache {
+				path: path.clone(),
+				source
+			};
+			if let Some(dir) = path.parent() {
+				std::fs::create_dir_all(dir).map_err(err)?;
+			}
+			std::fs::write(&path, cover.as_deref().unwrap_or_default()).map_err(err)?;
+		}
+		Ok(cover)
+	}
+
+	fn download(&self, mbid: &str) -> Result<Option<Vec<u8>>, EnrichErr> {
+		let request_err = |message: String| EnrichErr::Request {
+			service: NAME,
+			message
+		};
+		let mut response = self
+			.agent
+			.get(format!("{API_URL}/{mbid}/front-250"))
+			.call()
+			.map_err(|e| request_err(e.to_string()))?;
+
+		let status = response.status();
+		// Plenty of albums just don't have a cover uploaded
+		if status == 404 {
+			return Ok(None);
+		}
+		if !status.is_success() {
+			return Err(EnrichErr::Service {
+				service: NAME,
+				message: status.to_string()
+			});
+		}
+
+		let mut cover = Vec::new();
+		response
+			.body_mut()
+			.as_reader()
+			.take(MAX_COVER_LEN)
+			.read_to_end(&mut cover)
+			.map_err(|e| request_err(e.to_string()))?;
+		Ok(Some(cover))
+	}
+}
+
+impl Default for CoverArt {
+	fn default() -> Self {
+		Self::new()
+	}
+}
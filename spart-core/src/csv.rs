@@ -0,0 +1,251 @@
+//! Spreadsheet exports and the like, where the first line names the keys and each line after it
+//! is a row. CSV doesn't say what type anything is, so each column's type is worked out from all of
+//! its values: it's made of integers if they all are, and failing that floats or booleans, and
+//! strings otherwise. Empty fields are null.
+
+use merde::{CowStr, Map, Value};
+use serde::Deserialize;
+
+use crate::load::{DataSource, FormatOptions, Progress};
+
+/// How many records are read between each time progress is reported
+const PROGRESS_RECORDS: usize = 10_000;
+
+pub struct Csv;
+
+/// How a CSV file is laid out, for the ones that aren't laid out the usual way
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+	/// What's between the fields on each line, like `;` or a tab instead of a comma
+	pub delimiter: char,
+	/// Whether the first line names the keys. If it doesn't, they're named by where they are,
+	/// from `column1` on.
+	pub header: bool
+}
+
+impl Default for CsvOptions {
+	fn default() -> Self {
+		Self {
+			delimiter: ',',
+			header: true
+		}
+	}
+}
+
+impl DataSource for Csv {
+	fn name(&self) -> &'static str {
+		"csv"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["csv"]
+	}
+
+	// JSON always starts with a bracket or brace, and nearly any header line has a comma in it
+	fn sniff(&self, start: &str) -> bool {
+		let first_line = start.trim_start().lines().next().unwrap_or_default();
+		!first_line.starts_with(['[', '{']) && first_line.contains(',')
+	}
+
+	fn parse<'s>(
+		&self,
+		contents: &'s str,
+		options: &FormatOptions,
+		progress: &mut dyn FnMut(Progress)
+	) -> Result<Vec<Map<'s>>, String> {
+		let CsvOptions { delimiter, header } = options.csv;
+		let delimiter = u8::try_from(delimiter)
+			.ok()
+			.filter(u8::is_ascii)
+			.ok_or_else(|| {
+				format!("The delimiter has to be an ASCII character, not '{delimiter}'")
+			})?;
+		let mut reader = ::csv::ReaderBuilder::new()
+			.delimiter(delimiter)
+			.has_headers(header)
+			.flexible(true)
+			.trim(::csv::Trim::All)
+			.from_reader(contents.as_bytes());
+		let mut headers = match header {
+			true => reader
+				.headers()
+				.map_err(|e| e.to_string())?
+				.iter()
+				.map(str::to_string)
+				.collect(),
+			false => Vec::new()
+		};
+
+		let mut records = Vec::new();
+		for record in reader.records() {
+			records.push(record.map_err(|e| e.to_string())?);
+			if records.len() % PROGRESS_RECORDS == 0 {
+				progress(Progress {
+					bytes_done: records
+						.last()
+						.and_then(|record| record.position())
+						.map_or(0, |position| position.byte() as usize),
+					bytes_total: contents.len()
+				});
+			}
+		}
+
+		if !header {
+			let columns = records.iter().map(|record| record.len()).max().unwrap_or(0);
+			headers = (1..=columns)
+				.map(|column| format!("column{column}"))
+				.collect();
+		}
+
+		let types = (0..headers.len())
+			.map(|column| {
+				ColumnType::of(
+					records
+						.iter()
+						.filter_map(|record| record.get(column))
+						.filter(|field| !field.is_empty())
+				)
+			})
+			.collect::<Vec<_>>();
+
+		Ok(records
+			.iter()
+			.map(|record| {
+				let mut row = Map::new();
+				for (column, (key, ty)) in headers.iter().zip(&types).enumerate() {
+					let value = record
+						.get(column)
+						.filter(|field| !field.is_empty())
+						.map_or(Value::Null, |field| ty.value(field));
+					row.insert(CowStr::from(key.clone()), value);
+				}
+				row
+			})
+			.collect())
+	}
+}
+
+/// What every value in a column can be read as
+#[derive(Clone, Copy)]
+enum ColumnType {
+	Integer,
+	Float,
+	Bool,
+	String
+}
+
+impl ColumnType {
+	/// The narrowest type that all of `fields` fit
+	fn of<'f>(fields: impl Iterator<Item = &'f str>) -> Self {
+		let (mut integers, mut floats, mut bools) = (true, true, true);
+		for field in fields {
+			integers &= field.parse::<i64>().is_ok();
+			floats &= field.parse::<f64>().is_ok();
+			bools &= parse_bool(field).is_some();
+			if !floats && !bools {
+				return Self::String;
+			}
+		}
+
+		match (integers, floats, bools) {
+			(true, ..) => Self::Integer,
+			(_, true, _) => Self::Float,
+			(_, _, true) => Self::Bool,
+			_ => Self::String
+		}
+	}
+
+	/// `field` as this type. It's only ever given fields that [`Self::of`] decided were this
+	/// type, so they always parse.
+	fn value(self, field: &str) -> Value<'static> {
+		match self {
+			Self::Integer => field.parse::<i64>().map_or(Value::Null, Value::I64),
+			Self::Float => field
+				.parse::<f64>()
+				.map_or(Value::Null, |n| Value::Float(n.into())),
+			Self::Bool => parse_bool(field).map_or(Value::Null, Value::Bool),
+			Self::String => Value::Str(CowStr::from(field.to_string()))
+		}
+	}
+}
+
+fn parse_bool(field: &str) -> Option<bool> {
+	match field.to_ascii_lowercase().as_str() {
+		"true" => Some(true),
+		"false" => Some(false),
+		_ => None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(contents: &str) -> Vec<Map<'_>> {
+		Csv.parse(contents, &FormatOptions::default(), &mut |_| ())
+			.unwrap()
+	}
+
+	fn get<'m>(row: &'m Map, key: &str) -> &'m Value<'m> {
+		row.get(&CowStr::from(key.to_string())).unwrap()
+	}
+
+	#[test]
+	fn quoted_fields() {
+		let rows = parse("name,notes\n\"Smith, Jo\",\"said \"\"hi\"\"\"\nplain,\"two\nlines\"\n");
+		assert_eq!(rows.len(), 2);
+		assert_eq!(get(&rows[0], "name"), &Value::Str("Smith, Jo".into()));
+		assert_eq!(get(&rows[0], "notes"), &Value::Str("said \"hi\"".into()));
+		assert_eq!(get(&rows[1], "notes"), &Value::Str("two\nlines".into()));
+	}
+
+	#[test]
+	fn column_types() {
+		let rows = parse("n,f,b,s\n1,1.5,true,1\n-2,3,FALSE,x\n,,,\n");
+		assert_eq!(get(&rows[0], "n"), &Value::I64(1));
+		assert_eq!(get(&rows[1], "n"), &Value::I64(-2));
+		// A column is only integers if all of it is
+		assert_eq!(get(&rows[1], "f"), &Value::Float(3.0.into()));
+		assert_eq!(get(&rows[1], "b"), &Value::Bool(false));
+		assert_eq!(get(&rows[0], "s"), &Value::Str("1".into()));
+		// Empty fields are null, and don't decide what type the column is
+		assert!(
+			["n", "f", "b", "s"]
+				.iter()
+				.all(|key| get(&rows[2], key) == &Value::Null)
+		);
+	}
+
+	#[test]
+	fn other_layouts() {
+		let options = FormatOptions {
+			csv: CsvOptions {
+				delimiter: ';',
+				header: false
+			}
+		};
+		let rows = Csv
+			.parse("a;\"b;c\"\n1;2;3\n", &options, &mut |_| ())
+			.unwrap();
+		assert_eq!(rows.len(), 2);
+		assert_eq!(get(&rows[0], "column2"), &Value::Str("b;c".into()));
+		assert_eq!(get(&rows[0], "column3"), &Value::Null);
+		assert_eq!(get(&rows[1], "column3"), &Value::I64(3));
+
+		let options = FormatOptions {
+			csv: CsvOptions {
+				delimiter: '→',
+				header: true
+			}
+		};
+		assert!(Csv.parse("a,b\n", &options, &mut |_| ()).is_err());
+	}
+
+	#[test]
+	fn short_rows() {
+		let rows = parse("a,b\n1\n");
+		assert_eq!(get(&rows[0], "a"), &Value::I64(1));
+		assert_eq!(get(&rows[0], "b"), &Value::Null);
+	}
+}
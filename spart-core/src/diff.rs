@@ -0,0 +1,62 @@
+//! Comparing two datasets (like the same export from one year and the next). They're loaded
+//! together, with each row marked with which one it came from, so that the bars for one can be
+//! compared against the other's like any other two sets of bounds, and then charted as how much
+//! each bar changed.
+
+use merde::{Map, Value};
+
+use crate::{
+	bars::LabeledBar,
+	format::NumberFormat,
+	settings::{Inclusion, Settings, ValueBound}
+};
+
+/// The key that says which dataset a row came from
+pub const DATASET: &str = "dataset";
+pub const BEFORE: &str = "before";
+pub const AFTER: &str = "after";
+
+/// Marks every row as having come from `dataset`
+pub fn mark(rows: &mut [Map<'static>], dataset: &'static str) {
+	for row in rows {
+		row.insert(DATASET.into(), Value::Str(dataset.into()));
+	}
+}
+
+/// Charts how each bar changed from the rows marked [`BEFORE`] to the ones marked [`AFTER`]
+pub fn apply(settings: &mut Settings) {
+	let only = |dataset: &str| ValueBound::Str {
+		include: Inclusion::Include,
+		values: vec![dataset.to_string()]
+	};
+
+	settings.bounds.insert(DATASET.to_string(), only(BEFORE));
+	let mut compare = settings
+		.compare
+		.take()
+		.unwrap_or_else(|| settings.bounds.clone());
+	compare.insert(DATASET.to_string(), only(AFTER));
+	settings.compare = Some(compare);
+	settings.diff = true;
+}
+
+/// How much a bar changed from itself to what it's compared against
+pub fn change(bar: &LabeledBar) -> f64 {
+	bar.compared.unwrap_or(0.) - bar.value
+}
+
+/// How much a bar changed relative to what it was, or if it's only in one of them
+pub fn describe(bar: &LabeledBar) -> String {
+	match (bar.value, bar.compared.unwrap_or(0.)) {
+		(0., 0.) => "unchanged".to_string(),
+		(0., _) => "new".to_string(),
+		(_, 0.) => "gone".to_string(),
+		(before, after) => {
+			let sign = if after >= before { "+" } else { "" };
+			format!(
+				"{sign}{}",
+				NumberFormat::Percent.format((after - before) / before)
+			)
+		}
+	}
+}
@@ -0,0 +1,34 @@
+//! Line and point charts can easily end up with far more points than there are pixels to draw
+//! them in, which is a lot of work for nothing. Cutting them down to just the lowest and highest
+//! point within each pixel's worth looks the same, since those are the only ones that would've
+//! stuck out anyways.
+
+/// Cuts `points` (which must be in order of x) down to at most two points per bucket, the lowest
+/// and highest in it, keeping them in order
+pub fn min_max(points: &[[f64; 2]], buckets: usize) -> Vec<[f64; 2]> {
+	if buckets == 0 || points.len() <= buckets * 2 {
+		return points.to_vec();
+	}
+
+	let per_bucket = points.len().div_ceil(buckets);
+	let mut kept = Vec::with_capacity(buckets * 2);
+
+	for bucket in points.chunks(per_bucket) {
+		let by_y = |a: &(usize, &[f64; 2]), b: &(usize, &[f64; 2])| a.1[1].total_cmp(&b.1[1]);
+		// Chunks are never empty, so these always find something
+		let (Some((lowest, _)), Some((highest, _))) = (
+			bucket.iter().enumerate().min_by(by_y),
+			bucket.iter().enumerate().max_by(by_y)
+		) else {
+			continue;
+		};
+
+		let (first, second) = (lowest.min(highest), lowest.max(highest));
+		kept.push(bucket[first]);
+		if second != first {
+			kept.push(bucket[second]);
+		}
+	}
+
+	kept
+}
@@ -0,0 +1,64 @@
+//! Treating the x-axis keys as a hierarchy to drill down through, like country, then artist, then
+//! track. The rows are only grouped by one of the keys at a time, starting with the first, and
+//! picking one of its bars moves down to the next key with just that bar's rows.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{brush::Brush, settings::Settings};
+
+/// Where in the hierarchy of keys the chart is. While drilling, the settings' x-axis is just the
+/// key that's being grouped by at the moment (see [`Self::key`]).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Drill {
+	/// The keys of the hierarchy, from the top down
+	pub keys: Vec<String>,
+	/// The labels of the bars that were drilled into on the way down, one for each of the keys
+	/// above the current one
+	pub path: Vec<String>
+}
+
+impl Drill {
+	/// Starts at the top of `keys`
+	pub fn new(keys: Vec<String>) -> Self {
+		Self {
+			keys,
+			path: Vec::new()
+		}
+	}
+
+	/// The key that's being grouped by at the moment
+	pub fn key(&self) -> &str {
+		&self.keys[self.path.len()]
+	}
+
+	/// If there's a key below the current one to drill down to
+	pub fn can_descend(&self) -> bool {
+		self.path.len() + 1 < self.keys.len()
+	}
+
+	/// Moves down to the next key, with just the rows of the bar labelled `label`
+	pub fn descend(&mut self, label: String) {
+		if self.can_descend() {
+			self.path.push(label);
+		}
+	}
+
+	/// Moves back up to the `depth`th key, where 0 is the top
+	pub fn climb(&mut self, depth: usize) {
+		self.path.truncate(depth);
+	}
+
+	/// What keeps only the rows that are in each of the bars that were drilled into, if any were
+	pub(crate) fn brush(&self, settings: &Settings) -> Option<Brush> {
+		(!self.path.is_empty())
+			.then(|| Brush::of(&self.keys[..self.path.len()], self.path.clone(), settings))
+	}
+
+	/// What narrows other charts down to the rows of `labels`, which is one of the bars being
+	/// drilled through at the moment, including the bars above it
+	pub fn brush_for(&self, labels: &[String], settings: &Settings) -> Brush {
+		let depth = self.path.len() + 1;
+		let labels = self.path.iter().chain(labels).cloned().collect();
+		Brush::of(&self.keys[..depth], labels, settings)
+	}
+}
@@ -0,0 +1,205 @@
+//! Adding keys to the data by looking up its values online, like the tags of each artist on
+//! Last.fm. Lookups are slow and rate-limited, so every answer is cached on disk and only values
+//! that have never been looked up before are ever sent anywhere.
+
+use std::{
+	io,
+	path::{Path, PathBuf},
+	thread,
+	time::{Duration, Instant}
+};
+
+use fxhash::{FxHashMap, FxHashSet};
+use merde::{CowStr, Value};
+use serde::{Serialize, de::DeserializeOwned};
+use ureq::Agent;
+
+use crate::{lastfm::LastFm, musicbrainz::MusicBrainz};
+
+/// Somewhere that values can be looked up
+pub trait Lookup {
+	/// What's learned about a single value
+	type Answer: Serialize + DeserializeOwned;
+
+	/// Shows up in errors, and names the file that answers are cached in
+	const NAME: &'static str;
+	/// How long to wait between requests, so that we're not rate-limited (or worse)
+	const INTERVAL: Duration;
+	/// The keys that are added to each row. Each one's values have to all be the same type (or
+	/// null), just like any other key.
+	const KEYS: &'static [&'static str];
+
+	/// Finds out about `value`, or returns `None` if the service has never heard of it
+	fn look_up(&self, value: &str) -> Result<Option<Self::Answer>, EnrichErr>;
+
+	/// The value of each of [`Self::KEYS`] for a row, in the same order
+	fn values(answer: &Self::Answer) -> Vec<Value<'static>>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EnrichErr {
+	#[error("There's no key named '{0}' to look up")]
+	UnknownKey(String),
+	#[error("The data already has a key named '{0}'")]
+	DuplicateKey(&'static str),
+	#[error("Couldn't ask {service}: {message}")]
+	Request {
+		service: &'static str,
+		message: String
+	},
+	#[error("{service} couldn't answer: {message}")]
+	Service {
+		service: &'static str,
+		message: String
+	},
+	#[error("Couldn't save what was looked up to {path}: {source}")]
+	Cache { path: PathBuf, source: io::Error }
+}
+
+/// Each service that spart knows how to look things up with, along with which key's values it
+/// looks up
+#[derive(Clone)]
+pub enum Enrichment {
+	LastFm { service: LastFm, key: String },
+	MusicBrainz { service: MusicBrainz, key: String }
+}
+
+impl Enrichment {
+	/// Adds this enrichment's keys to every row of `data`. `progress` is told how many values
+	/// have been looked up so far, out of how many need to be.
+	pub fn apply(
+		&self,
+		data: &mut [merde::Map<'static>],
+		progress: &mut dyn FnMut(usize, usize)
+	) -> Result<(), EnrichErr> {
+		match self {
+			Self::LastFm { service, key } => enrich(data, key, service, progress),
+			Self::MusicBrainz { service, key } => enrich(data, key, service, progress)
+		}
+	}
+}
+
+/// What every service is talked to with. Errors are read from the body of the response instead
+/// of its status, since that's where the services explain themselves.
+pub(crate) fn agent() -> Agent {
+	Agent::config_builder()
+		.timeout_global(Some(Duration::from_secs(10)))
+		.http_status_as_error(false)
+		// Some services (like MusicBrainz) turn away anyone who doesn't say who they are
+		.user_agent(concat!(
+			"spart/",
+			env!("CARGO_PKG_VERSION"),
+			" ( https://github.com/itsjunetime/spart )"
+		))
+		.build()
+		.into()
+}
+
+/// Where the answers that have been looked up are kept: `$XDG_CACHE_HOME/spart`, falling back
+/// to `~/.cache/spart`
+pub fn cache_dir() -> Option<PathBuf> {
+	let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+	Some(cache_dir.join("spart"))
+}
+
+/// Everything that a [`Lookup`] has answered before, where `None` means that it had nothing to
+/// say about that value
+struct Cache<T> {
+	path: Option<PathBuf>,
+	answers: FxHashMap<String, Option<T>>
+}
+
+impl<T: Serialize + DeserializeOwned> Cache<T> {
+	/// Reads the cache for `name`, starting afresh if there isn't one yet (or it can't be read)
+	fn open(name: &str) -> Self {
+		let path = cache_dir().map(|dir| dir.join(format!("{name}.json")));
+		let answers = path
+			.as_deref()
+			.and_then(|path| std::fs::read_to_string(path).ok())
+			.and_then(|contents| serde_json::from_str(&contents).ok())
+			.unwrap_or_default();
+		Self { path, answers }
+	}
+
+	fn save(&self) -> Result<(), EnrichErr> {
+		let Some(path) = &self.path else {
+			return Ok(());
+		};
+
+		let err = |source| EnrichErr::Cache {
+			path: path.clone(),
+			source
+		};
+		std::fs::create_dir_all(path.parent().unwrap_or(Path::new("."))).map_err(err)?;
+		let contents = serde_json::to_string(&self.answers).map_err(|e| err(e.into()))?;
+		std::fs::write(path, contents).map_err(err)
+	}
+}
+
+/// Looks up every distinct string value of `key` with `lookup` (unless it's already in the cache),
+/// then adds `lookup`'s keys to each row from the answer for its value
+pub fn enrich<L: Lookup>(
+	data: &mut [merde::Map<'static>],
+	key: &str,
+	lookup: &L,
+	progress: &mut dyn FnMut(usize, usize)
+) -> Result<(), EnrichErr> {
+	let first = data
+		.first()
+		.ok_or_else(|| EnrichErr::UnknownKey(key.to_string()))?;
+	if !first.contains_key(&CowStr::from(key)) {
+		return Err(EnrichErr::UnknownKey(key.to_string()));
+	}
+	if let Some(existing) = L::KEYS
+		.iter()
+		.find(|new| first.contains_key(&CowStr::from(**new)))
+	{
+		return Err(EnrichErr::DuplicateKey(existing));
+	}
+
+	let mut cache = Cache::<L::Answer>::open(L::NAME);
+	let unknown = data
+		.iter()
+		.filter_map(|row| match row.get(&CowStr::from(key)) {
+			Some(Value::Str(value)) if !cache.answers.contains_key(value.as_ref()) =>
+				Some(value.to_string()),
+			_ => None
+		})
+		.collect::<FxHashSet<_>>();
+
+	let mut last_request = None::<Instant>;
+	for (done, value) in unknown.iter().enumerate() {
+		progress(done, unknown.len());
+		if let Some(wait) = last_request.and_then(|last| L::INTERVAL.checked_sub(last.elapsed())) {
+			thread::sleep(wait);
+		}
+		last_request = Some(Instant::now());
+
+		match lookup.look_up(value) {
+			Ok(answer) => _ = cache.answers.insert(value.clone(), answer),
+			Err(e) => {
+				// Don't make whatever was looked up before this have to be looked up again
+				cache.save()?;
+				return Err(e);
+			}
+		}
+	}
+	cache.save()?;
+
+	let nulls = vec![Value::Null; L::KEYS.len()];
+	for row in data {
+		let answer = match row.get(&CowStr::from(key)) {
+			Some(Value::Str(value)) => cache.answers.get(value.as_ref()).and_then(Option::as_ref),
+			_ => None
+		};
+		let values = answer.map_or_else(|| nulls.clone(), L::values);
+		for (new, value) in L::KEYS.iter().zip(values) {
+			row.insert((*new).into(), value);
+		}
+	}
+	Ok(())
+}
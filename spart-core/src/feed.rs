@@ -0,0 +1,239 @@
+//! Rows that keep arriving while spart's running, as JSON objects (one per line) written to a
+//! socket or FIFO. Everything's read on other threads so that nothing waits on whoever's writing,
+//! and the rows pile up until they're picked up with [`Feed::take`].
+
+use std::{
+	fmt,
+	io::{self, BufRead, BufReader, Read},
+	net::{SocketAddr, TcpListener},
+	path::PathBuf,
+	str::FromStr,
+	sync::mpsc::{Receiver, Sender, channel},
+	thread
+};
+
+use merde::{IntoStatic, json::from_str};
+
+use crate::load::LoadOptions;
+
+/// Where rows are read from
+#[derive(Clone, Debug)]
+pub enum Address {
+	/// Each connection to this address can send rows
+	Tcp(SocketAddr),
+	/// Each connection to a Unix socket created at this path can send rows
+	#[cfg(unix)]
+	Unix(PathBuf),
+	/// An existing FIFO, which is read from every time something opens it for writing
+	#[cfg(unix)]
+	Fifo(PathBuf)
+}
+
+impl FromStr for Address {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(addr) = s.parse() {
+			return Ok(Self::Tcp(addr));
+		}
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::FileTypeExt;
+
+			if let Some(path) = s.strip_prefix("unix:") {
+				return Ok(Self::Unix(PathBuf::from(path)));
+			}
+			// Anything else has to look like a path, so that a typo'd address doesn't quietly
+			// turn into a socket file named after it
+			if s.contains('/') {
+				let path = PathBuf::from(s);
+				let is_fifo = std::fs::metadata(&path).is_ok_and(|m| m.file_type().is_fifo());
+				return Ok(if is_fifo {
+					Self::Fifo(path)
+				} else {
+					Self::Unix(path)
+				});
+			}
+			Err(format!(
+				"'{s}' isn't an address to listen on, like 127.0.0.1:9000, unix:spart.sock, or \
+				 ./spart.fifo"
+			))
+		}
+		#[cfg(not(unix))]
+		Err(format!(
+			"'{s}' isn't an address to listen on, like 127.0.0.1:9000"
+		))
+	}
+}
+
+impl fmt::Display for Address {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Tcp(addr) => write!(f, "{addr}"),
+			#[cfg(unix)]
+			Self::Unix(path) | Self::Fifo(path) => write!(f, "{}", path.display())
+		}
+	}
+}
+
+/// A line that was read from the feed
+enum Line {
+	Row(merde::Map<'static>),
+	/// Why a line couldn't be parsed as a row
+	Malformed(String)
+}
+
+/// What's come in since the last time [`Feed::take`] was called
+#[derive(Default)]
+pub struct Received {
+	pub rows: Vec<merde::Map<'static>>,
+	/// How many lines couldn't be parsed, and why the last of them couldn't be
+	pub malformed: usize,
+	pub last_err: Option<String>
+}
+
+pub struct Feed {
+	pub address: Address,
+	lines: Receiver<Line>
+}
+
+impl Feed {
+	/// Starts listening at `address`, reading rows with `options`. Anything that goes wrong after
+	/// it's been set up (like a connection dropping) just means no more rows come from there.
+	pub fn listen(address: Address, options: LoadOptions) -> io::Result<Self> {
+		let (tx, lines) = channel();
+
+		match &address {
+			Address::Tcp(addr) => {
+				let listener = TcpListener::bind(addr)?;
+				thread::Builder::new()
+					.name("spart-feed".into())
+					.spawn(move || accept_all(listener.incoming(), &tx, &options))?;
+			}
+			#[cfg(unix)]
+			Address::Unix(path) => {
+				let listener = std::os::unix::net::UnixListener::bind(path)?;
+				thread::Builder::new()
+					.name("spart-feed".into())
+					.spawn(move || accept_all(listener.incoming(), &tx, &options))?;
+			}
+			#[cfg(unix)]
+			Address::Fifo(path) => {
+				let path = path.clone();
+				thread::Builder::new()
+					.name("spart-feed".into())
+					.spawn(move || {
+						// Opening blocks until there's a writer, and reading ends once they're
+						// done, so this just waits around for the next one each time
+						while let Ok(fifo) = std::fs::File::open(&path) {
+							if !read_lines(fifo, &tx, &options) {
+								break;
+							}
+						}
+					})?;
+			}
+		}
+
+		Ok(Self { address, lines })
+	}
+
+	pub fn take(&self) -> Received {
+		let mut received = Received::default();
+		for line in self.lines.try_iter() {
+			match line {
+				Line::Row(row) => received.rows.push(row),
+				Line::Malformed(err) => {
+					received.malformed += 1;
+					received.last_err = Some(err);
+				}
+			}
+		}
+		received
+	}
+}
+
+impl Drop for Feed {
+	fn drop(&mut self) {
+		// We made the socket, so it'd otherwise be left lying around (and keep the next listener
+		// from being able to bind to it)
+		#[cfg(unix)]
+		if let Address::Unix(path) = &self.address {
+			_ = std::fs::remove_file(path);
+		}
+	}
+}
+
+/// Reads from each connection as it comes in, each on its own thread so that they can all send
+/// rows at once
+fn accept_all<S: Read + Send + 'static>(
+	incoming: impl Iterator<Item = io::Result<S>>,
+	tx: &Sender<Line>,
+	options: &LoadOptions
+) {
+	for stream in incoming.flatten() {
+		let (tx, options) = (tx.clone(), options.clone());
+		let spawned = thread::Builder::new()
+			.name("spart-feed-conn".into())
+			.spawn(move || read_lines(stream, &tx, &options));
+		if spawned.is_err() {
+			break;
+		}
+	}
+}
+
+/// Sends every row that's read from `reader` until it runs out. Returns false if the receiver's
+/// gone, so there's no point in reading any more.
+fn read_lines(reader: impl Read, tx: &Sender<Line>, options: &LoadOptions) -> bool {
+	for line in BufReader::new(reader).lines() {
+		let Ok(line) = line else {
+			break;
+		};
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let parsed = match from_str::<merde::Map>(&line) {
+			Ok(mut row) => {
+				options.prepare(&mut row);
+				Line::Row(row.into_static())
+			}
+			Err(e) => Line::Malformed(e.to_string())
+		};
+		if tx.send(parsed).is_err() {
+			return false;
+		}
+	}
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn addresses() {
+		assert!(matches!("127.0.0.1:9000".parse(), Ok(Address::Tcp(_))));
+		assert!("localhost:9000".parse::<Address>().is_err());
+		#[cfg(unix)]
+		{
+			let unix = |s: &str| match s.parse() {
+				Ok(Address::Unix(path)) => Some(path),
+				_ => None
+			};
+			assert_eq!(unix("unix:spart.sock"), Some(PathBuf::from("spart.sock")));
+			assert_eq!(unix("./spart.sock"), Some(PathBuf::from("./spart.sock")));
+			assert_eq!(unix("127.0.0.1:90000"), None);
+		}
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn sockets_are_removed() {
+		let path = std::env::temp_dir().join(format!("spart-feed-{}.sock", std::process::id()));
+		let feed = Feed::listen(Address::Unix(path.clone()), LoadOptions::default()).unwrap();
+		assert!(path.exists());
+		drop(feed);
+		assert!(!path.exists());
+	}
+}
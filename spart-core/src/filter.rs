@@ -0,0 +1,349 @@
+//! Parsing of the textual filter syntax, shared by the `--filter` flag and the filter box in the
+//! UI. A filter looks like `key<op>value`, where `op` is one of:
+//!
+//! - `=` or `!=`, followed by one or more values separated by `|`, to include or exclude rows
+//!   with those values (e.g. `country=US|CA`). Bool keys can also be compared against `null`
+//!   (e.g. `skipped=false|null`)
+//! - `>`, `>=`, `<`, or `<=`, followed by a number, to bound a numeric key (e.g. `ms_played>30000`)
+
+use std::{ops::Range, str::FromStr};
+
+use merde::ValueType;
+
+use crate::settings::{Bools, Bound, Inclusion, Nans, ValueBound};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum FilterParseErr {
+	#[error("Filters need an operator (one of =, !=, >, >=, <, <=) between the key and value")]
+	NoOperator,
+	#[error("There's no key named '{0}' to filter on")]
+	UnknownKey(String),
+	#[error("'{value}' isn't a valid value for '{key}' (which holds {ty:?}s)")]
+	InvalidValue {
+		key: String,
+		value: String,
+		ty: ValueType
+	},
+	#[error("The '{op}' operator can't be used with '{key}' (which holds {ty:?}s)")]
+	UnsupportedOperator {
+		key: String,
+		op: &'static str,
+		ty: ValueType
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+	Eq,
+	NotEq,
+	Gt,
+	GtEq,
+	Lt,
+	LtEq
+}
+
+impl Op {
+	// Longer operators come first so that `>=` isn't mistaken for `>` followed by `=value`
+	const ALL: &[(&str, Self)] = &[
+		("!=", Self::NotEq),
+		(">=", Self::GtEq),
+		("<=", Self::LtEq),
+		("=", Self::Eq),
+		(">", Self::Gt),
+		("<", Self::Lt)
+	];
+
+	fn as_str(self) -> &'static str {
+		Self::ALL
+			.iter()
+			.find(|(_, op)| *op == self)
+			.map_or("", |(s, _)| s)
+	}
+}
+
+/// Splits a filter up into its key, operator, and value
+fn split(filter: &str) -> Result<(&str, Op, &str), FilterParseErr> {
+	let (op_idx, op_str, op) = Op::ALL
+		.iter()
+		.filter_map(|(s, op)| filter.find(s).map(|idx| (idx, *s, *op)))
+		// The earliest operator is the real one, and amongst those that start at the same place,
+		// `ALL` puts the longest first
+		.min_by_key(|(idx, _, _)| *idx)
+		.ok_or(FilterParseErr::NoOperator)?;
+	Ok((
+		filter[..op_idx].trim(),
+		op,
+		filter[op_idx + op_str.len()..].trim()
+	))
+}
+
+/// The key that a filter applies to, without having to know what keys there are
+pub fn filter_key(filter: &str) -> Result<&str, FilterParseErr> {
+	split(filter).map(|(key, _, _)| key)
+}
+
+/// Parses a single filter, returning the key it applies to and the bound it describes.
+pub fn parse_filter(
+	filter: &str,
+	keys: &[(String, ValueType)]
+) -> Result<(String, ValueBound), FilterParseErr> {
+	let (key, op, value) = split(filter)?;
+
+	let ty = keys
+		.iter()
+		.find(|(k, _)| k == key)
+		.map(|(_, ty)| *ty)
+		.ok_or_else(|| FilterParseErr::UnknownKey(key.to_string()))?;
+
+	let invalid = || FilterParseErr::InvalidValue {
+		key: key.to_string(),
+		value: value.to_string(),
+		ty
+	};
+	let unsupported = || FilterParseErr::UnsupportedOperator {
+		key: key.to_string(),
+		op: op.as_str(),
+		ty
+	};
+
+	let bound = match ty {
+		ValueType::I64 => ValueBound::I64(
+			numeric_bound(op, value, i64::MIN..i64::MAX, |n| n.checked_add(1))
+				.ok_or_else(invalid)?
+		),
+		ValueType::U64 => ValueBound::U64(
+			numeric_bound(op, value, u64::MIN..u64::MAX, |n| n.checked_add(1))
+				.ok_or_else(invalid)?
+		),
+		ValueType::Float => ValueBound::F64(
+			numeric_bound(op, value, f64::MIN..f64::MAX, |n| Some(n.next_up()))
+				.ok_or_else(invalid)?,
+			Nans::Exclude
+		),
+		ValueType::String => match op {
+			Op::Eq | Op::NotEq => ValueBound::Str {
+				include: inclusion(op),
+				values: value.split('|').map(str::to_string).collect()
+			},
+			_ => return Err(unsupported())
+		},
+		// Bools can also be compared against `null`, like `skipped=false|null`, since there are few
+		// enough values that a bound on them can keep nulls too
+		ValueType::Bool => {
+			if !matches!(op, Op::Eq | Op::NotEq) {
+				return Err(unsupported());
+			}
+			let mut listed = Bools {
+				trues: false,
+				falses: false,
+				nulls: false
+			};
+			for val in value.split('|') {
+				match val {
+					"true" => listed.trues = true,
+					"false" => listed.falses = true,
+					"null" => listed.nulls = true,
+					_ => return Err(invalid())
+				}
+			}
+			ValueBound::Bool(match op {
+				// Still leaving out nulls, like `!=` on anything else does
+				Op::NotEq => Bools {
+					trues: !listed.trues,
+					falses: !listed.falses,
+					nulls: false
+				},
+				_ => listed
+			})
+		}
+		_ => return Err(unsupported())
+	};
+
+	Ok((key.to_string(), bound))
+}
+
+fn inclusion(op: Op) -> Inclusion {
+	match op {
+		Op::NotEq => Inclusion::Exclude,
+		_ => Inclusion::Include
+	}
+}
+
+/// Builds the bound for a numeric key. `full` is the range that an unbounded side should extend
+/// to, and `next` returns the smallest value greater than the one given, since our ranges don't
+/// include their end.
+fn numeric_bound<T: FromStr + Copy>(
+	op: Op,
+	value: &str,
+	full: Range<T>,
+	next: impl Fn(T) -> Option<T>
+) -> Option<Bound<T>> {
+	let parse = |s: &str| s.trim().parse::<T>().ok();
+
+	Some(match op {
+		Op::Eq | Op::NotEq => Bound::Specifics {
+			include: inclusion(op),
+			values: value.split('|').map(parse).collect::<Option<_>>()?
+		},
+		Op::Gt => Bound::Range(next(parse(value)?)?..full.end),
+		Op::GtEq => Bound::Range(parse(value)?..full.end),
+		Op::Lt => Bound::Range(full.start..parse(value)?),
+		Op::LtEq => Bound::Range(full.start..next(parse(value)?)?)
+	})
+}
+
+/// Combines a newly-parsed bound with whatever bound already exists for the same key. Two ranges
+/// narrow each other down (so `x>1` and `x<5` can be given separately); anything else just
+/// replaces the old bound.
+pub fn merge_bounds(existing: Option<ValueBound>, new: ValueBound) -> ValueBound {
+	fn intersect<T: PartialOrd + Copy>(a: &Range<T>, b: &Range<T>) -> Range<T> {
+		let start = if a.start > b.start { a.start } else { b.start };
+		let end = if a.end < b.end { a.end } else { b.end };
+		start..end
+	}
+
+	match (existing, new) {
+		(Some(ValueBound::I64(Bound::Range(a))), ValueBound::I64(Bound::Range(b))) =>
+			ValueBound::I64(Bound::Range(intersect(&a, &b))),
+		(Some(ValueBound::U64(Bound::Range(a))), ValueBound::U64(Bound::Range(b))) =>
+			ValueBound::U64(Bound::Range(intersect(&a, &b))),
+		(Some(ValueBound::F64(Bound::Range(a), nans)), ValueBound::F64(Bound::Range(b), _)) =>
+			ValueBound::F64(Bound::Range(intersect(&a, &b)), nans),
+		(_, new) => new
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(filter: &str) -> Result<(String, ValueBound), FilterParseErr> {
+		let keys = [
+			("n", ValueType::I64),
+			("u", ValueType::U64),
+			("f", ValueType::Float),
+			("s", ValueType::String),
+			("b", ValueType::Bool)
+		]
+		.map(|(key, ty)| (key.to_string(), ty));
+		parse_filter(filter, &keys)
+	}
+
+	fn bound(filter: &str) -> ValueBound {
+		parse(filter).unwrap().1
+	}
+
+	#[test]
+	fn numeric_operators() {
+		assert_eq!(bound("n>1"), ValueBound::I64(Bound::Range(2..i64::MAX)));
+		assert_eq!(bound("n>=1"), ValueBound::I64(Bound::Range(1..i64::MAX)));
+		assert_eq!(bound("n<1"), ValueBound::I64(Bound::Range(i64::MIN..1)));
+		assert_eq!(bound("n<=1"), ValueBound::I64(Bound::Range(i64::MIN..2)));
+		assert_eq!(bound("u >= 3"), ValueBound::U64(Bound::Range(3..u64::MAX)));
+		assert_eq!(
+			bound("f<=0.5"),
+			ValueBound::F64(Bound::Range(f64::MIN..0.5f64.next_up()), Nans::Exclude)
+		);
+		// `<=` and `>=` aren't mistaken for `<` or `>` followed by `=value`
+		assert_eq!(parse("n<=x").unwrap_err(), FilterParseErr::InvalidValue {
+			key: "n".to_string(),
+			value: "x".to_string(),
+			ty: ValueType::I64
+		});
+	}
+
+	#[test]
+	fn listed_values() {
+		assert_eq!(
+			bound("n=1|2"),
+			ValueBound::I64(Bound::Specifics {
+				include: Inclusion::Include,
+				values: vec![1, 2]
+			})
+		);
+		assert_eq!(bound("s!=US|CA"), ValueBound::Str {
+			include: Inclusion::Exclude,
+			values: vec!["US".to_string(), "CA".to_string()]
+		});
+		// Values with spaces in them are kept whole, but the spaces around the operator aren't
+		assert_eq!(
+			parse("s = New York").unwrap(),
+			("s".to_string(), ValueBound::Str {
+				include: Inclusion::Include,
+				values: vec!["New York".to_string()]
+			})
+		);
+	}
+
+	#[test]
+	fn bools_and_nulls() {
+		assert_eq!(
+			bound("b=false|null"),
+			ValueBound::Bool(Bools {
+				trues: false,
+				falses: true,
+				nulls: true
+			})
+		);
+		// `!=` still leaves out nulls, even when they're listed
+		assert_eq!(
+			bound("b!=true|null"),
+			ValueBound::Bool(Bools {
+				trues: false,
+				falses: true,
+				nulls: false
+			})
+		);
+		assert!(matches!(
+			parse("b=maybe"),
+			Err(FilterParseErr::InvalidValue { .. })
+		));
+		// Only bools can be compared against null
+		assert!(matches!(
+			parse("n=null"),
+			Err(FilterParseErr::InvalidValue { .. })
+		));
+	}
+
+	#[test]
+	fn unusable_filters() {
+		assert_eq!(parse("n~1").unwrap_err(), FilterParseErr::NoOperator);
+		assert_eq!(
+			parse("missing=1").unwrap_err(),
+			FilterParseErr::UnknownKey("missing".to_string())
+		);
+		assert_eq!(
+			parse("s>a").unwrap_err(),
+			FilterParseErr::UnsupportedOperator {
+				key: "s".to_string(),
+				op: ">",
+				ty: ValueType::String
+			}
+		);
+		assert!(matches!(
+			parse("b<true"),
+			Err(FilterParseErr::UnsupportedOperator { .. })
+		));
+		assert_eq!(filter_key(" n >= 4"), Ok("n"));
+	}
+
+	#[test]
+	fn merging_ranges() {
+		let merged = merge_bounds(Some(bound("n>1")), bound("n<5"));
+		assert_eq!(merged, ValueBound::I64(Bound::Range(2..5)));
+		let merged = merge_bounds(Some(bound("f>=1")), bound("f<=2"));
+		assert_eq!(
+			merged,
+			ValueBound::F64(Bound::Range(1.0..2f64.next_up()), Nans::Exclude)
+		);
+		// Ranges that don't overlap leave nothing
+		let ValueBound::U64(Bound::Range(range)) = merge_bounds(Some(bound("u>5")), bound("u<3"))
+		else {
+			panic!("two ranges should merge into a range");
+		};
+		assert!(range.is_empty());
+		// Anything that isn't two ranges is replaced
+		assert_eq!(merge_bounds(Some(bound("n>1")), bound("n=3")), bound("n=3"));
+		assert_eq!(merge_bounds(None, bound("s=a")), bound("s=a"));
+	}
+}
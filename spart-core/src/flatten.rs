@@ -0,0 +1,54 @@
+//! Turning nested objects into flat rows, so that JSON that wasn't written with spart in mind can
+//! be charted as it is. Each value inside an object becomes a key of its own, named by the path to
+//! it (like `address.city`), and arrays that are small enough can be exploded into a key for each
+//! of their elements (like `tags.0`) the same way.
+
+use merde::{CowStr, Value};
+
+/// What's put between the keys on the way to a nested value
+pub const SEPARATOR: char = '.';
+
+/// Moves everything in `row`'s objects (and in its arrays of up to `limit` elements) out into keys
+/// of their own. Bigger arrays are left as they are, which [`crate::schema::Schema::infer`] then
+/// rejects, so nothing's exploded with a `limit` of 0, since a key for each element of a long
+/// array would be far too many keys. If a row already has a key that something nested would be
+/// moved to, the one that was already there is kept.
+pub fn flatten(row: &mut merde::Map, limit: usize) {
+	let nested = row
+		.iter()
+		.filter(|(_, value)| matches!(value, Value::Map(_) | Value::Array(_)))
+		.map(|(key, _)| key.clone())
+		.collect::<Vec<_>>();
+	if nested.is_empty() {
+		return;
+	}
+
+	for key in nested {
+		let Some(value) = row.remove(&key) else {
+			continue;
+		};
+		match value {
+			Value::Array(array) if array.len() > limit => {
+				row.insert(key, Value::Array(array));
+			}
+			value => flatten_into(row, &key, value, limit)
+		}
+	}
+}
+
+/// Puts `value` in `row` at `path`, or what's in it under `path` if it's nested
+fn flatten_into<'s>(row: &mut merde::Map<'s>, path: &str, value: Value<'s>, limit: usize) {
+	match value {
+		Value::Map(map) =>
+			for (key, value) in map {
+				flatten_into(row, &format!("{path}{SEPARATOR}{key}"), value, limit);
+			},
+		Value::Array(array) if array.len() <= limit =>
+			for (idx, value) in array.into_iter().enumerate() {
+				flatten_into(row, &format!("{path}{SEPARATOR}{idx}"), value, limit);
+			},
+		value => {
+			row.entry(CowStr::from(path.to_string())).or_insert(value);
+		}
+	}
+}
@@ -0,0 +1,412 @@
+use std::{borrow::Cow, fmt::Write};
+
+use chrono::DateTime;
+use merde::Value;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberFormat {
+	#[default]
+	Plain,
+	/// `1234567.5` -> `1,234,567.5`
+	Thousands,
+	/// A set number of digits after the decimal point
+	Fixed(usize),
+	/// `1234567` -> `1.2M`
+	Si,
+	/// A number of milliseconds, like `5025000` -> `1h 23m`
+	Duration,
+	/// A fraction, like `0.256` -> `25.6%`
+	Percent,
+	/// A number of bytes, like `1536` -> `1.5 KiB`
+	Bytes,
+	/// An amount of money, like `1536` -> `$1,536.00`
+	Currency(Currency)
+}
+
+/// What an amount of money is in, for [`NumberFormat::Currency`]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+	#[default]
+	Usd,
+	Eur,
+	Gbp,
+	Jpy
+}
+
+impl Currency {
+	pub const ALL: [Self; 4] = [Self::Usd, Self::Eur, Self::Gbp, Self::Jpy];
+
+	pub fn symbol(self) -> &'static str {
+		match self {
+			Self::Usd => "$",
+			Self::Eur => "€",
+			Self::Gbp => "£",
+			Self::Jpy => "¥"
+		}
+	}
+
+	/// How many digits there usually are after the decimal point
+	fn decimals(self) -> usize {
+		match self {
+			Self::Jpy => 0,
+			_ => 2
+		}
+	}
+}
+
+/// What a key's values are measured in, which decides how they're formatted. These can be given
+/// by name (in the config file or on the command line), which formats can't.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+	Bytes,
+	#[serde(rename = "ms")]
+	Milliseconds,
+	Usd,
+	Eur,
+	Gbp,
+	Jpy
+}
+
+impl Unit {
+	pub fn number_format(self) -> NumberFormat {
+		match self {
+			Self::Bytes => NumberFormat::Bytes,
+			Self::Milliseconds => NumberFormat::Duration,
+			Self::Usd => NumberFormat::Currency(Currency::Usd),
+			Self::Eur => NumberFormat::Currency(Currency::Eur),
+			Self::Gbp => NumberFormat::Currency(Currency::Gbp),
+			Self::Jpy => NumberFormat::Currency(Currency::Jpy)
+		}
+	}
+}
+
+impl std::str::FromStr for Unit {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"bytes" => Ok(Self::Bytes),
+			"ms" => Ok(Self::Milliseconds),
+			"usd" => Ok(Self::Usd),
+			"eur" => Ok(Self::Eur),
+			"gbp" => Ok(Self::Gbp),
+			"jpy" => Ok(Self::Jpy),
+			_ => Err(format!(
+				"Unknown unit '{s}' (expected bytes, ms, usd, eur, gbp, or jpy)"
+			))
+		}
+	}
+}
+
+impl NumberFormat {
+	pub const BASE_OPTIONS: &[Self] = &[
+		Self::Plain,
+		Self::Thousands,
+		Self::Fixed(2),
+		Self::Si,
+		Self::Duration,
+		Self::Percent,
+		Self::Bytes,
+		Self::Currency(Currency::Usd)
+	];
+
+	pub fn ui_descriptor(&self) -> &'static str {
+		match self {
+			Self::Plain => "Plain",
+			Self::Thousands => "Thousands separators",
+			Self::Fixed(_) => "Fixed decimals",
+			Self::Si => "SI suffixes",
+			Self::Duration => "Duration (ms)",
+			Self::Percent => "Percentage",
+			Self::Bytes => "Bytes (KiB, MiB, ...)",
+			Self::Currency(_) => "Currency"
+		}
+	}
+
+	pub fn format(&self, num: f64) -> String {
+		match self {
+			Self::Plain => num.to_string(),
+			Self::Thousands => {
+				let plain = num.to_string();
+				let (int, frac) = plain.split_once('.').unwrap_or((&plain, ""));
+				let (sign, digits) = int.strip_prefix('-').map_or(("", int), |d| ("-", d));
+				let mut out = sign.to_string();
+				out.push_str(&group_thousands(digits));
+				if !frac.is_empty() {
+					out.push('.');
+					out.push_str(frac);
+				}
+				out
+			}
+			Self::Fixed(decimals) => format!("{num:.decimals$}"),
+			Self::Si => {
+				const SUFFIXES: &[(f64, &str)] = &[(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k")];
+
+				SUFFIXES
+					.iter()
+					.find(|(scale, _)| num.abs() >= *scale)
+					.map_or_else(
+						|| trim_decimals(format!("{num:.1}")),
+						|&(scale, suffix)| trim_decimals(format!("{:.1}", num / scale)) + suffix
+					)
+			}
+			Self::Duration => {
+				let sign = if num < 0. { "-" } else { "" };
+				let secs = (num.abs() / 1000.).round() as u64;
+				let (hours, mins, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+				match (hours, mins) {
+					(0, 0) => format!("{sign}{secs}s"),
+					(0, _) => format!("{sign}{mins}m {secs:02}s"),
+					_ => format!("{sign}{}h {mins:02}m", group_thousands(&hours.to_string()))
+				}
+			}
+			Self::Percent => trim_decimals(format!("{:.1}", num * 100.)) + "%",
+			Self::Bytes => {
+				const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+				let power = (0..UNITS.len())
+					.rev()
+					.find(|power| num.abs() >= 1024f64.powi(*power as i32))
+					.unwrap_or(0);
+				let scaled = num / 1024f64.powi(power as i32);
+				format!("{} {}", trim_decimals(format!("{scaled:.1}")), UNITS[power])
+			}
+			Self::Currency(currency) => {
+				let sign = if num < 0. { "-" } else { "" };
+				let decimals = currency.decimals();
+				let amount = format!("{:.decimals$}", num.abs());
+				let (int, frac) = amount.split_once('.').unwrap_or((&amount, ""));
+				let frac = if frac.is_empty() {
+					String::new()
+				} else {
+					format!(".{frac}")
+				};
+				format!("{sign}{}{}{frac}", currency.symbol(), group_thousands(int))
+			}
+		}
+	}
+
+	/// Formats an integer, with every digit of it exact (even past 2^53, where an f64 would lose
+	/// some) in the formats that show every digit
+	pub fn format_int(&self, int: i128) -> String {
+		let sign = if int < 0 { "-" } else { "" };
+		let digits = int.unsigned_abs().to_string();
+		let zeros = |decimals: usize| match decimals {
+			0 => String::new(),
+			_ => format!(".{}", "0".repeat(decimals))
+		};
+		match self {
+			Self::Plain => int.to_string(),
+			Self::Thousands => format!("{sign}{}", group_thousands(&digits)),
+			Self::Fixed(decimals) => format!("{int}{}", zeros(*decimals)),
+			Self::Currency(currency) => format!(
+				"{sign}{}{}{}",
+				currency.symbol(),
+				group_thousands(&digits),
+				zeros(currency.decimals())
+			),
+			// These are rounded to a few digits anyway
+			Self::Si | Self::Duration | Self::Percent | Self::Bytes => self.format(int as f64)
+		}
+	}
+
+	/// Formats the value if it's numeric, returning `None` otherwise so that the caller can fall
+	/// back to whatever rendering they'd normally use.
+	pub fn format_value(&self, value: &Value) -> Option<String> {
+		match value {
+			Value::I64(i) => Some(self.format_int((*i).into())),
+			Value::U64(u) => Some(self.format_int((*u).into())),
+			Value::Float(f) => Some(self.format(f.into_inner())),
+			_ => None
+		}
+	}
+}
+
+fn group_thousands(digits: &str) -> String {
+	let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+	for (idx, c) in digits.chars().enumerate() {
+		if idx != 0 && (digits.len() - idx).is_multiple_of(3) {
+			out.push(',');
+		}
+		out.push(c);
+	}
+	out
+}
+
+fn trim_decimals(s: String) -> String {
+	match s.strip_suffix(".0") {
+		Some(trimmed) => trimmed.to_string(),
+		None => s
+	}
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+	b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Turns base64 back into bytes, or `None` if it isn't base64. The padding can be left off, and
+/// the URL-safe alphabet (with `-` and `_` instead of `+` and `/`) is accepted too.
+pub fn decode_base64(s: &str) -> Option<Vec<u8>> {
+	let s = s.trim_end_matches('=');
+	let mut out = Vec::with_capacity(s.len() * 3 / 4);
+	let (mut n, mut bits) = (0u32, 0);
+	for c in s.bytes() {
+		let digit = match c {
+			b'-' => 62,
+			b'_' => 63,
+			_ => BASE64_ALPHABET.iter().position(|a| *a == c)? as u32
+		};
+		n = (n << 6) | digit;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((n >> bits) as u8);
+		}
+	}
+	Some(out)
+}
+
+/// How the values of a bytes key are turned into text
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteEncoding {
+	/// `[0xca, 0xfe]` -> `cafe`
+	#[default]
+	Hex,
+	Base64,
+	/// As text, with anything that isn't valid UTF-8 replaced by `�`
+	Utf8
+}
+
+impl ByteEncoding {
+	pub const ALL: [Self; 3] = [Self::Hex, Self::Base64, Self::Utf8];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Hex => "Hex",
+			Self::Base64 => "Base64",
+			Self::Utf8 => "UTF-8"
+		}
+	}
+
+	pub fn encode(self, bytes: &[u8]) -> String {
+		match self {
+			Self::Hex => bytes.iter().fold(String::new(), |mut out, b| {
+				// Writing to a String can't fail
+				_ = write!(out, "{b:02x}");
+				out
+			}),
+			Self::Base64 => {
+				let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+				for chunk in bytes.chunks(3) {
+					let n = chunk
+						.iter()
+						.enumerate()
+						.fold(0u32, |n, (idx, b)| n | (u32::from(*b) << (16 - idx * 8)));
+					// A chunk of n bytes fills n + 1 characters, and the rest are padding
+					for idx in 0..4 {
+						out.push(if idx <= chunk.len() {
+							BASE64_ALPHABET[(n >> (18 - idx * 6)) as usize & 0x3f] as char
+						} else {
+							'='
+						});
+					}
+				}
+				out
+			}
+			Self::Utf8 => String::from_utf8_lossy(bytes).into_owned()
+		}
+	}
+}
+
+/// Some commonly-wanted strftime-style formats to offer before making the user write their own
+pub const DATE_FORMAT_PRESETS: &[(&str, &str)] = &[
+	("%Y", "Year"),
+	("%Y-%m", "Month"),
+	("%Y-%m-%d", "Day"),
+	("%Y-%m-%d %H:00", "Hour"),
+	("%H", "Hour of day"),
+	("%A", "Day of week")
+];
+
+/// Returns true if this value is a string that we can interpret as a timestamp
+pub fn is_timestamp(value: &Value) -> bool {
+	matches!(value, Value::Str(s) if DateTime::parse_from_rfc3339(s).is_ok())
+}
+
+/// Renders the given value with a strftime-style `format`, if it's a parseable timestamp and the
+/// format is valid.
+pub fn format_timestamp(value: &Value, format: &str) -> Option<String> {
+	let Value::Str(s) = value else {
+		return None;
+	};
+
+	let time = DateTime::parse_from_rfc3339(s).ok()?;
+	let mut out = String::new();
+	// chrono only reports invalid format specifiers when it actually tries to write them out
+	write!(out, "{}", time.format(format)).ok()?;
+	Some(out)
+}
+
+/// Cuts `s` down to at most `width` characters, marking it with an ellipsis if anything was
+/// removed.
+pub fn truncate(s: &str, width: usize) -> Cow<'_, str> {
+	match s.char_indices().nth(width) {
+		None => Cow::Borrowed(s),
+		Some(_) => {
+			let end = s
+				.char_indices()
+				.nth(width.saturating_sub(1))
+				.map_or(0, |(idx, _)| idx);
+			Cow::Owned(format!("{}…", &s[..end]))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn big_integers() {
+		let big = Value::I64(i64::MAX);
+		assert_eq!(
+			NumberFormat::Plain.format_value(&big).unwrap(),
+			"9223372036854775807"
+		);
+		assert_eq!(
+			NumberFormat::Thousands.format_value(&big).unwrap(),
+			"9,223,372,036,854,775,807"
+		);
+		assert_eq!(
+			NumberFormat::Fixed(2)
+				.format_value(&Value::I64(-9007199254740993))
+				.unwrap(),
+			"-9007199254740993.00"
+		);
+		assert_eq!(
+			NumberFormat::Currency(Currency::Usd)
+				.format_value(&Value::U64(u64::MAX))
+				.unwrap(),
+			"$18,446,744,073,709,551,615.00"
+		);
+		assert_eq!(NumberFormat::Si.format_value(&big).unwrap(), "9223372T");
+	}
+
+	#[test]
+	fn floats() {
+		assert_eq!(NumberFormat::Thousands.format(-1234567.5), "-1,234,567.5");
+		assert_eq!(NumberFormat::Fixed(1).format(2.25), "2.2");
+		assert_eq!(NumberFormat::Si.format(1500.), "1.5k");
+		assert_eq!(NumberFormat::Duration.format(5_025_000.), "1h 23m");
+		assert_eq!(NumberFormat::Percent.format(0.256), "25.6%");
+		assert_eq!(NumberFormat::Bytes.format(1536.), "1.5 KiB");
+		assert_eq!(
+			NumberFormat::Currency(Currency::Jpy).format(-1536.4),
+			"-¥1,536"
+		);
+	}
+}
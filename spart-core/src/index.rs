@@ -0,0 +1,152 @@
+//! When a bound picks out specific values of a key, it's much quicker to look up which rows have
+//! those values than to check every single row. Each key gets an index for that the first time
+//! it's bounded like this, and then keeps it for whenever its bound is changed again.
+
+use fxhash::FxHashMap;
+use merde::Value;
+use ordered_float::OrderedFloat;
+
+use crate::settings::{Bound, Inclusion, Nans, ValueBound};
+
+/// The indexes that have been built for some data so far
+#[derive(Default)]
+pub struct Indexes(FxHashMap<String, KeyIndex>);
+
+struct KeyIndex {
+	/// The rows that have each value of the key, in order. Rows that don't have the key at all
+	/// are under null, since that's what they're treated as.
+	rows: FxHashMap<Value<'static>, Vec<usize>>
+}
+
+/// The rows that a bound lets through, in order
+pub enum Matches {
+	/// Only these rows are in bounds
+	Only(Vec<usize>),
+	/// Every row except these is in bounds
+	Except(Vec<usize>)
+}
+
+impl Indexes {
+	/// Looks up which rows of `data` are in `bound`, building the index for `key` if it hasn't
+	/// been already. Returns `None` for bounds that can't be looked up this way (i.e. ranges).
+	pub fn matches(
+		&mut self,
+		data: &[merde::Map<'static>],
+		key: &str,
+		bound: &ValueBound
+	) -> Option<Matches> {
+		fn specifics<T>(
+			include: &Inclusion,
+			values: &[T],
+			to_value: impl Fn(&T) -> Value<'static>
+		) -> (bool, Vec<Value<'static>>) {
+			(
+				*include == Inclusion::Include,
+				values.iter().map(to_value).collect()
+			)
+		}
+
+		let (include, values) = match bound {
+			ValueBound::Str { include, values } =>
+				specifics(include, values, |s| Value::Str(s.clone().into())),
+			ValueBound::Bool(bools) => (
+				true,
+				[
+					(Some(true), Value::Bool(true)),
+					(Some(false), Value::Bool(false)),
+					(None, Value::Null)
+				]
+				.into_iter()
+				.filter(|(val, _)| bools.allows(*val))
+				.map(|(_, val)| val)
+				.collect()
+			),
+			ValueBound::I64(Bound::Specifics { include, values }) =>
+				specifics(include, values, |v| Value::I64(*v)),
+			ValueBound::U64(Bound::Specifics { include, values }) =>
+				specifics(include, values, |v| Value::U64(*v)),
+			ValueBound::F64(Bound::Specifics { include, values }, nans) => {
+				// NaNs are only ever let through by what the bound says to do with them, not by
+				// being one of its values
+				let (include, mut values) = specifics(
+					include,
+					&values.iter().filter(|v| !v.is_nan()).collect::<Vec<_>>(),
+					|v| Value::Float(OrderedFloat(**v))
+				);
+				if include == (*nans == Nans::Include) {
+					values.push(Value::Float(OrderedFloat(f64::NAN)));
+				}
+				(include, values)
+			}
+			ValueBound::I64(Bound::Range(_))
+			| ValueBound::U64(Bound::Range(_))
+			| ValueBound::F64(Bound::Range(_), _) => return None
+		};
+
+		let index = self
+			.0
+			.entry(key.to_string())
+			.or_insert_with(|| KeyIndex::build(data, key));
+
+		let rows_with = |values: &[Value<'static>]| {
+			let mut rows = values
+				.iter()
+				.filter_map(|val| index.rows.get(val))
+				.flatten()
+				.copied()
+				.collect::<Vec<_>>();
+			rows.sort_unstable();
+			// In case the same value was given more than once
+			rows.dedup();
+			rows
+		};
+
+		Some(if include {
+			Matches::Only(rows_with(&values))
+		} else {
+			// Having any bound at all excludes nulls, same as when checking rows one by one
+			let mut values = values;
+			values.push(Value::Null);
+			Matches::Except(rows_with(&values))
+		})
+	}
+}
+
+impl KeyIndex {
+	fn build(data: &[merde::Map<'static>], key: &str) -> Self {
+		let key = key.into();
+		let mut rows = FxHashMap::<_, Vec<usize>>::default();
+
+		for (idx, row) in data.iter().enumerate() {
+			let val = row.get(&key).unwrap_or(&Value::Null);
+			match rows.get_mut(val) {
+				Some(rows) => rows.push(idx),
+				// Only cloning the first time we see a value keeps this from copying every
+				// string in the key
+				None => drop(rows.insert(val.clone(), vec![idx]))
+			}
+		}
+
+		Self { rows }
+	}
+}
+
+/// The rows that are in both `a` and `b`, which must both be in order
+pub fn intersect(a: &[usize], b: &[usize]) -> Vec<usize> {
+	let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+	let mut both = Vec::new();
+
+	while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+		match x.cmp(y) {
+			std::cmp::Ordering::Less => _ = a.next(),
+			std::cmp::Ordering::Greater => _ = b.next(),
+			std::cmp::Ordering::Equal => {
+				both.push(**x);
+				a.next();
+				b.next();
+			}
+		}
+	}
+
+	both
+}
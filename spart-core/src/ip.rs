@@ -0,0 +1,124 @@
+//! Pulling coarser keys out of IP addresses, since every visitor in an access log tends to have an
+//! address of their own, which makes for a bar per visitor. The subnet an address is in, which
+//! version of IP it is, and whether it's a private address or a public one can all be grouped by
+//! instead.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use merde::{CowStr, Value};
+
+use crate::schema::DataErr;
+
+/// How long the prefix of an IPv4 address's subnet is
+pub const SUBNET_V4: u8 = 24;
+
+/// How long the prefix of an IPv6 address's subnet is, which is usually as small as they get
+pub const SUBNET_V6: u8 = 64;
+
+/// How many of a key's values are looked at to tell if it holds addresses
+const IP_SAMPLE: usize = 100;
+
+/// How many of those have to be addresses, so that the odd `-` or `unknown` doesn't stop a key
+/// from being one
+const IP_SHARE: f64 = 0.9;
+
+/// The address in `s`, which can also have a port on it (like `1.2.3.4:443` or `[::1]:443`).
+/// IPv4 addresses that have been written as IPv6 ones (like `::ffff:1.2.3.4`) are treated as the
+/// IPv4 ones that they are.
+pub fn parse(s: &str) -> Option<IpAddr> {
+	let addr = s
+		.parse::<IpAddr>()
+		.or_else(|_| s.parse::<SocketAddr>().map(|socket| socket.ip()))
+		.ok()?;
+	Some(match addr {
+		IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+		IpAddr::V4(_) => addr
+	})
+}
+
+/// The subnet that `addr` is in, like `192.168.1.0/24`
+pub fn subnet(addr: IpAddr) -> String {
+	match addr {
+		IpAddr::V4(v4) => {
+			let mask = u32::MAX << (32 - SUBNET_V4);
+			format!("{}/{SUBNET_V4}", Ipv4Addr::from(u32::from(v4) & mask))
+		}
+		IpAddr::V6(v6) => {
+			let mask = u128::MAX << (128 - SUBNET_V6);
+			format!("{}/{SUBNET_V6}", Ipv6Addr::from(u128::from(v6) & mask))
+		}
+	}
+}
+
+/// If `addr` is one that can't be reached from the internet, like a LAN's, a loopback, or a
+/// link-local one
+pub fn is_private(addr: IpAddr) -> bool {
+	match addr {
+		IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local(),
+		IpAddr::V6(v6) => v6.is_loopback() || v6.is_unique_local() || v6.is_unicast_link_local()
+	}
+}
+
+/// If the string key `key` holds IP addresses in nearly all of the rows that have it
+pub(crate) fn is_ip_key(data: &[merde::Map], key: &str) -> bool {
+	let key = CowStr::from(key);
+	let (addresses, values) = data
+		.iter()
+		.filter_map(|row| match row.get(&key)? {
+			Value::Str(s) => Some(parse(s).is_some()),
+			_ => None
+		})
+		.take(IP_SAMPLE)
+		.fold((0, 0), |(addresses, values), address| {
+			(addresses + usize::from(address), values + 1)
+		});
+	values > 0 && addresses as f64 >= values as f64 * IP_SHARE
+}
+
+/// The keys that [`add_fields`] adds for `key`: its subnet, its version, and if it's private
+pub fn field_names(key: &str) -> [String; 3] {
+	[
+		format!("{key}_subnet"),
+		format!("{key}_version"),
+		format!("{key}_private")
+	]
+}
+
+/// Adds the keys from [`field_names`] to each row, from its address in `key`. Rows without an
+/// address there have null for all of them.
+pub fn add_fields(data: &mut [merde::Map<'static>], key: &str) -> Result<(), DataErr> {
+	let names = field_names(key);
+	if let Some(taken) = names.iter().find(|name| {
+		data.iter()
+			.any(|row| row.contains_key(&name.as_str().into()))
+	}) {
+		return Err(DataErr::KeyExists(taken.clone()));
+	}
+	if !is_ip_key(data, key) {
+		return Err(DataErr::NotAddresses(key.to_string()));
+	}
+
+	let [subnet_key, version_key, private_key] = names.map(CowStr::from);
+	let key = CowStr::from(key);
+	for row in data {
+		let addr = match row.get(&key) {
+			Some(Value::Str(s)) => parse(s),
+			_ => None
+		};
+		let (subnet, version, private) = match addr {
+			Some(addr) => (
+				Value::Str(subnet(addr).into()),
+				Value::Str(CowStr::from(match addr {
+					IpAddr::V4(_) => "IPv4",
+					IpAddr::V6(_) => "IPv6"
+				})),
+				Value::Bool(is_private(addr))
+			),
+			None => (Value::Null, Value::Null, Value::Null)
+		};
+		row.insert(subnet_key.clone(), subnet);
+		row.insert(version_key.clone(), version);
+		row.insert(private_key.clone(), private);
+	}
+	Ok(())
+}
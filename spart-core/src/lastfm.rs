@@ -0,0 +1,125 @@
+//! Looking up artists on [Last.fm](https://www.last.fm/api), for the tags people have given them
+//! (which are about as close to genres as anything) and how much they've been played by everyone.
+
+use std::time::Duration;
+
+use merde::Value;
+use serde::{Deserialize, Serialize};
+use ureq::Agent;
+
+use crate::enrich::{self, EnrichErr, Lookup};
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// How many of an artist's tags are kept, since the rest are usually noise
+const MAX_TAGS: usize = 5;
+
+/// Last.fm's error code for when it doesn't know the artist
+const NOT_FOUND: u64 = 6;
+
+#[derive(Clone)]
+pub struct LastFm {
+	api_key: String,
+	agent: Agent
+}
+
+/// What Last.fm knows about an artist
+#[derive(Serialize, Deserialize)]
+pub struct ArtistInfo {
+	/// Most popular first
+	pub tags: Vec<String>,
+	pub playcount: u64,
+	pub listeners: u64
+}
+
+impl LastFm {
+	pub fn new(api_key: String) -> Self {
+		Self {
+			api_key,
+			agent: enrich::agent()
+		}
+	}
+}
+
+impl Lookup for LastFm {
+	type Answer = ArtistInfo;
+
+	const NAME: &'static str = "lastfm";
+	// Last.fm asks for no more than 5 requests a second
+	const INTERVAL: Duration = Duration::from_millis(200);
+	const KEYS: &'static [&'static str] = &[
+		"lastfm_tag",
+		"lastfm_tags",
+		"lastfm_playcount",
+		"lastfm_listeners"
+	];
+
+	fn look_up(&self, artist: &str) -> Result<Option<ArtistInfo>, EnrichErr> {
+		let request_err = |e: ureq::Error| EnrichErr::Request {
+			service: Self::NAME,
+			message: e.to_string()
+		};
+		let body = self
+			.agent
+			.get(API_URL)
+			.query("method", "artist.getinfo")
+			.query("artist", artist)
+			.query("autocorrect", "1")
+			.query("api_key", &self.api_key)
+			.query("format", "json")
+			.call()
+			.map_err(request_err)?
+			.body_mut()
+			.read_json::<serde_json::Value>()
+			.map_err(request_err)?;
+
+		if let Some(code) = body["error"].as_u64() {
+			if code == NOT_FOUND {
+				return Ok(None);
+			}
+			return Err(EnrichErr::Service {
+				service: Self::NAME,
+				message: body["message"]
+					.as_str()
+					.unwrap_or("no reason given")
+					.to_string()
+			});
+		}
+
+		let artist = &body["artist"];
+		// Numbers come back as strings, and a single tag comes back on its own instead of in an
+		// array
+		let count = |stat: &str| {
+			artist["stats"][stat]
+				.as_str()
+				.and_then(|n| n.parse().ok())
+				.unwrap_or_default()
+		};
+		let tags = match &artist["tags"]["tag"] {
+			serde_json::Value::Array(tags) => tags.iter().collect(),
+			tag => vec![tag]
+		};
+
+		Ok(Some(ArtistInfo {
+			tags: tags
+				.into_iter()
+				.filter_map(|tag| tag["name"].as_str())
+				.take(MAX_TAGS)
+				.map(str::to_lowercase)
+				.collect(),
+			playcount: count("playcount"),
+			listeners: count("listeners")
+		}))
+	}
+
+	fn values(info: &ArtistInfo) -> Vec<Value<'static>> {
+		vec![
+			info.tags
+				.first()
+				.map_or(Value::Null, |tag| Value::Str(tag.clone().into())),
+			Value::Str(info.tags.join(", ").into()),
+			Value::U64(info.playcount),
+			Value::U64(info.listeners),
+		]
+	}
+}
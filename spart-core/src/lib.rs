@@ -0,0 +1,54 @@
+//! Everything that goes into turning rows of data into the bars of a chart - loading it, checking
+//! its shape, filtering it, grouping it up, and labelling the groups - without any of the GUI, so
+//! that it can be used from other tools too.
+
+pub mod agents;
+pub mod bars;
+pub mod bins;
+pub mod brush;
+pub mod coloring;
+pub mod columns;
+#[cfg(feature = "enrich")]
+pub mod coverart;
+pub mod csv;
+pub mod diff;
+pub mod downsample;
+pub mod drill;
+#[cfg(feature = "enrich")]
+pub mod enrich;
+#[cfg(feature = "fs")]
+pub mod feed;
+pub mod filter;
+pub mod flatten;
+pub mod format;
+pub mod geo;
+pub mod index;
+pub mod ip;
+#[cfg(feature = "enrich")]
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod load;
+pub mod memory;
+#[cfg(feature = "enrich")]
+pub mod musicbrainz;
+pub mod outliers;
+pub mod periods;
+pub mod pipeline;
+pub mod profile;
+pub mod render;
+pub mod review;
+pub mod sample;
+pub mod schema;
+pub mod script;
+pub mod settings;
+pub mod share;
+pub mod song;
+#[cfg(feature = "sql")]
+pub mod sql;
+pub mod stats;
+#[cfg(feature = "fs")]
+pub mod stream;
+pub mod suggest;
+pub mod trend;
+pub mod urls;
+pub mod worker;
@@ -0,0 +1,188 @@
+//! [ListenBrainz](https://listenbrainz.org)'s listen histories, which can be loaded like any
+//! other data (ending up with the same keys that [`song`] gives Spotify's exports), and which rows
+//! can be written back out as to import them into ListenBrainz.
+
+use chrono::DateTime;
+use merde::{CowStr, Map, Value, json::from_str};
+
+use crate::{
+	load::{DataSource, FormatOptions, Progress},
+	song
+};
+
+/// How much of the start of a file is looked at to see if it's a ListenBrainz export
+const SNIFF_LEN: usize = 4096;
+
+/// What listens say they were submitted by when they're exported
+const SUBMISSION_CLIENT: &str = "spart";
+
+pub struct ListenBrainz;
+
+impl DataSource for ListenBrainz {
+	fn name(&self) -> &'static str {
+		"listenbrainz"
+	}
+
+	// Exports are just JSON (or JSON lines), so they can only be told apart by what's in them
+	fn extensions(&self) -> &'static [&'static str] {
+		&[]
+	}
+
+	fn sniff(&self, start: &str) -> bool {
+		looks_like_listens(start)
+	}
+
+	fn recognizes(&self, start: &str) -> bool {
+		looks_like_listens(start)
+	}
+
+	fn parse<'s>(
+		&self,
+		contents: &'s str,
+		_: &FormatOptions,
+		_: &mut dyn FnMut(Progress)
+	) -> Result<Vec<Map<'s>>, String> {
+		// Older exports are a single array, and newer ones have a listen on each line
+		let listens = if contents.trim_start().starts_with('[') {
+			from_str::<Vec<Map>>(contents).map_err(|e| e.to_string())?
+		} else {
+			contents
+				.lines()
+				.enumerate()
+				.filter(|(_, line)| !line.trim().is_empty())
+				.map(|(idx, line)| {
+					from_str::<Map>(line).map_err(|e| format!("line {}: {e}", idx + 1))
+				})
+				.collect::<Result<_, _>>()?
+		};
+
+		listens.iter().map(to_row).collect()
+	}
+}
+
+fn looks_like_listens(start: &str) -> bool {
+	let end = start
+		.char_indices()
+		.nth(SNIFF_LEN)
+		.map_or(start.len(), |(idx, _)| idx);
+	let start = &start[..end];
+
+	matches!(start.trim_start().chars().next(), Some('[' | '{'))
+		&& start.contains("\"listened_at\"")
+		&& start.contains("\"track_metadata\"")
+}
+
+fn field<'m>(map: &'m Map, key: &'static str) -> Option<&'m Value<'m>> {
+	map.get(&CowStr::from(key))
+}
+
+fn map_field<'m>(map: &'m Map, key: &'static str) -> Option<&'m Map<'m>> {
+	match field(map, key) {
+		Some(Value::Map(inner)) => Some(inner),
+		_ => None
+	}
+}
+
+fn string_field(map: Option<&Map>, key: &'static str) -> Value<'static> {
+	match map.and_then(|map| field(map, key)) {
+		Some(Value::Str(s)) => Value::Str(s.to_string().into()),
+		_ => Value::Null
+	}
+}
+
+fn number_field(map: Option<&Map>, key: &'static str) -> Option<f64> {
+	match map.and_then(|map| field(map, key))? {
+		Value::I64(i) => Some(*i as f64),
+		Value::U64(u) => Some(*u as f64),
+		Value::Float(f) => Some(f.into_inner()),
+		_ => None
+	}
+}
+
+/// Flattens a listen into the keys that the song module uses
+fn to_row(listen: &Map) -> Result<Map<'static>, String> {
+	let listened_at = number_field(Some(listen), "listened_at")
+		.and_then(|secs| DateTime::from_timestamp(secs as i64, 0))
+		.ok_or("A listen doesn't say when it was listened to")?;
+
+	let metadata = map_field(listen, "track_metadata");
+	let info = metadata.and_then(|metadata| map_field(metadata, "additional_info"));
+
+	// ListenBrainz doesn't know how long anything was actually played for, so the length of the
+	// track is the best there is
+	let ms_played = number_field(info, "duration_ms")
+		.or_else(|| number_field(info, "duration").map(|secs| secs * 1000.))
+		.map_or(Value::Null, |ms| Value::U64(ms as u64));
+
+	let recording_mbid = match string_field(info, "recording_mbid") {
+		Value::Null => string_field(map_field(listen, "mbid_mapping"), "recording_mbid"),
+		mbid => mbid
+	};
+
+	Ok(Map::new()
+		.with(
+			song::TIMESTAMP,
+			listened_at.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+		)
+		.with(song::ARTIST, string_field(metadata, "artist_name"))
+		.with(song::TRACK, string_field(metadata, "track_name"))
+		.with(song::ALBUM, string_field(metadata, "release_name"))
+		.with(song::MS_PLAYED, ms_played)
+		.with("recording_mbid", recording_mbid)
+		.with("username", string_field(Some(listen), "user_name")))
+}
+
+/// Writes `rows` out as a ListenBrainz export (a JSON array of listens), which can be imported
+/// into it. Rows that don't say when they were played and what was played are left out, and how
+/// many were is returned along with the export.
+pub fn export<'r>(
+	rows: impl IntoIterator<Item = &'r Map<'static>>
+) -> Result<(String, usize), String> {
+	let mut skipped = 0;
+	let listens = rows
+		.into_iter()
+		.filter_map(|row| {
+			let listen = to_listen(row);
+			skipped += usize::from(listen.is_none());
+			listen
+		})
+		.collect::<Vec<_>>();
+
+	let json = merde::json::to_string(&Value::from(listens)).map_err(|e| e.to_string())?;
+	Ok((json, skipped))
+}
+
+fn to_listen(row: &Map<'static>) -> Option<Value<'static>> {
+	let listened_at = match field(row, song::TIMESTAMP)? {
+		Value::Str(ts) => DateTime::parse_from_rfc3339(ts).ok()?.timestamp(),
+		_ => return None
+	};
+	let artist = string_field(Some(row), song::ARTIST);
+	let track = string_field(Some(row), song::TRACK);
+	if artist == Value::Null || track == Value::Null {
+		return None;
+	}
+
+	let mut info = Map::new().with("submission_client", SUBMISSION_CLIENT);
+	if let Some(ms) = number_field(Some(row), song::MS_PLAYED) {
+		info.insert("duration_ms".into(), Value::U64(ms as u64));
+	}
+	if let mbid @ Value::Str(_) = string_field(Some(row), "recording_mbid") {
+		info.insert("recording_mbid".into(), mbid);
+	}
+
+	let mut metadata = Map::new()
+		.with("artist_name", artist)
+		.with("track_name", track)
+		.with("additional_info", info);
+	if let album @ Value::Str(_) = string_field(Some(row), song::ALBUM) {
+		metadata.insert("release_name".into(), album);
+	}
+
+	Some(
+		Map::new()
+			.with("listened_at", Value::I64(listened_at))
+			.with("track_metadata", metadata)
+			.into()
+	)
+}
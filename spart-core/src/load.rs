@@ -0,0 +1,418 @@
+#[cfg(feature = "fs")]
+use std::{io::Read, time::SystemTime};
+use std::{path::Path, str::FromStr, sync::RwLock, time::Duration};
+
+use merde::{IntoStatic, json::from_str};
+use serde::Deserialize;
+
+#[cfg(feature = "fs")]
+use crate::memory::{Rows, Text};
+use crate::{
+	csv::{Csv, CsvOptions},
+	flatten,
+	listenbrainz::ListenBrainz,
+	song
+};
+
+/// Something that data can be loaded from. There's a built-in one for each format that spart
+/// knows about, and more can be added with [`register`] so that other tools can teach it about
+/// their own formats.
+pub trait DataSource: Send + Sync {
+	/// What it's called with `--format` (and in errors)
+	fn name(&self) -> &'static str;
+
+	/// The file extensions that are always in this format, without the leading `.`
+	fn extensions(&self) -> &'static [&'static str];
+
+	/// If `start` (the beginning of a file) looks like it's in this format. Only used when the
+	/// file's extension doesn't say what it is.
+	fn sniff(&self, start: &str) -> bool;
+
+	/// If `start` is definitely in this format, even if the file's extension says it's in some
+	/// other one. This is for formats that are a particular shape of some more general format
+	/// (like JSON), so that they aren't just loaded as that.
+	fn recognizes(&self, _start: &str) -> bool {
+		false
+	}
+
+	/// Turns `contents` into rows, borrowing any strings that it can. Call `progress` every so
+	/// often if it's easy to tell how far along parsing is.
+	fn parse<'s>(
+		&self,
+		contents: &'s str,
+		options: &FormatOptions,
+		progress: &mut dyn FnMut(Progress)
+	) -> Result<Vec<merde::Map<'s>>, String>;
+}
+
+/// What the formats that can be laid out more than one way are told about how their files are,
+/// which is the `[formats]` table of the config. Formats without any options just ignore it.
+#[derive(Clone, Default, PartialEq, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatOptions {
+	pub csv: CsvOptions
+}
+
+/// How rows are read out of text, besides what format it's in
+#[derive(Clone, PartialEq, Debug)]
+pub struct LoadOptions {
+	pub formats: FormatOptions,
+	/// Arrays with at most this many elements are exploded into a key for each of them (see
+	/// [`flatten::flatten`])
+	pub explode_limit: usize,
+	/// Plays shorter than this count as skips (see [`song::derive_keys`])
+	pub skip_threshold: Duration
+}
+
+impl Default for LoadOptions {
+	fn default() -> Self {
+		Self {
+			formats: FormatOptions::default(),
+			explode_limit: 0,
+			skip_threshold: song::DEFAULT_SKIP_THRESHOLD
+		}
+	}
+}
+
+impl LoadOptions {
+	/// Gets a row that was just parsed ready to be charted: flattened, with whatever keys can be
+	/// worked out from the others added
+	pub fn prepare(&self, row: &mut merde::Map) {
+		flatten::flatten(row, self.explode_limit);
+		song::derive_keys(row, self.skip_threshold);
+	}
+}
+
+/// How far through a file parsing has gotten
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+	pub bytes_done: usize,
+	pub bytes_total: usize
+}
+
+/// A single JSON array of objects
+pub struct Json;
+
+impl DataSource for Json {
+	fn name(&self) -> &'static str {
+		"json"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["json"]
+	}
+
+	fn sniff(&self, start: &str) -> bool {
+		start.trim_start().starts_with('[')
+	}
+
+	fn parse<'s>(
+		&self,
+		contents: &'s str,
+		_: &FormatOptions,
+		_: &mut dyn FnMut(Progress)
+	) -> Result<Vec<merde::Map<'s>>, String> {
+		// merde doesn't let us see how far it's gotten, so there's no progress to report
+		from_str(contents).map_err(|e| e.to_string())
+	}
+}
+
+/// How many lines of JSON lines are parsed between each time progress is reported
+const PROGRESS_LINES: usize = 10_000;
+
+/// A flat object on each line (also called NDJSON), like a lot of logs are. Blank lines are
+/// skipped.
+pub struct JsonLines;
+
+impl DataSource for JsonLines {
+	fn name(&self) -> &'static str {
+		"ndjson"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["ndjson", "jsonl"]
+	}
+
+	fn sniff(&self, start: &str) -> bool {
+		start.trim_start().starts_with('{')
+	}
+
+	fn parse<'s>(
+		&self,
+		contents: &'s str,
+		_: &FormatOptions,
+		progress: &mut dyn FnMut(Progress)
+	) -> Result<Vec<merde::Map<'s>>, String> {
+		let mut rows = Vec::new();
+		for (idx, line) in contents.lines().enumerate() {
+			if line.trim().is_empty() {
+				continue;
+			}
+			rows.push(from_str(line).map_err(|e| format!("line {}: {e}", idx + 1))?);
+
+			if rows.len() % PROGRESS_LINES == 0 {
+				progress(Progress {
+					// Every line points into `contents`, so this is how far into it the line is
+					bytes_done: line.as_ptr() as usize - contents.as_ptr() as usize,
+					bytes_total: contents.len()
+				});
+			}
+		}
+		Ok(rows)
+	}
+}
+
+/// The formats that have been added with [`register`]
+static REGISTERED: RwLock<Vec<Format>> = RwLock::new(Vec::new());
+
+/// Makes `source` available to everything that loads data, both with `--format` and when
+/// detecting what format a file's in. Formats registered later take precedence over the ones
+/// before them (including the built-in ones), so this can also be used to replace one.
+pub fn register(source: &'static dyn DataSource) {
+	REGISTERED
+		.write()
+		.unwrap_or_else(|e| e.into_inner())
+		.push(Format(source));
+}
+
+/// One of the [`DataSource`]s that's available to load data with
+#[derive(Clone, Copy)]
+pub struct Format(pub &'static dyn DataSource);
+
+impl Format {
+	pub const JSON: Self = Self(&Json);
+	pub const LISTENBRAINZ: Self = Self(&ListenBrainz);
+	pub const NDJSON: Self = Self(&JsonLines);
+	pub const CSV: Self = Self(&Csv);
+
+	/// Every format, in the order they're checked when detecting one
+	pub fn all() -> Vec<Self> {
+		let registered = REGISTERED.read().unwrap_or_else(|e| e.into_inner());
+		registered
+			.iter()
+			.rev()
+			.copied()
+			.chain([Self::LISTENBRAINZ, Self::JSON, Self::NDJSON, Self::CSV])
+			.collect()
+	}
+
+	pub fn name(self) -> &'static str {
+		self.0.name()
+	}
+
+	/// Guesses the format of a file from its extension, falling back to looking at its contents if
+	/// the extension doesn't tell us anything. A format that [recognizes](DataSource::recognizes)
+	/// the contents wins over either, though.
+	pub fn detect(path: &str, contents: &str) -> Option<Self> {
+		let formats = Self::all();
+		let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+
+		let by_extension = extension.and_then(|extension| {
+			formats.iter().copied().find(|format| {
+				format
+					.0
+					.extensions()
+					.iter()
+					.any(|ext| ext.eq_ignore_ascii_case(extension))
+			})
+		});
+		formats
+			.iter()
+			.copied()
+			.find(|format| format.0.recognizes(contents))
+			.or(by_extension)
+			.or_else(|| formats.into_iter().find(|format| format.0.sniff(contents)))
+	}
+
+	fn parse<'s>(
+		self,
+		path: &str,
+		contents: &'s str,
+		options: &LoadOptions,
+		progress: &mut dyn FnMut(Progress)
+	) -> Result<Vec<merde::Map<'s>>, LoadErr> {
+		let mut rows = self
+			.0
+			.parse(contents, &options.formats, progress)
+			.map_err(|message| LoadErr::Parse {
+				path: path.to_string(),
+				format: self,
+				message
+			})?;
+		for row in &mut rows {
+			options.prepare(row);
+		}
+		Ok(rows)
+	}
+}
+
+impl PartialEq for Format {
+	fn eq(&self, other: &Self) -> bool {
+		self.name() == other.name()
+	}
+}
+
+impl std::fmt::Debug for Format {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadErr {
+	#[error("Couldn't read '{path}': {source}")]
+	Io {
+		path: String,
+		source: std::io::Error
+	},
+	#[error("Couldn't parse '{path}' as {format:?}: {message}")]
+	Parse {
+		path: String,
+		format: Format,
+		message: String
+	},
+	#[error("Couldn't tell what format '{0}' is in; try passing --format")]
+	Undetectable(String),
+	#[error("Unknown format '{0}' (supported formats are: {supported})", supported = supported())]
+	UnknownFormat(String)
+}
+
+fn supported() -> String {
+	Format::all()
+		.into_iter()
+		.map(Format::name)
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+impl FromStr for Format {
+	type Err = LoadErr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::all()
+			.into_iter()
+			.find(|format| format.name().eq_ignore_ascii_case(s))
+			.ok_or_else(|| LoadErr::UnknownFormat(s.to_string()))
+	}
+}
+
+/// What happens to the text of each file once it's been parsed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Strings {
+	/// Every string is copied out of it, so that it can be freed
+	Copy,
+	/// It's kept with the rows and the strings just point into it, which takes a lot less memory
+	/// when the data is mostly strings. The whole file sticks around for as long as any of the rows
+	/// do, though, even if most of it was left out of them.
+	Borrow
+}
+
+/// Parses text that's already been read in from somewhere else (e.g. a file the user dropped on
+/// the window), with `name` standing in for its path when detecting its format or reporting errors
+pub fn load_text(
+	name: &str,
+	contents: &str,
+	format: Option<Format>,
+	options: &LoadOptions
+) -> Result<Vec<merde::Map<'static>>, LoadErr> {
+	let format = format
+		.or_else(|| Format::detect(name, contents))
+		.ok_or_else(|| LoadErr::Undetectable(name.to_string()))?;
+	Ok(format
+		.parse(name, contents, options, &mut |_| ())?
+		.into_static())
+}
+
+/// Reads and parses every file in `paths` (where `-` means stdin), concatenating all their rows.
+/// If `format` is given, it's used for every file instead of trying to detect each one's format.
+/// `progress` is told how far along each file is, for formats that can tell.
+#[cfg(feature = "fs")]
+pub fn load_files(
+	paths: &[String],
+	format: Option<Format>,
+	strings: Strings,
+	options: &LoadOptions,
+	progress: &mut dyn FnMut(&str, Progress)
+) -> Result<Rows, LoadErr> {
+	let mut rows = Rows::default();
+
+	for path in paths {
+		let io_err = |source| LoadErr::Io {
+			path: path.clone(),
+			source
+		};
+
+		let contents = if path == "-" {
+			let mut contents = String::new();
+			std::io::stdin()
+				.read_to_string(&mut contents)
+				.map_err(io_err)?;
+			contents
+		} else {
+			std::fs::read_to_string(path).map_err(io_err)?
+		};
+
+		let format = format
+			.or_else(|| Format::detect(path, &contents))
+			.ok_or_else(|| LoadErr::Undetectable(path.clone()))?;
+
+		let mut progress = |p| progress(path, p);
+		match strings {
+			Strings::Copy => rows.extend(
+				format
+					.parse(path, &contents, options, &mut progress)?
+					.into_static()
+			),
+			Strings::Borrow => {
+				let text = Text::new(contents);
+				// SAFETY: The rows that borrow from the text are kept with it
+				let parsed =
+					format.parse(path, unsafe { text.extend() }, options, &mut progress)?;
+				rows.append(Rows::borrowing(parsed, text));
+			}
+		}
+	}
+
+	Ok(rows)
+}
+
+/// Where some data was loaded from, so that it can be loaded again if it changes
+#[cfg(feature = "fs")]
+#[derive(Clone)]
+pub struct Source {
+	pub paths: Vec<String>,
+	pub format: Option<Format>,
+	pub options: LoadOptions
+}
+
+#[cfg(feature = "fs")]
+impl Source {
+	/// Returns `None` if there are no paths, or any of them can't be read a second time (i.e.
+	/// stdin)
+	pub fn new(paths: Vec<String>, format: Option<Format>, options: LoadOptions) -> Option<Self> {
+		let rereadable = !paths.is_empty() && !paths.iter().any(|p| p == "-");
+		rereadable.then_some(Self {
+			paths,
+			format,
+			options
+		})
+	}
+
+	pub fn load(&self) -> Result<Rows, LoadErr> {
+		load_files(
+			&self.paths,
+			self.format,
+			Strings::Copy,
+			&self.options,
+			&mut |_, _| ()
+		)
+	}
+
+	/// When each file was last modified, or `None` for any we can't tell about
+	pub fn modified_times(&self) -> Vec<Option<SystemTime>> {
+		self.paths
+			.iter()
+			.map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+			.collect()
+	}
+}
@@ -0,0 +1,212 @@
+//! Keeping an eye on how much memory the data is taking up, and squeezing it back down when it's
+//! gotten out of hand.
+
+use std::{
+	collections::HashMap,
+	ops::{Deref, DerefMut, Range},
+	sync::Arc
+};
+
+use fxhash::FxHashMap;
+use merde::{CowStr, Value};
+use rayon::prelude::*;
+
+/// Rows whose strings can point into text that's kept alongside them, so that it's freed along
+/// with the last of them instead of having to be leaked to be borrowed from. Each row derefs to a
+/// `Map<'static>`, but that's only true for as long as the `Rows` are around: anything that takes
+/// rows out of them has to copy their strings (with `into_static`) or keep the text too.
+#[derive(Clone, Default)]
+pub struct Rows {
+	rows: Vec<merde::Map<'static>>,
+	// This is after `rows` so that it's dropped after them
+	text: Vec<Text>
+}
+
+/// Some text that's never changed once it's made, so that strings can point into it for as long
+/// as it's held onto
+#[derive(Clone)]
+pub struct Text(Arc<str>);
+
+impl Text {
+	pub fn new(text: String) -> Self {
+		Self(text.into())
+	}
+
+	/// The text, 'til the last clone of `self` is dropped
+	///
+	/// # Safety
+	///
+	/// Whatever is borrowed from the returned string mustn't be used after every clone of `self`
+	/// is gone.
+	pub unsafe fn extend(&self) -> &'static str {
+		// SAFETY: The text never moves or changes while it's in the `Arc`, and it's up to the
+		// caller to keep the `Arc` alive for as long as the string is used.
+		unsafe { &*std::ptr::from_ref::<str>(&self.0) }
+	}
+}
+
+impl Rows {
+	/// Rows whose strings might point into `text`
+	pub fn borrowing(rows: Vec<merde::Map<'static>>, text: Text) -> Self {
+		Self {
+			rows,
+			text: vec![text]
+		}
+	}
+
+	/// Adds `other`'s rows after these, keeping whatever they borrow from around too
+	pub fn append(&mut self, other: Self) {
+		self.rows.extend(other.rows);
+		self.text.extend(other.text);
+	}
+
+	/// All the text that the strings in these rows might point into, which can be held onto to
+	/// use anything borrowed from them after they're gone
+	pub fn text(&self) -> &[Text] {
+		&self.text
+	}
+
+	/// Roughly how many bytes these take up, counting the text that they borrow from just once
+	pub fn size(&self) -> usize {
+		estimate(&self.rows) + self.text.iter().map(|text| text.0.len()).sum::<usize>()
+	}
+}
+
+impl From<Vec<merde::Map<'static>>> for Rows {
+	fn from(rows: Vec<merde::Map<'static>>) -> Self {
+		Self {
+			rows,
+			text: Vec::new()
+		}
+	}
+}
+
+impl Deref for Rows {
+	type Target = Vec<merde::Map<'static>>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.rows
+	}
+}
+
+impl DerefMut for Rows {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.rows
+	}
+}
+
+/// Roughly how many bytes `data` takes up. Strings that are borrowed aren't counted, since they're
+/// wherever they're borrowed from (see [`Rows::size`]).
+pub fn estimate(data: &[merde::Map]) -> usize {
+	// Each entry in a map takes up space for its key and value, plus a control byte
+	const PER_ENTRY: usize = size_of::<CowStr>() + size_of::<Value>() + 1;
+
+	let rows = data
+		.par_iter()
+		.map(|row| {
+			let strings = row
+				.iter()
+				.map(|(key, val)| {
+					heap_size(key)
+						+ match val {
+							Value::Str(s) => heap_size(s),
+							Value::Bytes(b) => b.len(),
+							_ => 0
+						}
+				})
+				.sum::<usize>();
+			row.capacity() * PER_ENTRY + strings
+		})
+		.sum::<usize>();
+
+	size_of_val(data) + rows
+}
+
+/// How much a string has allocated outside of itself. Short ones are stored inline, and borrowed
+/// ones are counted wherever they're borrowed from.
+fn heap_size(s: &CowStr) -> usize {
+	match s {
+		CowStr::Owned(s) if s.is_heap_allocated() => s.capacity(),
+		_ => 0
+	}
+}
+
+/// A copy of `data` with only the keys that `keep` returns true for, where every string is
+/// stored just once, in text of its own that all the rows that have it point into, and every map
+/// is only as big as it needs to be. Nothing in the new rows points into `data`'s text, so it can
+/// be freed once `data` is.
+pub fn compact(data: &Rows, keep: impl Fn(&str) -> bool) -> Rows {
+	// Every distinct string goes into one big string first, so that there's just the one
+	// allocation, and then the rows are made to point into it
+	let mut interned = String::new();
+	let mut ranges = FxHashMap::<&str, Range<usize>>::default();
+	for row in data.iter() {
+		for (key, val) in row.iter().filter(|(key, _)| keep(key)) {
+			let val = match val {
+				Value::Str(s) => Some(&**s),
+				_ => None
+			};
+			for s in std::iter::once(&**key).chain(val) {
+				ranges.entry(s).or_insert_with(|| {
+					interned.push_str(s);
+					interned.len() - s.len()..interned.len()
+				});
+			}
+		}
+	}
+
+	let text = Text::new(interned);
+	// SAFETY: The rows that borrow from this are kept with `text`
+	let all = unsafe { text.extend() };
+	let point = |s: &CowStr<'static>| CowStr::Borrowed(&all[ranges[&**s].clone()]);
+
+	let rows = data
+		.iter()
+		.map(|row| {
+			let mut map = HashMap::with_capacity(row.keys().filter(|key| keep(key)).count());
+			for (key, val) in row.iter().filter(|(key, _)| keep(key)) {
+				let val = match val {
+					Value::Str(s) => Value::Str(point(s)),
+					val => val.clone()
+				};
+				map.insert(point(key), val);
+			}
+			merde::Map(map)
+		})
+		.collect();
+	Rows::borrowing(rows, text)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rows(json: &str) -> Rows {
+		let rows: Vec<merde::Map> = merde::json::from_str(json).unwrap();
+		merde::IntoStatic::into_static(rows).into()
+	}
+
+	#[test]
+	fn compacting() {
+		let data = rows(
+			r#"[{"artist": "a very long artist name indeed", "plays": 1, "note": "x"},
+			{"artist": "a very long artist name indeed", "plays": 2, "note": "y"}]"#
+		);
+		let compacted = compact(&data, |key| key != "note");
+		drop(data);
+
+		assert_eq!(compacted.len(), 2);
+		assert!(compacted.iter().all(|row| row.len() == 2));
+		let artist = CowStr::from("artist");
+		assert_eq!(
+			compacted[1].get(&artist),
+			Some(&Value::Str("a very long artist name indeed".into()))
+		);
+		let plays = CowStr::from("plays");
+		assert_eq!(compacted[1].get(&plays), Some(&Value::I64(2)));
+
+		// Each distinct string is in the text once: "artist", "plays", and the artist's name
+		let text = "artistplaysa very long artist name indeed".len();
+		assert_eq!(compacted.size(), estimate(&compacted) + text);
+	}
+}
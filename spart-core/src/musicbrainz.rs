@@ -0,0 +1,163 @@
+//! Looking up artists on [MusicBrainz](https://musicbrainz.org/doc/MusicBrainz_API), for their
+//! genres and where they're from. Spotify's exports only name each artist, so this first has to
+//! search for the artist to find their MBID (MusicBrainz's ID for them), and then look that up.
+
+use std::{thread, time::Duration};
+
+use merde::Value;
+use serde::{Deserialize, Serialize};
+use ureq::Agent;
+
+use crate::enrich::{self, EnrichErr, Lookup};
+
+const API_URL: &str = "https://musicbrainz.org/ws/2";
+
+/// How sure the search has to be (out of 100) that it's found the artist we asked about, since
+/// it'll happily return someone with a vaguely similar name otherwise
+const MIN_SCORE: u64 = 90;
+
+/// How many of an artist's genres are kept, since the rest are usually noise
+const MAX_GENRES: usize = 5;
+
+#[derive(Clone)]
+pub struct MusicBrainz {
+	agent: Agent
+}
+
+/// What MusicBrainz knows about an artist
+#[derive(Serialize, Deserialize)]
+pub struct ArtistInfo {
+	pub mbid: String,
+	/// Most voted-for first
+	pub genres: Vec<String>,
+	/// As an ISO 3166-1 code, like `US`
+	pub country: Option<String>
+}
+
+impl MusicBrainz {
+	pub fn new() -> Self {
+		Self {
+			agent: enrich::agent()
+		}
+	}
+
+	/// The MBID of the release group (all the editions of an album) called `album` by `artist`,
+	/// if there's one that's a close enough match
+	pub fn release_group(&self, artist: &str, album: &str) -> Result<Option<String>, EnrichErr> {
+		let found = self.get(&format!("{API_URL}/release-group"), &[
+			(
+				"query",
+				&format!(
+					"releasegroup:{} AND artist:{}",
+					phrase(album),
+					phrase(artist)
+				)
+			),
+			("limit", "1")
+		])?;
+
+		let best = &found["release-groups"][0];
+		Ok(best["id"]
+			.as_str()
+			.filter(|_| {
+				best["score"]
+					.as_u64()
+					.is_some_and(|score| score >= MIN_SCORE)
+			})
+			.map(str::to_string))
+	}
+
+	fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<serde_json::Value, EnrichErr> {
+		let request_err = |e: ureq::Error| EnrichErr::Request {
+			service: Self::NAME,
+			message: e.to_string()
+		};
+		let body = self
+			.agent
+			.get(url)
+			.query_pairs(query.iter().copied().chain([("fmt", "json")]))
+			.call()
+			.map_err(request_err)?
+			.body_mut()
+			.read_json::<serde_json::Value>()
+			.map_err(request_err)?;
+
+		match body["error"].as_str() {
+			Some(message) => Err(EnrichErr::Service {
+				service: Self::NAME,
+				message: message.to_string()
+			}),
+			None => Ok(body)
+		}
+	}
+}
+
+/// Quotes `s` so that it's searched for as-is
+fn phrase(s: &str) -> String {
+	format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Default for MusicBrainz {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Lookup for MusicBrainz {
+	type Answer = ArtistInfo;
+
+	const NAME: &'static str = "musicbrainz";
+	// MusicBrainz asks for no more than one request a second
+	const INTERVAL: Duration = Duration::from_secs(1);
+	const KEYS: &'static [&'static str] = &["mb_id", "mb_genre", "mb_genres", "mb_country"];
+
+	fn look_up(&self, artist: &str) -> Result<Option<ArtistInfo>, EnrichErr> {
+		let found = self.get(&format!("{API_URL}/artist"), &[
+			("query", &format!("artist:{}", phrase(artist))),
+			("limit", "1")
+		])?;
+
+		let best = &found["artists"][0];
+		let (Some(mbid), Some(score)) = (best["id"].as_str(), best["score"].as_u64()) else {
+			return Ok(None);
+		};
+		if score < MIN_SCORE {
+			return Ok(None);
+		}
+
+		// Searching is one request and looking them up is another, so this has to wait too
+		thread::sleep(Self::INTERVAL);
+		let details = self.get(&format!("{API_URL}/artist/{mbid}"), &[("inc", "genres")])?;
+
+		let mut genres = details["genres"]
+			.as_array()
+			.into_iter()
+			.flatten()
+			.filter_map(|genre| Some((genre["name"].as_str()?, genre["count"].as_u64()?)))
+			.collect::<Vec<_>>();
+		genres.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+		Ok(Some(ArtistInfo {
+			mbid: mbid.to_string(),
+			genres: genres
+				.into_iter()
+				.take(MAX_GENRES)
+				.map(|(name, _)| name.to_string())
+				.collect(),
+			country: details["country"].as_str().map(str::to_string)
+		}))
+	}
+
+	fn values(info: &ArtistInfo) -> Vec<Value<'static>> {
+		vec![
+			Value::Str(info.mbid.clone().into()),
+			info.genres
+				.first()
+				.map_or(Value::Null, |genre| Value::Str(genre.clone().into())),
+			Value::Str(info.genres.join(", ").into()),
+			info.country
+				.as_ref()
+				.map_or(Value::Null, |country| Value::Str(country.clone().into())),
+		]
+	}
+}
@@ -0,0 +1,133 @@
+//! Finding the values of numeric keys that are far enough from the rest that they're probably
+//! mistakes (or at least worth a second look), like a track that was apparently played for a
+//! whole week. Bars with rows like that in them are flagged, and the rows can be bounded out.
+
+use merde::{CowStr, Value, ValueType};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{Bound, Nans, ValueBound};
+
+/// How many interquartile ranges past the quartiles a value has to be to be an outlier, which is
+/// the usual for box plots
+const IQR_FACTOR: f64 = 1.5;
+
+/// How many standard deviations from the mean a value has to be to be an outlier
+const Z_LIMIT: f64 = 3.;
+
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutlierTest {
+	/// Outside 1.5 interquartile ranges of the middle half of the values, which isn't thrown off
+	/// by the outliers themselves
+	#[default]
+	Iqr,
+	/// More than 3 standard deviations from the mean, which suits values that are roughly
+	/// normally distributed
+	ZScore
+}
+
+/// The range of values that aren't outliers, inclusive on both ends
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fences {
+	pub low: f64,
+	pub high: f64
+}
+
+/// The outliers of a single key
+pub struct Outliers {
+	pub fences: Fences,
+	/// How many rows have values outside of the fences
+	pub rows: usize
+}
+
+impl OutlierTest {
+	pub const ALL: [Self; 2] = [Self::Iqr, Self::ZScore];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Iqr => "IQR",
+			Self::ZScore => "z-score"
+		}
+	}
+
+	/// Where the outliers of `key` start, if it has any numeric values to go by
+	pub fn fences(self, data: &[merde::Map], key: &str) -> Option<Fences> {
+		let key = CowStr::from(key);
+		let mut values = data
+			.iter()
+			.filter_map(|row| number(row.get(&key)?))
+			.filter(|val| !val.is_nan())
+			.map(OrderedFloat)
+			.collect::<Vec<_>>();
+		if values.is_empty() {
+			return None;
+		}
+
+		Some(match self {
+			Self::Iqr => {
+				values.sort_unstable();
+				let quartile = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize].0;
+				let (q1, q3) = (quartile(0.25), quartile(0.75));
+				let spread = (q3 - q1) * IQR_FACTOR;
+				Fences {
+					low: q1 - spread,
+					high: q3 + spread
+				}
+			}
+			Self::ZScore => {
+				let count = values.len() as f64;
+				let mean = values.iter().map(|val| val.0).sum::<f64>() / count;
+				let variance = values.iter().map(|val| (val.0 - mean).powi(2)).sum::<f64>() / count;
+				let spread = variance.sqrt() * Z_LIMIT;
+				Fences {
+					low: mean - spread,
+					high: mean + spread
+				}
+			}
+		})
+	}
+
+	/// The outliers of `key`, if it has any numeric values to go by
+	pub fn find(self, data: &[merde::Map], key: &str) -> Option<Outliers> {
+		let fences = self.fences(data, key)?;
+		let key = CowStr::from(key);
+		let rows = data
+			.iter()
+			.filter(|row| row.get(&key).is_some_and(|val| fences.is_outlier(val)))
+			.count();
+		Some(Outliers { fences, rows })
+	}
+}
+
+impl Fences {
+	/// Whether `value` is a number outside of the fences
+	pub fn is_outlier(&self, value: &Value) -> bool {
+		number(value).is_some_and(|val| val < self.low || val > self.high)
+	}
+
+	/// A bound that only lets through values of a key of type `ty` that are inside the fences
+	pub fn bound(&self, ty: ValueType) -> Option<ValueBound> {
+		Some(match ty {
+			ValueType::I64 => ValueBound::I64(Bound::Range(
+				self.low.ceil() as i64..(self.high.floor() as i64).saturating_add(1)
+			)),
+			ValueType::U64 => ValueBound::U64(Bound::Range(
+				self.low.max(0.).ceil() as u64..(self.high.floor() as u64).saturating_add(1)
+			)),
+			// Ranges leave out their end, so it has to be just past the high fence
+			ValueType::Float =>
+				ValueBound::F64(Bound::Range(self.low..self.high.next_up()), Nans::Exclude),
+			_ => return None
+		})
+	}
+}
+
+fn number(value: &Value) -> Option<f64> {
+	match value {
+		Value::I64(i) => Some(*i as f64),
+		Value::U64(u) => Some(*u as f64),
+		Value::Float(f) => Some(f.into_inner()),
+		_ => None
+	}
+}
@@ -0,0 +1,154 @@
+//! Comparing each bar's latest period of time (like its latest week or month) against the period
+//! before it, for week-over-week or month-over-month charts. The rows are grouped by which period
+//! their timestamp falls in on top of the x-axis keys, and then the groups of the two periods are
+//! pulled apart into a pair of bars for each category, just like when bounds are being compared.
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use merde::{CowStr, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{bars::Groups, format::format_timestamp, settings::Settings};
+
+/// How long each of the periods being compared is
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+	Day,
+	/// From Monday to Sunday, numbered like ISO 8601 weeks are
+	Week,
+	Month,
+	Year
+}
+
+impl Period {
+	pub const ALL: [Self; 4] = [Self::Day, Self::Week, Self::Month, Self::Year];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Day => "Day over day",
+			Self::Week => "Week over week",
+			Self::Month => "Month over month",
+			Self::Year => "Year over year"
+		}
+	}
+
+	/// The date format that names the period a timestamp is in. These all sort the same way as
+	/// the periods they name.
+	fn date_format(self) -> &'static str {
+		match self {
+			Self::Day => "%Y-%m-%d",
+			Self::Week => "%G-W%V",
+			Self::Month => "%Y-%m",
+			Self::Year => "%Y"
+		}
+	}
+
+	/// The name of the period right before the one named `label`, if it's the name of one
+	fn before(self, label: &str) -> Option<String> {
+		match self {
+			Self::Day => {
+				let day = NaiveDate::parse_from_str(label, self.date_format()).ok()?;
+				Some(day.pred_opt()?.format(self.date_format()).to_string())
+			}
+			Self::Week => {
+				let (year, week) = label.split_once("-W")?;
+				let monday = NaiveDate::from_isoywd_opt(
+					year.parse().ok()?,
+					week.parse().ok()?,
+					Weekday::Mon
+				)?;
+				let before = monday.checked_sub_days(Days::new(7))?;
+				Some(before.format(self.date_format()).to_string())
+			}
+			Self::Month => {
+				let first = NaiveDate::parse_from_str(&format!("{label}-01"), "%Y-%m-%d").ok()?;
+				let before = match first.month() {
+					1 => first.with_year(first.year() - 1)?.with_month(12)?,
+					month => first.with_month(month - 1)?
+				};
+				Some(before.format(self.date_format()).to_string())
+			}
+			Self::Year => Some((label.parse::<i32>().ok()? - 1).to_string())
+		}
+	}
+}
+
+impl std::str::FromStr for Period {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"day" => Ok(Self::Day),
+			"week" => Ok(Self::Week),
+			"month" => Ok(Self::Month),
+			"year" => Ok(Self::Year),
+			_ => Err(format!(
+				"Unknown period '{s}' (expected day, week, month, or year)"
+			))
+		}
+	}
+}
+
+/// Which periods of which key are being compared
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PeriodComparison {
+	/// The timestamp key whose periods the rows are split up by
+	pub key: String,
+	pub period: Period,
+	/// The name of the period that's compared against the one before it
+	pub latest: String
+}
+
+impl PeriodComparison {
+	/// Compares the latest period that any of the rows in `data` have a timestamp of `key` in
+	/// against the one before it, if any of them have one at all
+	pub fn latest(data: &[merde::Map<'static>], key: &str, period: Period) -> Option<Self> {
+		let key = CowStr::from(key);
+		let latest = data
+			.iter()
+			.filter_map(|row| format_timestamp(row.get(&key)?, period.date_format()))
+			.max()?;
+		Some(Self {
+			key: key.to_string(),
+			period,
+			latest
+		})
+	}
+
+	/// The name of the period that the latest one is compared against
+	pub fn previous(&self) -> String {
+		// Only a view that was shared with a mangled period could make this fail, and then
+		// there's just nothing to compare against
+		self.period.before(&self.latest).unwrap_or_default()
+	}
+
+	/// The settings that group rows by which period of the key they're in, on top of its x-axis
+	pub(crate) fn grouping<'s>(&self, settings: &Settings<'s>) -> Settings<'s> {
+		let mut grouping = settings.clone();
+		grouping.x_axis.push(self.key.clone());
+		grouping
+			.date_formats
+			.insert(self.key.clone(), self.period.date_format().to_string());
+		grouping
+	}
+
+	/// Pulls apart groups made with [`Self::grouping`] into those of the period before the latest
+	/// one and those of the latest one, without the period in them. Groups of any other period
+	/// are left out.
+	pub(crate) fn split(&self, groups: &Groups) -> (Groups, Groups) {
+		let previous = self.previous();
+		let (mut before, mut latest) = (Vec::new(), Vec::new());
+		for (group, tally) in groups {
+			let Some((Value::Str(label), rest)) = group.split_last() else {
+				continue;
+			};
+			let periods = match label.as_ref() {
+				label if label == self.latest => &mut latest,
+				label if label == previous => &mut before,
+				_ => continue
+			};
+			periods.push((rest.to_vec(), tally.clone()));
+		}
+		(before, latest)
+	}
+}
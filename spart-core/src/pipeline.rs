@@ -0,0 +1,322 @@
+//! Turning the data into bars happens in a few steps - filtering out the rows that aren't in
+//! bounds, grouping the rest up, and then labelling and ordering the groups - and each step only
+//! depends on some of the settings. [`Pipeline`] holds onto what each step came up with last time,
+//! so that e.g. changing a number format doesn't mean going through all the rows again.
+
+use fxhash::FxHashMap;
+use web_time::Instant;
+
+use crate::{
+	bars::{
+		Groups, LabeledBar, filter_rows, group_rows_progressively, keeps_values, label_groups,
+		make_bars, pair, sort_bars
+	},
+	bins::Bins,
+	brush::Brush,
+	columns::Columns,
+	index::Indexes,
+	outliers::OutlierTest,
+	profile::Profile,
+	sample::Sample,
+	settings::{Settings, ValueBound, YAxisKey}
+};
+
+#[derive(Default)]
+pub struct Pipeline {
+	/// The keys that have been pulled out into columns to make filtering on them faster
+	columns: Columns,
+	/// Which rows have each value of the keys that have been bounded to specific values
+	indexes: Indexes,
+	bars: Steps,
+	/// The steps for the bars that are being compared against, if there are any
+	compared: Steps,
+	/// How long each step took the last time the bars were made
+	profile: Profile
+}
+
+/// What each step came up with last time, for one set of bars
+#[derive(Default)]
+struct Steps {
+	/// What rows were last filtered with, and the indices of the rows that got through
+	filtered: Option<(Filtering, Vec<usize>)>,
+	/// How the rows that got through were last grouped, and the groups that came out of it
+	grouped: Option<(Grouping, Groups)>
+}
+
+/// The parts of the settings that decide which rows are kept
+#[derive(PartialEq)]
+struct Filtering {
+	bounds: FxHashMap<String, ValueBound>,
+	predicate: Option<String>,
+	brushes: Vec<Brush>,
+	/// What keeps the rows of the bars that have been drilled into
+	drilled: Option<Brush>,
+	sample: Option<Sample>,
+	/// The date formats and bins that stratified samples' categories are made with
+	date_formats: FxHashMap<String, String>,
+	bins: FxHashMap<String, Bins>
+}
+
+impl Filtering {
+	fn of(settings: &Settings) -> Self {
+		Self {
+			bounds: settings.bounds.clone(),
+			predicate: settings.predicate.as_ref().map(|p| p.source.clone()),
+			brushes: settings.brushes.clone(),
+			drilled: settings
+				.drill
+				.as_ref()
+				.and_then(|drill| drill.brush(settings)),
+			sample: settings.sample.clone(),
+			date_formats: match &settings.sample {
+				Some(_) => settings.date_formats.clone(),
+				None => FxHashMap::default()
+			},
+			bins: match &settings.sample {
+				Some(_) => settings.bins.clone(),
+				None => FxHashMap::default()
+			}
+		}
+	}
+}
+
+/// The parts of the settings that decide which group each row ends up in
+#[derive(PartialEq)]
+struct Grouping {
+	/// The x-axis keys, and then the one that the bars are stacked by if there is one
+	keys: Vec<String>,
+	/// If the last of `keys` is the one that the bars are stacked by, and not on the x-axis
+	stacked: bool,
+	/// The date format of each of `keys`, if it has one
+	date_formats: Vec<Option<String>>,
+	/// The bins of each of `keys`, if it's bucketed into them
+	bins: Vec<Option<Bins>>,
+	/// What each of `keys` is split up by, if it is
+	delimiters: Vec<Option<String>>,
+	/// The key whose values are added up in each group, if they aren't just counted. Rates are
+	/// worked out from the same totals, so they don't need regrouping.
+	y_axis: Option<String>,
+	/// If each group keeps all of its values (see [`keeps_values`]), which the rest of the
+	/// aggregations don't need
+	keeps_values: bool,
+	/// Which keys' outliers are counted up in each group, sorted by key
+	outliers: Vec<(String, OutlierTest)>
+}
+
+impl Grouping {
+	fn of(settings: &Settings) -> Self {
+		Self {
+			keys: settings.grouped_keys().cloned().collect(),
+			stacked: settings.stack_by.is_some(),
+			date_formats: settings
+				.grouped_keys()
+				.map(|key| settings.date_formats.get(key).cloned())
+				.collect(),
+			bins: settings
+				.grouped_keys()
+				.map(|key| settings.bins.get(key).copied())
+				.collect(),
+			delimiters: settings
+				.grouped_keys()
+				.map(|key| settings.delimiters.get(key).cloned())
+				.collect(),
+			y_axis: match settings.y_axis {
+				YAxisKey::Count => None,
+				YAxisKey::Key(key) | YAxisKey::Rate(key) => Some(key.to_string())
+			},
+			keeps_values: keeps_values(settings),
+			outliers: {
+				let mut outliers = settings
+					.outliers
+					.iter()
+					.map(|(key, test)| (key.clone(), *test))
+					.collect::<Vec<_>>();
+				outliers.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+				outliers
+			}
+		}
+	}
+}
+
+impl Pipeline {
+	/// Makes the bars for `data`, redoing only the steps whose settings have changed since last
+	/// time. If `data` itself has changed, [`Self::invalidate`] needs to be called first.
+	pub fn run(&mut self, data: &[merde::Map<'static>], settings: &Settings) -> Vec<LabeledBar> {
+		self.run_progressively(data, settings, |_| ())
+	}
+
+	/// Makes the bars like [`Self::run`], but shows `partial` the bars of the rows that have been
+	/// grouped so far while there are a lot of them to go through (see
+	/// [`group_rows_progressively`]). Pairs of bars need both sets of rows, so they're only ever
+	/// shown once they're done.
+	pub fn run_progressively(
+		&mut self,
+		data: &[merde::Map<'static>],
+		settings: &Settings,
+		mut partial: impl FnMut(Vec<LabeledBar>)
+	) -> Vec<LabeledBar> {
+		self.profile = Profile::default();
+		if let Some(periods) = &settings.periods {
+			// Each bar's already split in two, so there's no stacking them as well
+			let settings = &Settings {
+				stack_by: None,
+				..settings.clone()
+			};
+			self.compared = Steps::default();
+			let grouping = periods.grouping(settings);
+			let groups = self.bars.groups(
+				data,
+				&grouping,
+				&mut self.columns,
+				&mut self.indexes,
+				&mut self.profile,
+				&mut |_| ()
+			);
+			let (before, latest) = periods.split(groups);
+			let before = bars_of(&before, settings, &mut self.profile);
+			let latest = bars_of(&latest, settings, &mut self.profile);
+			return pair(before, latest, settings);
+		}
+
+		let Some(bounds) = &settings.compare else {
+			self.compared = Steps::default();
+			return self.bars.run(
+				data,
+				settings,
+				&mut self.columns,
+				&mut self.indexes,
+				&mut self.profile,
+				&mut |groups| partial(make_bars(groups, settings))
+			);
+		};
+		let bars = self.bars.run(
+			data,
+			settings,
+			&mut self.columns,
+			&mut self.indexes,
+			&mut self.profile,
+			&mut |_| ()
+		);
+
+		let compared_settings = Settings {
+			bounds: bounds.clone(),
+			compare: None,
+			..settings.clone()
+		};
+		let ours = self.profile.stages.len();
+		let compared = self.compared.run(
+			data,
+			&compared_settings,
+			&mut self.columns,
+			&mut self.indexes,
+			&mut self.profile,
+			&mut |_| ()
+		);
+		for stage in &mut self.profile.stages[ours..] {
+			stage.compared = true;
+		}
+		pair(bars, compared, settings)
+	}
+
+	/// How long each step took the last time the bars were made
+	pub fn profile(&self) -> &Profile {
+		&self.profile
+	}
+
+	/// Forgets everything, for when the data has been swapped out
+	pub fn invalidate(&mut self) {
+		*self = Self::default();
+	}
+}
+
+impl Steps {
+	fn run(
+		&mut self,
+		data: &[merde::Map<'static>],
+		settings: &Settings,
+		columns: &mut Columns,
+		indexes: &mut Indexes,
+		profile: &mut Profile,
+		partial: &mut dyn FnMut(&Groups)
+	) -> Vec<LabeledBar> {
+		// Labelling only has to go through each group instead of each row, so it's cheap enough
+		// to just always redo
+		let groups = self.groups(data, settings, columns, indexes, profile, partial);
+		bars_of(groups, settings, profile)
+	}
+
+	fn groups(
+		&mut self,
+		data: &[merde::Map<'static>],
+		settings: &Settings,
+		columns: &mut Columns,
+		indexes: &mut Indexes,
+		profile: &mut Profile,
+		partial: &mut dyn FnMut(&Groups)
+	) -> &Groups {
+		let filtering = Filtering::of(settings);
+		let grouping = Grouping::of(settings);
+
+		let started = Instant::now();
+		let (rows, filtered) = match &mut self.filtered {
+			Some((filtered_by, rows)) if *filtered_by == filtering => (&*rows, false),
+			filtered => {
+				self.grouped = None;
+				let rows = filter_rows(data, settings, columns, indexes);
+				(&filtered.insert((filtering, rows)).1, true)
+			}
+		};
+		profile.record(
+			"Filtering",
+			filtered.then_some(started),
+			(data.len(), "rows"),
+			(rows.len(), "rows")
+		);
+
+		if self
+			.grouped
+			.as_ref()
+			.is_some_and(|(grouped_by, _)| *grouped_by != grouping)
+		{
+			self.grouped = None;
+		}
+		let started = Instant::now();
+		let grouped = self.grouped.is_none();
+		let groups = &self
+			.grouped
+			.get_or_insert_with(|| {
+				let groups = group_rows_progressively(data, rows, settings, partial);
+				(grouping, groups)
+			})
+			.1;
+		profile.record(
+			"Grouping",
+			grouped.then_some(started),
+			(rows.len(), "rows"),
+			(groups.len(), "groups")
+		);
+		groups
+	}
+}
+
+/// Makes the bars of `groups` like [`make_bars`] does, noting down how long each half took
+fn bars_of(groups: &Groups, settings: &Settings, profile: &mut Profile) -> Vec<LabeledBar> {
+	let started = Instant::now();
+	let mut bars = label_groups(groups, settings);
+	profile.record(
+		"Building bars",
+		Some(started),
+		(groups.len(), "groups"),
+		(bars.len(), "bars")
+	);
+
+	let started = Instant::now();
+	sort_bars(&mut bars, settings);
+	profile.record(
+		"Sorting",
+		Some(started),
+		(bars.len(), "bars"),
+		(bars.len(), "bars")
+	);
+	bars
+}
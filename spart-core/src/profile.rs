@@ -0,0 +1,50 @@
+//! How long the last rebuild of the bars spent in each of its steps, and how much went into and
+//! came out of each of them, so that it's possible to tell which setting is making things slow
+//! when there's a lot of data.
+
+// `std`'s `Instant` panics in browsers, and this is just `std`'s everywhere else
+use web_time::{Duration, Instant};
+
+#[derive(Clone, Default, Debug)]
+pub struct Profile {
+	/// In the order they were done
+	pub stages: Vec<Stage>
+}
+
+#[derive(Clone, Debug)]
+pub struct Stage {
+	/// Like `Filtering` or `Grouping`
+	pub name: &'static str,
+	/// How long it took, or `None` if what it came up with last time was reused instead
+	pub took: Option<Duration>,
+	/// How many of what went into it, like `(1000, "rows")`
+	pub input: (usize, &'static str),
+	pub output: (usize, &'static str),
+	/// If it was for the bars that are being compared against, rather than the bars themselves
+	pub compared: bool
+}
+
+impl Profile {
+	/// Notes down that the stage called `name` was done, having started at `started` if it wasn't
+	/// reused
+	pub(crate) fn record(
+		&mut self,
+		name: &'static str,
+		started: Option<Instant>,
+		input: (usize, &'static str),
+		output: (usize, &'static str)
+	) {
+		self.stages.push(Stage {
+			name,
+			took: started.map(|started| started.elapsed()),
+			input,
+			output,
+			compared: false
+		});
+	}
+
+	/// How long all of the stages took together
+	pub fn total(&self) -> Duration {
+		self.stages.iter().filter_map(|stage| stage.took).sum()
+	}
+}
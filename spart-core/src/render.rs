@@ -0,0 +1,211 @@
+use std::fmt::Write;
+
+use crate::{
+	bars::{self, LabeledBar},
+	coloring::NAMED_COLORS,
+	format::{NumberFormat, truncate},
+	settings::{Annotation, ReferenceLine}
+};
+
+/// Space around the plot for the axes and their labels, in pixels
+const MARGIN_LEFT: f64 = 70.;
+const MARGIN_RIGHT: f64 = 20.;
+const MARGIN_TOP: f64 = 20.;
+const MARGIN_BOTTOM: f64 = 140.;
+
+const BAR_COLOR: &str = "#4e79a7";
+const ANNOTATION_COLOR: &str = "#c0392b";
+const REFERENCE_COLOR: &str = "#59a14f";
+/// About how many ticks to put along the y-axis
+const Y_TICKS: f64 = 5.;
+/// How far apart the entries in the legend of stacked bars are
+const LEGEND_STEP: f64 = 16.;
+/// How much room the legend leaves for each entry's label
+const LEGEND_WIDTH: f64 = 120.;
+
+pub struct RenderOptions {
+	pub width: u32,
+	pub height: u32,
+	pub value_format: NumberFormat,
+	pub label_width: usize,
+	pub annotations: Vec<Annotation>,
+	pub reference_lines: Vec<ReferenceLine>
+}
+
+/// Draws the bars as a standalone SVG bar chart
+pub fn to_svg(bars: &[LabeledBar], opts: &RenderOptions) -> String {
+	let (width, height) = (f64::from(opts.width), f64::from(opts.height));
+	let plot_width = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.);
+	let plot_height = (height - MARGIN_TOP - MARGIN_BOTTOM).max(1.);
+	let bottom = MARGIN_TOP + plot_height;
+
+	let heights = bars.iter().map(|b| b.value).collect::<Vec<_>>();
+	let references = opts
+		.reference_lines
+		.iter()
+		.filter_map(|line| Some((*line, line.height(&heights)?)))
+		.collect::<Vec<_>>();
+	// Lines above all the bars still have to fit
+	let max = heights
+		.iter()
+		.chain(references.iter().map(|(_, height)| height))
+		.copied()
+		.fold(0., f64::max)
+		.max(f64::MIN_POSITIVE);
+	let step = nice_step(max / Y_TICKS);
+	let top = (max / step).ceil() * step;
+	let y_of = |value: f64| bottom - value / top * plot_height;
+
+	let mut svg = String::new();
+	// Writing to a String can't fail, so all the `write!`s below are just unwrapped
+	writeln!(
+		svg,
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12">"#
+	)
+	.unwrap();
+	writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+	let mut tick = 0.;
+	while tick <= top {
+		let y = y_of(tick);
+		writeln!(
+			svg,
+			r##"<line x1="{MARGIN_LEFT}" x2="{}" y1="{y}" y2="{y}" stroke="#ddd"/><text x="{}" y="{y}" text-anchor="end" dominant-baseline="middle">{}</text>"##,
+			MARGIN_LEFT + plot_width,
+			MARGIN_LEFT - 6.,
+			escape(&opts.value_format.format(tick))
+		)
+		.unwrap();
+		tick += step;
+	}
+
+	// Stacked bars' segments are colored by where they are in the legend
+	let legend = bars::legend(bars);
+	let color_of = |label: &str| {
+		let idx = legend.iter().position(|l| l == label).unwrap_or_default();
+		hex(NAMED_COLORS[idx % NAMED_COLORS.len()].1)
+	};
+
+	let slot = plot_width / bars.len().max(1) as f64;
+	for (idx, bar) in bars.iter().enumerate() {
+		let x = MARGIN_LEFT + slot * idx as f64;
+		let y = y_of(bar.value);
+		let center = x + slot / 2.;
+
+		if bar.segments.is_empty() {
+			writeln!(
+				svg,
+				r#"<rect x="{}" y="{y}" width="{}" height="{}" fill="{}"><title>{}: {}</title></rect>"#,
+				x + slot * 0.1,
+				slot * 0.8,
+				bottom - y,
+				bar.color.map_or_else(|| BAR_COLOR.to_string(), hex),
+				escape(&bar.name),
+				escape(&opts.value_format.format(bar.value))
+			)
+			.unwrap();
+		}
+		let mut base = 0.;
+		for segment in &bar.segments {
+			let (from, to) = (y_of(base), y_of(base + segment.value));
+			writeln!(
+				svg,
+				r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"><title>{}, {}: {}</title></rect>"#,
+				x + slot * 0.1,
+				from.min(to),
+				slot * 0.8,
+				(from - to).abs(),
+				color_of(&segment.label),
+				escape(&bar.name),
+				escape(&segment.label),
+				escape(&opts.value_format.format(segment.value))
+			)
+			.unwrap();
+			base += segment.value;
+		}
+		writeln!(
+			svg,
+			r#"<text x="{center}" y="{}" text-anchor="end" transform="rotate(-45 {center} {})">{}</text>"#,
+			bottom + 12.,
+			bottom + 12.,
+			escape(&truncate(&bar.short_name(), opts.label_width))
+		)
+		.unwrap();
+	}
+
+	for (line, height) in references {
+		let y = y_of(height);
+		writeln!(
+			svg,
+			r##"<line x1="{MARGIN_LEFT}" x2="{}" y1="{y}" y2="{y}" stroke="{REFERENCE_COLOR}" stroke-dasharray="6 3"/><text x="{}" y="{}" text-anchor="end" fill="{REFERENCE_COLOR}">{}</text>"##,
+			MARGIN_LEFT + plot_width,
+			MARGIN_LEFT + plot_width,
+			y - 4.,
+			escape(&line.label(height, opts.value_format))
+		)
+		.unwrap();
+	}
+
+	for annotation in &opts.annotations {
+		let Some((x, height)) = annotation.position(bars) else {
+			continue;
+		};
+		let center = MARGIN_LEFT + slot * (x + 0.5);
+		// Notes on a bar sit just above it, and ones between bars span the whole height
+		let (top, text_y) = match height {
+			Some(height) => (y_of(height) - 4., y_of(height) - 8.),
+			None => (MARGIN_TOP, MARGIN_TOP + 12.)
+		};
+		writeln!(
+			svg,
+			r##"<line x1="{center}" x2="{center}" y1="{top}" y2="{bottom}" stroke="{ANNOTATION_COLOR}" stroke-dasharray="4 3"/><text x="{}" y="{text_y}" fill="{ANNOTATION_COLOR}">{}</text>"##,
+			center + 4.,
+			escape(&annotation.text)
+		)
+		.unwrap();
+	}
+
+	let left = width - MARGIN_RIGHT - LEGEND_WIDTH;
+	for (idx, label) in legend.iter().enumerate() {
+		let y = MARGIN_TOP + LEGEND_STEP * idx as f64;
+		writeln!(
+			svg,
+			r#"<rect x="{left}" y="{y}" width="10" height="10" fill="{}"/><text x="{}" y="{}" dominant-baseline="middle">{}</text>"#,
+			color_of(label),
+			left + 14.,
+			y + 5.,
+			escape(&truncate(label, opts.label_width))
+		)
+		.unwrap();
+	}
+
+	writeln!(
+		svg,
+		r#"<line x1="{MARGIN_LEFT}" x2="{MARGIN_LEFT}" y1="{MARGIN_TOP}" y2="{bottom}" stroke="black"/><line x1="{MARGIN_LEFT}" x2="{}" y1="{bottom}" y2="{bottom}" stroke="black"/>"#,
+		MARGIN_LEFT + plot_width
+	)
+	.unwrap();
+	svg.push_str("</svg>\n");
+	svg
+}
+
+/// Rounds `rough` up to a 1, 2, or 5 times some power of ten, so the ticks land on nice numbers
+fn nice_step(rough: f64) -> f64 {
+	let magnitude = 10f64.powf(rough.log10().floor());
+	[1., 2., 5., 10.]
+		.into_iter()
+		.map(|m| m * magnitude)
+		.find(|step| *step >= rough)
+		.unwrap_or(10. * magnitude)
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+	format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+pub(crate) fn escape(s: &str) -> String {
+	s.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
@@ -0,0 +1,258 @@
+//! A year in review of a listening history, like the one Spotify puts together at the end of each
+//! year: the top artists and tracks, how long was spent listening, how that changed over the
+//! months, and what times of the week it happened. It's written out as a single HTML page, with
+//! the charts as SVGs inside it, so that it can be opened (or sent to someone) on its own.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, FixedOffset, Timelike};
+use fxhash::{FxHashMap, FxHashSet};
+use merde::{CowStr, Map, Value};
+use ordered_float::OrderedFloat;
+
+use crate::{
+	bars::LabeledBar,
+	format::NumberFormat,
+	render::{self, RenderOptions},
+	song
+};
+
+/// How many artists and tracks are in each top list
+const TOP_COUNT: usize = 10;
+
+const CHART_WIDTH: u32 = 900;
+const CHART_HEIGHT: u32 = 420;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReviewErr {
+	#[error(
+		"The data isn't a listening history (it needs '{}', '{}', and '{}' keys)",
+		song::TIMESTAMP,
+		song::ARTIST,
+		song::TRACK
+	)]
+	NotHistory,
+	#[error("Nothing was played in {0}")]
+	NothingPlayed(i32)
+}
+
+/// A single play, with just what the review needs from it
+struct Play<'r> {
+	time: DateTime<FixedOffset>,
+	artist: &'r str,
+	track: &'r str,
+	ms_played: Option<f64>
+}
+
+/// Writes the review of everything in `rows` that was played in `year`, or all of it if that's
+/// `None`
+pub fn report<'r>(
+	rows: impl IntoIterator<Item = &'r Map<'static>>,
+	year: Option<i32>
+) -> Result<String, ReviewErr> {
+	let mut rows = rows.into_iter().peekable();
+	let is_history = rows.peek().is_some_and(|row| {
+		[song::TIMESTAMP, song::ARTIST, song::TRACK]
+			.iter()
+			.all(|key| row.contains_key(&CowStr::from(*key)))
+	});
+	if !is_history {
+		return Err(ReviewErr::NotHistory);
+	}
+
+	let plays = rows
+		.filter_map(to_play)
+		.filter(|play| year.is_none_or(|year| play.time.year() == year))
+		.collect::<Vec<_>>();
+	if plays.is_empty() {
+		return Err(year.map_or(ReviewErr::NotHistory, ReviewErr::NothingPlayed));
+	}
+
+	let has_time = plays.iter().any(|play| play.ms_played.is_some());
+	let title = year.map_or_else(|| "All time".to_string(), |year| year.to_string());
+
+	let mut html = String::new();
+	// Writing to a String can't fail, so all the `write!`s below are just unwrapped
+	writeln!(
+		html,
+		r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title} in review</title>
+<style>
+body {{ font-family: sans-serif; max-width: {CHART_WIDTH}px; margin: 2em auto; color: #222; }}
+.stats {{ display: flex; gap: 2em; }}
+.stat b {{ display: block; font-size: 2em; }}
+table.heatmap {{ border-collapse: collapse; }}
+table.heatmap td {{ width: 30px; height: 24px; text-align: center; font-size: 10px; }}
+</style>
+</head>
+<body>
+<h1>{title} in review</h1>"#
+	)
+	.unwrap();
+
+	let distinct =
+		|of: fn(&Play<'r>) -> &'r str| plays.iter().map(of).collect::<FxHashSet<_>>().len();
+	html.push_str(r#"<div class="stats">"#);
+	let mut stats = vec![
+		("plays", NumberFormat::Thousands.format(plays.len() as f64)),
+		(
+			"artists",
+			NumberFormat::Thousands.format(distinct(|play| play.artist) as f64)
+		),
+		(
+			"tracks",
+			NumberFormat::Thousands.format(distinct(|play| play.track) as f64)
+		),
+	];
+	if has_time {
+		let total = plays.iter().filter_map(|play| play.ms_played).sum::<f64>();
+		stats.insert(0, ("listened", NumberFormat::Duration.format(total)));
+	}
+	for (label, value) in stats {
+		write!(
+			html,
+			r#"<div class="stat"><b>{}</b>{label}</div>"#,
+			render::escape(&value)
+		)
+		.unwrap();
+	}
+	html.push_str("</div>\n");
+
+	// Tracks are listed by how long they were listened to if that's known, since that's what
+	// they were actually spent time on
+	let track_value = |play: &Play| match play.ms_played {
+		Some(ms) if has_time => ms,
+		_ => 1.
+	};
+	let track_format = if has_time {
+		NumberFormat::Duration
+	} else {
+		NumberFormat::Thousands
+	};
+
+	let artists = tally(&plays, |play| play.artist.to_string(), |_| 1.);
+	section(
+		&mut html,
+		"Top artists",
+		&top(artists),
+		NumberFormat::Thousands
+	);
+
+	let tracks = tally(
+		&plays,
+		|play| format!("{} – {}", play.track, play.artist),
+		track_value
+	);
+	section(&mut html, "Top tracks", &top(tracks), track_format);
+
+	let mut months = tally(
+		&plays,
+		|play| play.time.format("%Y-%m").to_string(),
+		track_value
+	);
+	months.sort_by(|a, b| a.labels.cmp(&b.labels));
+	let heading = if has_time {
+		"Listening time each month"
+	} else {
+		"Plays each month"
+	};
+	section(&mut html, heading, &months, track_format);
+
+	heatmap(&mut html, &plays);
+
+	html.push_str("</body>\n</html>\n");
+	Ok(html)
+}
+
+fn to_play<'r>(row: &'r Map<'static>) -> Option<Play<'r>> {
+	let string = |key| match row.get(&CowStr::from(key)) {
+		Some(Value::Str(s)) => Some(s.as_ref()),
+		_ => None
+	};
+
+	Some(Play {
+		time: DateTime::parse_from_rfc3339(string(song::TIMESTAMP)?).ok()?,
+		artist: string(song::ARTIST)?,
+		track: string(song::TRACK)?,
+		ms_played: match row.get(&CowStr::from(song::MS_PLAYED)) {
+			Some(Value::I64(ms)) => Some(*ms as f64),
+			Some(Value::U64(ms)) => Some(*ms as f64),
+			Some(Value::Float(ms)) => Some(ms.into_inner()),
+			_ => None
+		}
+	})
+}
+
+/// Adds up `value` of each play, grouped by `label`
+fn tally<'r>(
+	plays: &[Play<'r>],
+	label: impl Fn(&Play<'r>) -> String,
+	value: impl Fn(&Play<'r>) -> f64
+) -> Vec<LabeledBar> {
+	let mut sums = FxHashMap::<String, f64>::default();
+	for play in plays {
+		*sums.entry(label(play)).or_insert(0.) += value(play);
+	}
+	sums.into_iter()
+		.map(|(label, sum)| LabeledBar::new(sum, vec![label], &[]))
+		.collect()
+}
+
+/// The tallest of `bars`, tallest first
+fn top(mut bars: Vec<LabeledBar>) -> Vec<LabeledBar> {
+	bars.sort_by(|a, b| {
+		OrderedFloat(b.value)
+			.cmp(&OrderedFloat(a.value))
+			.then_with(|| a.labels.cmp(&b.labels))
+	});
+	bars.truncate(TOP_COUNT);
+	bars
+}
+
+fn section(html: &mut String, heading: &str, bars: &[LabeledBar], value_format: NumberFormat) {
+	writeln!(html, "<h2>{heading}</h2>").unwrap();
+	html.push_str(&render::to_svg(bars, &RenderOptions {
+		width: CHART_WIDTH,
+		height: CHART_HEIGHT,
+		value_format,
+		label_width: 30,
+		annotations: Vec::new(),
+		reference_lines: Vec::new()
+	}));
+}
+
+/// A grid of how many plays happened in each hour of each day of the week, where darker cells
+/// had more
+fn heatmap(html: &mut String, plays: &[Play]) {
+	let mut counts = [[0usize; 24]; 7];
+	for play in plays {
+		let time = play.time.to_utc();
+		counts[time.weekday().num_days_from_monday() as usize][time.hour() as usize] += 1;
+	}
+	let max = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+
+	html.push_str("<h2>When it was played (UTC)</h2>\n<table class=\"heatmap\">\n<tr><td></td>");
+	for hour in 0..24 {
+		write!(html, "<td>{hour}</td>").unwrap();
+	}
+	html.push_str("</tr>\n");
+
+	for (day, hours) in WEEKDAYS.iter().zip(counts) {
+		write!(html, "<tr><td>{day}</td>").unwrap();
+		for count in hours {
+			write!(
+				html,
+				r#"<td title="{count} plays" style="background: rgba(78, 121, 167, {:.2})"></td>"#,
+				count as f64 / max as f64
+			)
+			.unwrap();
+		}
+		html.push_str("</tr>\n");
+	}
+	html.push_str("</table>\n");
+}
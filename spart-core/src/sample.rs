@@ -0,0 +1,148 @@
+//! Charting a sample of the rows instead of all of them, for a quick look at data that's too big
+//! to go through every time something changes. Since a plain random sample of a long tail mostly
+//! leaves out the rare categories, it can also take the same number of rows from each category of
+//! a key, or pick rows more often the larger some numeric key of theirs is.
+//!
+//! Which rows are picked only depends on the seed and their indices, so that the sample stays the
+//! same from one rebuild to the next instead of jumping around whenever the filters change.
+
+use std::cmp::Ordering;
+
+use fxhash::FxHashMap;
+use merde::{CowStr, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Sample {
+	/// How many rows are kept, at most
+	pub rows: usize,
+	pub method: Sampling,
+	pub seed: u64
+}
+
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sampling {
+	/// Every row is as likely to be picked as any other
+	#[default]
+	Random,
+	/// The same number of rows are picked from each value of the key (or all of them, for values
+	/// with fewer rows than that)
+	Stratified(String),
+	/// Rows are picked in proportion to their value of the key, and never if it's not positive
+	Weighted(String)
+}
+
+impl Sampling {
+	pub fn describe(&self) -> String {
+		match self {
+			Self::Random => "Random".to_string(),
+			Self::Stratified(key) => format!("Stratified by {key}"),
+			Self::Weighted(key) => format!("Weighted by {key}")
+		}
+	}
+
+	/// The key that the rows are sampled by, if they are
+	pub fn key(&self) -> Option<&str> {
+		match self {
+			Self::Random => None,
+			Self::Stratified(key) | Self::Weighted(key) => Some(key)
+		}
+	}
+}
+
+impl Sample {
+	pub fn new(rows: usize, method: Sampling) -> Self {
+		Self {
+			rows,
+			method,
+			seed: 0
+		}
+	}
+
+	/// Which of `rows` (indices into `data`) are kept, in the order they were in
+	pub fn pick(
+		&self,
+		data: &[merde::Map<'static>],
+		rows: Vec<usize>,
+		settings: &Settings
+	) -> Vec<usize> {
+		if rows.len() <= self.rows && !matches!(self.method, Sampling::Weighted(_)) {
+			return rows;
+		}
+
+		let mut picked = match &self.method {
+			Sampling::Random =>
+				self.smallest(rows.into_iter().map(|idx| (self.draw(idx), idx)).collect()),
+			Sampling::Stratified(key) => {
+				let mut strata = FxHashMap::<String, Vec<(f64, usize)>>::default();
+				for idx in rows {
+					let value = data[idx]
+						.get(&CowStr::from(key.as_str()))
+						.unwrap_or(&Value::Null);
+					let label = settings.label_for(key, &settings.grouping_value(key, value));
+					strata.entry(label).or_default().push((self.draw(idx), idx));
+				}
+
+				let per_stratum = self.rows.div_ceil(strata.len().max(1));
+				strata
+					.into_values()
+					.flat_map(|stratum| {
+						Self {
+							rows: per_stratum,
+							..self.clone()
+						}
+						.smallest(stratum)
+					})
+					.collect()
+			}
+			// Efraimidis and Spirakis' weighted sampling: each row gets a key of u^(1/weight), and
+			// the ones with the largest keys win. Flipping that around to -ln(u)/weight keeps the
+			// same order without it rounding down to 0 for small weights.
+			Sampling::Weighted(key) => self.smallest(
+				rows.into_iter()
+					.filter_map(|idx| {
+						let weight = weight(data[idx].get(&CowStr::from(key.as_str())))?;
+						Some((-self.draw(idx).ln() / weight, idx))
+					})
+					.collect()
+			)
+		};
+		picked.sort_unstable();
+		picked
+	}
+
+	/// The indices that drew the smallest numbers, as many of them as are being kept
+	fn smallest(&self, mut draws: Vec<(f64, usize)>) -> Vec<usize> {
+		let by_draw =
+			|a: &(f64, usize), b: &(f64, usize)| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal);
+		if draws.len() > self.rows {
+			if self.rows > 0 {
+				draws.select_nth_unstable_by(self.rows - 1, by_draw);
+			}
+			draws.truncate(self.rows);
+		}
+		draws.into_iter().map(|(_, idx)| idx).collect()
+	}
+
+	/// A random number in (0, 1] for the row at `idx`, from splitmix64
+	fn draw(&self, idx: usize) -> f64 {
+		let mut z = self.seed ^ (idx as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^= z >> 31;
+		((z >> 11) as f64 + 1.) / (1u64 << 53) as f64
+	}
+}
+
+fn weight(value: Option<&Value>) -> Option<f64> {
+	let weight = match value? {
+		Value::I64(i) => *i as f64,
+		Value::U64(u) => *u as f64,
+		Value::Float(f) => f.into_inner(),
+		_ => return None
+	};
+	(weight > 0.).then_some(weight)
+}
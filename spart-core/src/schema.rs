@@ -0,0 +1,496 @@
+use chrono::{DateTime, SecondsFormat};
+use fxhash::{FxHashMap, FxHashSet};
+use merde::{CowStr, IntoStatic, Value, ValueType};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	agents,
+	filter::FilterParseErr,
+	format::is_timestamp,
+	geo::{self, Coordinates},
+	ip, urls
+};
+
+#[derive(thiserror::Error, Debug)]
+/// Why some data can't be charted
+pub enum DataErr {
+	#[error("The provided data is empty")]
+	NoData,
+	#[error("Differing types were found for the key '{key}': {expected:?} and {found:?}")]
+	DifferentTypes {
+		key: String,
+		expected: ValueType,
+		found: ValueType
+	},
+	#[error(
+		"'{0}' holds arrays, which can't be made into bars unless they're small enough to be \
+		 exploded into a key for each element (see `explode_arrays` in the config)"
+	)]
+	NestedArrays(String),
+	#[error(
+		"'{0}' holds objects, which can't be made into bars until they're flattened into a key for \
+		 each of their values"
+	)]
+	NestedMaps(String),
+	#[error("There's no key named '{0}' in the provided data")]
+	UnknownKey(String),
+	#[error("That kind of bound can't be used on '{key}' (which holds {ty:?}s)")]
+	MismatchedBound { key: String, ty: ValueType },
+	#[error("That can only be done with numeric keys, which '{0}' isn't")]
+	NotNumeric(String),
+	#[error("The values of '{0}' can't be put into bins, since they aren't all finite")]
+	Unbinnable(String),
+	#[error(
+		"Only numeric keys can be treated as categories, and only string keys as numbers, but \
+		 '{key}' holds {ty:?}s"
+	)]
+	Untreatable { key: String, ty: ValueType },
+	#[error("Only boolean keys have a share of true values, which '{0}' isn't")]
+	NotBoolean(String),
+	#[error("Only string keys can be split up, which '{0}' isn't")]
+	Unsplittable(String),
+	#[error("'{0}' doesn't hold IP addresses")]
+	NotAddresses(String),
+	#[error("'{0}' doesn't hold URLs")]
+	NotUrls(String),
+	#[error("'{0}' doesn't hold user agents")]
+	NotAgents(String),
+	#[error("There's already a key named '{0}'")]
+	KeyExists(String),
+	#[error("'{0}' doesn't have any timestamps to compare periods of")]
+	NoTimestamps(String),
+	#[error("The data doesn't have the keys that the {0} preset needs")]
+	UnsupportedPreset(&'static str),
+	#[error("Couldn't parse filter: {0}")]
+	Filter(#[from] FilterParseErr)
+}
+
+/// What we know about the shape of the data that's been loaded
+pub struct Schema {
+	/// Every key in the data, and the type of its values, sorted by key
+	pub keys: Vec<(String, ValueType)>,
+	/// The keys whose values look like timestamps, and can thus be given a date format
+	pub timestamp_keys: Vec<String>,
+	/// The integer keys whose values look like Unix timestamps, and which of
+	/// [`KeyType::EpochSeconds`] or [`KeyType::EpochMillis`] they'd be, so that they can be
+	/// offered as dates too
+	pub epoch_keys: Vec<(String, KeyType)>,
+	/// The string keys whose values look like country codes, which can be drawn on a map
+	pub country_keys: Vec<String>,
+	/// The string keys whose values look like IP addresses, which can have coarser keys pulled
+	/// out of them (see [`crate::ip`])
+	pub ip_keys: Vec<String>,
+	/// The string keys whose values look like URLs (or their paths), which can have their parts
+	/// pulled out of them (see [`crate::urls`])
+	pub url_keys: Vec<String>,
+	/// The string keys whose values look like user agents, which can have browsers and such
+	/// pulled out of them (see [`crate::agents`])
+	pub agent_keys: Vec<String>,
+	/// Which keys hold where each row is, if there are any
+	pub coordinates: Option<Coordinates>
+}
+
+impl Schema {
+	/// Checks that the data is something we can work with (flat objects, where each key's values
+	/// are all the same type or null) and figures out what keys it has. Rows don't all have to
+	/// have every key, since e.g. Spotify's podcast plays have keys that its song plays don't -
+	/// a key that's missing from a row is treated as if it were null there.
+	pub fn infer(data: &[merde::Map]) -> Result<Self, DataErr> {
+		if data.is_empty() {
+			return Err(DataErr::NoData);
+		}
+
+		let mut types = KeyTypes::default();
+		for map in data {
+			types.check(map)?;
+		}
+
+		let mut keys: Vec<(String, _)> = types.0.into_iter().collect();
+
+		keys.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+		let timestamp_keys = keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::String)
+			.filter(|(key, _)| {
+				let key = key.as_str().into();
+				data.iter()
+					.filter_map(|map| map.get(&key))
+					.find(|val| !matches!(val, Value::Null))
+					.is_some_and(is_timestamp)
+			})
+			.map(|(key, _)| key.clone())
+			.collect();
+
+		let epoch_keys = keys
+			.iter()
+			.filter(|(_, ty)| matches!(ty, ValueType::I64 | ValueType::U64))
+			.filter_map(|(key, _)| Some((key.clone(), epoch_unit(data, key)?)))
+			.collect();
+
+		let country_keys = keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::String)
+			.filter(|(key, _)| geo::is_country_key(data, key))
+			.map(|(key, _)| key.clone())
+			.collect();
+		let ip_keys = keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::String)
+			.filter(|(key, _)| ip::is_ip_key(data, key))
+			.map(|(key, _)| key.clone())
+			.collect();
+		let url_keys = keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::String)
+			.filter(|(key, _)| urls::is_url_key(data, key))
+			.map(|(key, _)| key.clone())
+			.collect();
+		let agent_keys = keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::String)
+			.filter(|(key, _)| agents::is_agent_key(data, key))
+			.map(|(key, _)| key.clone())
+			.collect();
+		let coordinates = Coordinates::find(&keys);
+
+		Ok(Self {
+			keys,
+			timestamp_keys,
+			epoch_keys,
+			country_keys,
+			ip_keys,
+			url_keys,
+			agent_keys,
+			coordinates
+		})
+	}
+
+	/// If `row` could be added to the data this was inferred from without breaking any of the
+	/// rules that [`Self::infer`] checks for. It can be missing keys, but it can't have any new
+	/// ones, since nothing else would know about them.
+	pub fn fits(&self, row: &merde::Map) -> bool {
+		row.iter().all(|(key, value)| {
+			self.keys
+				.binary_search_by(|(k, _)| k.as_str().cmp(key))
+				.is_ok_and(|idx| match (self.keys[idx].1, value.value_type()) {
+					(_, ValueType::Map | ValueType::Array) => false,
+					(ValueType::Null, _) | (_, ValueType::Null) => true,
+					(a, b) => a == b
+				})
+		})
+	}
+}
+
+/// The types of the keys in rows that are looked at one at a time, which every row has to agree
+/// with. Each key's type stays null until a row has something else for it.
+#[derive(Default)]
+pub struct KeyTypes(FxHashMap<String, ValueType>);
+
+impl KeyTypes {
+	/// Checks that `row` is flat, and that each of its values is null or the type that the key's
+	/// had so far
+	pub fn check(&mut self, row: &merde::Map) -> Result<(), DataErr> {
+		for (key, value) in row.iter() {
+			let found = value.value_type();
+			// Objects have normally been flattened by now (see [`crate::flatten`]), but arrays that
+			// were too big to explode are still around
+			match found {
+				ValueType::Array => return Err(DataErr::NestedArrays(key.to_string())),
+				ValueType::Map => return Err(DataErr::NestedMaps(key.to_string())),
+				_ => ()
+			}
+
+			let Some(expected) = self.0.get_mut(key.as_ref()) else {
+				self.0.insert(key.to_string(), found);
+				continue;
+			};
+			match (*expected, found) {
+				// we're ok with type differences if one is null and the other is a different
+				// type - everything's Option around here
+				(ValueType::Null, _) => *expected = found,
+				(_, ValueType::Null) => (),
+				// But if they're two different types otherwise, that's an error.
+				(a, b) if a != b =>
+					return Err(DataErr::DifferentTypes {
+						key: key.to_string(),
+						expected: a,
+						found: b
+					}),
+				_ => ()
+			}
+		}
+		Ok(())
+	}
+
+	/// The type of `key`'s values, if any row's had it (which is null if they've all been null)
+	pub fn get(&self, key: &str) -> Option<ValueType> {
+		self.0.get(key).copied()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+/// Guesses whether the values of `key` are Unix timestamps, and if so whether they're in seconds or
+/// milliseconds. They have to all be somewhere between 2001 and 2100 in one of those, and mostly go
+/// in one direction from row to row (as things that are logged do), since plenty of other numbers
+/// are that big too.
+fn epoch_unit(data: &[merde::Map], key: &str) -> Option<KeyType> {
+	let key = CowStr::from(key);
+	let values = data
+		.iter()
+		.filter_map(|row| match row.get(&key)? {
+			Value::I64(n) => Some(*n as f64),
+			Value::U64(n) => Some(*n as f64),
+			_ => None
+		})
+		.take(EPOCH_SAMPLE)
+		.collect::<Vec<_>>();
+
+	let unit = [(KeyType::EpochSeconds, 1.), (KeyType::EpochMillis, 1000.)]
+		.into_iter()
+		.find(|(_, per_second)| {
+			values
+				.iter()
+				.all(|n| (EPOCH_RANGE.0 * per_second..EPOCH_RANGE.1 * per_second).contains(n))
+		})?
+		.0;
+
+	let steps = values.windows(2).map(|pair| pair[1] - pair[0]);
+	let (rising, falling) = steps.fold((0, 0), |(rising, falling), step| {
+		(
+			rising + usize::from(step >= 0.),
+			falling + usize::from(step <= 0.)
+		)
+	});
+	let all = values.len().saturating_sub(1);
+	let monotonic = rising.max(falling) as f64 >= all as f64 * EPOCH_MONOTONIC_SHARE;
+	// A key that's the same everywhere isn't going anywhere, timestamp or not
+	let varies = values.iter().any(|n| *n != values[0]);
+	(all > 0 && monotonic && varies).then_some(unit)
+}
+
+/// How many of a key's values are looked at to guess if it's a Unix timestamp
+const EPOCH_SAMPLE: usize = 10_000;
+
+/// The seconds from 2001-09-09 to 2100-01-01, which a key's values have to all be in (or that
+/// times 1000) for it to be guessed to be a Unix timestamp
+const EPOCH_RANGE: (f64, f64) = (1_000_000_000., 4_102_444_800.);
+
+/// How many of the steps between one row's value and the next have to go the same way
+const EPOCH_MONOTONIC_SHARE: f64 = 0.9;
+
+/// What's in a single key, across every row - unlike [`Schema`], this can be built for any data,
+/// even if it's not something we'd be able to chart
+pub struct KeySummary {
+	pub key: String,
+	/// Every type (other than null) that this key's values have. If there's more than one, the
+	/// data doesn't fit our invariants.
+	pub types: Vec<ValueType>,
+	/// How many rows either have this key set to null or don't have it at all
+	pub nulls: usize,
+	pub distinct: usize
+}
+
+/// Summarizes every key in the data, sorted by key
+pub fn describe(data: &[merde::Map]) -> Vec<KeySummary> {
+	#[derive(Default)]
+	struct Seen<'v, 's> {
+		types: Vec<ValueType>,
+		present: usize,
+		values: FxHashSet<&'v Value<'s>>
+	}
+
+	let mut seen = FxHashMap::<&str, Seen>::default();
+	for map in data {
+		for (key, value) in map.iter() {
+			let entry = seen.entry(key).or_default();
+			if matches!(value, Value::Null) {
+				continue;
+			}
+
+			entry.present += 1;
+			entry.values.insert(value);
+			if !entry.types.contains(&value.value_type()) {
+				entry.types.push(value.value_type());
+			}
+		}
+	}
+
+	let mut summaries = seen
+		.into_iter()
+		.map(|(key, seen)| KeySummary {
+			key: key.to_string(),
+			types: seen.types,
+			nulls: data.len() - seen.present,
+			distinct: seen.values.len()
+		})
+		.collect::<Vec<_>>();
+	summaries.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+	summaries
+}
+
+/// What a key's values are treated as instead of the type they were loaded as (see
+/// [`convert`])
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+	/// Numbers that are really names, like IDs or HTTP status codes, which are turned into
+	/// strings so that they're bounded and grouped by their exact values
+	Categorical,
+	/// Strings that are really numbers, which are parsed as floats (or become null if they
+	/// can't be), so that they can be bounded by ranges and added up
+	Numeric,
+	/// Numbers that are seconds since the Unix epoch, which are turned into RFC 3339 timestamps
+	/// so that they can be given date formats
+	EpochSeconds,
+	/// The same, but in milliseconds
+	EpochMillis
+}
+
+impl KeyType {
+	pub const ALL: [Self; 4] = [
+		Self::Categorical,
+		Self::Numeric,
+		Self::EpochSeconds,
+		Self::EpochMillis
+	];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Categorical => "Categories",
+			Self::Numeric => "Numbers",
+			Self::EpochSeconds => "Dates (Unix seconds)",
+			Self::EpochMillis => "Dates (Unix milliseconds)"
+		}
+	}
+
+	/// If a key whose values are `ty` can be treated as this
+	pub fn applies_to(self, ty: ValueType) -> bool {
+		match self {
+			Self::Categorical | Self::EpochSeconds | Self::EpochMillis =>
+				matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float),
+			Self::Numeric => ty == ValueType::String
+		}
+	}
+
+	/// What `value` is once it's treated as this. Values that already are (or that this doesn't
+	/// apply to) are left as they are, so converting something twice is the same as once.
+	fn convert(self, value: &Value<'static>) -> Option<Value<'static>> {
+		let number = match value {
+			Value::I64(n) => Some(*n as f64),
+			Value::U64(n) => Some(*n as f64),
+			Value::Float(n) => Some(n.into_inner()),
+			_ => None
+		};
+		match (self, value, number) {
+			(Self::Categorical, Value::Float(n), _) => Some(Value::Str(n.to_string().into())),
+			(Self::Categorical, Value::I64(n), _) => Some(Value::Str(n.to_string().into())),
+			(Self::Categorical, Value::U64(n), _) => Some(Value::Str(n.to_string().into())),
+			(Self::Numeric, Value::Str(s), _) => Some(
+				s.trim()
+					.parse::<f64>()
+					.map_or(Value::Null, |n| Value::Float(n.into()))
+			),
+			(Self::EpochSeconds | Self::EpochMillis, _, Some(n)) => {
+				let millis = if self == Self::EpochSeconds {
+					n * 1000.
+				} else {
+					n
+				};
+				Some(
+					DateTime::from_timestamp_millis(millis as i64).map_or(Value::Null, |time| {
+						Value::Str(time.to_rfc3339_opts(SecondsFormat::AutoSi, true).into())
+					})
+				)
+			}
+			_ => None
+		}
+	}
+}
+
+/// Treats the values of `key` in `data` as `ty`, returning what each row's value was before so
+/// that they can be put back with [`restore`]. They're copies, so they can outlive whatever text
+/// `data`'s strings might borrow from (see [`Rows`](crate::memory::Rows)).
+pub fn convert(data: &mut [merde::Map<'static>], key: &str, ty: KeyType) -> Vec<Value<'static>> {
+	let key = CowStr::from(key.to_string());
+	data.iter_mut()
+		.map(|row| match row.get_mut(&key) {
+			Some(value) => match ty.convert(value) {
+				Some(converted) => std::mem::replace(value, converted).into_static(),
+				None => value.clone().into_static()
+			},
+			None => Value::Null
+		})
+		.collect()
+}
+
+/// Puts back the values of `key` that [`convert`] returned. Rows that don't have `key` anymore
+/// are left without it.
+pub fn restore(data: &mut [merde::Map<'static>], key: &str, originals: Vec<Value<'static>>) {
+	let key = CowStr::from(key.to_string());
+	for (row, original) in data.iter_mut().zip(originals) {
+		if let Some(value) = row.get_mut(&key) {
+			*value = original;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rows(json: &str) -> Vec<merde::Map<'_>> {
+		merde::json::from_str(json).unwrap()
+	}
+
+	#[test]
+	fn sparse_keys() {
+		let data = rows(r#"[{"a": 1}, {"a": null, "b": "x"}, {"c": null}]"#);
+		let schema = Schema::infer(&data).unwrap();
+		assert_eq!(schema.keys, [
+			("a".to_string(), ValueType::I64),
+			("b".to_string(), ValueType::String),
+			("c".to_string(), ValueType::Null)
+		]);
+	}
+
+	#[test]
+	fn mixed_types() {
+		let data = rows(r#"[{"a": 1}, {"a": null}, {"a": 1.5}]"#);
+		assert!(matches!(
+			Schema::infer(&data),
+			Err(DataErr::DifferentTypes {
+				expected: ValueType::I64,
+				found: ValueType::Float,
+				..
+			})
+		));
+
+		// The same goes for rows checked one at a time, which don't change the types on failing
+		let mut types = KeyTypes::default();
+		types.check(&data[0]).unwrap();
+		types.check(&data[1]).unwrap();
+		assert!(types.check(&data[2]).is_err());
+		assert_eq!(types.get("a"), Some(ValueType::I64));
+		assert_eq!(types.get("b"), None);
+	}
+
+	#[test]
+	fn nested_values() {
+		let data = rows(r#"[{"a": [1, 2]}]"#);
+		assert!(matches!(
+			Schema::infer(&data),
+			Err(DataErr::NestedArrays(key)) if key == "a"
+		));
+		let data = rows(r#"[{"a": {"b": 1}}]"#);
+		let Err(err) = Schema::infer(&data) else {
+			panic!("Objects should be rejected");
+		};
+		assert!(matches!(&err, DataErr::NestedMaps(key) if key == "a"));
+		assert!(!err.to_string().contains("explode_arrays"));
+		assert!(matches!(Schema::infer(&[]), Err(DataErr::NoData)));
+	}
+}
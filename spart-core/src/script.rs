@@ -0,0 +1,270 @@
+//! Little Rhai expressions that the user writes, for whatever the rest of the settings can't do.
+//! They're run once per row, with each of the row's keys available as a variable (and all of
+//! them through `row`, for keys that aren't valid names), so e.g. `ms_played / 60000` makes a
+//! column of minutes, and `row["track name"].contains("Live")` only keeps some rows.
+
+use std::sync::Arc;
+
+use merde::{CowStr, IntoStatic, Value};
+use rayon::prelude::*;
+use rhai::{AST, Dynamic, Engine, EvalAltResult, Scope};
+
+/// How many steps a script can take on a single row before it's stopped, so that one that never
+/// finishes (like `loop {}`) can't hang everything that's waiting on it
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScriptErr {
+	#[error("Couldn't compile the script: {0}")]
+	Compile(#[from] rhai::ParseError),
+	#[error("The script failed on row {row}: {message}")]
+	Eval { row: usize, message: String },
+	#[error("The script returned a {0}, but it has to be a number, string, bool, or ()")]
+	Unstorable(&'static str),
+	#[error("The script should return true or false, not a {0}")]
+	NotABool(&'static str),
+	#[error("'{0}' should look like NAME=EXPRESSION")]
+	BadColumn(String),
+	#[error("There's already a key called '{0}'")]
+	DuplicateKey(String)
+}
+
+/// Compiles expressions, along with any functions that the user has defined for them to use
+#[derive(Clone)]
+pub struct Scripts {
+	engine: Arc<Engine>,
+	/// Only the functions from the user's library, without anything else that was in it
+	library: AST
+}
+
+impl Default for Scripts {
+	fn default() -> Self {
+		Self {
+			engine: Arc::new(engine()),
+			library: AST::empty()
+		}
+	}
+}
+
+fn engine() -> Engine {
+	let mut engine = Engine::new();
+	engine.set_max_operations(MAX_OPERATIONS);
+	engine
+}
+
+impl Scripts {
+	/// Makes the functions defined in `library` (a whole Rhai script) callable from every
+	/// expression that's compiled afterwards
+	pub fn with_library(library: &str) -> Result<Self, ScriptErr> {
+		let engine = engine();
+		let library = engine.compile(library)?.clone_functions_only();
+		Ok(Self {
+			engine: Arc::new(engine),
+			library
+		})
+	}
+
+	pub fn compile(&self, source: &str) -> Result<Expression, ScriptErr> {
+		let ast = self.engine.compile_expression(source)?;
+		Ok(Expression {
+			source: source.to_string(),
+			engine: Arc::clone(&self.engine),
+			ast: Arc::new(self.library.merge(&ast))
+		})
+	}
+}
+
+/// A compiled expression, ready to be run on rows
+#[derive(Clone)]
+pub struct Expression {
+	/// What the user wrote
+	pub source: String,
+	engine: Arc<Engine>,
+	ast: Arc<AST>
+}
+
+impl PartialEq for Expression {
+	fn eq(&self, other: &Self) -> bool {
+		self.source == other.source
+	}
+}
+
+impl Expression {
+	fn eval(&self, row: &merde::Map) -> Result<Dynamic, String> {
+		let mut scope = Scope::new();
+		let mut all = rhai::Map::new();
+		for (key, value) in row.iter() {
+			let value = to_dynamic(value);
+			if is_identifier(key) {
+				scope.push_dynamic(key.to_string(), value.clone());
+			}
+			all.insert(key.as_ref().into(), value);
+		}
+		scope.push_constant("row", all);
+
+		self.engine
+			.eval_ast_with_scope(&mut scope, &self.ast)
+			.map_err(|e| match *e {
+				EvalAltResult::ErrorTooManyOperations(_) => format!(
+					"it was stopped after {MAX_OPERATIONS} steps, in case it was never going to \
+					 finish"
+				),
+				e => e.to_string()
+			})
+	}
+
+	/// What this comes out to for `row`, as something that can be stored in the data
+	pub fn value_for(&self, row: &merde::Map, idx: usize) -> Result<Value<'static>, ScriptErr> {
+		let result = self
+			.eval(row)
+			.map_err(|message| ScriptErr::Eval { row: idx, message })?;
+		from_dynamic(result)
+	}
+
+	/// If `row` should be kept, when this is being used as a filter. Rows that it fails on (or
+	/// doesn't return a bool for) aren't kept; [`Self::check_filter`] can say why.
+	pub fn keeps(&self, row: &merde::Map) -> bool {
+		self.eval(row)
+			.is_ok_and(|result| result.as_bool() == Ok(true))
+	}
+
+	/// Finds out if this works as a filter by trying it on the first of `data`, since most
+	/// mistakes will show up on any row
+	pub fn check_filter(&self, data: &[merde::Map]) -> Result<(), ScriptErr> {
+		let Some(row) = data.first() else {
+			return Ok(());
+		};
+		let result = self
+			.eval(row)
+			.map_err(|message| ScriptErr::Eval { row: 0, message })?;
+		match result.as_bool() {
+			Ok(_) => Ok(()),
+			Err(ty) => Err(ScriptErr::NotABool(ty))
+		}
+	}
+}
+
+/// Splits up a column definition like `minutes=ms_played / 60000` into its name and expression
+pub fn parse_column(definition: &str) -> Result<(&str, &str), ScriptErr> {
+	match definition.split_once('=') {
+		Some((name, expr)) if !name.trim().is_empty() && !expr.starts_with('=') =>
+			Ok((name.trim(), expr.trim())),
+		_ => Err(ScriptErr::BadColumn(definition.to_string()))
+	}
+}
+
+/// Adds a key called `name` to every row of `data`, with whatever `expr` comes out to for it
+pub fn add_column(
+	data: &mut [merde::Map<'static>],
+	name: &str,
+	expr: &Expression
+) -> Result<(), ScriptErr> {
+	let key = CowStr::from(name);
+	if data.iter().any(|row| row.contains_key(&key)) {
+		return Err(ScriptErr::DuplicateKey(name.to_string()));
+	}
+
+	let values = column_values(data, expr)?;
+	insert_column(data, name, values);
+	Ok(())
+}
+
+/// What `expr` comes out to for each row of `data`, in order
+pub fn column_values(
+	data: &[merde::Map],
+	expr: &Expression
+) -> Result<Vec<Value<'static>>, ScriptErr> {
+	data.par_iter()
+		.enumerate()
+		.map(|(idx, row)| expr.value_for(row, idx))
+		.collect()
+}
+
+/// Adds a key called `name` to every row of `data`, with the value at the same place in `values`
+/// (from [`column_values`])
+pub fn insert_column(data: &mut [merde::Map<'static>], name: &str, values: Vec<Value<'static>>) {
+	for (row, value) in data.iter_mut().zip(values) {
+		row.insert(CowStr::from(name).into_static(), value);
+	}
+}
+
+fn is_identifier(key: &str) -> bool {
+	let mut chars = key.chars();
+	chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn to_dynamic(value: &Value) -> Dynamic {
+	match value {
+		Value::I64(i) => (*i).into(),
+		Value::U64(u) => i64::try_from(*u).map_or_else(|_| (*u as f64).into(), Dynamic::from),
+		Value::Float(f) => f.0.into(),
+		Value::Str(s) => s.as_ref().into(),
+		Value::Bool(b) => (*b).into(),
+		// The data can't have anything nested in it, and there's not much a script could do with
+		// bytes
+		Value::Null | Value::Bytes(_) | Value::Array(_) | Value::Map(_) => Dynamic::UNIT
+	}
+}
+
+fn from_dynamic(value: Dynamic) -> Result<Value<'static>, ScriptErr> {
+	if value.is_unit() {
+		Ok(Value::Null)
+	} else if let Ok(i) = value.as_int() {
+		Ok(Value::I64(i))
+	} else if let Ok(f) = value.as_float() {
+		Ok(Value::Float(f.into()))
+	} else if let Ok(b) = value.as_bool() {
+		Ok(Value::Bool(b))
+	} else if value.is_string() {
+		Ok(Value::Str(value.cast::<String>().into()))
+	} else {
+		Err(ScriptErr::Unstorable(value.type_name()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rows(json: &str) -> Vec<merde::Map<'static>> {
+		let rows: Vec<merde::Map> = merde::json::from_str(json).unwrap();
+		rows.into_static()
+	}
+
+	#[test]
+	fn columns() {
+		let mut data = rows(r#"[{"ms": 120000}, {"ms": 30000}]"#);
+		let expr = Scripts::default().compile("ms / 60000.0").unwrap();
+		add_column(&mut data, "minutes", &expr).unwrap();
+		let minutes = CowStr::from("minutes");
+		assert_eq!(data[0].get(&minutes), Some(&Value::Float(2.0.into())));
+		assert_eq!(data[1].get(&minutes), Some(&Value::Float(0.5.into())));
+	}
+
+	#[test]
+	fn duplicate_keys() {
+		// Only a later row has the key, which still counts
+		let mut data = rows(r#"[{"a": 1}, {"a": 2, "b": 3}]"#);
+		let expr = Scripts::default().compile("a").unwrap();
+		assert!(matches!(
+			add_column(&mut data, "b", &expr),
+			Err(ScriptErr::DuplicateKey(key)) if key == "b"
+		));
+	}
+
+	#[test]
+	fn endless_scripts() {
+		let data = rows(r#"[{"a": 1}]"#);
+		let expr = Scripts::with_library("fn spin() { loop {} }")
+			.unwrap()
+			.compile("spin()")
+			.unwrap();
+		assert!(matches!(
+			column_values(&data, &expr),
+			Err(ScriptErr::Eval { row: 0, message }) if message.contains("stopped")
+		));
+	}
+}
@@ -0,0 +1,825 @@
+use std::{borrow::Cow, ops::Range, sync::Mutex};
+
+use fxhash::FxHashMap;
+use merde::{Value, ValueType};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	bars::LabeledBar,
+	bins::Bins,
+	brush::Brush,
+	coloring::ColorRule,
+	drill::Drill,
+	format::{ByteEncoding, NumberFormat, format_timestamp},
+	outliers::OutlierTest,
+	periods::PeriodComparison,
+	sample::Sample,
+	schema::KeyType,
+	script::Expression
+};
+
+/// What values are split up by if no delimiter is given, since it's what lists (like the genres
+/// that enrichment adds) are usually joined with
+pub const DEFAULT_DELIMITER: &str = ", ";
+
+/// Everything about how the data is turned into bars. This (de)serializes to the same shape
+/// wherever it's stored, except for `predicate`, which can only be compiled with a
+/// [`Scripts`](crate::script::Scripts) and so has to be stored (as its source) separately.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings<'keys> {
+	pub bounds: FxHashMap<String, ValueBound>,
+	/// The bounds of a second set of bars to compare the first against, category by category, if
+	/// they're being compared
+	pub compare: Option<FxHashMap<String, ValueBound>>,
+	/// Whether bars being compared are charted as how much they changed (from the first set of
+	/// bounds to `compare`) instead of side by side
+	pub diff: bool,
+	/// The periods of a timestamp key that each bar's rows are split up by to compare them,
+	/// instead of by `compare`'s bounds, if they are
+	pub periods: Option<PeriodComparison>,
+	/// A script that rows have to return `true` for to be charted, on top of `bounds`
+	#[serde(skip)]
+	pub predicate: Option<Expression>,
+	/// Bars that were picked in other charts of the same data, which only their rows are kept of.
+	/// These only mean anything next to those charts, so they aren't stored with the rest.
+	#[serde(skip)]
+	pub brushes: Vec<Brush>,
+	/// Where the x-axis keys are being drilled down through, if they are (see [`Drill`])
+	pub drill: Option<Drill>,
+	/// If only some of the rows that get through the bounds and predicate are charted, and how
+	/// they're picked
+	pub sample: Option<Sample>,
+	/// Keys whose values are treated as a different type than they were loaded as. The data
+	/// itself is converted when these change (with [`crate::schema::convert`]), so everything
+	/// else just sees the new type.
+	pub key_types: FxHashMap<String, KeyType>,
+	pub x_axis: Vec<String>,
+	/// A key that each bar is split up by, into segments stacked on top of each other for each of
+	/// its values (like the artists in each month's bar)
+	pub stack_by: Option<String>,
+	#[serde(deserialize_with = "YAxisKey::deserialize_known")]
+	pub y_axis: YAxisKey<'keys>,
+	/// How each bar's values of the y-axis key are combined, when it's a numeric key
+	pub aggregation: Aggregation,
+	/// How many bars make up a single page of the chart, or `usize::MAX` to show all of them
+	/// however many there are
+	pub max_shown: usize,
+	/// The index of the first bar being shown
+	pub offset: usize,
+	/// The numeric keys whose outliers are looked for, and how, so that the bars with them can
+	/// be flagged
+	pub outliers: FxHashMap<String, OutlierTest>,
+	/// Groups that make up less than this fraction of the total are merged into a single
+	/// "Other" bar, so that a long tail of tiny bars doesn't drown out the rest
+	pub merge_below: Option<f64>,
+	/// How numeric values of each key are rendered in bar labels, ticks, and tooltips
+	pub formats: FxHashMap<String, NumberFormat>,
+	/// How the y-axis is rendered when it's just counting rows
+	pub count_format: NumberFormat,
+	/// strftime-style formats for keys that contain timestamps. Values of these keys are grouped
+	/// by their formatted representation, so e.g. `%Y-%m` groups them into months.
+	pub date_formats: FxHashMap<String, String>,
+	/// Numeric keys whose values are bucketed into bins, so that they're charted as a histogram
+	/// instead of with a bar for each distinct value
+	pub bins: FxHashMap<String, Bins>,
+	/// How values of bytes keys are shown, for keys that aren't just hex
+	pub byte_encodings: FxHashMap<String, ByteEncoding>,
+	/// Keys whose values each list several things (like `rock, indie`), and what those are
+	/// separated by. Rows are counted towards the bar of each thing they list, instead of one for
+	/// the whole list.
+	pub delimiters: FxHashMap<String, String>,
+	/// What bars are named, with each `{key}` replaced by the bar's value of it (like
+	/// `{track} — {artist}`), instead of just listing the values
+	pub label_template: Option<String>,
+	/// The maximum number of characters of each bar's name that is shown along the x-axis
+	pub label_width: usize,
+	/// What `max_shown` is reset to whenever bars first appear, if anything
+	pub default_page_size: Option<usize>,
+	pub chart: ChartKind,
+	/// Whether a least-squares line is drawn through line and points charts
+	pub trend: bool,
+	/// How many bars each point of a moving average is taken over, if one's drawn. It goes
+	/// through the bars in the order they're in, so it's only meaningful for ones ordered by
+	/// time, like dates bucketed by a date format.
+	pub moving_average: Option<usize>,
+	/// Notes to put on the chart, like "moved countries here"
+	pub annotations: Vec<Annotation>,
+	/// Horizontal lines to compare the bars against, like a target to be above
+	pub reference_lines: Vec<ReferenceLine>,
+	/// Rules that color the bars they match (see [`crate::coloring`])
+	pub color_rules: Vec<ColorRule>,
+	pub order: BarOrder,
+	/// How bars that are just as tall are ordered, when the tallest come first
+	pub tie_break: TieBreak
+}
+
+impl Default for Settings<'_> {
+	fn default() -> Self {
+		Self {
+			bounds: FxHashMap::default(),
+			compare: None,
+			diff: false,
+			periods: None,
+			predicate: None,
+			brushes: Vec::new(),
+			drill: None,
+			sample: None,
+			key_types: FxHashMap::default(),
+			x_axis: Vec::new(),
+			stack_by: None,
+			y_axis: YAxisKey::default(),
+			aggregation: Aggregation::default(),
+			max_shown: usize::MAX,
+			offset: 0,
+			outliers: FxHashMap::default(),
+			merge_below: None,
+			formats: FxHashMap::default(),
+			count_format: NumberFormat::default(),
+			date_formats: FxHashMap::default(),
+			bins: FxHashMap::default(),
+			byte_encodings: FxHashMap::default(),
+			delimiters: FxHashMap::default(),
+			label_template: None,
+			label_width: 24,
+			default_page_size: None,
+			chart: ChartKind::default(),
+			trend: false,
+			moving_average: None,
+			annotations: Vec::new(),
+			reference_lines: Vec::new(),
+			color_rules: Vec::new(),
+			order: BarOrder::default(),
+			tie_break: TieBreak::default()
+		}
+	}
+}
+
+impl Settings<'_> {
+	pub fn shows_all(&self) -> bool {
+		self.max_shown == usize::MAX
+	}
+
+	pub fn page_size(&self) -> usize {
+		self.max_shown.max(1)
+	}
+
+	/// The largest offset that still fills a whole page, if there are enough bars to do so
+	pub fn max_offset(&self, num_bars: usize) -> usize {
+		num_bars.saturating_sub(self.page_size())
+	}
+
+	/// The range of bars (out of `num_bars` total) that should currently be shown
+	pub fn shown_range(&self, num_bars: usize) -> Range<usize> {
+		let start = self.offset.min(self.max_offset(num_bars));
+		start..start.saturating_add(self.page_size()).min(num_bars)
+	}
+
+	/// The keys that rows are grouped by, which are the x-axis keys and then the one that the bars
+	/// are stacked by, if they are
+	pub fn grouped_keys(&self) -> impl Iterator<Item = &String> {
+		self.x_axis.iter().chain(&self.stack_by)
+	}
+
+	/// The format that should be used for the values (heights) of the bars
+	pub fn value_format(&self) -> NumberFormat {
+		match self.y_axis {
+			YAxisKey::Count => self.count_format,
+			YAxisKey::Key(key) => self.formats.get(key).copied().unwrap_or_default(),
+			YAxisKey::Rate(_) => NumberFormat::Percent
+		}
+	}
+
+	pub fn byte_encoding(&self, key: &str) -> ByteEncoding {
+		self.byte_encodings.get(key).copied().unwrap_or_default()
+	}
+
+	/// The value that should be used when grouping rows by `key`, which may not be exactly the
+	/// value stored in the data (if, for instance, it's a timestamp or number that's being
+	/// bucketed).
+	pub fn grouping_value<'v, 's>(&self, key: &str, value: &'v Value<'s>) -> Cow<'v, Value<'s>> {
+		if let Some(binned) = self
+			.bins
+			.get(key)
+			.and_then(|bins| bins.grouping_value(value))
+		{
+			return Cow::Owned(binned);
+		}
+		self.date_formats
+			.get(key)
+			.and_then(|format| format_timestamp(value, format))
+			.map_or(Cow::Borrowed(value), |s| Cow::Owned(Value::Str(s.into())))
+	}
+
+	/// How the given value (as returned by [`Self::grouping_value`]) should be shown in a bar
+	/// name.
+	pub fn label_for(&self, key: &str, value: &Value) -> String {
+		match value {
+			Value::Str(s) => s.to_string(),
+			Value::Bytes(bytes) => self.byte_encoding(key).encode(bytes),
+			Value::Bool(b) => b.to_string(),
+			Value::Null => "null".to_string(),
+			_ => {
+				let format = self.formats.get(key).copied().unwrap_or_default();
+				match (value, self.bins.get(key)) {
+					(Value::Float(start), Some(bins)) => bins.label(start.into_inner(), format),
+					_ => format
+						.format_value(value)
+						.unwrap_or_else(|| format!("{value:?}"))
+				}
+			}
+		}
+	}
+
+	/// Stops bounding (in `bounds` and `compare`) the keys that `type_of` doesn't know the type of,
+	/// or whose bounds can't bound that type, returning which keys those were
+	pub fn retain_bounds(&mut self, type_of: impl Fn(&str) -> Option<ValueType>) -> Vec<String> {
+		let mut dropped = Vec::new();
+		for bounds in std::iter::once(&mut self.bounds).chain(&mut self.compare) {
+			bounds.retain(|key, bound| {
+				let fits = type_of(key).is_some_and(|ty| bound.fits(ty));
+				if !fits {
+					dropped.push(key.clone());
+				}
+				fits
+			});
+		}
+		dropped.sort();
+		dropped.dedup();
+		dropped
+	}
+
+	pub fn scroll_by(&mut self, delta: isize, num_bars: usize) {
+		self.offset = self
+			.offset
+			.saturating_add_signed(delta)
+			.min(self.max_offset(num_bars));
+	}
+}
+
+/// How the bars are drawn on the plot
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+	#[default]
+	Bars,
+	/// A line through the top of where each bar would be
+	Line,
+	/// A point at the top of where each bar would be
+	Points,
+	/// Where the rows are on a map of the world, by their country codes or coordinates
+	Map
+}
+
+impl ChartKind {
+	pub const ALL: [Self; 4] = [Self::Bars, Self::Line, Self::Points, Self::Map];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Bars => "Bars",
+			Self::Line => "Line",
+			Self::Points => "Points",
+			Self::Map => "Map"
+		}
+	}
+}
+
+/// Which bars come first
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarOrder {
+	#[default]
+	Tallest,
+	/// By their labels, which puts e.g. months (formatted like `%Y-%m`) in the order they happened
+	Labels
+}
+
+impl BarOrder {
+	pub const ALL: [Self; 2] = [Self::Tallest, Self::Labels];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Tallest => "Tallest first",
+			Self::Labels => "By label"
+		}
+	}
+}
+
+/// Which of the bars that are just as tall as each other comes first
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TieBreak {
+	/// By their labels, alphabetically
+	#[default]
+	Alphabetical,
+	/// By their labels, but with numbers ordered by value (like [`BarOrder::Labels`])
+	Natural,
+	/// The one made up of more rows, which is only different from how tall they are when a key's
+	/// being added up or a rate's being taken
+	Rows
+}
+
+impl TieBreak {
+	pub const ALL: [Self; 3] = [Self::Alphabetical, Self::Natural, Self::Rows];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Alphabetical => "Alphabetically",
+			Self::Natural => "By label, numbers by value",
+			Self::Rows => "Most rows first"
+		}
+	}
+}
+
+impl std::str::FromStr for TieBreak {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"alphabetical" => Ok(Self::Alphabetical),
+			"natural" => Ok(Self::Natural),
+			"rows" => Ok(Self::Rows),
+			_ => Err(format!(
+				"Unknown tie break '{s}' (expected alphabetical, natural, or rows)"
+			))
+		}
+	}
+}
+
+/// How the values of a numeric y-axis key are combined into the height of each bar
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+	#[default]
+	Sum,
+	Mean,
+	/// The middle of the values, which means keeping every one of them around until the bars are
+	/// made
+	Median,
+	Min,
+	Max
+}
+
+impl Aggregation {
+	pub const ALL: [Self; 5] = [Self::Sum, Self::Mean, Self::Median, Self::Min, Self::Max];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Sum => "Sum",
+			Self::Mean => "Mean",
+			Self::Median => "Median",
+			Self::Min => "Minimum",
+			Self::Max => "Maximum"
+		}
+	}
+}
+
+impl std::str::FromStr for Aggregation {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"sum" => Ok(Self::Sum),
+			"mean" => Ok(Self::Mean),
+			"median" => Ok(Self::Median),
+			"min" => Ok(Self::Min),
+			"max" => Ok(Self::Max),
+			_ => Err(format!(
+				"Unknown aggregation '{s}' (expected sum, mean, median, min, or max)"
+			))
+		}
+	}
+}
+
+/// A note on the chart, which is drawn above what it's anchored to
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Annotation {
+	pub text: String,
+	pub anchor: Anchor
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+	/// The bar with these labels, wherever it ends up
+	Bar(Vec<String>),
+	/// A position along the x-axis, where each bar is 1 wide and the first one is at 0
+	X(f64)
+}
+
+impl Annotation {
+	/// Where the annotation goes among `bars`, and how tall the bar it's on is, if it's on one
+	pub fn position(&self, bars: &[LabeledBar]) -> Option<(f64, Option<f64>)> {
+		match &self.anchor {
+			Anchor::Bar(labels) => bars
+				.iter()
+				.position(|bar| bar.labels == *labels)
+				.map(|idx| (idx as f64, Some(bars[idx].value))),
+			Anchor::X(x) => Some((*x, None))
+		}
+	}
+}
+
+/// A horizontal line across the chart
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceLine {
+	Value(f64),
+	/// The mean of all the bars' heights (not just the ones on the page)
+	Mean,
+	/// The median of all the bars' heights
+	Median
+}
+
+impl ReferenceLine {
+	/// Where the line goes, for bars of these heights
+	pub fn height(self, heights: &[f64]) -> Option<f64> {
+		match self {
+			Self::Value(value) => Some(value),
+			_ if heights.is_empty() => None,
+			Self::Mean => Some(heights.iter().sum::<f64>() / heights.len() as f64),
+			Self::Median => {
+				let mut sorted = heights.to_vec();
+				sorted.sort_unstable_by(f64::total_cmp);
+				let mid = sorted.len() / 2;
+				Some(if sorted.len().is_multiple_of(2) {
+					(sorted[mid - 1] + sorted[mid]) / 2.
+				} else {
+					sorted[mid]
+				})
+			}
+		}
+	}
+
+	/// What the line's labelled with, when it's at `height`
+	pub fn label(self, height: f64, format: NumberFormat) -> String {
+		let value = format.format(height);
+		match self {
+			Self::Value(_) => value,
+			Self::Mean => format!("Mean: {value}"),
+			Self::Median => format!("Median: {value}")
+		}
+	}
+}
+
+impl std::str::FromStr for ReferenceLine {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"mean" => Ok(Self::Mean),
+			"median" => Ok(Self::Median),
+			_ => s
+				.parse()
+				.map(Self::Value)
+				.map_err(|_| format!("expected a number, 'mean', or 'median', not '{s}'"))
+		}
+	}
+}
+
+#[derive(Default, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum YAxisKey<'keys> {
+	#[default]
+	Count,
+	/// The key's values, combined by [`Settings::aggregation`]
+	Key(&'keys str),
+	/// The fraction of rows where the key (a boolean) is true
+	Rate(&'keys str)
+}
+
+/// `key`, kept around for as long as the program runs so that a [`YAxisKey`] can point to it.
+/// Each key is only ever kept once, and there are only so many keys.
+pub fn static_key(key: &str) -> &'static str {
+	static KEYS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+	let mut keys = KEYS.lock().unwrap_or_else(|e| e.into_inner());
+	match keys.iter().find(|k| **k == key) {
+		Some(kept) => kept,
+		None => {
+			let kept = String::leak(key.to_string());
+			keys.push(kept);
+			kept
+		}
+	}
+}
+
+impl YAxisKey<'_> {
+	/// Deserializes a y-axis without borrowing its key from what's being deserialized, so that
+	/// settings can be read from anywhere. It can be of any key, which is checked against the data
+	/// once there is some.
+	fn deserialize_known<'de, 'keys, D>(deserializer: D) -> Result<YAxisKey<'keys>, D::Error>
+	where
+		D: serde::Deserializer<'de>
+	{
+		#[derive(Deserialize)]
+		#[serde(rename_all = "lowercase")]
+		enum Owned {
+			Count,
+			Key(String),
+			Rate(String)
+		}
+
+		Ok(match Owned::deserialize(deserializer)? {
+			Owned::Count => YAxisKey::Count,
+			Owned::Key(key) => YAxisKey::Key(static_key(&key)),
+			Owned::Rate(key) => YAxisKey::Rate(static_key(&key))
+		})
+	}
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bound<T> {
+	Range(Range<T>),
+	Specifics { include: Inclusion, values: Vec<T> }
+}
+
+impl<T> Bound<T> {
+	const fn specifics(include: Inclusion) -> Self {
+		Self::Specifics {
+			include,
+			values: vec![]
+		}
+	}
+
+	pub fn excludes(&self, val: &T) -> bool
+	where
+		T: PartialEq + PartialOrd
+	{
+		match self {
+			Self::Range(range) => !range.contains(val),
+			Self::Specifics {
+				include: Inclusion::Exclude,
+				values
+			} => values.contains(val),
+			Self::Specifics {
+				include: Inclusion::Include,
+				values
+			} => !values.contains(val)
+		}
+	}
+}
+
+impl<T> Default for Bound<T> {
+	fn default() -> Self {
+		Self::specifics(Inclusion::Exclude)
+	}
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Inclusion {
+	Include,
+	Exclude
+}
+
+/// What a bound on a float key does with NaNs, which aren't in any range and aren't equal to
+/// anything (not even themselves), so they'd otherwise come and go depending on the kind of bound
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Nans {
+	/// Leaves them out, the same as nulls (which every bound except a bool one does)
+	#[default]
+	Exclude,
+	/// Keeps them, whatever else the bound leaves out
+	Include
+}
+
+impl Nans {
+	pub const ALL: [Self; 2] = [Self::Exclude, Self::Include];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Exclude => "Leave out NaNs",
+			Self::Include => "Keep NaNs"
+		}
+	}
+}
+
+/// Which of `true`, `false`, and null a bound on a bool key lets through. Since there are only
+/// three, this can say exactly which, and so (unlike every other bound) it can also keep nulls.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Bools {
+	pub trues: bool,
+	pub falses: bool,
+	pub nulls: bool
+}
+
+impl Bools {
+	/// Lets through everything but nulls
+	pub const NOT_NULL: Self = Self {
+		trues: true,
+		falses: true,
+		nulls: false
+	};
+
+	/// Lets through only `val`
+	pub fn only(val: bool) -> Self {
+		Self {
+			trues: val,
+			falses: !val,
+			nulls: false
+		}
+	}
+
+	pub fn allows(&self, val: Option<bool>) -> bool {
+		match val {
+			Some(true) => self.trues,
+			Some(false) => self.falses,
+			None => self.nulls
+		}
+	}
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueBound {
+	I64(Bound<i64>),
+	U64(Bound<u64>),
+	F64(Bound<f64>, Nans),
+	Str {
+		include: Inclusion,
+		values: Vec<String>
+	},
+	Bool(Bools)
+}
+
+impl ValueBound {
+	/// If this can bound the values of a key of type `ty`
+	pub fn fits(&self, ty: ValueType) -> bool {
+		!matches!(ty, ValueType::Map | ValueType::Array)
+			&& Self::base_options_for(ty)
+				.iter()
+				.any(|option| std::mem::discriminant(option) == std::mem::discriminant(self))
+	}
+
+	pub fn base_options_for(ty: ValueType) -> &'static [Self] {
+		static I64_ARR: &[ValueBound] = &[
+			ValueBound::I64(Bound::Range(0..i64::MAX)),
+			ValueBound::I64(Bound::specifics(Inclusion::Exclude)),
+			ValueBound::I64(Bound::specifics(Inclusion::Include))
+		];
+		static U64_ARR: &[ValueBound] = &[
+			ValueBound::U64(Bound::Range(0..u64::MAX)),
+			ValueBound::U64(Bound::specifics(Inclusion::Exclude)),
+			ValueBound::U64(Bound::specifics(Inclusion::Include))
+		];
+		static F64_ARR: &[ValueBound] = &[
+			ValueBound::F64(Bound::Range(0.0..f64::MAX), Nans::Exclude),
+			ValueBound::F64(Bound::specifics(Inclusion::Exclude), Nans::Exclude),
+			ValueBound::F64(Bound::specifics(Inclusion::Include), Nans::Exclude)
+		];
+		static STR_ARR: &[ValueBound] = &[
+			ValueBound::Str {
+				include: Inclusion::Include,
+				values: vec![]
+			},
+			ValueBound::Str {
+				include: Inclusion::Exclude,
+				values: vec![]
+			}
+		];
+
+		match ty {
+			ValueType::I64 => I64_ARR,
+			ValueType::U64 => U64_ARR,
+			ValueType::Float => F64_ARR,
+			ValueType::String => STR_ARR,
+			ValueType::Bool => &[ValueBound::Bool(Bools::NOT_NULL)],
+			ValueType::Bytes | ValueType::Null => &[],
+			_ => unreachable!("These values should've been checked by this point")
+		}
+	}
+
+	pub fn ui_descriptor(&self) -> &'static str {
+		match self {
+			ValueBound::I64(Bound::Range(_))
+			| ValueBound::U64(Bound::Range(_))
+			| ValueBound::F64(Bound::Range(_), _) => "Range",
+			ValueBound::I64(Bound::Specifics {
+				include: Inclusion::Exclude,
+				..
+			})
+			| ValueBound::U64(Bound::Specifics {
+				include: Inclusion::Exclude,
+				..
+			})
+			| ValueBound::F64(
+				Bound::Specifics {
+					include: Inclusion::Exclude,
+					..
+				},
+				_
+			)
+			| ValueBound::Str {
+				include: Inclusion::Exclude,
+				..
+			} => "Exclude Values",
+			ValueBound::I64(Bound::Specifics {
+				include: Inclusion::Include,
+				..
+			})
+			| ValueBound::U64(Bound::Specifics {
+				include: Inclusion::Include,
+				..
+			})
+			| ValueBound::F64(
+				Bound::Specifics {
+					include: Inclusion::Include,
+					..
+				},
+				_
+			)
+			| ValueBound::Str {
+				include: Inclusion::Include,
+				..
+			} => "Include Values",
+			ValueBound::Bool(_) => "Values"
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn paged(max_shown: usize, offset: usize) -> Settings<'static> {
+		Settings {
+			max_shown,
+			offset,
+			..Settings::default()
+		}
+	}
+
+	#[test]
+	fn shown_range() {
+		assert_eq!(paged(10, 0).shown_range(25), 0..10);
+		assert_eq!(paged(10, 10).shown_range(25), 10..20);
+		// The last page is still a whole one, if there are enough bars for that
+		assert_eq!(paged(10, 20).shown_range(25), 15..25);
+		assert_eq!(paged(10, 5).shown_range(3), 0..3);
+		assert_eq!(paged(usize::MAX, 0).shown_range(25), 0..25);
+		// Showing none still shows one
+		assert_eq!(paged(0, 0).shown_range(25), 0..1);
+	}
+
+	#[test]
+	fn scrolling() {
+		let mut settings = paged(10, 0);
+		settings.scroll_by(-3, 25);
+		assert_eq!(settings.offset, 0);
+		settings.scroll_by(12, 25);
+		assert_eq!(settings.offset, 12);
+		settings.scroll_by(100, 25);
+		assert_eq!(settings.offset, 15);
+		settings.scroll_by(-5, 25);
+		assert_eq!(settings.offset, 10);
+		settings.scroll_by(isize::MIN, 25);
+		assert_eq!(settings.offset, 0);
+		// Fewer bars than fit on a page can't be scrolled at all
+		settings.scroll_by(1, 5);
+		assert_eq!(settings.offset, 0);
+	}
+
+	#[test]
+	fn bounds_that_fit() {
+		let range = ValueBound::I64(Bound::Range(0..5));
+		assert!(range.fits(ValueType::I64));
+		assert!(!range.fits(ValueType::U64));
+		assert!(!range.fits(ValueType::Float));
+		let values = ValueBound::Str {
+			include: Inclusion::Include,
+			values: vec!["a".to_string()]
+		};
+		assert!(values.fits(ValueType::String));
+		assert!(!values.fits(ValueType::I64));
+		assert!(!values.fits(ValueType::Null));
+		assert!(!values.fits(ValueType::Array));
+	}
+
+	#[test]
+	fn retaining_bounds() {
+		let values = ValueBound::Str {
+			include: Inclusion::Include,
+			values: vec!["a".to_string()]
+		};
+		let mut settings = Settings::default();
+		settings.bounds.insert("same".to_string(), values.clone());
+		settings
+			.bounds
+			.insert("retyped".to_string(), values.clone());
+		settings.bounds.insert("gone".to_string(), values.clone());
+		settings.compare = Some(settings.bounds.clone());
+
+		let dropped = settings.retain_bounds(|key| match key {
+			"same" => Some(ValueType::String),
+			"retyped" => Some(ValueType::I64),
+			_ => None
+		});
+		assert_eq!(dropped, ["gone", "retyped"]);
+		for bounds in [Some(&settings.bounds), settings.compare.as_ref()] {
+			let keys = bounds.unwrap().keys().collect::<Vec<_>>();
+			assert_eq!(keys, ["same"]);
+		}
+	}
+}
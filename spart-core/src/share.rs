@@ -0,0 +1,71 @@
+//! Turning [`Settings`] into a short token (or a `spart://` URL) that can be sent to someone else,
+//! so that they can see exactly the same chart of the same data by pasting it in. Only what's
+//! different from the defaults goes into it, which keeps it short.
+
+use serde_json::Value;
+
+use crate::{
+	format::{ByteEncoding, decode_base64},
+	schema::DataErr,
+	script::ScriptErr,
+	settings::Settings
+};
+
+/// What a token is prefixed by when it's shared as a URL
+pub const URL_PREFIX: &str = "spart://view/";
+
+/// The key that a predicate's source is stored under, since [`Settings`] can't (de)serialize
+/// compiled scripts
+const PREDICATE: &str = "predicate";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ShareErr {
+	#[error("That isn't a view that was shared from spart")]
+	NotAView,
+	#[error("The view couldn't be read: {0}")]
+	Invalid(#[from] serde_json::Error),
+	#[error("The view doesn't fit this data: {0}")]
+	Data(#[from] DataErr),
+	#[error("The view's filter couldn't be compiled: {0}")]
+	Script(#[from] ScriptErr)
+}
+
+/// The token for `settings`, which [`decode`] turns back into them
+pub fn encode(settings: &Settings) -> String {
+	// Serializing settings can't fail, since all their maps have string keys
+	let mut changed = match serde_json::to_value(settings) {
+		Ok(Value::Object(object)) => object,
+		_ => unreachable!("settings always serialize to an object")
+	};
+	if let Ok(Value::Object(defaults)) = serde_json::to_value(Settings::default()) {
+		changed.retain(|key, value| defaults.get(key) != Some(value));
+	}
+	if let Some(predicate) = &settings.predicate {
+		changed.insert(PREDICATE.to_string(), predicate.source.clone().into());
+	}
+
+	ByteEncoding::Base64
+		.encode(Value::Object(changed).to_string().as_bytes())
+		.trim_end_matches('=')
+		.replace('+', "-")
+		.replace('/', "_")
+}
+
+/// The settings that a token (or a URL with one) was made from, along with the source of their
+/// predicate if they had one, which still has to be compiled
+pub fn decode(token: &str) -> Result<(Settings<'static>, Option<String>), ShareErr> {
+	let token = token.trim();
+	let token = token.strip_prefix(URL_PREFIX).unwrap_or(token);
+	let json = decode_base64(token).ok_or(ShareErr::NotAView)?;
+
+	let mut object = match serde_json::from_slice(&json)? {
+		Value::Object(object) => object,
+		_ => return Err(ShareErr::NotAView)
+	};
+	let predicate = match object.remove(PREDICATE) {
+		Some(Value::String(source)) => Some(source),
+		Some(_) => return Err(ShareErr::NotAView),
+		None => None
+	};
+	Ok((serde_json::from_value(Value::Object(object))?, predicate))
+}
@@ -0,0 +1,433 @@
+//! Things that only make sense for listening histories, like Spotify's streaming history exports
+//! (which are what spart was first made to look at) and Apple Music's play activity. Each export
+//! has its own names for things, so they're all given the same ones here, and everything else
+//! works off of those.
+
+use std::{str::FromStr, time::Duration};
+
+use chrono::{DateTime, Timelike};
+use merde::{CowStr, Value, ValueType};
+
+use crate::{
+	format::NumberFormat,
+	schema::Schema,
+	settings::{BarOrder, Bools, ChartKind, Settings, ValueBound, YAxisKey}
+};
+
+/// How many milliseconds of the track were played, in every kind of Spotify export (and copied
+/// here from Apple's)
+pub const MS_PLAYED: &str = "ms_played";
+
+/// When the track was played, as an RFC 3339 timestamp
+pub const TIMESTAMP: &str = "ts";
+
+/// Which hour of the day (0 to 23, in UTC like the timestamps are) the track was played in, once
+/// [`derive_keys`] has worked it out
+pub const HOUR: &str = "hour";
+
+/// Whether the track was skipped, once [`derive_keys`] has worked it out. This is also what
+/// Spotify's extended history calls its own flag for it, which is kept wherever it was set.
+pub const SKIPPED: &str = "skipped";
+
+/// How short a play has to be to count as a skip unless it's been changed, which is how long
+/// Spotify needs a track to play for to count it as a stream
+pub const DEFAULT_SKIP_THRESHOLD: Duration = Duration::from_secs(30);
+
+// What was played, once [`derive_keys`] has pulled it out of the export's raw keys
+pub const ARTIST: &str = "artist";
+pub const ALBUM: &str = "album";
+pub const TRACK: &str = "track";
+
+/// The keys of each export that have cleaner names, as the name they're also given and the raw
+/// keys that they might be copied from (Spotify's extended streaming history first, then Apple
+/// Music's play activity)
+const RENAMED: &[(&str, &[&str])] = &[
+	(ARTIST, &[
+		"master_metadata_album_artist_name",
+		"Artist Name",
+		"Container Artist Name"
+	]),
+	(ALBUM, &["master_metadata_album_album_name", "Album Name"]),
+	(TRACK, &["master_metadata_track_name", "Song Name"]),
+	(TIMESTAMP, &["Event Start Timestamp"]),
+	// Apple's are in milliseconds too, so they can be copied as they are
+	(MS_PLAYED, &["Play Duration Milliseconds"])
+];
+
+/// What Apple's daily play history calls the date each track was played, given like `20240131`
+const APPLE_DATE_PLAYED: &str = "Date Played";
+
+/// Apple's daily play history names each track like `Artist - Track` instead of having a key for
+/// each
+const APPLE_DESCRIPTION: &str = "Track Description";
+
+/// Why Apple says each play ended, which is also how it says it was skipped
+const APPLE_END_REASON: &str = "End Reason Type";
+const APPLE_SKIP_REASONS: &[&str] = &["TRACK_SKIPPED_FORWARDS", "TRACK_SKIPPED_BACKWARDS"];
+
+/// Whether the play was of a podcast episode instead of a song, once [`derive_keys`] has worked it
+/// out
+pub const PODCAST: &str = "podcast";
+
+/// Keys that are only set for podcast episodes
+const EPISODE_KEYS: &[&str] = &[
+	"spotify_episode_uri",
+	"episode_name",
+	"episode_show_name",
+	"show_name"
+];
+
+/// What Spotify calls the ID of each track that was played, which is only set for songs
+const SPOTIFY_TRACK_URI: &str = "spotify_track_uri";
+
+/// Keys that might hold when a track was released, if the data's been given that somewhere along
+/// the way (Spotify's own exports don't have it)
+const RELEASE_KEYS: &[&str] = &["release_date", "album_release_date", "release_year", "year"];
+
+/// Adds the keys that can be worked out from each export's raw ones: `artist`, `album`, `track`,
+/// `ts`, and `ms_played` (where they're called something else), `podcast` (whether it was an
+/// episode instead of a song), `decade` (when it was released), `hour` (what time of day it was
+/// played), and `skipped` (if it was played for less than `skip_threshold`, unless the export
+/// says otherwise). Each is only added if the row has what it's worked out from and doesn't have
+/// it already, so this does nothing to data that isn't a listening history.
+pub fn derive_keys(row: &mut merde::Map, skip_threshold: Duration) {
+	let missing = |row: &merde::Map, key: &'static str| !row.contains_key(&CowStr::from(key));
+
+	for &(derived, raws) in RENAMED {
+		if let Some(value) = raws.iter().find_map(|raw| row.get(&CowStr::from(*raw)))
+			&& missing(row, derived)
+		{
+			let value = value.clone();
+			row.insert(derived.into(), value);
+		}
+	}
+	derive_apple_keys(row);
+
+	// Rows that have had their nulls stripped out might only have the keys of what they are, so a
+	// track's URI is enough to know that it wasn't an episode
+	let says_what_it_is = EPISODE_KEYS
+		.iter()
+		.chain([&SPOTIFY_TRACK_URI])
+		.any(|key| row.contains_key(&CowStr::from(*key)));
+	if says_what_it_is && missing(row, PODCAST) {
+		let podcast = EPISODE_KEYS
+			.iter()
+			.any(|key| matches!(row.get(&CowStr::from(*key)), Some(Value::Str(_))));
+		row.insert(PODCAST.into(), Value::Bool(podcast));
+	}
+
+	let release = RELEASE_KEYS
+		.iter()
+		.find_map(|key| row.get(&CowStr::from(*key)));
+	if let Some(release) = release
+		&& missing(row, "decade")
+	{
+		let year = match release {
+			Value::Str(date) => date.get(..4).and_then(|year| year.parse::<i64>().ok()),
+			Value::I64(year) => Some(*year),
+			Value::U64(year) => i64::try_from(*year).ok(),
+			_ => None
+		};
+		let decade = year.map_or(Value::Null, |year| {
+			Value::Str(format!("{}s", year - year.rem_euclid(10)).into())
+		});
+		row.insert("decade".into(), decade);
+	}
+
+	if let Some(ts) = row.get(&CowStr::from(TIMESTAMP))
+		&& missing(row, HOUR)
+	{
+		let hour = match ts {
+			Value::Str(ts) => DateTime::parse_from_rfc3339(ts)
+				.map_or(Value::Null, |time| Value::U64(time.to_utc().hour().into())),
+			_ => Value::Null
+		};
+		row.insert(HOUR.into(), hour);
+	}
+
+	// Spotify only sets its flag some of the time, so the rest of the rows are filled in from how
+	// long they were played for
+	let played = match row.get(&CowStr::from(MS_PLAYED)) {
+		Some(Value::I64(ms)) => u64::try_from(*ms).ok(),
+		Some(Value::U64(ms)) => Some(*ms),
+		Some(Value::Float(ms)) => Some(ms.into_inner() as u64),
+		_ => None
+	};
+	if let Some(played) = played
+		&& matches!(row.get(&CowStr::from(SKIPPED)), None | Some(Value::Null))
+	{
+		let skipped = u128::from(played) < skip_threshold.as_millis();
+		row.insert(SKIPPED.into(), Value::Bool(skipped));
+	}
+}
+
+/// The keys that Apple's exports need more than renaming for
+fn derive_apple_keys(row: &mut merde::Map) {
+	let missing = |row: &merde::Map, key: &'static str| !row.contains_key(&CowStr::from(key));
+
+	let date = match row.get(&CowStr::from(APPLE_DATE_PLAYED)) {
+		Some(Value::U64(date)) => Some(date.to_string()),
+		Some(Value::I64(date)) => Some(date.to_string()),
+		Some(Value::Str(date)) => Some(date.to_string()),
+		_ => None
+	};
+	if let Some(date) = date
+		&& missing(row, TIMESTAMP)
+	{
+		let ts = match (date.get(..4), date.get(4..6), date.get(6..8)) {
+			(Some(year), Some(month), Some(day)) if date.len() == 8 =>
+				Value::Str(format!("{year}-{month}-{day}T00:00:00Z").into()),
+			_ => Value::Null
+		};
+		row.insert(TIMESTAMP.into(), ts);
+	}
+
+	let description = match row.get(&CowStr::from(APPLE_DESCRIPTION)) {
+		Some(Value::Str(description)) => description.split_once(" - ").map(|(artist, track)| {
+			(
+				Value::Str(artist.to_string().into()),
+				Value::Str(track.to_string().into())
+			)
+		}),
+		_ => None
+	};
+	if let Some((artist, track)) = description
+		&& missing(row, ARTIST)
+		&& missing(row, TRACK)
+	{
+		row.insert(ARTIST.into(), artist);
+		row.insert(TRACK.into(), track);
+	}
+
+	let skipped = match row.get(&CowStr::from(APPLE_END_REASON)) {
+		Some(Value::Str(reason)) => Some(APPLE_SKIP_REASONS.contains(&reason.as_ref())),
+		_ => None
+	};
+	if let Some(skipped) = skipped
+		&& missing(row, SKIPPED)
+	{
+		row.insert(SKIPPED.into(), Value::Bool(skipped));
+	}
+}
+
+/// If each row of the data is a play of some track, which it can be exported as
+pub fn has_plays(schema: &Schema) -> bool {
+	[TIMESTAMP, ARTIST, TRACK]
+		.iter()
+		.all(|key| schema.keys.iter().any(|(k, _)| k == key))
+}
+
+/// If each bar is a track, an album, or an artist (or some mix of them), so it has an album
+/// cover that can be shown alongside it
+pub fn has_covers(schema: &Schema, settings: &Settings) -> bool {
+	[ARTIST, ALBUM]
+		.iter()
+		.all(|key| schema.keys.iter().any(|(k, _)| k == key))
+		&& !settings.x_axis.is_empty()
+		&& settings
+			.x_axis
+			.iter()
+			.all(|key| [ARTIST, ALBUM, TRACK].contains(&key.as_str()))
+}
+
+/// If the data has `ms_played`, so that the listening time can be charted
+pub fn has_listening_time(schema: &Schema) -> bool {
+	schema.keys.iter().any(|(key, ty)| {
+		key == MS_PLAYED && matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float)
+	})
+}
+
+/// If the bars are showing how long was spent listening, instead of how many times
+pub fn is_listening_time(settings: &Settings) -> bool {
+	matches!(settings.y_axis, YAxisKey::Key(MS_PLAYED))
+}
+
+/// If the data says which plays were skipped, so that skip rates can be charted
+pub fn has_skips(schema: &Schema) -> bool {
+	schema
+		.keys
+		.iter()
+		.any(|(key, ty)| key == SKIPPED && *ty == ValueType::Bool)
+}
+
+/// What's done with plays of podcast episodes, which would otherwise crowd out the music in most
+/// charts (since an episode is usually played for so much longer than a song)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Podcasts {
+	Included,
+	Excluded,
+	/// Nothing but podcasts, so they can be charted on their own
+	Only
+}
+
+impl Podcasts {
+	pub const ALL: [Self; 3] = [Self::Included, Self::Excluded, Self::Only];
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::Included => "Included",
+			Self::Excluded => "Left out",
+			Self::Only => "Only podcasts"
+		}
+	}
+
+	/// What the bounds in `settings` are doing with podcasts
+	pub fn of(settings: &Settings) -> Self {
+		match settings.bounds.get(PODCAST) {
+			Some(ValueBound::Bool(bools)) if *bools == Bools::only(false) => Self::Excluded,
+			Some(ValueBound::Bool(bools)) if *bools == Bools::only(true) => Self::Only,
+			_ => Self::Included
+		}
+	}
+
+	/// Bounds the data to this choice of podcasts
+	pub fn apply(self, settings: &mut Settings) {
+		let bound = match self {
+			Self::Included => None,
+			Self::Excluded => Some(ValueBound::Bool(Bools::only(false))),
+			Self::Only => Some(ValueBound::Bool(Bools::only(true)))
+		};
+		match bound {
+			Some(bound) => _ = settings.bounds.insert(PODCAST.to_string(), bound),
+			None => _ = settings.bounds.remove(PODCAST)
+		}
+	}
+}
+
+/// If the data says which plays were of podcasts
+pub fn has_podcasts(schema: &Schema) -> bool {
+	schema
+		.keys
+		.iter()
+		.any(|(key, ty)| key == PODCAST && *ty == ValueType::Bool)
+}
+
+/// If the bars are showing how often they were skipped
+pub fn is_skip_rate(settings: &Settings) -> bool {
+	matches!(settings.y_axis, YAxisKey::Rate(SKIPPED))
+}
+
+/// Makes each bar as tall as the fraction of its plays that were skipped
+pub fn show_skip_rate(settings: &mut Settings) {
+	settings.y_axis = YAxisKey::Rate(SKIPPED);
+}
+
+/// Makes each bar as tall as the total time spent listening to it, shown in hours and minutes
+pub fn show_listening_time(settings: &mut Settings) {
+	settings.y_axis = YAxisKey::Key(MS_PLAYED);
+	settings
+		.formats
+		.insert(MS_PLAYED.to_string(), NumberFormat::Duration);
+}
+
+/// The charts that anyone looking at their listening history probably wants to see first
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preset {
+	/// The 50 most played artists
+	TopArtists,
+	/// The 50 tracks that were listened to for longest
+	TopTracks,
+	/// How many times anything was played each month, in order
+	ListensPerMonth,
+	/// How many times anything was played in each hour of the day, from midnight to 11pm
+	HourOfDay
+}
+
+/// How many bars the top artists and tracks are cut down to
+const TOP_COUNT: usize = 50;
+
+impl Preset {
+	pub const ALL: [Self; 4] = [
+		Self::TopArtists,
+		Self::TopTracks,
+		Self::ListensPerMonth,
+		Self::HourOfDay
+	];
+
+	/// What the preset's called on the command line
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::TopArtists => "top-artists",
+			Self::TopTracks => "top-tracks",
+			Self::ListensPerMonth => "listens-per-month",
+			Self::HourOfDay => "hour-of-day"
+		}
+	}
+
+	pub fn ui_descriptor(self) -> &'static str {
+		match self {
+			Self::TopArtists => "Top artists",
+			Self::TopTracks => "Top tracks",
+			Self::ListensPerMonth => "Listens per month",
+			Self::HourOfDay => "Listens by hour"
+		}
+	}
+
+	/// If the data has the keys that this preset charts
+	pub fn applies_to(self, schema: &Schema) -> bool {
+		let has = |key| schema.keys.iter().any(|(k, _)| k == key);
+		match self {
+			Self::TopArtists => has(ARTIST),
+			Self::TopTracks => has(ARTIST) && has(TRACK) && has_listening_time(schema),
+			Self::ListensPerMonth => has(TIMESTAMP),
+			Self::HourOfDay => has(HOUR)
+		}
+	}
+
+	/// Sets up `settings` to show this preset's chart. Bounds are left alone, so that e.g. the top
+	/// artists of a single year can still be seen.
+	pub fn apply(self, settings: &mut Settings) {
+		settings.chart = ChartKind::Bars;
+		settings.offset = 0;
+		match self {
+			Self::TopArtists => {
+				settings.x_axis = vec![ARTIST.to_string()];
+				settings.y_axis = YAxisKey::Count;
+				settings.order = BarOrder::Tallest;
+				settings.max_shown = TOP_COUNT;
+			}
+			Self::TopTracks => {
+				// Plenty of different tracks share a name, so they're told apart by their artist
+				settings.x_axis = vec![TRACK.to_string(), ARTIST.to_string()];
+				show_listening_time(settings);
+				settings.order = BarOrder::Tallest;
+				settings.max_shown = TOP_COUNT;
+			}
+			Self::ListensPerMonth => {
+				settings.x_axis = vec![TIMESTAMP.to_string()];
+				settings
+					.date_formats
+					.insert(TIMESTAMP.to_string(), "%Y-%m".to_string());
+				settings.y_axis = YAxisKey::Count;
+				settings.order = BarOrder::Labels;
+				settings.max_shown = usize::MAX;
+			}
+			Self::HourOfDay => {
+				settings.x_axis = vec![HOUR.to_string()];
+				settings
+					.formats
+					.insert(HOUR.to_string(), NumberFormat::Plain);
+				settings.y_axis = YAxisKey::Count;
+				settings.order = BarOrder::Labels;
+				settings.max_shown = usize::MAX;
+			}
+		}
+	}
+}
+
+impl FromStr for Preset {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|preset| preset.name().eq_ignore_ascii_case(s))
+			.ok_or_else(|| {
+				let names = Self::ALL.map(Self::name);
+				format!(
+					"Unknown preset '{s}' (expected one of {})",
+					names.join(", ")
+				)
+			})
+	}
+}
@@ -0,0 +1,184 @@
+//! Running SQL over the data with DataFusion, for charts that the settings can't describe. The
+//! data is a table called `data`, and each row that a query returns becomes a bar: the last column
+//! is how tall it is, and the rest are what it's labelled with. So
+//! `SELECT artist, count(*) FROM data GROUP BY 1` charts the same thing as grouping by `artist`.
+
+use std::sync::Arc;
+
+use datafusion::{
+	arrow::{
+		array::{
+			Array, ArrayRef, AsArray, BinaryArray, BooleanArray, Float64Array, Int64Array,
+			NullArray, StringArray, UInt64Array
+		},
+		compute::cast,
+		datatypes::{DataType, Field, Float64Type, Schema as ArrowSchema},
+		error::ArrowError,
+		record_batch::RecordBatch,
+		util::display::array_value_to_string
+	},
+	error::DataFusionError,
+	prelude::SessionContext
+};
+use merde::{Value, ValueType};
+use tokio::runtime::Runtime;
+
+use crate::{bars::LabeledBar, schema::Schema};
+
+/// What the data's called in queries
+pub const TABLE: &str = "data";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SqlErr {
+	#[error(transparent)]
+	DataFusion(#[from] DataFusionError),
+	#[error(transparent)]
+	Arrow(#[from] ArrowError),
+	#[error("Couldn't start up the query engine: {0}")]
+	Runtime(std::io::Error),
+	#[error("The query has to return at least one column, for how tall the bars are")]
+	NoColumns,
+	#[error("The last column ('{0}') has to be a number, since it's how tall the bars are")]
+	NotNumeric(String)
+}
+
+/// The bars that a query came up with
+pub struct QueryResult {
+	/// The columns that the bars are labelled with
+	pub labels: Vec<String>,
+	pub bars: Vec<LabeledBar>
+}
+
+pub struct SqlTable {
+	ctx: SessionContext,
+	runtime: Runtime
+}
+
+impl SqlTable {
+	/// Copies `data` into a table that queries can run on. It takes about as much memory again as
+	/// `data` itself does, so this is best put off until there's actually a query to run.
+	pub fn new(data: &[merde::Map], schema: &Schema) -> Result<Self, SqlErr> {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.map_err(SqlErr::Runtime)?;
+		let ctx = SessionContext::new();
+		ctx.register_batch(TABLE, to_batch(data, schema)?)?;
+		Ok(Self { ctx, runtime })
+	}
+
+	/// Runs `sql`, keeping the bars in whatever order it returned them in
+	pub fn query(&self, sql: &str) -> Result<QueryResult, SqlErr> {
+		let (columns, batches) = self.runtime.block_on(async {
+			let frame = self.ctx.sql(sql).await?;
+			let columns = frame
+				.schema()
+				.fields()
+				.iter()
+				.map(|field| field.name().clone())
+				.collect::<Vec<_>>();
+			Ok::<_, DataFusionError>((columns, frame.collect().await?))
+		})?;
+
+		let Some((value_column, labels)) = columns.split_last() else {
+			return Err(SqlErr::NoColumns);
+		};
+
+		let mut bars = Vec::new();
+		for batch in &batches {
+			let values = batch.column(labels.len());
+			if !values.data_type().is_numeric() {
+				return Err(SqlErr::NotNumeric(value_column.clone()));
+			}
+			let values = cast(values, &DataType::Float64)?;
+			let values = values.as_primitive::<Float64Type>();
+
+			for row in 0..batch.num_rows() {
+				let row_labels = (0..labels.len())
+					.map(|col| array_value_to_string(batch.column(col), row))
+					.collect::<Result<Vec<_>, _>>()?;
+				// A null height (like from summing nothing) is as good as no height at all
+				let value = if values.is_null(row) {
+					0.
+				} else {
+					values.value(row)
+				};
+				bars.push(LabeledBar::new(value, row_labels, labels));
+			}
+		}
+
+		Ok(QueryResult {
+			labels: labels.to_vec(),
+			bars
+		})
+	}
+}
+
+/// Turns the rows of `data` into a column for each key
+fn to_batch(data: &[merde::Map], schema: &Schema) -> Result<RecordBatch, ArrowError> {
+	let (fields, columns): (Vec<_>, Vec<_>) = schema
+		.keys
+		.iter()
+		.map(|(key, ty)| {
+			let cow_key = key.as_str().into();
+			let values = data.iter().map(|row| row.get(&cow_key));
+
+			let column: ArrayRef = match ty {
+				ValueType::I64 => Arc::new(
+					values
+						.map(|val| match val {
+							Some(Value::I64(i)) => Some(*i),
+							_ => None
+						})
+						.collect::<Int64Array>()
+				),
+				ValueType::U64 => Arc::new(
+					values
+						.map(|val| match val {
+							Some(Value::U64(u)) => Some(*u),
+							_ => None
+						})
+						.collect::<UInt64Array>()
+				),
+				ValueType::Float => Arc::new(
+					values
+						.map(|val| match val {
+							Some(Value::Float(f)) => Some(f.0),
+							_ => None
+						})
+						.collect::<Float64Array>()
+				),
+				ValueType::String => Arc::new(
+					values
+						.map(|val| match val {
+							Some(Value::Str(s)) => Some(s.as_ref()),
+							_ => None
+						})
+						.collect::<StringArray>()
+				),
+				ValueType::Bool => Arc::new(
+					values
+						.map(|val| match val {
+							Some(Value::Bool(b)) => Some(*b),
+							_ => None
+						})
+						.collect::<BooleanArray>()
+				),
+				ValueType::Bytes => Arc::new(
+					values
+						.map(|val| match val {
+							Some(Value::Bytes(b)) => Some(b.as_ref()),
+							_ => None
+						})
+						.collect::<BinaryArray>()
+				),
+				// The schema makes sure there's nothing nested, so all that's left is keys that
+				// are only ever null
+				_ => Arc::new(NullArray::new(data.len()))
+			};
+
+			(Field::new(key, column.data_type().clone(), true), column)
+		})
+		.unzip();
+
+	RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), columns)
+}
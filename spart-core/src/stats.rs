@@ -0,0 +1,101 @@
+use std::ops::Deref;
+
+use fxhash::FxHashMap;
+use merde::Value;
+use ordered_float::OrderedFloat;
+
+use crate::format::ByteEncoding;
+
+/// How many of the most common values are kept for non-numeric keys
+const TOP_VALUES: usize = 10;
+
+pub struct KeyStats {
+	pub rows: usize,
+	pub nulls: usize,
+	pub summary: Summary
+}
+
+pub enum Summary {
+	Numeric {
+		min: f64,
+		max: f64,
+		mean: f64,
+		median: f64
+	},
+	/// The most common values, with how many times each one shows up, from most to least common
+	TopValues(Vec<(String, usize)>),
+	/// Either all the values were null, or they were of a type we can't summarize
+	Nothing
+}
+
+impl KeyStats {
+	/// Values of bytes keys are counted up by how they look in `encoding`
+	pub fn compute(data: &[merde::Map], key: &str, encoding: ByteEncoding) -> Self {
+		let key = key.into();
+		let values = data
+			.iter()
+			.filter_map(|map| map.get(&key))
+			.filter(|val| !matches!(val, Value::Null))
+			.collect::<Vec<_>>();
+
+		let summary = match values.first() {
+			Some(Value::I64(_) | Value::U64(_) | Value::Float(_)) => {
+				let mut nums = values
+					.iter()
+					.filter_map(|val| match val {
+						Value::I64(i) => Some(OrderedFloat(*i as f64)),
+						Value::U64(u) => Some(OrderedFloat(*u as f64)),
+						Value::Float(f) => Some(*f),
+						_ => None
+					})
+					.collect::<Vec<_>>();
+				nums.sort_unstable();
+
+				let mid = nums.len() / 2;
+				let median = if nums.len() % 2 == 0 {
+					(nums[mid - 1].0 + nums[mid].0) / 2.
+				} else {
+					nums[mid].0
+				};
+
+				Summary::Numeric {
+					min: nums[0].0,
+					max: nums[nums.len() - 1].0,
+					mean: nums.iter().map(|n| n.0).sum::<f64>() / nums.len() as f64,
+					median
+				}
+			}
+			Some(Value::Str(_) | Value::Bool(_) | Value::Bytes(_)) => {
+				let mut counts = FxHashMap::<String, usize>::default();
+				for val in &values {
+					let s = match val {
+						Value::Str(s) => s.deref().to_string(),
+						Value::Bool(b) => b.to_string(),
+						Value::Bytes(b) => encoding.encode(b),
+						_ => continue
+					};
+					*counts.entry(s).or_default() += 1;
+				}
+
+				let mut counts = counts.into_iter().collect::<Vec<_>>();
+				counts.sort_unstable_by(|(a_val, a), (b_val, b)| b.cmp(a).then(a_val.cmp(b_val)));
+				counts.truncate(TOP_VALUES);
+				Summary::TopValues(counts)
+			}
+			_ => Summary::Nothing
+		};
+
+		Self {
+			rows: data.len(),
+			nulls: data.len() - values.len(),
+			summary
+		}
+	}
+
+	pub fn null_percentage(&self) -> f64 {
+		if self.rows == 0 {
+			return 0.;
+		}
+		self.nulls as f64 / self.rows as f64 * 100.
+	}
+}
@@ -0,0 +1,475 @@
+//! For data that's too big to hold onto, the rows can instead be counted up into their groups as
+//! they're parsed, so that only one row (plus a running count for each group) is ever in memory at
+//! once. The catch is that nothing can be changed afterwards without going through all the data
+//! again, so this is only for making a single chart without the GUI.
+
+use std::io::{BufRead, BufReader, Read};
+
+use fxhash::FxHashMap;
+use merde::{IntoStatic, Value, ValueType, json::from_str};
+
+use crate::{
+	bars::{Groups, Tally, bar_value, groups_of, is_excluded, keeps_values},
+	filter::{FilterParseErr, filter_key, merge_bounds, parse_filter},
+	load::{Format, LoadErr, LoadOptions},
+	schema::{DataErr, KeyTypes},
+	settings::{Settings, YAxisKey}
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum StreamErr {
+	#[error(transparent)]
+	Load(#[from] LoadErr),
+	#[error(transparent)]
+	Data(#[from] DataErr),
+	#[error("Only JSON arrays can be streamed, but '{0}' doesn't start with '['")]
+	NotAnArray(String),
+	#[error("Only JSON and JSON lines can be streamed, not {0:?}")]
+	Unstreamable(Format)
+}
+
+/// Goes through every row in `paths`, counting up the ones that get through `filters` (in the
+/// syntax described in [`crate::filter`]) by the keys in `x_axis`. Returns the groups along with
+/// `settings`, updated to match what they were made with. Rows don't all have to have every key,
+/// so keys that no row has are only found out about once they've all been read.
+pub fn aggregate(
+	paths: &[String],
+	format: Option<Format>,
+	x_axis: Vec<String>,
+	filters: &[String],
+	mut settings: Settings<'static>,
+	options: &LoadOptions
+) -> Result<(Groups, Settings<'static>), StreamErr> {
+	let mut counts = FxHashMap::<Vec<Value<'static>>, Tally>::default();
+	let mut types = KeyTypes::default();
+	let mut pending = filters
+		.iter()
+		.map(|filter| PendingFilter::new(filter))
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(DataErr::from)?;
+	settings.x_axis = x_axis;
+
+	for path in paths {
+		let io_err = |source| LoadErr::Io {
+			path: path.clone(),
+			source
+		};
+
+		let reader: Box<dyn Read> = if path == "-" {
+			Box::new(std::io::stdin())
+		} else {
+			Box::new(std::fs::File::open(path).map_err(io_err)?)
+		};
+		let mut reader = BufReader::new(reader);
+
+		let start = String::from_utf8_lossy(reader.fill_buf().map_err(io_err)?).into_owned();
+		let format = format
+			.or_else(|| Format::detect(path, &start))
+			.ok_or_else(|| LoadErr::Undetectable(path.clone()))?;
+		// These are the only formats that this knows how to split up
+		let mut texts = match format {
+			format if format == Format::JSON => RowTexts::Array(JsonElements::new(reader, path)?),
+			format if format == Format::NDJSON => RowTexts::Lines {
+				lines: reader.lines(),
+				path,
+				number: 0
+			},
+			format => return Err(StreamErr::Unstreamable(format))
+		};
+
+		while let Some(text) = texts.next_text()? {
+			let mut row = from_str::<merde::Map>(&text).map_err(|e| LoadErr::Parse {
+				path: path.clone(),
+				format,
+				message: texts.locate(e)
+			})?;
+			options.prepare(&mut row);
+
+			// Every row has to agree with the ones before it on what type each key holds, like
+			// they do when they're all loaded at once, so that they fit the bounds
+			types.check(&row)?;
+			add_bounds(&mut pending, &types, &mut settings)?;
+			if let Some((key, ty)) = settings.bounds.iter().find_map(|(key, bound)| {
+				let ty = types.get(key)?;
+				(ty != ValueType::Null && !bound.fits(ty)).then_some((key, ty))
+			}) {
+				return Err(DataErr::MismatchedBound {
+					key: key.clone(),
+					ty
+				}
+				.into());
+			}
+
+			let bounds = settings.bounds.iter().collect::<Vec<_>>();
+			let kept = settings.predicate.as_ref().is_none_or(|p| p.keeps(&row))
+				&& pending.iter().all(|filter| filter.keeps_nulls);
+			if kept && !is_excluded(&row, &bounds) {
+				let value = bar_value(&row, &settings);
+				for group in groups_of(&row, &settings) {
+					let group = group
+						.into_iter()
+						.map(|val| val.into_owned().into_static())
+						.collect();
+					counts
+						.entry(group)
+						.or_insert_with(Tally::default)
+						.add(value, keeps_values(&settings));
+				}
+			}
+		}
+	}
+
+	check_view(&pending, &types, &settings)?;
+	Ok((counts.into_iter().collect(), settings))
+}
+
+/// A filter that can't be parsed yet, since no row has had a value for its key to say what type
+/// it holds
+struct PendingFilter<'f> {
+	key: String,
+	filter: &'f str,
+	/// If it keeps the rows that don't have a value for its key, which every one of them is until
+	/// it can be parsed. Only filters on bools can.
+	keeps_nulls: bool
+}
+
+impl<'f> PendingFilter<'f> {
+	fn new(filter: &'f str) -> Result<Self, FilterParseErr> {
+		let key = filter_key(filter)?.to_string();
+		let keeps_nulls = parse_filter(filter, &[(key.clone(), ValueType::Bool)])
+			.is_ok_and(|(key, bound)| !is_excluded(&merde::Map::new(), &[(&key, &bound)]));
+		Ok(Self {
+			key,
+			filter,
+			keeps_nulls
+		})
+	}
+}
+
+/// Parses the filters whose keys some row has now had a value for into bounds in `settings`
+fn add_bounds(
+	pending: &mut Vec<PendingFilter>,
+	types: &KeyTypes,
+	settings: &mut Settings
+) -> Result<(), DataErr> {
+	while let Some((idx, ty)) = pending.iter().enumerate().find_map(|(idx, filter)| {
+		let ty = types.get(&filter.key)?;
+		(ty != ValueType::Null).then_some((idx, ty))
+	}) {
+		let filter = pending.remove(idx);
+		let (key, bound) = parse_filter(filter.filter, &[(filter.key, ty)])?;
+		let existing = settings.bounds.remove(&key);
+		settings.bounds.insert(key, merge_bounds(existing, bound));
+	}
+	Ok(())
+}
+
+/// Checks that the keys that the bars were made with were all in the data, and hold the types
+/// they need to, now that all of it's been seen
+fn check_view(
+	pending: &[PendingFilter],
+	types: &KeyTypes,
+	settings: &Settings
+) -> Result<(), DataErr> {
+	if types.is_empty() {
+		return Err(DataErr::NoData);
+	}
+	// This is what says why a filter whose key never had a value can't be used
+	if let Some(filter) = pending.first() {
+		let keys = types
+			.get(&filter.key)
+			.map(|ty| vec![(filter.key.clone(), ty)])
+			.unwrap_or_default();
+		parse_filter(filter.filter, &keys)?;
+	}
+
+	let type_of = |key: &str| {
+		types
+			.get(key)
+			.ok_or_else(|| DataErr::UnknownKey(key.to_string()))
+	};
+	for key in settings.grouped_keys() {
+		type_of(key)?;
+	}
+	match settings.y_axis {
+		YAxisKey::Count => (),
+		YAxisKey::Key(key) =>
+			if !matches!(
+				type_of(key)?,
+				ValueType::I64 | ValueType::U64 | ValueType::Float
+			) {
+				return Err(DataErr::NotNumeric(key.to_string()));
+			},
+		YAxisKey::Rate(key) =>
+			if type_of(key)? != ValueType::Bool {
+				return Err(DataErr::NotBoolean(key.to_string()));
+			},
+	}
+	Ok(())
+}
+
+/// Splits up the text of a file into the text of each of its rows, one at a time
+enum RowTexts<'p, R> {
+	Array(JsonElements<'p, R>),
+	/// A row on each line, with the number of the line that was read last
+	Lines {
+		lines: std::io::Lines<R>,
+		path: &'p str,
+		number: usize
+	}
+}
+
+impl<R: BufRead> RowTexts<'_, R> {
+	/// The text of the next row, or `None` once there aren't any more
+	fn next_text(&mut self) -> Result<Option<String>, LoadErr> {
+		match self {
+			Self::Array(elements) => elements.next_element(),
+			Self::Lines {
+				lines,
+				path,
+				number
+			} => {
+				for line in lines {
+					*number += 1;
+					let line = line.map_err(|source| LoadErr::Io {
+						path: path.to_string(),
+						source
+					})?;
+					if !line.trim().is_empty() {
+						return Ok(Some(line));
+					}
+				}
+				Ok(None)
+			}
+		}
+	}
+
+	/// Says where in the file the last row was, along with why it couldn't be parsed
+	fn locate(&self, message: impl std::fmt::Display) -> String {
+		match self {
+			Self::Array(_) => message.to_string(),
+			Self::Lines { number, .. } => format!("line {number}: {message}")
+		}
+	}
+}
+
+/// Splits the text of a JSON array into the text of each of its elements, without ever reading
+/// more than one element at a time
+struct JsonElements<'p, R> {
+	bytes: std::io::Bytes<R>,
+	path: &'p str,
+	done: bool
+}
+
+impl<'p, R: BufRead> JsonElements<'p, R> {
+	fn new(reader: R, path: &'p str) -> Result<Self, StreamErr> {
+		let mut elements = Self {
+			bytes: reader.bytes(),
+			path,
+			done: false
+		};
+
+		match elements.next_non_whitespace()? {
+			Some(b'[') => Ok(elements),
+			_ => Err(StreamErr::NotAnArray(path.to_string()))
+		}
+	}
+
+	fn next_byte(&mut self) -> Result<Option<u8>, LoadErr> {
+		self.bytes.next().transpose().map_err(|source| LoadErr::Io {
+			path: self.path.to_string(),
+			source
+		})
+	}
+
+	fn next_non_whitespace(&mut self) -> Result<Option<u8>, LoadErr> {
+		while let Some(byte) = self.next_byte()? {
+			if !byte.is_ascii_whitespace() {
+				return Ok(Some(byte));
+			}
+		}
+		Ok(None)
+	}
+
+	fn malformed(&self, message: &str) -> LoadErr {
+		LoadErr::Parse {
+			path: self.path.to_string(),
+			format: Format::JSON,
+			message: message.to_string()
+		}
+	}
+
+	/// The text of the next element, or `None` once the end of the array has been reached
+	fn next_element(&mut self) -> Result<Option<String>, LoadErr> {
+		if self.done {
+			return Ok(None);
+		}
+
+		let mut element = Vec::new();
+		let (mut depth, mut in_string, mut escaped) = (0_usize, false, false);
+
+		loop {
+			let Some(byte) = (if element.is_empty() {
+				self.next_non_whitespace()?
+			} else {
+				self.next_byte()?
+			}) else {
+				return Err(self.malformed("the array never ends"));
+			};
+
+			if in_string {
+				match (escaped, byte) {
+					(true, _) => escaped = false,
+					(false, b'\\') => escaped = true,
+					(false, b'"') => in_string = false,
+					_ => ()
+				}
+			} else {
+				match byte {
+					b'"' => in_string = true,
+					b'{' | b'[' => depth += 1,
+					b'}' | b']' if depth > 0 => depth -= 1,
+					// The end of the array itself (possibly right after its last element)
+					b']' => {
+						self.done = true;
+						if element.is_empty() {
+							return Ok(None);
+						}
+						return self.finish(element).map(Some);
+					}
+					b',' if depth == 0 => {
+						if element.is_empty() {
+							return Err(self.malformed("an element of the array is missing"));
+						}
+						return self.finish(element).map(Some);
+					}
+					_ => ()
+				}
+			}
+
+			element.push(byte);
+		}
+	}
+
+	fn finish(&self, element: Vec<u8>) -> Result<String, LoadErr> {
+		String::from_utf8(element).map_err(|_| self.malformed("it isn't valid UTF-8"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn elements(json: &str) -> Result<Vec<String>, LoadErr> {
+		let mut elements = JsonElements::new(json.as_bytes(), "test.json").unwrap();
+		let mut all = Vec::new();
+		while let Some(element) = elements.next_element()? {
+			all.push(element);
+		}
+		Ok(all)
+	}
+
+	/// Streams `rows` out of a file, counting up the ones that `filters` let through by `x`
+	fn stream(
+		name: &str,
+		rows: &str,
+		x: &str,
+		filters: &[&str]
+	) -> Result<Vec<(String, usize)>, StreamErr> {
+		let path = std::env::temp_dir().join(format!("spart-stream-{}-{name}", std::process::id()));
+		std::fs::write(&path, rows).unwrap();
+		let filters = filters.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+		let result = aggregate(
+			&[path.display().to_string()],
+			None,
+			vec![x.to_string()],
+			&filters,
+			Settings::default(),
+			&LoadOptions::default()
+		);
+		_ = std::fs::remove_file(path);
+
+		let mut counts = result?
+			.0
+			.into_iter()
+			.map(|(group, tally)| (format!("{:?}", group[0]), tally.rows))
+			.collect::<Vec<_>>();
+		counts.sort();
+		Ok(counts)
+	}
+
+	#[test]
+	fn nested_elements() {
+		let all = elements(r#" [ {"a": [1, {"b": [2]}]}, [3, [4]] ,5 ] "#).unwrap();
+		assert_eq!(all, [r#"{"a": [1, {"b": [2]}]}"#, "[3, [4]] ", "5 "]);
+	}
+
+	#[test]
+	fn escaped_elements() {
+		let all = elements(r#"[{"a": "],[}{"}, {"b": "\"]\\"}, "\\\""]"#).unwrap();
+		assert_eq!(all, [r#"{"a": "],[}{"}"#, r#"{"b": "\"]\\"}"#, r#""\\\"""#]);
+	}
+
+	#[test]
+	fn malformed_arrays() {
+		assert_eq!(elements("[]").unwrap(), Vec::<String>::new());
+		assert!(elements(r#"[{"a": 1}"#).is_err());
+		assert!(elements("[1,,2]").is_err());
+		assert!(matches!(
+			JsonElements::new(r#"{"a": 1}"#.as_bytes(), "test.json"),
+			Err(StreamErr::NotAnArray(_))
+		));
+	}
+
+	#[test]
+	fn filtered_rows() {
+		let rows = r#"[{"k": "a", "v": 1}, {"k": "b", "v": 5}, {"k": "b", "v": 7}]"#;
+		let counts = stream("filtered.json", rows, "k", &["v>1"]).unwrap();
+		assert_eq!(counts, [(r#"Str("b")"#.to_string(), 2)]);
+	}
+
+	#[test]
+	fn mixed_types() {
+		let rows = r#"[{"k": "a", "v": 5}, {"k": "b", "v": 5.5}]"#;
+		assert!(matches!(
+			stream("mixed.json", rows, "k", &["v>1"]),
+			Err(StreamErr::Data(DataErr::DifferentTypes { .. }))
+		));
+		// Rows have to agree with each other even when nothing's filtered by the key
+		assert!(matches!(
+			stream("mixed-unfiltered.json", rows, "k", &[]),
+			Err(StreamErr::Data(DataErr::DifferentTypes { .. }))
+		));
+	}
+
+	#[test]
+	fn sparse_keys() {
+		// The key that's filtered on isn't in the first row, so the filter waits until it shows up
+		let rows = "{\"k\": \"a\"}\n{\"k\": \"b\", \"v\": 5}\n{\"k\": \"c\", \"v\": 1}\n";
+		let counts = stream("sparse.ndjson", rows, "k", &["v>2"]).unwrap();
+		assert_eq!(counts, [(r#"Str("b")"#.to_string(), 1)]);
+
+		// Bool filters can keep the rows from before their key showed up
+		let rows = "{\"k\": \"a\"}\n{\"k\": \"b\", \"s\": true}\n{\"k\": \"c\", \"s\": false}\n";
+		let counts = stream("sparse-bools.ndjson", rows, "k", &["s=false|null"]).unwrap();
+		assert_eq!(counts, [
+			(r#"Str("a")"#.to_string(), 1),
+			(r#"Str("c")"#.to_string(), 1)
+		]);
+	}
+
+	#[test]
+	fn unknown_keys() {
+		let rows = "{\"k\": \"a\"}\n";
+		assert!(matches!(
+			stream("unknown-filter.ndjson", rows, "k", &["v>2"]),
+			Err(StreamErr::Data(DataErr::Filter(
+				FilterParseErr::UnknownKey(_)
+			)))
+		));
+		assert!(matches!(
+			stream("unknown-x.ndjson", rows, "x", &[]),
+			Err(StreamErr::Data(DataErr::UnknownKey(_)))
+		));
+	}
+}
@@ -0,0 +1,118 @@
+//! Guessing which keys would make for a good first chart, for when the data's just been loaded and
+//! nothing's grouped by yet. With dozens of keys it's hard to know where to start, so the ones with
+//! a readable number of different values (that most rows have) are suggested, along with when in
+//! the day and which month any timestamps are.
+
+use merde::ValueType;
+
+use crate::{
+	format::DATE_FORMAT_PRESETS,
+	schema::{self, KeySummary, Schema}
+};
+
+/// How many groupings are suggested at most
+pub const MAX_SUGGESTIONS: usize = 5;
+
+/// Keys with more different values than this make too many bars to take in at once
+const MAX_DISTINCT: usize = 100;
+
+/// Keys that more than this share of the rows don't have would mostly just make one big null bar
+const MAX_NULL_SHARE: f64 = 0.5;
+
+/// Keys with somewhere in this many different values make the easiest charts to read
+const IDEAL_DISTINCT: (usize, usize) = (3, 30);
+
+/// The date formats that timestamps are suggested to be bucketed by
+const DATE_SUGGESTIONS: [&str; 2] = ["%H", "%Y-%m"];
+
+/// A grouping that would probably make an interesting chart
+#[derive(Clone, PartialEq, Debug)]
+pub struct Suggestion {
+	pub key: String,
+	/// What the key's timestamps are bucketed by, if it's a timestamp key
+	pub date_format: Option<&'static str>
+}
+
+impl Suggestion {
+	/// What it's called when it's suggested, like `country` or `ts (hour of day)`
+	pub fn name(&self) -> String {
+		let preset = self.date_format.and_then(|format| {
+			DATE_FORMAT_PRESETS
+				.iter()
+				.find(|(preset, _)| *preset == format)
+		});
+		match preset {
+			Some((_, name)) => format!("{} ({})", self.key, name.to_lowercase()),
+			None => self.key.clone()
+		}
+	}
+}
+
+/// The groupings of `data` that are most likely to be worth looking at first, best first
+pub fn suggest(data: &[merde::Map], schema: &Schema) -> Vec<Suggestion> {
+	let rows = data.len().max(1);
+	// Timestamps are suggested bucketed instead, since each one's usually different
+	let is_time = |key: &str| {
+		schema.timestamp_keys.iter().any(|k| k == key)
+			|| schema.epoch_keys.iter().any(|(k, _)| k == key)
+	};
+	let mut keys = schema::describe(data)
+		.into_iter()
+		.filter(|summary| !is_time(&summary.key))
+		.filter_map(|summary| Some((interest(&summary, rows)?, summary.key)))
+		.collect::<Vec<_>>();
+	// The summaries are sorted by key, so keys that are just as interesting stay in that order
+	keys.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+	let dates = schema
+		.timestamp_keys
+		.first()
+		.into_iter()
+		.flat_map(|key| {
+			DATE_SUGGESTIONS.map(|format| Suggestion {
+				key: key.clone(),
+				date_format: Some(format)
+			})
+		})
+		.collect::<Vec<_>>();
+
+	// There's always room left for the dates, since when things happened is nearly always
+	// worth a look
+	let mut suggestions = keys
+		.into_iter()
+		.take(MAX_SUGGESTIONS - dates.len())
+		.map(|(_, key)| Suggestion {
+			key,
+			date_format: None
+		})
+		.collect::<Vec<_>>();
+	suggestions.extend(dates);
+	suggestions
+}
+
+/// How good of a chart grouping by the key would make, from 0 to 1, if it'd make a readable one
+/// at all
+fn interest(summary: &KeySummary, rows: usize) -> Option<f64> {
+	let groupable = matches!(summary.types.as_slice(), [ValueType::String
+		| ValueType::Bool
+		| ValueType::I64
+		| ValueType::U64]);
+	let null_share = summary.nulls as f64 / rows as f64;
+	// A key that has a different value in most rows is probably an ID of some sort
+	if !groupable
+		|| !(2..=MAX_DISTINCT).contains(&summary.distinct)
+		|| summary.distinct * 2 > rows
+		|| null_share > MAX_NULL_SHARE
+	{
+		return None;
+	}
+
+	// Too few values are almost as boring as too many are overwhelming
+	let (fewest, most) = IDEAL_DISTINCT;
+	let fit = match summary.distinct {
+		distinct if distinct < fewest => distinct as f64 / fewest as f64,
+		distinct if distinct > most => most as f64 / distinct as f64,
+		_ => 1.
+	};
+	Some(fit * (1. - null_share))
+}
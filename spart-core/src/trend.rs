@@ -0,0 +1,67 @@
+//! Lines through the tops of the bars, to see which way they're heading when they're in some
+//! order that means something (like months) and there's too much noise to tell by eye: either the
+//! straight line that fits them best, or an average of the last few of them at each bar.
+
+/// A line fit through some points by least squares
+#[derive(Clone, Copy, Debug)]
+pub struct Fit {
+	pub slope: f64,
+	pub intercept: f64,
+	/// How much of the points' variation the line accounts for, from 0 (none of it) to 1 (they
+	/// all sit right on it)
+	pub r_squared: f64
+}
+
+impl Fit {
+	/// The least-squares line through `points`, if there are at least two of them at different
+	/// x values
+	pub fn least_squares(points: &[[f64; 2]]) -> Option<Self> {
+		let count = points.len() as f64;
+		if points.len() < 2 {
+			return None;
+		}
+		let mean_x = points.iter().map(|[x, _]| x).sum::<f64>() / count;
+		let mean_y = points.iter().map(|[_, y]| y).sum::<f64>() / count;
+
+		let (mut xx, mut xy, mut yy) = (0., 0., 0.);
+		for [x, y] in points {
+			let (dx, dy) = (x - mean_x, y - mean_y);
+			xx += dx * dx;
+			xy += dx * dy;
+			yy += dy * dy;
+		}
+		if xx == 0. {
+			return None;
+		}
+
+		let slope = xy / xx;
+		Some(Self {
+			slope,
+			intercept: mean_y - slope * mean_x,
+			// If every point has the same y, the flat line through them explains all of it
+			r_squared: if yy == 0. { 1. } else { xy * xy / (xx * yy) }
+		})
+	}
+
+	pub fn at(&self, x: f64) -> f64 {
+		self.slope * x + self.intercept
+	}
+}
+
+/// The average of each value and the `window - 1` before it (or however many of them there are,
+/// for the first few)
+pub fn moving_average(values: &[f64], window: usize) -> Vec<f64> {
+	let window = window.max(1);
+	let mut sum = 0.;
+	values
+		.iter()
+		.enumerate()
+		.map(|(idx, val)| {
+			sum += val;
+			if idx >= window {
+				sum -= values[idx - window];
+			}
+			sum / (idx + 1).min(window) as f64
+		})
+		.collect()
+}
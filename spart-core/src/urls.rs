@@ -0,0 +1,131 @@
+//! Pulling the parts of URLs out into keys of their own, since a web log's URLs are nearly all
+//! different once their query strings are counted, but there are only so many hosts and paths.
+//! URLs can be whole ones (like `https://example.com/search?q=cats`) or just their path and query
+//! (like `/search?q=cats`), which is how most servers log them.
+
+use fxhash::FxHashMap;
+use merde::{CowStr, Value};
+use url::Url;
+
+use crate::schema::DataErr;
+
+/// How many of a key's values are looked at to tell if it holds URLs
+const URL_SAMPLE: usize = 100;
+
+/// How many of those have to be URLs for the key to be one
+const URL_SHARE: f64 = 0.9;
+
+/// How many of the most common query parameters get keys of their own, since some URLs have
+/// parameters (like cache busters) that are named differently every time
+pub const MAX_PARAMS: usize = 20;
+
+/// What paths are parsed as if they were on, so they can be parsed like whole URLs are
+const BASE: &str = "http://relative.invalid";
+
+/// The URL in `s`, and if it's a whole one (or just a path)
+fn parse(s: &str) -> Option<(Url, bool)> {
+	match Url::parse(s) {
+		Ok(url) if url.has_host() => Some((url, true)),
+		Ok(_) => None,
+		Err(_) if s.starts_with('/') => Some((Url::parse(&format!("{BASE}{s}")).ok()?, false)),
+		Err(_) => None
+	}
+}
+
+/// If the string key `key` holds URLs (or paths) in nearly all of the rows that have it
+pub(crate) fn is_url_key(data: &[merde::Map], key: &str) -> bool {
+	let key = CowStr::from(key);
+	let (urls, values) = data
+		.iter()
+		.filter_map(|row| match row.get(&key)? {
+			Value::Str(s) => Some(parse(s).is_some()),
+			_ => None
+		})
+		.take(URL_SAMPLE)
+		.fold((0, 0), |(urls, values), url| {
+			(urls + usize::from(url), values + 1)
+		});
+	values > 0 && urls as f64 >= values as f64 * URL_SHARE
+}
+
+/// The names of the keys that [`add_fields`] adds for `key`: its host and path, and then one for
+/// each of `params`
+pub fn field_names(key: &str, params: &[String]) -> Vec<String> {
+	[format!("{key}_host"), format!("{key}_path")]
+		.into_iter()
+		.chain(params.iter().map(|param| format!("{key}_query_{param}")))
+		.collect()
+}
+
+/// Adds each row's host and path (from its URL in `key`) as keys, along with a key for each of
+/// the most common query parameters. Paths don't have a host, so it's null for them, and so is
+/// everything for rows without a URL. Returns the names of the keys that were added.
+pub fn add_fields(data: &mut [merde::Map<'static>], key: &str) -> Result<Vec<String>, DataErr> {
+	if !is_url_key(data, key) {
+		return Err(DataErr::NotUrls(key.to_string()));
+	}
+
+	let key = CowStr::from(key);
+	let urls = data
+		.iter()
+		.map(|row| match row.get(&key) {
+			Some(Value::Str(s)) => parse(s),
+			_ => None
+		})
+		.collect::<Vec<_>>();
+
+	let mut counts = FxHashMap::<String, usize>::default();
+	for (url, _) in urls.iter().flatten() {
+		for (param, _) in url.query_pairs() {
+			*counts.entry(param.into_owned()).or_default() += 1;
+		}
+	}
+	let mut params = counts.into_iter().collect::<Vec<_>>();
+	params.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+	let params = params
+		.into_iter()
+		.take(MAX_PARAMS)
+		.map(|(param, _)| param)
+		.collect::<Vec<_>>();
+
+	let names = field_names(&key, &params);
+	if let Some(taken) = names.iter().find(|name| {
+		data.iter()
+			.any(|row| row.contains_key(&name.as_str().into()))
+	}) {
+		return Err(DataErr::KeyExists(taken.clone()));
+	}
+
+	let keys = names
+		.iter()
+		.map(|name| CowStr::from(name.clone()))
+		.collect::<Vec<_>>();
+	for (row, url) in data.iter_mut().zip(urls) {
+		let Some((url, whole)) = url else {
+			for key in &keys {
+				row.insert(key.clone(), Value::Null);
+			}
+			continue;
+		};
+
+		let host = match (whole, url.host_str()) {
+			(true, Some(host)) => Value::Str(host.to_string().into()),
+			_ => Value::Null
+		};
+		let query = url.query_pairs().collect::<Vec<_>>();
+		let values = [host, Value::Str(url.path().to_string().into())]
+			.into_iter()
+			.chain(params.iter().map(|param| {
+				query
+					.iter()
+					.find(|(name, _)| name == param)
+					.map_or(Value::Null, |(_, value)| {
+						Value::Str(value.to_string().into())
+					})
+			}));
+		for (key, value) in keys.iter().zip(values) {
+			row.insert(key.clone(), value);
+		}
+	}
+	Ok(names)
+}
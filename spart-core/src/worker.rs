@@ -0,0 +1,238 @@
+//! Building the bars can take a while when there's a lot of data, so it's done on its own thread
+//! to keep the UI from freezing up in the meantime.
+
+use std::{
+	sync::{
+		Arc, Mutex, Weak,
+		mpsc::{Receiver, Sender, channel}
+	},
+	thread
+};
+
+use crate::{
+	bars::LabeledBar,
+	memory::{Rows, Text},
+	pipeline::Pipeline,
+	profile::Profile,
+	settings::Settings
+};
+
+pub type Data = Arc<Rows>;
+
+pub struct Worker {
+	builder: Builder,
+	results: Receiver<Built>,
+	/// The id of the last job that was sent off
+	latest: u64,
+	/// The id of the last job whose bars we've received
+	finished: u64,
+	/// How long each step of building the last bars we received all of took
+	profile: Option<Profile>
+}
+
+enum Builder {
+	/// Jobs are sent off to be done on another thread
+	Thread(Sender<Job>),
+	/// Jobs are done as soon as they're requested, with the results put straight into our own
+	/// channel
+	Inline(Box<Rebuilder>, Sender<Built>)
+}
+
+/// The state that's kept around between jobs so that each one can reuse what the last one
+/// worked out
+#[derive(Default)]
+struct Rebuilder {
+	pipeline: Pipeline,
+	// Only used to tell if we've been given different data. It's a `Weak` so that we don't keep
+	// old data around, but still keep its allocation from being reused (which could trick us into
+	// thinking new data was the same).
+	last_data: Option<Weak<Rows>>,
+	/// What the strings that `pipeline` has held onto from the last data point into, which has to
+	/// stick around until it's let go of them
+	last_text: Vec<Text>
+}
+
+impl Rebuilder {
+	/// Builds the bars for `job`, sending along the bars of the rows grouped so far while it
+	/// goes if there are a lot of them (see [`Pipeline::run_progressively`])
+	fn build(&mut self, job: Job, results: &Sender<Built>) -> Built {
+		let data = Arc::downgrade(&job.data);
+		if !self
+			.last_data
+			.as_ref()
+			.is_some_and(|last| last.ptr_eq(&data))
+		{
+			self.pipeline.invalidate();
+			self.last_data = Some(data);
+			self.last_text = job.data.text().to_vec();
+		}
+
+		let bars = self
+			.pipeline
+			.run_progressively(&job.data, &job.settings, |bars| {
+				_ = results.send(Built {
+					id: job.id,
+					bars,
+					profile: None
+				});
+			});
+		Built {
+			id: job.id,
+			bars,
+			profile: Some(self.pipeline.profile().clone())
+		}
+	}
+}
+
+struct Job {
+	id: u64,
+	data: Data,
+	settings: Settings<'static>
+}
+
+struct Built {
+	id: u64,
+	bars: Vec<LabeledBar>,
+	/// How long each step took, if these are all of the bars rather than those of the rows
+	/// grouped so far
+	profile: Option<Profile>
+}
+
+impl Worker {
+	/// Starts up the thread that builds the bars. If threads aren't available (like in a
+	/// browser), the bars are just built right away whenever they're requested instead.
+	pub fn spawn() -> Self {
+		let (jobs, job_rx) = channel::<Job>();
+		let (result_tx, results) = channel();
+		let inline_tx = result_tx.clone();
+
+		let spawned = thread::Builder::new()
+			.name("spart-rebuild".into())
+			.spawn(move || {
+				let mut rebuilder = Rebuilder::default();
+				while let Ok(mut job) = job_rx.recv() {
+					// Only the newest job matters, so skip any that piled up while we were busy
+					while let Ok(newer) = job_rx.try_recv() {
+						job = newer;
+					}
+
+					let built = rebuilder.build(job, &result_tx);
+					if result_tx.send(built).is_err() {
+						// The app's gone, so nobody's going to look at these anyways
+						break;
+					}
+				}
+			});
+
+		let builder = match spawned {
+			Ok(_) => Builder::Thread(jobs),
+			Err(_) => Builder::Inline(Box::default(), inline_tx)
+		};
+
+		Self {
+			builder,
+			results,
+			latest: 0,
+			finished: 0,
+			profile: None
+		}
+	}
+
+	/// Starts building bars for `data` with the given settings, superseding any job that's
+	/// still in progress
+	pub fn request(&mut self, data: &Data, settings: &Settings<'static>) {
+		self.latest += 1;
+		let job = Job {
+			id: self.latest,
+			data: Arc::clone(data),
+			settings: settings.clone()
+		};
+
+		// The thread only stops if we've been dropped, and we're holding onto the receiver
+		// ourselves when there is no thread, so neither of these can fail
+		match &mut self.builder {
+			Builder::Thread(jobs) => _ = jobs.send(job),
+			Builder::Inline(rebuilder, results) => {
+				let built = rebuilder.build(job, results);
+				_ = results.send(built);
+			}
+		}
+	}
+
+	/// If we're waiting on bars that haven't been built yet, which is still the case while only
+	/// some of them have come in
+	pub fn is_busy(&self) -> bool {
+		self.finished < self.latest
+	}
+
+	/// The bars from the latest request, if they've arrived since the last time this was called.
+	/// They might only be the bars of some of the rows, if [`Self::is_busy`] still is.
+	pub fn poll(&mut self) -> Option<Vec<LabeledBar>> {
+		let mut newest = None;
+		while let Ok(built) = self.results.try_recv() {
+			newest = self.accept(built).or(newest);
+		}
+		newest
+	}
+
+	/// Blocks until the bars from the latest request have been built, for when there's nothing
+	/// better to do in the meantime. Returns `None` if they've already been handed out by
+	/// [`Self::poll`].
+	pub fn wait(&mut self) -> Option<Vec<LabeledBar>> {
+		while self.is_busy() {
+			let built = self.results.recv().ok()?;
+			let done = built.profile.is_some();
+			if let Some(bars) = self.accept(built)
+				&& done
+			{
+				return Some(bars);
+			}
+		}
+		None
+	}
+
+	/// Keeps only bars that are from the latest request, since anything else is out of date
+	fn accept(&mut self, built: Built) -> Option<Vec<LabeledBar>> {
+		(built.id == self.latest).then(|| {
+			if built.profile.is_some() {
+				self.finished = built.id;
+				self.profile = built.profile;
+			}
+			built.bars
+		})
+	}
+
+	/// How long each step of building the last complete set of bars took
+	pub fn profile(&self) -> Option<&Profile> {
+		self.profile.as_ref()
+	}
+}
+
+/// Does `work` on a thread of its own, for anything besides building bars that takes too long to
+/// do between frames. What it comes out to is sent through the returned receiver, which is done
+/// right away instead if threads aren't available.
+pub fn background<T: Send + 'static>(
+	name: &str,
+	work: impl FnOnce() -> T + Send + 'static
+) -> Receiver<T> {
+	type Job<W, T> = Mutex<Option<(W, Sender<T>)>>;
+	fn run<T>(job: &Job<impl FnOnce() -> T, T>) {
+		let taken = job.lock().unwrap_or_else(|e| e.into_inner()).take();
+		if let Some((work, results)) = taken {
+			_ = results.send(work());
+		}
+	}
+
+	let (results, received) = channel();
+	// Spawning takes the closure whether or not it works, so this is how it gets it back
+	let job = Arc::new(Mutex::new(Some((work, results))));
+	let shared = Arc::clone(&job);
+	if thread::Builder::new()
+		.name(name.to_string())
+		.spawn(move || run(&shared))
+		.is_err()
+	{
+		run(&job);
+	}
+	received
+}
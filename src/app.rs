@@ -1,217 +1,5010 @@
 use std::{
 	collections::hash_map::Entry,
-	ops::{Deref, Range}
+	ops::{Deref, Range},
+	sync::{
+		Arc,
+		mpsc::{Receiver, TryRecvError}
+	}
+};
+#[cfg(feature = "native")]
+use std::{
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH}
+};
+
+use eframe::{
+	egui::{
+		self, Align, Align2, Button, Color32, ColorImage, ComboBox, DragValue, Event, Key, Layout,
+		Rect, Slider, Spinner, Stroke, UiBuilder, Vec2, Vec2b, ViewportCommand, vec2
+	},
+	emath::Numeric
+};
+use egui_plot::{
+	Bar, BarChart, HLine, Legend, Line, LineStyle, Plot, PlotMemory, PlotPoint, PlotPoints, Points,
+	Text, VLine
+};
+use fxhash::{FxHashMap, FxHashSet};
+use merde::{Value, ValueType};
+#[cfg(feature = "enrich")]
+use spart_core::enrich::Enrichment;
+#[cfg(feature = "sql")]
+use spart_core::sql::{SqlErr, SqlTable};
+use spart_core::{
+	agents,
+	bars::{self, LabeledBar},
+	bins::Bins,
+	brush::Brush,
+	coloring::{self, ColorRule, ColorRuleErr},
+	columns::Columns,
+	diff, downsample,
+	drill::Drill,
+	filter::{FilterParseErr, merge_bounds, parse_filter},
+	format::{ByteEncoding, Currency, DATE_FORMAT_PRESETS, NumberFormat, Unit, truncate},
+	geo,
+	index::Indexes,
+	ip,
+	load::LoadOptions,
+	memory::{self, Rows},
+	outliers::{OutlierTest, Outliers},
+	periods::{Period, PeriodComparison},
+	sample::{Sample, Sampling},
+	schema::{self, DataErr, KeyType, Schema},
+	script::{self, Expression, ScriptErr, Scripts, parse_column},
+	settings::{
+		self, Aggregation, Anchor, Annotation, BarOrder, Bound, ChartKind, DEFAULT_DELIMITER, Nans,
+		ReferenceLine, Settings, TieBreak, ValueBound, YAxisKey
+	},
+	share::{self, ShareErr},
+	song,
+	stats::{KeyStats, Summary},
+	suggest::{self, Suggestion},
+	trend::{self, Fit},
+	urls,
+	worker::{self, Data, Worker}
+};
+#[cfg(feature = "native")]
+use spart_core::{
+	feed::Feed,
+	listenbrainz,
+	load::{Format, Source}
+};
+// `std`'s `Instant` panics in browsers, and this is just `std`'s everywhere else
+use web_time::{Duration, Instant};
+
+#[cfg(feature = "enrich")]
+use crate::covers::Covers;
+#[cfg(feature = "native")]
+use crate::{
+	autosave::{self, AUTOSAVE_INTERVAL},
+	recent::{AddedKeys, Recent, RecentFile},
+	session
+};
+use crate::{
+	config::Config,
+	dropped,
+	history::History,
+	map::{self, Layer},
+	offscreen,
+	palette::{self, Outcome, Palette}
 };
 
-use eframe::{
-	egui::{self, Align, ComboBox, Key, Layout, Slider, UiBuilder, Vec2b},
-	emath::Numeric
-};
-use egui_plot::{Bar, BarChart, Plot};
-use fxhash::FxHashMap;
-use merde::ValueType;
+/// How many bars a single notch of the scroll wheel moves the chart by
+const SCROLL_STEP: isize = 1;
+
+pub struct App {
+	// We could try to do zero-copy deserialization, but it'll be much easier to work with if we
+	// just copy it all - plus, the data will normally just be loaded in once and then manipulated
+	// a bunch, so we don't need to optimize the loading-in phase very much. Also it may not
+	// actually be super possible to do `yoke`-like stuff 'cause we don't want to have a set
+	// schema, meaning that we have to use `merde::Value`, and we can't necessarily
+	// `derive(Yokeable)` on that. So. (If memory's tight, `--zero-copy` gets around this by keeping
+	// the text the data was parsed from in the `Rows`, alongside the rows that borrow from it.)
+	//
+	// Invariant: Each `Map` inside this vec fits `schema` (though it may be missing some of its
+	// keys, which are treated as null), and contains no nested data structures - no inner `Map`s
+	// or `Array`s. It is also not empty.
+	data: Data,
+	/// Roughly how many bytes `data` is taking up
+	memory: usize,
+	/// Builds the bars from `data` in the background. Until it's done, `bars` are the ones from
+	/// before the last change.
+	worker: Worker,
+	schema: Schema,
+	settings: Settings<'static>,
+	/// How files that are dropped onto the window are read
+	load_options: LoadOptions,
+	/// What the values of each key in [`Settings::key_types`] were before they were converted,
+	/// row by row, so that they can be put back
+	uncoerced: FxHashMap<String, Vec<Value<'static>>>,
+	pub bars: Vec<LabeledBar>,
+	/// What's handed to the plot for the bars that are currently shown, so it doesn't have to be
+	/// put together again every frame
+	visible: Option<VisibleBars>,
+	/// Summaries of keys that have been looked at, since they're expensive to compute each frame
+	stats: FxHashMap<String, KeyStats>,
+	/// The outliers of keys that are being checked for them, for the same reason
+	outlier_counts: FxHashMap<String, Outliers>,
+	/// The key whose stats popup is currently open
+	stats_shown: Option<String>,
+	/// The groupings offered to start off with while nothing's being grouped by, once they've
+	/// been worked out
+	suggestions: Option<Vec<Suggestion>>,
+	/// If the plot should be zoomed to fit the shown bars on the next frame. Set when the shown
+	/// bars change, since whatever the user zoomed into probably doesn't make sense anymore.
+	fit_plot: bool,
+	/// The range of bars that was shown last frame
+	last_shown: Range<usize>,
+	/// The bar that's been picked out with the arrow keys, if any
+	selected: Option<usize>,
+	/// The labels of the bars that are always shown (after the rest of the page, if they're not
+	/// on it), however the filters change
+	pinned: Vec<Vec<String>>,
+	/// If it's B's bounds that are being edited while comparing, in which case they've been
+	/// swapped with A's in the settings (so that everything that edits bounds edits them)
+	editing_b: bool,
+	/// Where the plot was when the user asked to export it as an image
+	export: Option<ImageExport>,
+	/// What size of PNG the plot's about to be drawn off screen at, while that's being picked
+	png_export: Option<PngExport>,
+	/// A message to show the user about the outcome of some action they took
+	status: Option<String>,
+	/// Set while the plot is taking up the whole window
+	presenting: Option<Presentation>,
+	/// What's been typed into the filter box so far
+	filter_input: String,
+	/// The note that's being written, and where along the x-axis it'd go if it isn't put on the
+	/// selected bar
+	note_input: (String, f64),
+	/// The value of the next reference line that's added at a fixed value
+	reference_input: f64,
+	/// Why the last filter that was submitted couldn't be applied
+	filter_err: Option<FilterParseErr>,
+	/// What's been typed into the color rule box so far
+	color_rule_input: String,
+	/// Why the last color rule that was submitted couldn't be parsed
+	color_rule_err: Option<ColorRuleErr>,
+	/// What's been pasted into the shared view box so far
+	view_input: String,
+	/// Why the last shared view that was pasted couldn't be opened
+	view_err: Option<ShareErr>,
+	/// What the user's expressions are compiled with
+	scripts: Scripts,
+	/// The keys that were added to `data` by scripts, so they can be added again when it's
+	/// reloaded
+	columns: Vec<(String, Expression)>,
+	/// The key from a script that's being worked out for every row on another thread, if any
+	adding_column: Option<AddingColumn>,
+	/// The keys of IP addresses that have had keys pulled out of them, so they can be pulled out
+	/// again when it's reloaded
+	addresses: Vec<String>,
+	/// The keys of URLs that have had their parts pulled out of them, for the same reason
+	urls: Vec<String>,
+	/// The keys of user agents that have had browsers and such pulled out of them, for the same
+	/// reason
+	agents: Vec<String>,
+	script_input: ScriptInput,
+	#[cfg(feature = "sql")]
+	sql: Sql,
+	/// The keys that were added to `data` from online services, so they can be added again when
+	/// it's reloaded
+	#[cfg(feature = "enrich")]
+	enrichments: Vec<Enrichment>,
+	/// The album covers shown when hovering over bars of song data
+	#[cfg(feature = "enrich")]
+	covers: Covers,
+	/// Where `data` was loaded from, if it can be loaded again
+	#[cfg(feature = "native")]
+	source: Option<Source>,
+	/// Set while we're reloading the data whenever `source` changes
+	#[cfg(feature = "native")]
+	watch: Option<Watch>,
+	/// Where more rows keep coming in from, if anywhere
+	#[cfg(feature = "native")]
+	live: Option<Live>,
+	/// The files that have been opened before, read again whenever the list might've changed
+	#[cfg(feature = "native")]
+	recent: Option<Recent>,
+	/// When the session was last autosaved, along with the view it was saved with
+	#[cfg(feature = "native")]
+	autosaved: Option<(Instant, String)>,
+	/// Where a session's being saved to or loaded from, while the user's picking that
+	#[cfg(feature = "native")]
+	session_dialog: Option<SessionDialog>,
+	/// Files that were dropped onto the window, while the user's picking what to do with them
+	dropped: Option<Dropped>,
+	/// New rows that are getting the keys that were added to the data on another thread, in the
+	/// order that they'll be put into it
+	deriving: Vec<Deriving>,
+	/// How tall each bar was before the data was last refreshed (by reloading it or rows coming
+	/// in), so that what changed can be shown until the chart's changed some other way
+	refreshed: Option<FxHashMap<Vec<String>, f64>>,
+	/// Set while the bars are being rebuilt for refreshed data, so that `refreshed` is kept for
+	/// them
+	refreshing: bool,
+	/// Every different view that's been charted, so that any of them can be gone back to
+	history: History,
+	/// How many of the filtered rows are around each place, if they have coordinates and the map's
+	/// been drawn since they were last filtered
+	density: Option<Vec<([f64; 2], usize)>>,
+	/// When the bars should be rebuilt for a change that's still being made
+	rebuild_due: Option<Instant>,
+	/// Set while the command palette's open
+	palette: Option<Palette>,
+	/// Something picked in the command palette that has to wait for the plot to be drawn
+	plot_action: Option<PlotAction>,
+	/// Whether how long the last rebuild took is shown over the plot
+	profiling: bool
+}
+
+/// How wide each of a pair of compared bars is, which together take up the width of one bar
+const PAIR_WIDTH: f64 = 0.4;
+
+/// The colors of the bars for A's bounds and B's, when they're being compared
+const COMPARE_COLORS: [Color32; 2] = [
+	Color32::from_rgb(78, 121, 167),
+	Color32::from_rgb(242, 142, 43)
+];
+
+/// The colors of bars that went up and down, when they're charted as how much they changed
+const CHANGE_COLORS: [Color32; 2] = [
+	Color32::from_rgb(89, 161, 79),
+	Color32::from_rgb(225, 87, 89)
+];
+
+/// What share of the total bars are merged under when merging is first turned on
+const DEFAULT_MERGE_BELOW: f64 = 0.01;
+
+/// How many rows a sample starts out with when sampling is first turned on
+const DEFAULT_SAMPLE_ROWS: usize = 10_000;
+
+/// The color of reference lines, and their labels
+const REFERENCE_COLOR: Color32 = Color32::from_rgb(118, 183, 178);
+
+/// The color of the moving average's line
+const AVERAGE_COLOR: Color32 = Color32::from_rgb(176, 122, 161);
+
+/// How many bars a moving average starts out being taken over
+const DEFAULT_AVERAGE_WINDOW: usize = 7;
+
+/// How many bins a key is bucketed into when it's first made into a histogram
+const DEFAULT_BIN_COUNT: usize = 10;
+
+/// The most bins a key can be bucketed into from the settings panel, past which it may as well
+/// not be binned at all
+const MAX_BIN_COUNT: usize = 1000;
+
+/// How long a slider has to sit still mid-drag before the bars are rebuilt with its new value
+const REBUILD_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// How often the files being watched are checked for changes
+#[cfg(feature = "native")]
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often rows that have come in from a feed are added to the chart, so that it's not rebuilt
+/// for every single one
+#[cfg(feature = "native")]
+const FEED_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(feature = "native")]
+struct Live {
+	feed: Feed,
+	last_checked: Instant,
+	/// How many rows couldn't be added, either because they couldn't be parsed or didn't match
+	/// the rest of the data
+	skipped: usize
+}
+
+#[cfg(feature = "native")]
+struct Watch {
+	/// When each of the source's files was last modified as of the last check
+	modified: Vec<Option<SystemTime>>,
+	last_checked: Instant
+}
+
+/// For when the bars are the results of a SQL query instead of what the settings make
+#[cfg(feature = "sql")]
+#[derive(Default)]
+struct Sql {
+	/// What's been typed into the query box
+	input: String,
+	/// The data, copied into a form that can be queried. It's only made once there's a query to
+	/// run, and dropped whenever the data changes.
+	table: Option<SqlTable>,
+	/// The query whose results are being shown, and the columns they're labelled with
+	active: Option<(String, Vec<String>)>,
+	err: Option<String>
+}
+
+/// What's been typed into the boxes for scripts
+#[derive(Default)]
+struct ScriptInput {
+	predicate: String,
+	predicate_err: Option<String>,
+	column: String,
+	column_err: Option<String>
+}
+
+/// A key that [`App::add_column`] is working out the values of in the background
+struct AddingColumn {
+	/// What the user typed, to give back to them if it doesn't work
+	definition: String,
+	name: String,
+	expr: Expression,
+	/// The data that the values are for, which they can only be added to
+	data: Data,
+	values: Receiver<Result<Vec<Value<'static>>, ScriptErr>>
+}
+
+struct Presentation {
+	/// If the window was already fullscreen before we started presenting, so that we can put it
+	/// back the way it was once we're done
+	was_fullscreen: bool
+}
+
+struct VisibleBars {
+	/// Which page of the bars these are
+	range: Range<usize>,
+	/// The index of each bar that's drawn, which is the page followed by any pinned bars that
+	/// aren't on it. They're drawn one after another starting at `range.start`.
+	indices: Arc<[usize]>,
+	/// The bars, lacking their names so that they're cheap to copy for each frame
+	bars: Vec<Bar>,
+	/// If the bars are being compared, each one and the one it's compared against, squeezed in
+	/// side by side where the bar would be
+	pairs: Option<(Vec<Bar>, Vec<Bar>)>,
+	/// If the bars are stacked, the segments of each of them for each entry in the legend, along
+	/// with its color
+	stacks: Option<Vec<(String, Color32, Vec<Bar>)>>,
+	/// The name of each bar, which the tooltips look up by the bar's argument
+	names: Arc<[String]>,
+	/// The top of each bar as a point, cut down to fit into some number of pixels, for the charts
+	/// that aren't drawn with bars
+	points: Option<(usize, Vec<[f64; 2]>)>,
+	/// The top of each bar that has outlying rows in it, so that they can be marked on the charts
+	/// that aren't drawn with bars
+	outlying: Vec<[f64; 2]>,
+	/// The line that best fits the tops of the bars
+	trend: Option<Fit>,
+	/// The moving average at each bar on the page, if one's being drawn
+	average: Option<Vec<[f64; 2]>>,
+	/// How tall each bar was before the data was last refreshed, for the ones that were there
+	/// then
+	ghosts: Vec<Bar>
+}
+
+impl VisibleBars {
+	fn new(
+		bars: &[LabeledBar],
+		range: Range<usize>,
+		pinned: &[Vec<String>],
+		visuals: &egui::Visuals,
+		settings: &Settings,
+		refreshed: Option<&FxHashMap<Vec<String>, f64>>
+	) -> Self {
+		let is_pinned = |bar: &LabeledBar| pinned.contains(&bar.labels);
+		let indices = range
+			.clone()
+			.chain((0..bars.len()).filter(|idx| !range.contains(idx) && is_pinned(&bars[*idx])))
+			.collect::<Arc<[_]>>();
+
+		let comparing = indices.iter().any(|idx| bars[*idx].compared.is_some());
+		// Compared bars can be drawn as how much they changed instead of as pairs
+		let diff = settings.diff && comparing;
+		let pairs = (comparing && !diff).then(|| {
+			let side = |offset: f64, height: fn(&LabeledBar) -> f64| {
+				indices
+					.iter()
+					.zip(range.start..)
+					.map(|(idx, x)| {
+						Bar::new(x as f64 + offset, height(&bars[*idx])).width(PAIR_WIDTH)
+					})
+					.collect()
+			};
+			(
+				side(-PAIR_WIDTH / 2., |bar| bar.value),
+				side(PAIR_WIDTH / 2., |bar| bar.compared.unwrap_or(0.))
+			)
+		});
+
+		let drawn = indices
+			.iter()
+			.zip(range.start..)
+			.map(|(idx, x)| {
+				let labeled = &bars[*idx];
+				let bar = match diff {
+					true => {
+						let change = diff::change(labeled);
+						Bar::new(x as f64, change).fill(CHANGE_COLORS[usize::from(change < 0.)])
+					}
+					false => Bar::new(x as f64, labeled.value)
+				};
+				// Being pinned is something the user did, so it wins over everything else
+				if is_pinned(labeled) {
+					bar.fill(visuals.warn_fg_color)
+				} else if let Some([r, g, b]) = labeled.color {
+					bar.fill(Color32::from_rgb(r, g, b))
+				} else if labeled.outliers > 0 {
+					bar.fill(visuals.error_fg_color)
+				} else {
+					bar
+				}
+			})
+			.collect::<Vec<_>>();
+		let tops = drawn
+			.iter()
+			.map(|bar| [bar.argument, bar.value])
+			.collect::<Vec<_>>();
+
+		// Compared bars are already split in two
+		let legend = bars::legend(bars);
+		let stacks = (!comparing && !legend.is_empty()).then(|| {
+			let mut stacks = legend
+				.into_iter()
+				.zip(coloring::NAMED_COLORS.iter().cycle())
+				.map(|(label, (_, [r, g, b]))| (label, Color32::from_rgb(*r, *g, *b), Vec::new()))
+				.collect::<Vec<_>>();
+			for (idx, x) in indices.iter().zip(range.start..) {
+				let mut base = 0.;
+				for segment in &bars[*idx].segments {
+					let Some((.., stack)) = stacks
+						.iter_mut()
+						.find(|(label, ..)| *label == segment.label)
+					else {
+						continue;
+					};
+					stack.push(Bar::new(x as f64, segment.value).base_offset(base));
+					base += segment.value;
+				}
+			}
+			stacks
+		});
+
+		// The average goes through all the bars, not just the page, so that the first ones on it
+		// are still averaged over the ones before them
+		let average = settings.moving_average.map(|window| {
+			let heights = bars
+				.iter()
+				.map(|bar| match diff {
+					true => diff::change(bar),
+					false => bar.value
+				})
+				.collect::<Vec<_>>();
+			let averages = trend::moving_average(&heights, window);
+			range
+				.clone()
+				.map(|idx| [idx as f64, averages[idx]])
+				.collect()
+		});
+
+		// Compared bars are already two bars each, so there's no room for a third
+		let refreshed = refreshed.filter(|_| !comparing);
+		let format = settings.value_format();
+
+		Self {
+			ghosts: indices
+				.iter()
+				.zip(range.start..)
+				.filter_map(|(idx, x)| {
+					let before = refreshed?.get(&bars[*idx].labels)?;
+					Some(Bar::new(x as f64, *before))
+				})
+				.collect(),
+			pairs,
+			stacks,
+			trend: Fit::least_squares(&tops),
+			average,
+			outlying: indices
+				.iter()
+				.zip(&drawn)
+				.filter(|(idx, _)| bars[**idx].outliers > 0)
+				.map(|(_, bar)| [bar.argument, bar.value])
+				.collect(),
+			bars: drawn,
+			names: indices
+				.iter()
+				.map(|idx| {
+					let bar = &bars[*idx];
+					let mut name = bar.name.clone();
+					if diff {
+						name = format!("{name}\n{}", diff::describe(bar));
+					}
+					if bar.outliers > 0 {
+						name = format!("{name}\n⚠ {} outlying rows", bar.outliers);
+					}
+					if let Some(refreshed) = refreshed {
+						let change = refresh_change(refreshed.get(&bar.labels), bar.value, format);
+						name = format!("{name}\n↻ {change}");
+					}
+					name
+				})
+				.collect(),
+			indices,
+			points: None,
+			range
+		}
+	}
+
+	/// Which bar is drawn at `x`
+	fn bar_at(&self, x: f64) -> Option<usize> {
+		if x < 0. {
+			return None;
+		}
+		let pos = (x as usize).checked_sub(self.range.start)?;
+		self.indices.get(pos).copied()
+	}
+
+	/// Where the bar at `idx` is drawn, if it is
+	fn x_of(&self, idx: usize) -> Option<f64> {
+		self.indices
+			.iter()
+			.position(|i| *i == idx)
+			.map(|pos| (self.range.start + pos) as f64)
+	}
+
+	/// The points to draw for a line or points chart that's `pixels` wide
+	fn points(&mut self, pixels: usize) -> PlotPoints {
+		let points = match &self.points {
+			Some((p, points)) if *p == pixels => points,
+			_ => {
+				let points = self
+					.bars
+					.iter()
+					.map(|bar| [bar.argument, bar.value])
+					.collect::<Vec<_>>();
+				&self
+					.points
+					.insert((pixels, downsample::min_max(&points, pixels)))
+					.1
+			}
+		};
+		PlotPoints::from(points.clone())
+	}
+}
+
+enum ImageExport {
+	/// The user asked for an export, but the frame that was just drawn has the context menu all
+	/// over it, so we need to wait a frame before taking the screenshot
+	Requested(Rect),
+	/// We've asked for a screenshot and are waiting for it to come back
+	Waiting(Rect)
+}
+
+/// Where to save a session to or load one from (see [`session`])
+#[cfg(feature = "native")]
+#[derive(Default)]
+struct SessionDialog {
+	path: String,
+	/// If the session's being saved, instead of loaded
+	saving: bool
+}
+
+/// Everything that's been added to the data since it was loaded, so that it can be added to new
+/// rows too (see [`App::derive_keys`])
+#[derive(Clone)]
+struct DerivedKeys {
+	#[cfg(feature = "enrich")]
+	enrichments: Vec<Enrichment>,
+	addresses: Vec<String>,
+	urls: Vec<String>,
+	agents: Vec<String>,
+	columns: Vec<(String, Expression)>
+}
+
+impl DerivedKeys {
+	fn add_to(&self, data: &mut [merde::Map<'static>]) -> Result<(), String> {
+		// Anything that's been looked up before is cached, so this won't have to wait on the
+		// network unless the new data has something new in it
+		#[cfg(feature = "enrich")]
+		for enrichment in &self.enrichments {
+			enrichment
+				.apply(data, &mut |_, _| ())
+				.map_err(|e| e.to_string())?;
+		}
+		for key in &self.addresses {
+			ip::add_fields(data, key).map_err(|e| e.to_string())?;
+		}
+		for key in &self.urls {
+			urls::add_fields(data, key).map_err(|e| e.to_string())?;
+		}
+		for key in &self.agents {
+			agents::add_fields(data, key).map_err(|e| e.to_string())?;
+		}
+		for (name, expr) in &self.columns {
+			script::add_column(data, name, expr).map_err(|e| e.to_string())?;
+		}
+		Ok(())
+	}
+}
+
+/// Rows that [`DerivedKeys`] are being added to in the background
+struct Deriving {
+	rows: Receiver<Result<Rows, String>>,
+	/// What happens to them once they've got the keys
+	then: Derived
+}
+
+enum Derived {
+	/// They're the data's files, loaded again
+	#[cfg(feature = "native")]
+	Reload,
+	/// They're from files that were dropped onto the window, to add to the data
+	Merge(Dropped)
+}
+
+/// Files that were dropped onto the window, loaded and waiting on whether they're added to the
+/// data or replace it
+struct Dropped {
+	/// What the files are called
+	names: Vec<String>,
+	/// Where the files are, if they're all somewhere on disk that they can be reloaded from
+	#[cfg(feature = "native")]
+	paths: Option<Vec<String>>,
+	/// What format each of the files turned out to be in
+	#[cfg(feature = "native")]
+	formats: Vec<Format>,
+	rows: Vec<merde::Map<'static>>
+}
+
+/// How big of an image to draw the plot again at, for exporting it at a size of its own instead of
+/// the window's (see [`offscreen`])
+struct PngExport {
+	width: usize,
+	height: usize,
+	/// How many pixels there are to each point, which makes the text and lines bigger
+	scale: f32,
+	/// Set once the user's picked a size, so that it's drawn right after the plot next is
+	due: bool
+}
+
+impl Default for PngExport {
+	fn default() -> Self {
+		Self {
+			width: 1920,
+			height: 1080,
+			scale: 1.,
+			due: false
+		}
+	}
+}
+
+enum PlotAction {
+	ExportImage,
+	/// Opening the dialog to pick how big of a PNG to export
+	ExportPng,
+	CopyData,
+	ClearFilters,
+	ResetZoom,
+	/// Saving the rows that get through the filters as a ListenBrainz export
+	#[cfg(feature = "native")]
+	ExportListens
+}
+
+/// Something that can be done from the command palette (see [`App::commands`])
+enum Command {
+	AddKey(String),
+	RemoveKey(String),
+	GroupBy(String),
+	SetDrilling(bool),
+	CountRows,
+	ListeningTime,
+	SkipRate,
+	Sum(String),
+	Aggregate(Aggregation),
+	Rate(String),
+	Chart(ChartKind),
+	Plot(PlotAction),
+	TogglePresenting,
+	ToggleProfiling,
+	#[cfg(feature = "native")]
+	Reload,
+	#[cfg(feature = "native")]
+	OpenRecent(RecentFile)
+}
+
+impl App {
+	pub fn new(data: impl Into<Rows>, config: &Config) -> Result<Self, DataErr> {
+		let data = data.into();
+		let schema = Schema::infer(&data)?;
+
+		Ok(Self {
+			memory: data.size(),
+			data: Arc::new(data),
+			worker: Worker::spawn(),
+			schema,
+			settings: config.settings(),
+			load_options: config.load_options(),
+			bars: Vec::new(),
+			visible: None,
+			stats: FxHashMap::default(),
+			outlier_counts: FxHashMap::default(),
+			uncoerced: FxHashMap::default(),
+			stats_shown: None,
+			suggestions: None,
+			fit_plot: true,
+			last_shown: 0..0,
+			selected: None,
+			pinned: Vec::new(),
+			editing_b: false,
+			export: None,
+			png_export: None,
+			status: None,
+			presenting: None,
+			filter_input: String::new(),
+			note_input: (String::new(), 0.),
+			reference_input: 0.,
+			filter_err: None,
+			color_rule_input: String::new(),
+			color_rule_err: None,
+			view_input: String::new(),
+			view_err: None,
+			scripts: Scripts::default(),
+			columns: Vec::new(),
+			addresses: Vec::new(),
+			urls: Vec::new(),
+			agents: Vec::new(),
+			adding_column: None,
+			script_input: ScriptInput::default(),
+			#[cfg(feature = "sql")]
+			sql: Sql::default(),
+			#[cfg(feature = "enrich")]
+			enrichments: Vec::new(),
+			#[cfg(feature = "enrich")]
+			covers: Covers::new(),
+			#[cfg(feature = "native")]
+			source: None,
+			#[cfg(feature = "native")]
+			watch: None,
+			#[cfg(feature = "native")]
+			live: None,
+			#[cfg(feature = "native")]
+			recent: None,
+			#[cfg(feature = "native")]
+			autosaved: None,
+			#[cfg(feature = "native")]
+			session_dialog: None,
+			dropped: None,
+			deriving: Vec::new(),
+			refreshed: None,
+			refreshing: false,
+			history: History::default(),
+			density: None,
+			rebuild_due: None,
+			palette: None,
+			plot_action: None,
+			profiling: false
+		})
+	}
+
+	/// Uses `scripts` for any expressions the user writes from now on. `columns` are the keys
+	/// that scripts have already added to the data.
+	pub fn with_scripts(mut self, scripts: Scripts, columns: Vec<(String, Expression)>) -> Self {
+		self.scripts = scripts;
+		self.columns = columns;
+		self
+	}
+
+	/// Remembers which keys of IP addresses have already had keys pulled out of them (see
+	/// [`ip::add_fields`])
+	pub fn with_addresses(mut self, keys: Vec<String>) -> Self {
+		self.addresses = keys;
+		self
+	}
+
+	/// Remembers which keys of URLs have already had their parts pulled out of them (see
+	/// [`urls::add_fields`])
+	pub fn with_urls(mut self, keys: Vec<String>) -> Self {
+		self.urls = keys;
+		self
+	}
+
+	/// Remembers which keys of user agents have already had browsers and such pulled out of them
+	/// (see [`agents::add_fields`])
+	pub fn with_agents(mut self, keys: Vec<String>) -> Self {
+		self.agents = keys;
+		self
+	}
+
+	/// Remembers the online services that the data's already been enriched with, so that
+	/// reloading it enriches it again
+	#[cfg(feature = "enrich")]
+	pub fn with_enrichments(mut self, enrichments: Vec<Enrichment>) -> Self {
+		self.enrichments = enrichments;
+		self
+	}
+
+	/// Keeps adding the rows that come in from `feed` to the data
+	#[cfg(feature = "native")]
+	pub fn with_feed(mut self, feed: Feed) -> Self {
+		self.live = Some(Live {
+			feed,
+			last_checked: Instant::now(),
+			skipped: 0
+		});
+		self
+	}
+
+	/// Adds whatever's come in from the feed since we last checked
+	#[cfg(feature = "native")]
+	fn check_feed(&mut self, ctx: &egui::Context) {
+		let Some(live) = &mut self.live else {
+			return;
+		};
+
+		ctx.request_repaint_after(FEED_INTERVAL);
+		if live.last_checked.elapsed() < FEED_INTERVAL {
+			return;
+		}
+		live.last_checked = Instant::now();
+
+		let received = live.feed.take();
+		let mut rows = Vec::new();
+		let mut misfits = 0;
+		for mut row in received.rows {
+			// Rows come in as whatever type they were sent as, so they have to be converted like
+			// the rest of the data was before they'll fit in with it
+			let originals = self
+				.settings
+				.key_types
+				.iter()
+				.map(|(key, ty)| {
+					(
+						key,
+						schema::convert(std::slice::from_mut(&mut row), key, *ty)
+					)
+				})
+				.collect::<Vec<_>>();
+			if !self.schema.fits(&row) {
+				misfits += 1;
+				continue;
+			}
+			for (key, original) in originals {
+				self.uncoerced
+					.entry(key.clone())
+					.or_default()
+					.extend(original);
+			}
+			rows.push(row);
+		}
+
+		let skipped = received.malformed + misfits;
+		if skipped > 0 {
+			live.skipped += skipped;
+			let why = received.last_err.unwrap_or_else(|| {
+				"its keys or types didn't match the rest of the data".to_string()
+			});
+			self.status = Some(format!(
+				"Skipped {} rows from {} so far (the last one: {why})",
+				live.skipped, live.feed.address
+			));
+		}
+
+		if !rows.is_empty() {
+			self.remember_bars();
+			self.memory += memory::estimate(&rows);
+			// Between jobs the worker only holds onto a `Weak` of the data, so this normally just
+			// moves it instead of copying all of it (which also tells the worker it's changed)
+			Arc::make_mut(&mut self.data).extend(rows);
+			self.stats.clear();
+			self.outlier_counts.clear();
+			self.suggestions = None;
+			#[cfg(feature = "sql")]
+			{
+				self.sql.table = None;
+			}
+			self.rebuild_bars();
+		}
+	}
+
+	/// Remembers where the data came from so that it can be reloaded, optionally starting to
+	/// watch it for changes right away
+	#[cfg(feature = "native")]
+	pub fn with_source(mut self, source: Source, watch: bool) -> Self {
+		self.source = Some(source);
+		self.set_watching(watch);
+		self
+	}
+
+	#[cfg(feature = "native")]
+	fn set_watching(&mut self, watch: bool) {
+		self.watch = match (&self.source, watch) {
+			(Some(source), true) => Some(Watch {
+				modified: source.modified_times(),
+				last_checked: Instant::now()
+			}),
+			_ => None
+		};
+	}
+
+	/// Reloads the data if any of the files being watched have changed since we last checked
+	#[cfg(feature = "native")]
+	fn check_for_changes(&mut self, ctx: &egui::Context) {
+		let (Some(watch), Some(source)) = (&mut self.watch, &self.source) else {
+			return;
+		};
+
+		// Make sure we get woken up to check again even if the user isn't doing anything
+		ctx.request_repaint_after(WATCH_INTERVAL);
+		if watch.last_checked.elapsed() < WATCH_INTERVAL {
+			return;
+		}
+
+		watch.last_checked = Instant::now();
+		let modified = source.modified_times();
+		if modified != watch.modified {
+			watch.modified = modified;
+			self.reload();
+		}
+	}
+
+	#[cfg(feature = "native")]
+	fn reload(&mut self) {
+		let Some(source) = &self.source else {
+			return;
+		};
+
+		match source.load() {
+			Ok(data) => self.derive_keys(data, Derived::Reload),
+			Err(e) =>
+				self.status = Some(format!(
+					"Couldn't reload the data, so the old data is still shown: {e}"
+				)),
+		}
+	}
+
+	/// Swaps the data out for what it was reloaded as, once it's been through
+	/// [`Self::derive_keys`]
+	#[cfg(feature = "native")]
+	fn finish_reload(&mut self, mut data: Rows) {
+		let uncoerced = self
+			.settings
+			.key_types
+			.iter()
+			.map(|(key, ty)| (key.clone(), schema::convert(&mut data, key, *ty)))
+			.collect();
+		self.remember_bars();
+		self.status = Some(match self.replace_data(data) {
+			Ok(()) => {
+				self.uncoerced = uncoerced;
+				format!("Reloaded {} rows", self.data.len())
+			}
+			Err(e) => format!("Couldn't reload the data, so the old data is still shown: {e}")
+		});
+	}
+
+	/// Starts adding all the keys that were added to the data since it was loaded to `rows` too,
+	/// like columns from scripts and the parts of URLs. Looking values up online can take a
+	/// while, so that's done on another thread, and `then` happens once
+	/// [`Self::receive_derived`] gets them back.
+	fn derive_keys(&mut self, mut rows: Rows, then: Derived) {
+		let keys = DerivedKeys {
+			#[cfg(feature = "enrich")]
+			enrichments: self.enrichments.clone(),
+			addresses: self.addresses.clone(),
+			urls: self.urls.clone(),
+			agents: self.agents.clone(),
+			columns: self.columns.clone()
+		};
+		let rows = worker::background("spart-derive", move || {
+			keys.add_to(&mut rows).map(|()| rows)
+		});
+		self.deriving.push(Deriving { rows, then });
+	}
+
+	/// Puts the rows from [`Self::derive_keys`] wherever they were going, once they're ready
+	fn receive_derived(&mut self) {
+		while let Some(deriving) = self.deriving.first() {
+			let derived = match deriving.rows.try_recv() {
+				Ok(derived) => derived,
+				Err(TryRecvError::Empty) => return,
+				Err(TryRecvError::Disconnected) =>
+					Err("the thread it was being done on stopped".to_string()),
+			};
+
+			match (self.deriving.remove(0).then, derived) {
+				#[cfg(feature = "native")]
+				(Derived::Reload, Ok(data)) => self.finish_reload(data),
+				#[cfg(feature = "native")]
+				(Derived::Reload, Err(e)) =>
+					self.status = Some(format!(
+						"Couldn't reload the data, so the old data is still shown: {e}"
+					)),
+				(Derived::Merge(dropped), Ok(rows)) => {
+					let count = rows.len();
+					match self.merge_data(rows) {
+						Ok(()) => self.finish_dropped(dropped, count, false),
+						Err(e) =>
+							self.status = Some(format!("Couldn't open the dropped files: {e}")),
+					}
+				}
+				(Derived::Merge(_), Err(e)) =>
+					self.status = Some(format!("Couldn't add the dropped files to the data: {e}")),
+			}
+		}
+	}
+
+	/// Forgets everything that was added to or changed about data that's been swapped out, since
+	/// none of it applies to the new data
+	fn forget_derived_keys(&mut self) {
+		self.columns.clear();
+		self.addresses.clear();
+		self.urls.clear();
+		self.agents.clear();
+		#[cfg(feature = "enrich")]
+		self.enrichments.clear();
+		self.uncoerced.clear();
+		self.settings.key_types.clear();
+		self.history = History::default();
+		self.selected = None;
+	}
+
+	/// Swaps the data out for that of the files in `file`, showing it the way it was last left
+	#[cfg(feature = "native")]
+	fn open_recent(&mut self, file: &RecentFile) {
+		self.remember_source();
+		self.recent = None;
+
+		let result = file
+			.format()
+			.map_err(|e| e.to_string())
+			.and_then(|format| {
+				Source::new(file.paths.clone(), format, self.load_options.clone())
+					.ok_or_else(|| "there are no files to open".to_string())
+			})
+			.and_then(|source| {
+				let data = source.load().map_err(|e| e.to_string())?;
+				self.replace_data(data).map_err(|e| e.to_string())?;
+				self.source = Some(source);
+				Ok(())
+			});
+		if let Err(e) = result {
+			self.status = Some(format!("Couldn't open {}: {e}", file.paths.join(", ")));
+			return;
+		}
+
+		self.forget_derived_keys();
+		self.set_watching(self.watch.is_some());
+		self.status = Some(format!("Opened {} rows", self.data.len()));
+		self.rebuild_bars();
+		self.fit_plot = true;
+
+		self.restore(file);
+		if let Some(file) = self.session(None) {
+			Recent::remember(file);
+		}
+	}
+
+	/// Adds the keys that were added to `file`'s data before and shows the view it was last left
+	/// on, saying so if either can't be done anymore
+	#[cfg(feature = "native")]
+	pub(crate) fn restore(&mut self, file: &RecentFile) {
+		if let Err(e) = self.restore_keys(&file.added) {
+			self.status = Some(format!("Couldn't add the keys this had before: {e}"));
+		}
+		if let Some(view) = &file.view
+			&& let Err(e) = self.open_view(view)
+		{
+			self.status = Some(format!("Couldn't show the view this was last left on: {e}"));
+		}
+	}
+
+	#[cfg(feature = "native")]
+	fn restore_keys(&mut self, added: &AddedKeys) -> Result<(), String> {
+		if *added == AddedKeys::default() {
+			return Ok(());
+		}
+		let columns = added
+			.columns
+			.iter()
+			.map(|column| {
+				let (name, expr) = parse_column(column)?;
+				Ok((name.to_string(), self.scripts.compile(expr)?))
+			})
+			.collect::<Result<Vec<_>, ScriptErr>>()
+			.map_err(|e| e.to_string())?;
+		let keys = DerivedKeys {
+			#[cfg(feature = "enrich")]
+			enrichments: Vec::new(),
+			addresses: added.addresses.clone(),
+			urls: added.urls.clone(),
+			agents: added.agents.clone(),
+			columns
+		};
+
+		let mut data = (*self.data).clone();
+		keys.add_to(&mut data)?;
+		self.replace_data(data).map_err(|e| e.to_string())?;
+		self.addresses = keys.addresses;
+		self.urls = keys.urls;
+		self.agents = keys.agents;
+		self.columns = keys.columns;
+		Ok(())
+	}
+
+	/// The files being looked at, along with everything it takes to open them again just like
+	/// this (other than what was looked up online)
+	#[cfg(feature = "native")]
+	pub fn session(&self, view: Option<String>) -> Option<RecentFile> {
+		let source = self.source.as_ref()?;
+		let added = AddedKeys {
+			columns: self
+				.columns
+				.iter()
+				.map(|(name, expr)| format!("{name}={}", expr.source))
+				.collect(),
+			addresses: self.addresses.clone(),
+			urls: self.urls.clone(),
+			agents: self.agents.clone()
+		};
+		Some(RecentFile::new(&source.paths, source.format, view, added))
+	}
+
+	/// Remembers the files being looked at as recently opened, along with the view they're on
+	#[cfg(feature = "native")]
+	fn remember_source(&self) {
+		if let Some(file) = self.session(Some(self.view_token())) {
+			Recent::remember(file);
+		}
+	}
+
+	/// Saves the files being looked at and the view they're on, if it's been a while since they
+	/// last were and they've changed since
+	#[cfg(feature = "native")]
+	fn autosave(&mut self, ctx: &egui::Context) {
+		if self.source.is_none() {
+			return;
+		}
+
+		ctx.request_repaint_after(AUTOSAVE_INTERVAL);
+		if self
+			.autosaved
+			.as_ref()
+			.is_some_and(|(saved, _)| saved.elapsed() < AUTOSAVE_INTERVAL)
+		{
+			return;
+		}
+
+		let view = self.view_token();
+		if self
+			.autosaved
+			.as_ref()
+			.is_none_or(|(_, saved)| *saved != view)
+			&& let Some(session) = self.session(Some(view.clone()))
+			&& let Err(e) = autosave::save(&session)
+		{
+			self.status = Some(format!("Couldn't autosave the session: {e}"));
+		}
+		self.autosaved = Some((Instant::now(), view));
+	}
+
+	#[cfg(feature = "native")]
+	fn show_menu(&mut self, ui: &mut egui::Ui) {
+		egui::menu::bar(ui, |ui| {
+			ui.menu_button("File", |ui| {
+				ui.menu_button("Open recent", |ui| {
+					let recent = self.recent.get_or_insert_with(Recent::load);
+					if recent.is_empty() {
+						ui.label("Nothing's been opened yet");
+					}
+					if let Some(file) = recent.show(ui) {
+						ui.close_menu();
+						self.open_recent(&file);
+					}
+				});
+				ui.separator();
+				for (label, saving) in [("Save session…", true), ("Load session…", false)] {
+					if ui.button(label).clicked() {
+						ui.close_menu();
+						self.session_dialog = Some(SessionDialog {
+							path: session::DEFAULT_PATH.to_string(),
+							saving
+						});
+					}
+				}
+				ui.separator();
+				if ui.button("Export PNG…").clicked() {
+					ui.close_menu();
+					self.png_export = Some(PngExport::default());
+				}
+			});
+		});
+	}
+
+	/// Lets the user pick where to save the session to or load one from, and does that once
+	/// they have
+	#[cfg(feature = "native")]
+	fn show_session_dialog(&mut self, ctx: &egui::Context) {
+		let Some(dialog) = &mut self.session_dialog else {
+			return;
+		};
+
+		let (mut open, mut done) = (true, false);
+		let title = match dialog.saving {
+			true => "Save session",
+			false => "Load session"
+		};
+		egui::Window::new(title)
+			.open(&mut open)
+			.collapsible(false)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("File");
+					let path = ui.text_edit_singleline(&mut dialog.path);
+					let entered = path.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+					done = ui.button(title).clicked() || entered;
+				});
+			});
+
+		if done {
+			let SessionDialog { path, saving } = self.session_dialog.take().unwrap_or_default();
+			match saving {
+				true => self.status = Some(self.save_session(Path::new(&path))),
+				false => self.load_session(Path::new(&path))
+			}
+		} else if !open {
+			self.session_dialog = None;
+		}
+	}
+
+	/// Saves the files being looked at and the view they're on to `path`, saying how that went
+	#[cfg(feature = "native")]
+	fn save_session(&self, path: &Path) -> String {
+		let Some(session) = self.session(Some(self.view_token())) else {
+			return "Only data that was loaded from files can be saved as a session".to_string();
+		};
+		match session::save(path, &session) {
+			Ok(()) => format!("Saved the session to {}", path.display()),
+			Err(e) => format!("Couldn't save the session to {}: {e}", path.display())
+		}
+	}
+
+	/// Opens the files of the session at `path` on the view it was saved with
+	#[cfg(feature = "native")]
+	fn load_session(&mut self, path: &Path) {
+		match session::load(path) {
+			Ok(session) => self.open_recent(&session),
+			Err(e) =>
+				self.status = Some(format!(
+					"Couldn't open the session at {}: {e}",
+					path.display()
+				)),
+		}
+	}
+
+	/// Keeps the bars as they are now, to show how they change once the data's been refreshed
+	#[cfg(feature = "native")]
+	fn remember_bars(&mut self) {
+		if !self.bars.is_empty() {
+			self.refreshed = Some(
+				self.bars
+					.iter()
+					.map(|bar| (bar.labels.clone(), bar.value))
+					.collect()
+			);
+			self.refreshing = true;
+		}
+	}
+
+	/// Swaps out all the data we're looking at, keeping as much of the current settings as still
+	/// makes sense with the new data
+	pub fn replace_data(&mut self, data: impl Into<Rows>) -> Result<(), DataErr> {
+		let data = data.into();
+		let schema = Schema::infer(&data)?;
+		let type_of = |key: &str| {
+			schema
+				.keys
+				.iter()
+				.find(|(k, _)| k == key)
+				.map(|(_, ty)| *ty)
+		};
+
+		self.stop_drilling();
+		self.settings.x_axis.retain(|key| type_of(key).is_some());
+		// A key can still be there but hold something else now, which its bounds can't bound
+		self.settings.retain_bounds(type_of);
+
+		self.memory = data.size();
+		self.data = Arc::new(data);
+		self.schema = schema;
+		self.stats.clear();
+		self.outlier_counts.clear();
+		self.suggestions = None;
+		#[cfg(feature = "sql")]
+		{
+			self.sql.table = None;
+		}
+		self.rebuild_bars();
+		Ok(())
+	}
+
+	/// Adds `rows` to the data, as long as each of their keys holds the same type of values as it
+	/// does in the rest of it. Keys that only one of them has are null in the rows of the other.
+	pub fn merge_data(&mut self, rows: impl Into<Rows>) -> Result<(), DataErr> {
+		let mut rows = rows.into();
+		// They have to be converted like the rest of the data was before they'll fit in with it
+		let originals = self
+			.settings
+			.key_types
+			.iter()
+			.map(|(key, ty)| (key.clone(), schema::convert(&mut rows, key, *ty)))
+			.collect::<Vec<_>>();
+		let merging = Schema::infer(&rows)?;
+		for (key, found) in &merging.keys {
+			let Some((_, expected)) = self.schema.keys.iter().find(|(k, _)| k == key) else {
+				continue;
+			};
+			match (*expected, *found) {
+				(ValueType::Null, _) | (_, ValueType::Null) => (),
+				(expected, found) if expected != found =>
+					return Err(DataErr::DifferentTypes {
+						key: key.clone(),
+						expected,
+						found
+					}),
+				_ => ()
+			}
+		}
+
+		for (key, original) in originals {
+			self.uncoerced.entry(key).or_default().extend(original);
+		}
+		self.memory += rows.size();
+		let data = Arc::make_mut(&mut self.data);
+		data.append(rows);
+		self.schema = Schema::infer(data)?;
+		self.stats.clear();
+		self.outlier_counts.clear();
+		self.suggestions = None;
+		#[cfg(feature = "sql")]
+		{
+			self.sql.table = None;
+		}
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Loads whatever files were just dropped onto the window, and asks what should be done with
+	/// them
+	fn show_dropped(&mut self, ctx: &egui::Context) {
+		let files = ctx.input(|i| i.raw.dropped_files.clone());
+		if !files.is_empty() {
+			let names = files
+				.iter()
+				.map(|file| match &file.path {
+					Some(path) => path.display().to_string(),
+					None => file.name.clone()
+				})
+				.collect();
+			match dropped::load(&files, &self.load_options) {
+				Ok((rows, _)) if rows.is_empty() =>
+					self.status = Some("There weren't any rows in the dropped files".to_string()),
+				// Only files on disk can be reloaded as part of the data, which is all that their
+				// formats matter for
+				#[cfg_attr(not(feature = "native"), allow(unused_variables))]
+				Ok((rows, formats)) =>
+					self.dropped = Some(Dropped {
+						names,
+						#[cfg(feature = "native")]
+						paths: files
+							.iter()
+							.map(|file| Some(file.path.as_ref()?.display().to_string()))
+							.collect(),
+						#[cfg(feature = "native")]
+						formats,
+						rows
+					}),
+				Err(e) => self.status = Some(format!("Couldn't load the dropped files: {e}"))
+			}
+		}
+
+		if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+			let screen = ctx.screen_rect();
+			let painter = ctx.layer_painter(egui::LayerId::new(
+				egui::Order::Foreground,
+				egui::Id::new("drop_hint")
+			));
+			painter.rect_filled(screen, 0., Color32::from_black_alpha(160));
+			painter.text(
+				screen.center(),
+				Align2::CENTER_CENTER,
+				"Drop to add to the data or replace it",
+				egui::TextStyle::Heading.resolve(&ctx.style()),
+				Color32::WHITE
+			);
+		}
+
+		let Some(dropped) = &self.dropped else {
+			return;
+		};
+		let (mut open, mut replace) = (true, None);
+		egui::Window::new("Dropped files")
+			.open(&mut open)
+			.collapsible(false)
+			.resizable(false)
+			.show(ctx, |ui| {
+				for name in &dropped.names {
+					ui.label(name);
+				}
+				ui.weak(format!("{} rows", dropped.rows.len()));
+				ui.horizontal(|ui| {
+					if ui
+						.button("Add to the data")
+						.on_hover_text("Chart these rows along with the ones that are already here")
+						.clicked()
+					{
+						replace = Some(false);
+					}
+					if ui
+						.button("Replace the data")
+						.on_hover_text("Chart only these rows, instead of the ones that are here")
+						.clicked()
+					{
+						replace = Some(true);
+					}
+				});
+			});
+
+		match replace {
+			Some(replace) => self.open_dropped(replace),
+			None if !open => self.dropped = None,
+			None => ()
+		}
+	}
+
+	/// Adds the rows of the files that were dropped onto the window to the data, or replaces the
+	/// data with them if `replace`
+	fn open_dropped(&mut self, replace: bool) {
+		let Some(mut dropped) = self.dropped.take() else {
+			return;
+		};
+		// The data's files are all read as the format that was forced on them when they're
+		// reloaded, dropped ones included, so ones in any other format can't be added to them
+		#[cfg(feature = "native")]
+		if !replace
+			&& dropped.paths.is_some()
+			&& let Some(format) = self.source.as_ref().and_then(|source| source.format)
+			&& let Some(other) = dropped.formats.iter().find(|f| f.name() != format.name())
+		{
+			self.status = Some(format!(
+				"The dropped files can't be added to the data, since they're {} and the data's \
+				 files are always read as {} (they can replace it instead)",
+				other.name(),
+				format.name()
+			));
+			self.dropped = Some(dropped);
+			return;
+		}
+		let rows = std::mem::take(&mut dropped.rows);
+		if !replace {
+			self.status = Some(format!(
+				"Adding the rows from {}…",
+				dropped.names.join(", ")
+			));
+			self.derive_keys(rows.into(), Derived::Merge(dropped));
+			return;
+		}
+
+		let count = rows.len();
+		#[cfg(feature = "native")]
+		self.remember_source();
+		match self.replace_data(rows) {
+			Ok(()) => {
+				self.forget_derived_keys();
+				self.rebuild_bars();
+				self.finish_dropped(dropped, count, true);
+			}
+			Err(e) => self.status = Some(format!("Couldn't open the dropped files: {e}"))
+		}
+	}
+
+	/// Keeps track of where the data's from now that `dropped` has been opened, for
+	/// [`Self::open_dropped`]
+	fn finish_dropped(&mut self, dropped: Dropped, count: usize, replace: bool) {
+		// The data can only be reloaded if all of it came from files that are still around
+		#[cfg(feature = "native")]
+		{
+			self.source = match replace {
+				true => dropped
+					.paths
+					.and_then(|paths| Source::new(paths, None, self.load_options.clone())),
+				false => self
+					.source
+					.take()
+					.zip(dropped.paths)
+					.map(|(mut source, paths)| {
+						source.paths.extend(paths);
+						source
+					})
+			};
+			if let Some(file) = self.session(None) {
+				Recent::remember(file);
+				self.recent = None;
+			}
+			self.set_watching(self.watch.is_some());
+		}
+
+		self.fit_plot = true;
+		self.status = Some(format!(
+			"{} {count} rows from {}",
+			if replace { "Opened" } else { "Added" },
+			dropped.names.join(", ")
+		));
+	}
+
+	/// The keys that the current settings depend on
+	fn keys_in_use<'s>(settings: &'s Settings) -> FxHashSet<&'s str> {
+		let y_key = match settings.y_axis {
+			YAxisKey::Count => None,
+			YAxisKey::Key(key) | YAxisKey::Rate(key) => Some(key)
+		};
+
+		settings
+			.x_axis
+			.iter()
+			.chain(settings.bounds.keys())
+			.map(String::as_str)
+			.chain(y_key)
+			.collect()
+	}
+
+	/// Shrinks the data down to only what's needed for the current settings, since it's otherwise
+	/// only ever going to grow
+	fn compact(&mut self) {
+		let before = self.memory;
+		let keep = Self::keys_in_use(&self.settings);
+		let data = memory::compact(&self.data, |key| keep.contains(key));
+
+		self.status = Some(match self.replace_data(data) {
+			Ok(()) => format!(
+				"Compacted the data from about {}B to {}B",
+				NumberFormat::Si.format(before as f64),
+				NumberFormat::Si.format(self.memory as f64)
+			),
+			Err(e) => format!("Couldn't compact the data: {e}")
+		});
+	}
+
+	/// The rows that get through the filters, in the order they're in in the data
+	pub fn filtered_rows(&self) -> Vec<&merde::Map<'static>> {
+		bars::filter_rows(
+			&self.data,
+			&self.settings,
+			&mut Columns::default(),
+			&mut Indexes::default()
+		)
+		.into_iter()
+		.map(|idx| &self.data[idx])
+		.collect()
+	}
+
+	pub fn settings(&self) -> &Settings<'static> {
+		&self.settings
+	}
+
+	/// The type of `key`'s values, if it's in the data at all
+	fn key_type(&self, key: &str) -> Result<ValueType, DataErr> {
+		self.schema
+			.keys
+			.iter()
+			.find(|(k, _)| k == key)
+			.map(|(_, ty)| *ty)
+			.ok_or_else(|| DataErr::UnknownKey(key.to_string()))
+	}
+
+	/// Groups the bars by the given keys, in order, as if they'd all been clicked in the UI
+	pub fn select_keys(&mut self, keys: Vec<String>) -> Result<(), DataErr> {
+		for key in &keys {
+			self.key_type(key)?;
+		}
+
+		if !keys.is_empty() {
+			self.stop_drilling();
+			self.settings.x_axis.extend(keys);
+			self.rebuild_bars();
+		}
+		Ok(())
+	}
+
+	/// Groups the bars by only the given keys, instead of whichever they were grouped by before
+	pub fn set_x_axis(&mut self, keys: Vec<String>) -> Result<(), DataErr> {
+		for key in &keys {
+			self.key_type(key)?;
+		}
+
+		self.settings.drill = None;
+		self.settings.x_axis = keys;
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Starts drilling down through the x-axis keys from the top one (see [`Drill`]), or goes
+	/// back to grouping by all of them at once
+	pub fn set_drilling(&mut self, drilling: bool) {
+		match (drilling, &self.settings.drill) {
+			(true, None) if self.settings.x_axis.len() > 1 => {
+				let drill = Drill::new(std::mem::take(&mut self.settings.x_axis));
+				self.settings.x_axis = vec![drill.key().to_string()];
+				self.settings.drill = Some(drill);
+			}
+			(false, Some(_)) => self.stop_drilling(),
+			_ => return
+		}
+		self.selected = None;
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Goes back to grouping by all of the keys that were being drilled through, without
+	/// rebuilding the bars (since whatever stopped it will)
+	fn stop_drilling(&mut self) {
+		if let Some(drill) = self.settings.drill.take() {
+			self.settings.x_axis = drill.keys;
+		}
+	}
+
+	/// Moves to wherever `step` moves the drill to, if drilling
+	fn move_drill(&mut self, step: impl FnOnce(&mut Drill)) {
+		let Some(drill) = &mut self.settings.drill else {
+			return;
+		};
+		step(drill);
+		self.settings.x_axis = vec![drill.key().to_string()];
+		self.selected = None;
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Drills down into the bar labelled `label`, if there's a key below the current one
+	pub fn drill_down(&mut self, label: String) {
+		self.move_drill(|drill| drill.descend(label));
+	}
+
+	/// Climbs back up to the `depth`th of the keys being drilled through, where 0 is the top
+	pub fn drill_up(&mut self, depth: usize) {
+		self.move_drill(|drill| drill.climb(depth));
+	}
+
+	/// Bounds `key` to `bound`, or stops bounding it at all if that's `None`
+	pub fn set_bound(&mut self, key: String, bound: Option<ValueBound>) -> Result<(), DataErr> {
+		let ty = self.key_type(&key)?;
+		match bound {
+			Some(bound) => {
+				if !bound.fits(ty) {
+					return Err(DataErr::MismatchedBound { key, ty });
+				}
+				self.settings.bounds.insert(key, bound);
+			}
+			None => _ = self.settings.bounds.remove(&key)
+		}
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Sums up how long each bar was listened to (see [`song`]), or goes back to counting rows
+	pub fn set_listening_time(&mut self, listening: bool) {
+		if listening {
+			song::show_listening_time(&mut self.settings);
+		} else {
+			self.settings.y_axis = YAxisKey::Count;
+		}
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Makes each bar as tall as how often it was skipped (see [`song`]), or goes back to counting
+	/// rows
+	pub fn set_skip_rate(&mut self, skip_rate: bool) {
+		if skip_rate {
+			song::show_skip_rate(&mut self.settings);
+		} else {
+			self.settings.y_axis = YAxisKey::Count;
+		}
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Makes each bar as tall as the sum of the numeric `key` over its rows, or goes back to
+	/// counting rows
+	pub fn set_sum(&mut self, key: Option<&str>) -> Result<(), DataErr> {
+		self.settings.y_axis = match key {
+			Some(key)
+				if !matches!(
+					self.key_type(key)?,
+					ValueType::I64 | ValueType::U64 | ValueType::Float
+				) =>
+				return Err(DataErr::NotNumeric(key.to_string())),
+			Some(key) => YAxisKey::Key(settings::static_key(key)),
+			None => YAxisKey::Count
+		};
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Changes how the values of a numeric y-axis key are combined into each bar's height
+	pub fn set_aggregate(&mut self, aggregation: Aggregation) {
+		self.settings.aggregation = aggregation;
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Splits each bar into segments for the values of `key` that its rows have, stacked on top of
+	/// each other, or stops stacking them if that's `None`
+	pub fn set_stack_by(&mut self, key: Option<&str>) -> Result<(), DataErr> {
+		if let Some(key) = key {
+			self.key_type(key)?;
+		}
+		self.settings.stack_by = key.map(str::to_string);
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Makes each bar as tall as the share of its rows where the boolean `key` is true, or goes
+	/// back to counting rows
+	pub fn set_rate(&mut self, key: Option<&str>) -> Result<(), DataErr> {
+		self.settings.y_axis = match key {
+			Some(key) if self.key_type(key)? != ValueType::Bool =>
+				return Err(DataErr::NotBoolean(key.to_string())),
+			Some(key) => YAxisKey::Rate(settings::static_key(key)),
+			None => YAxisKey::Count
+		};
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Includes or leaves out podcast episodes (see [`song::Podcasts`])
+	pub fn set_podcasts(&mut self, podcasts: song::Podcasts) {
+		podcasts.apply(&mut self.settings);
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Shows one of the charts in [`song::Preset`], if the data has what it needs
+	pub fn apply_preset(&mut self, preset: song::Preset) -> Result<(), DataErr> {
+		if !preset.applies_to(&self.schema) {
+			return Err(DataErr::UnsupportedPreset(preset.name()));
+		}
+
+		self.stop_drilling();
+		preset.apply(&mut self.settings);
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	pub fn clear_filters(&mut self) {
+		self.settings.bounds.clear();
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Applies a filter written in the syntax described in [`spart_core::filter`]
+	pub fn add_filter(&mut self, filter: &str) -> Result<(), FilterParseErr> {
+		let (key, bound) = parse_filter(filter, &self.schema.keys)?;
+		let existing = self.settings.bounds.remove(&key);
+		self.settings
+			.bounds
+			.insert(key, merge_bounds(existing, bound));
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Flags the bars that have rows with outlying values of `key` (as found by `test`), or stops
+	/// looking for them if that's `None`
+	pub fn set_outlier_test(
+		&mut self,
+		key: &str,
+		test: Option<OutlierTest>
+	) -> Result<(), DataErr> {
+		if !matches!(
+			self.key_type(key)?,
+			ValueType::I64 | ValueType::U64 | ValueType::Float
+		) {
+			return Err(DataErr::NotNumeric(key.to_string()));
+		}
+
+		self.outlier_counts.remove(key);
+		match test {
+			Some(test) => self.settings.outliers.insert(key.to_string(), test),
+			None => self.settings.outliers.remove(key)
+		};
+		self.rebuild_bars();
+		Ok(())
+	}
+
+	/// Bounds `key` to just the values that aren't outliers, going by the test it's being
+	/// checked with (or by IQR if it isn't)
+	pub fn exclude_outliers(&mut self, key: &str) -> Result<(), DataErr> {
+		let ty = self.key_type(key)?;
+		let test = self.settings.outliers.get(key).copied().unwrap_or_default();
+		let bound = test
+			.fences(&self.data, key)
+			.and_then(|fences| fences.bound(ty))
+			.ok_or_else(|| DataErr::NotNumeric(key.to_string()))?;
+
+		let existing = self.settings.bounds.remove(key);
+		self.settings
+			.bounds
+			.insert(key.to_string(), merge_bounds(existing, bound));
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Formats the values of `key` as being measured in `unit`
+	pub fn set_unit(&mut self, key: &str, unit: Unit) -> Result<(), DataErr> {
+		if !matches!(
+			self.key_type(key)?,
+			ValueType::I64 | ValueType::U64 | ValueType::Float
+		) {
+			return Err(DataErr::NotNumeric(key.to_string()));
+		}
+		self.settings
+			.formats
+			.insert(key.to_string(), unit.number_format());
+		self.rebuild_bars();
+		Ok(())
+	}
+
+	/// Counts rows towards a bar for each of the things that their value of `key` lists, when
+	/// they're separated by `delimiter` (see [`Settings::delimiters`]), or for the whole value
+	/// again if that's `None`
+	pub fn set_delimiter(&mut self, key: &str, delimiter: Option<String>) -> Result<(), DataErr> {
+		if self.key_type(key)? != ValueType::String {
+			return Err(DataErr::Unsplittable(key.to_string()));
+		}
+		match delimiter.filter(|delimiter| !delimiter.is_empty()) {
+			Some(delimiter) => _ = self.settings.delimiters.insert(key.to_string(), delimiter),
+			None => _ = self.settings.delimiters.remove(key)
+		}
+		if self.settings.x_axis.iter().any(|k| k == key) {
+			self.rebuild_bars();
+			self.fit_plot = true;
+		}
+		Ok(())
+	}
+
+	/// Buckets the values of `key` into `bins` (see [`Settings::bins`]), so that it's charted as a
+	/// histogram, or gives each of its values a bar of its own again if that's `None`
+	pub fn set_bins(&mut self, key: &str, bins: Option<Bins>) -> Result<(), DataErr> {
+		if !matches!(
+			self.key_type(key)?,
+			ValueType::I64 | ValueType::U64 | ValueType::Float
+		) {
+			return Err(DataErr::NotNumeric(key.to_string()));
+		}
+		match bins {
+			Some(bins) => {
+				self.settings.bins.insert(key.to_string(), bins);
+				// Bins are only a histogram when they're in order
+				self.settings.order = BarOrder::Labels;
+			}
+			None => _ = self.settings.bins.remove(key)
+		}
+		if self.settings.x_axis.iter().any(|k| k == key) {
+			self.rebuild_bars();
+			self.fit_plot = true;
+		}
+		Ok(())
+	}
+
+	/// Buckets the values of `key` into `count` bins that are all as wide, from its smallest
+	/// value to its biggest
+	pub fn set_bin_count(&mut self, key: &str, count: usize) -> Result<(), DataErr> {
+		let (min, max) = self
+			.numeric_range(key)
+			.ok_or_else(|| DataErr::NotNumeric(key.to_string()))?;
+		let bins =
+			Bins::covering(min, max, count).ok_or_else(|| DataErr::Unbinnable(key.to_string()))?;
+		self.set_bins(key, Some(bins))
+	}
+
+	/// The smallest and biggest values of `key`, if it's numeric
+	fn numeric_range(&mut self, key: &str) -> Option<(f64, f64)> {
+		let stats = self.stats.entry(key.to_string()).or_insert_with(|| {
+			KeyStats::compute(&self.data, key, self.settings.byte_encoding(key))
+		});
+		match stats.summary {
+			Summary::Numeric { min, max, .. } => Some((min, max)),
+			_ => None
+		}
+	}
+
+	/// Treats `key`'s values as `ty` (see [`KeyType`]), or as what they were loaded as again if
+	/// that's `None`
+	pub fn set_key_type(&mut self, key: &str, ty: Option<KeyType>) -> Result<(), DataErr> {
+		let mut key_types = self.settings.key_types.clone();
+		match ty {
+			Some(ty) => _ = key_types.insert(key.to_string(), ty),
+			None => _ = key_types.remove(key)
+		}
+		self.retype(key_types)
+	}
+
+	/// What `key`'s values were when they were loaded, before any [`KeyType`] was applied
+	fn loaded_type(&self, key: &str) -> Result<ValueType, DataErr> {
+		match self.uncoerced.get(key) {
+			Some(originals) => Ok(originals
+				.iter()
+				.map(Value::value_type)
+				.find(|ty| *ty != ValueType::Null)
+				.unwrap_or(ValueType::Null)),
+			None => self.key_type(key)
+		}
+	}
+
+	/// Converts the data so that exactly the keys in `key_types` are treated as the types they're
+	/// given there, and the rest as what they were loaded as
+	fn retype(&mut self, key_types: FxHashMap<String, KeyType>) -> Result<(), DataErr> {
+		let old = &self.settings.key_types;
+		let changed = old
+			.keys()
+			.chain(key_types.keys())
+			.filter(|key| old.get(*key) != key_types.get(*key))
+			.cloned()
+			.collect::<FxHashSet<_>>();
+		if changed.is_empty() {
+			return Ok(());
+		}
+		for key in &changed {
+			let ty = self.loaded_type(key)?;
+			if key_types
+				.get(key)
+				.is_some_and(|key_type| !key_type.applies_to(ty))
+			{
+				return Err(DataErr::Untreatable {
+					key: key.clone(),
+					ty
+				});
+			}
+		}
+
+		let mut data = (*self.data).clone();
+		for key in &changed {
+			if let Some(originals) = self.uncoerced.remove(key) {
+				schema::restore(&mut data, key, originals);
+			}
+			if let Some(ty) = key_types.get(key) {
+				self.uncoerced
+					.insert(key.clone(), schema::convert(&mut data, key, *ty));
+			}
+
+			// Anything that depended on the key's old type doesn't make sense with its new one
+			self.settings.bounds.remove(key);
+			if let Some(compare) = &mut self.settings.compare {
+				compare.remove(key);
+			}
+			self.settings.outliers.remove(key);
+		}
+		let sampled_by = self
+			.settings
+			.sample
+			.as_ref()
+			.and_then(|sample| sample.method.key());
+		if sampled_by.is_some_and(|key| changed.contains(key)) {
+			self.settings.sample = None;
+		}
+
+		self.settings.key_types = key_types;
+		self.replace_data(data)?;
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Compares the bars against a second set of them with their own bounds (B), which start out
+	/// the same as the current ones (A). Stopping keeps only A's.
+	pub fn set_comparing(&mut self, comparing: bool) {
+		self.edit_b(false);
+		self.settings.compare = comparing.then(|| self.settings.bounds.clone());
+		if comparing {
+			self.settings.periods = None;
+		}
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Compares each bar's rows in the latest period of a timestamp key against its rows in the
+	/// period before it, instead of comparing bounds, or stops if that's `None`
+	pub fn set_periods(&mut self, periods: Option<(&str, Period)>) -> Result<(), DataErr> {
+		self.settings.periods = match periods {
+			Some((key, period)) => {
+				self.key_type(key)?;
+				Some(
+					PeriodComparison::latest(&self.data, key, period)
+						.ok_or_else(|| DataErr::NoTimestamps(key.to_string()))?
+				)
+			}
+			None => None
+		};
+		if self.settings.periods.is_some() {
+			self.edit_b(false);
+			self.settings.compare = None;
+		}
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// The bar that's selected, for narrowing other charts of the same data down to its rows with
+	/// [`Self::set_brushes`]
+	pub fn brush(&self) -> Option<Brush> {
+		if self.querying() {
+			return None;
+		}
+		let bar = self.bars.get(self.selected?)?;
+		Some(match &self.settings.drill {
+			Some(drill) => drill.brush_for(&bar.labels, &self.settings),
+			None => Brush::new(bar, &self.settings)
+		})
+	}
+
+	/// Only charts the rows that are in all the bars picked in other charts (see [`Self::brush`])
+	pub fn set_brushes(&mut self, brushes: Vec<Brush>) {
+		if self.settings.brushes != brushes {
+			self.settings.brushes = brushes;
+			self.rebuild_bars();
+			self.fit_plot = true;
+		}
+	}
+
+	/// Charts how much each bar changed between two datasets that have been loaded together, with
+	/// their rows marked by [`diff::mark`]
+	pub fn diff_datasets(&mut self) {
+		self.edit_b(false);
+		diff::apply(&mut self.settings);
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// Switches to editing B's bounds (or A's again), if the bars are being compared
+	fn edit_b(&mut self, b: bool) {
+		if let Some(compare) = &mut self.settings.compare
+			&& b != self.editing_b
+		{
+			std::mem::swap(&mut self.settings.bounds, compare);
+			self.editing_b = b;
+			self.rebuild_bars();
+		}
+	}
+
+	/// Applies a filter to B's bounds, starting to compare the bars if they weren't already
+	pub fn add_compare_filter(&mut self, filter: &str) -> Result<(), FilterParseErr> {
+		if self.settings.compare.is_none() {
+			self.set_comparing(true);
+		}
+		let editing_b = self.editing_b;
+		self.edit_b(true);
+		let added = self.add_filter(filter);
+		self.edit_b(editing_b);
+		added
+	}
+
+	/// Only charts rows that the expression `source` is true for, or all of them again if it's
+	/// empty
+	pub fn set_predicate(&mut self, source: &str) -> Result<(), ScriptErr> {
+		self.settings.predicate = match source.trim() {
+			"" => None,
+			source => {
+				let predicate = self.scripts.compile(source)?;
+				predicate.check_filter(&self.data)?;
+				Some(predicate)
+			}
+		};
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Only charts a sample of the rows that get through the bounds, or all of them again if
+	/// that's `None`
+	pub fn set_sample(&mut self, sample: Option<Sample>) -> Result<(), DataErr> {
+		if let Some(key) = sample.as_ref().and_then(|sample| sample.method.key()) {
+			let ty = self.key_type(key)?;
+			if let Some(Sample {
+				method: Sampling::Weighted(_),
+				..
+			}) = &sample && !matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float)
+			{
+				return Err(DataErr::NotNumeric(key.to_string()));
+			}
+		}
+
+		self.settings.sample = sample;
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Names the bars with `template` (see [`Settings::label_template`]), or by just listing their
+	/// values again if that's `None`
+	pub fn set_label_template(&mut self, template: Option<String>) {
+		self.settings.label_template = template;
+		self.rebuild_bars();
+	}
+
+	/// Merges the groups that make up less than `fraction` of the total into one bar (see
+	/// [`Settings::merge_below`]), or stops merging them if that's `None`
+	pub fn set_merge_below(&mut self, fraction: Option<f64>) {
+		self.settings.merge_below = fraction;
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+		self.settings.tie_break = tie_break;
+		self.rebuild_bars();
+		self.fit_plot = true;
+	}
+
+	/// A token that [`Self::open_view`] (here or in someone else's spart) turns back into the
+	/// current view of the data
+	pub fn view_token(&self) -> String {
+		let mut settings = self.settings.clone();
+		if let Some(compare) = &mut settings.compare
+			&& self.editing_b
+		{
+			std::mem::swap(&mut settings.bounds, compare);
+		}
+		share::encode(&settings)
+	}
+
+	/// Shows the data the way it was when `token` was made by [`Self::view_token`], replacing all
+	/// the current settings
+	pub fn open_view(&mut self, token: &str) -> Result<(), ShareErr> {
+		let (mut settings, predicate) = share::decode(token)?;
+		let periods = settings.periods.as_ref().map(|periods| &periods.key);
+		let drilled = settings.drill.iter().flat_map(|drill| &drill.keys);
+		for key in settings
+			.x_axis
+			.iter()
+			.chain(settings.bounds.keys())
+			.chain(periods)
+			.chain(drilled)
+		{
+			self.key_type(key)?;
+		}
+		match settings.y_axis {
+			YAxisKey::Count => (),
+			YAxisKey::Key(key) =>
+				if !matches!(
+					self.key_type(key)?,
+					ValueType::I64 | ValueType::U64 | ValueType::Float
+				) {
+					return Err(DataErr::NotNumeric(key.to_string()).into());
+				},
+			YAxisKey::Rate(key) =>
+				if self.key_type(key)? != ValueType::Bool {
+					return Err(DataErr::NotBoolean(key.to_string()).into());
+				},
+		}
+		// The predicate's checked against the data as the view would see it
+		self.retype(settings.key_types.clone())?;
+		if let Some(source) = &predicate {
+			let compiled = self.scripts.compile(source)?;
+			compiled.check_filter(&self.data)?;
+			settings.predicate = Some(compiled);
+		}
+		// And so are the bounds, since the view could've been made when a key held something else
+		let dropped = settings.retain_bounds(|key| self.key_type(key).ok());
+		if !dropped.is_empty() {
+			self.status = Some(format!(
+				"Left out the filters on {}, which don't fit what those keys hold now",
+				dropped.join(", ")
+			));
+		}
+
+		self.settings = settings;
+		self.script_input.predicate = predicate.unwrap_or_default();
+		self.editing_b = false;
+		self.selected = None;
+		self.rebuild_bars();
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// Starts adding a key whose values come from a script, given something like
+	/// `minutes=ms_played / 60000`. Running it on every row can take a while, so that's done on
+	/// another thread, and the key's added once [`Self::receive_column`] gets its values.
+	fn add_column(&mut self, definition: String) -> Result<(), ScriptErr> {
+		let (name, expr) = parse_column(&definition)?;
+		let expr = self.scripts.compile(expr)?;
+		let name = name.to_string();
+		self.work_out_column(definition, name, expr)
+	}
+
+	fn work_out_column(
+		&mut self,
+		definition: String,
+		name: String,
+		expr: Expression
+	) -> Result<(), ScriptErr> {
+		if self.schema.keys.iter().any(|(key, _)| *key == name) {
+			return Err(ScriptErr::DuplicateKey(name));
+		}
+
+		let (data, running) = (Arc::clone(&self.data), expr.clone());
+		let values = worker::background("spart-column", move || {
+			script::column_values(&data, &running)
+		});
+		self.adding_column = Some(AddingColumn {
+			definition,
+			name,
+			expr,
+			data: Arc::clone(&self.data),
+			values
+		});
+		Ok(())
+	}
+
+	/// Adds the key from [`Self::add_column`] once its values have all been worked out
+	fn receive_column(&mut self) {
+		let Some(adding) = &self.adding_column else {
+			return;
+		};
+		let values = match adding.values.try_recv() {
+			Ok(values) => values,
+			Err(TryRecvError::Empty) => return,
+			Err(TryRecvError::Disconnected) => Err(ScriptErr::Eval {
+				row: 0,
+				message: "the thread it was running on stopped".to_string()
+			})
+		};
+		let Some(adding) = self.adding_column.take() else {
+			return;
+		};
+
+		// If the data's changed since, the values don't go with it anymore
+		if !Arc::ptr_eq(&adding.data, &self.data) {
+			if let Err(e) =
+				self.work_out_column(adding.definition.clone(), adding.name, adding.expr)
+			{
+				self.script_input.column_err = Some(e.to_string());
+				self.script_input.column = adding.definition;
+			}
+			return;
+		}
+
+		let result = values.map_err(|e| e.to_string()).and_then(|values| {
+			let mut data = (*self.data).clone();
+			script::insert_column(&mut data, &adding.name, values);
+			self.replace_data(data).map_err(|e| e.to_string())
+		});
+		match result {
+			Ok(()) => {
+				self.status = Some(format!("Added the key '{}'", adding.name));
+				self.columns.push((adding.name, adding.expr));
+			}
+			Err(e) => {
+				self.script_input.column_err = Some(e);
+				self.script_input.column = adding.definition;
+			}
+		}
+	}
+
+	/// Adds the subnet, version, and privacy of the IP addresses in `key` as keys of their own
+	pub fn add_ip_fields(&mut self, key: &str) -> Result<(), DataErr> {
+		let mut data = (*self.data).clone();
+		ip::add_fields(&mut data, key)?;
+		self.replace_data(data)?;
+
+		self.status = Some(format!("Added {}", ip::field_names(key).join(", ")));
+		self.addresses.push(key.to_string());
+		Ok(())
+	}
+
+	/// Adds the host, path, and most common query parameters of the URLs in `key` as keys of
+	/// their own
+	pub fn add_url_fields(&mut self, key: &str) -> Result<(), DataErr> {
+		let mut data = (*self.data).clone();
+		let added = urls::add_fields(&mut data, key)?;
+		self.replace_data(data)?;
+
+		self.status = Some(format!("Added {}", added.join(", ")));
+		self.urls.push(key.to_string());
+		Ok(())
+	}
+
+	/// Adds the browser, operating system, and kind of device of the user agents in `key` as keys
+	/// of their own
+	pub fn add_agent_fields(&mut self, key: &str) -> Result<(), DataErr> {
+		let mut data = (*self.data).clone();
+		agents::add_fields(&mut data, key)?;
+		self.replace_data(data)?;
+
+		self.status = Some(format!("Added {}", agents::field_names(key).join(", ")));
+		self.agents.push(key.to_string());
+		Ok(())
+	}
+
+	fn add_key(&mut self, key: String) {
+		self.stop_drilling();
+		self.settings.x_axis.push(key);
+		self.rebuild_bars();
+	}
+
+	fn remove_key(&mut self, key: &str) {
+		self.stop_drilling();
+		if let Some(idx) = self.settings.x_axis.iter().position(|k| k == key) {
+			self.settings.x_axis.remove(idx);
+		}
+		self.rebuild_bars();
+	}
+
+	/// Asks for the bars to be rebuilt to match the current settings. They'll show up in `bars`
+	/// once the worker's done with them (see [`Self::receive_bars`]).
+	fn rebuild_bars(&mut self) {
+		#[cfg(feature = "sql")]
+		if let Some((sql, _)) = &self.sql.active {
+			// The query's results don't depend on the settings, only on the data
+			if self.sql.table.is_none() {
+				let sql = sql.clone();
+				self.sql.err = self.set_query(&sql).err().map(|e| e.to_string());
+			}
+			return;
+		}
+
+		self.worker.request(&self.data, &self.settings);
+		self.rebuild_due = None;
+	}
+
+	/// Blocks until any rebuild that's in progress is done, for when the bars are needed right
+	/// away
+	pub fn wait_for_bars(&mut self) {
+		if let Some(bars) = self.worker.wait()
+			&& !self.querying()
+		{
+			self.receive_bars(bars);
+		}
+	}
+
+	/// If the bars are the results of a SQL query, so the worker's don't matter
+	fn querying(&self) -> bool {
+		#[cfg(feature = "sql")]
+		{
+			self.sql.active.is_some()
+		}
+		#[cfg(not(feature = "sql"))]
+		{
+			false
+		}
+	}
+
+	/// Shows the results of `sql` instead of the bars that the settings make, or goes back to
+	/// those if it's empty
+	#[cfg(feature = "sql")]
+	pub fn set_query(&mut self, sql: &str) -> Result<(), SqlErr> {
+		if sql.trim().is_empty() {
+			self.sql.active = None;
+			// It's about as big as the data itself, so don't keep it around if it's not needed
+			self.sql.table = None;
+			self.rebuild_bars();
+			return Ok(());
+		}
+
+		let table = match &mut self.sql.table {
+			Some(table) => table,
+			table => table.insert(SqlTable::new(&self.data, &self.schema)?)
+		};
+		let result = table.query(sql)?;
+
+		self.sql.active = Some((sql.to_string(), result.labels));
+		self.receive_bars(result.bars);
+		self.fit_plot = true;
+		Ok(())
+	}
+
+	/// The columns that the bars are labelled with, if they're the results of a query
+	#[cfg(feature = "sql")]
+	pub fn query_labels(&self) -> Option<&[String]> {
+		self.sql
+			.active
+			.as_ref()
+			.map(|(_, labels)| labels.as_slice())
+	}
+
+	fn receive_bars(&mut self, bars: Vec<LabeledBar>) {
+		// Bars of only the rows that have been grouped so far keep coming in until they're done
+		let partial = self.worker.is_busy();
+		let was_empty = self.bars.is_empty();
+		// The same bar is probably somewhere else now, if it's still there at all
+		let selected = self.selected.and_then(|idx| self.bars.get(idx));
+		self.selected =
+			selected.and_then(|old| bars.iter().position(|bar| bar.labels == old.labels));
+		self.bars = bars;
+		self.visible = None;
+		// What the bars were before a refresh only means anything next to the bars it made
+		if !partial && !std::mem::take(&mut self.refreshing) {
+			self.refreshed = None;
+		}
+		if !partial {
+			self.density = None;
+		}
+
+		let settings = &mut self.settings;
+		if let (true, Some(page_size)) = (was_empty, settings.default_page_size) {
+			settings.max_shown = page_size;
+		}
+		// A cap that's bigger than the bars there are now would otherwise be left over for
+		// whenever there are more of them, even though nobody asked for it then
+		if !settings.shows_all() && !self.bars.is_empty() {
+			settings.max_shown = settings.max_shown.clamp(1, self.bars.len());
+		}
+
+		settings.offset = settings.offset.min(settings.max_offset(self.bars.len()));
+
+		if !partial && !self.bars.is_empty() && !self.querying() {
+			let token = self.view_token();
+			self.history.record(&self.settings, &self.bars, token);
+		}
+	}
+
+	/// Colors the bars that `rule` matches, unless an earlier rule matches them first
+	pub fn add_color_rule(&mut self, rule: ColorRule) {
+		self.settings.color_rules.push(rule);
+		self.rebuild_bars();
+	}
+
+	/// Draws a horizontal line across the chart
+	pub fn add_reference_line(&mut self, line: ReferenceLine) {
+		self.settings.reference_lines.push(line);
+	}
+
+	/// Puts a note on the chart
+	pub fn annotate(&mut self, annotation: Annotation) {
+		self.settings.annotations.push(annotation);
+	}
+
+	/// Pins the bar with these labels if it isn't already, or unpins it if it is
+	fn toggle_pin(&mut self, labels: Vec<String>) {
+		match self.pinned.iter().position(|pinned| *pinned == labels) {
+			Some(idx) => _ = self.pinned.remove(idx),
+			None => self.pinned.push(labels)
+		}
+		self.visible = None;
+	}
+
+	/// Moves the selection `delta` bars along (or onto the first or last shown bar, if nothing's
+	/// selected yet), scrolling so that it's still shown
+	fn move_selection(&mut self, delta: isize) {
+		let Some(last) = self.bars.len().checked_sub(1) else {
+			return;
+		};
+		let shown = self.settings.shown_range(self.bars.len());
+		let idx = match self.selected {
+			Some(idx) => idx.saturating_add_signed(delta).min(last),
+			None if delta < 0 => shown.end.saturating_sub(1),
+			None => shown.start
+		};
+
+		if idx < shown.start {
+			self.settings.offset = idx;
+		} else if idx >= shown.end {
+			self.settings.offset = idx + 1 - self.settings.page_size().min(idx + 1);
+		}
+		self.selected = Some(idx);
+	}
+
+	fn toggle_presenting(&mut self, ctx: &egui::Context) {
+		match self.presenting.take() {
+			Some(presentation) =>
+				ctx.send_viewport_cmd(ViewportCommand::Fullscreen(presentation.was_fullscreen)),
+			None => {
+				let was_fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+				ctx.send_viewport_cmd(ViewportCommand::Fullscreen(true));
+				self.presenting = Some(Presentation { was_fullscreen });
+			}
+		}
+		self.fit_plot = true;
+	}
+
+	/// How long each step of the last rebuild took, and how many rows made it through each of
+	/// them, in the corner of the window
+	fn show_profile(&self, ui: &mut egui::Ui) {
+		let Some(profile) = self.worker.profile() else {
+			return;
+		};
+
+		egui::Area::new(ui.id().with("profile"))
+			.anchor(Align2::RIGHT_TOP, vec2(-8., 8.))
+			.show(ui.ctx(), |ui| {
+				egui::Frame::popup(ui.style()).show(ui, |ui| {
+					egui::Grid::new(ui.id().with("stages")).show(ui, |ui| {
+						for stage in &profile.stages {
+							match stage.compared {
+								true => ui.label(format!("{} (B)", stage.name)),
+								false => ui.label(stage.name)
+							};
+							match stage.took {
+								Some(took) => ui.label(format_duration(took)),
+								None => ui.weak("reused")
+							};
+							let ((input, inputs), (output, outputs)) = (stage.input, stage.output);
+							ui.label(format!("{input} {inputs} → {output} {outputs}"));
+							ui.end_row();
+						}
+						ui.strong("Total");
+						ui.strong(format_duration(profile.total()));
+						ui.end_row();
+					});
+				});
+			});
+	}
+
+	/// Everything that can be done from the command palette right now, along with what each is
+	/// called there
+	fn commands(&mut self) -> Vec<(String, Command)> {
+		let mut commands = Vec::new();
+		for (key, _) in &self.schema.keys {
+			match self.settings.x_axis.contains(key) {
+				true => commands.push((
+					format!("Remove key: {key}"),
+					Command::RemoveKey(key.clone())
+				)),
+				false => commands.push((format!("Add key: {key}"), Command::AddKey(key.clone())))
+			}
+			commands.push((
+				format!("Group by just: {key}"),
+				Command::GroupBy(key.clone())
+			));
+		}
+		match &self.settings.drill {
+			Some(_) => commands.push(("Stop drilling down".into(), Command::SetDrilling(false))),
+			None if self.settings.x_axis.len() > 1 => commands.push((
+				"Drill down through the keys".into(),
+				Command::SetDrilling(true)
+			)),
+			None => ()
+		}
+
+		if !matches!(self.settings.y_axis, YAxisKey::Count) {
+			commands.push(("Y axis: count rows".into(), Command::CountRows));
+		}
+		if song::has_listening_time(&self.schema) {
+			commands.push((
+				"Y axis: total listening time".into(),
+				Command::ListeningTime
+			));
+		}
+		if song::has_skips(&self.schema) {
+			commands.push(("Y axis: skip rate".into(), Command::SkipRate));
+		}
+		for (key, ty) in &self.schema.keys {
+			match ty {
+				ValueType::I64 | ValueType::U64 | ValueType::Float =>
+					commands.push((format!("Y axis: sum of {key}"), Command::Sum(key.clone()))),
+				ValueType::Bool => commands.push((
+					format!("Y axis: % true of {key}"),
+					Command::Rate(key.clone())
+				)),
+				_ => ()
+			}
+		}
+		if matches!(self.settings.y_axis, YAxisKey::Key(_)) {
+			for aggregation in Aggregation::ALL {
+				if aggregation != self.settings.aggregation {
+					commands.push((
+						format!(
+							"Y axis: aggregate by {}",
+							aggregation.ui_descriptor().to_lowercase()
+						),
+						Command::Aggregate(aggregation)
+					));
+				}
+			}
+		}
+
+		for kind in ChartKind::ALL {
+			if kind != self.settings.chart && (kind != ChartKind::Map || self.mappable()) {
+				commands.push((
+					format!("Chart: {}", kind.ui_descriptor()),
+					Command::Chart(kind)
+				));
+			}
+		}
+
+		for (name, action) in [
+			("Export image", PlotAction::ExportImage),
+			("Export PNG…", PlotAction::ExportPng),
+			("Copy data", PlotAction::CopyData),
+			("Clear filters", PlotAction::ClearFilters),
+			("Reset zoom", PlotAction::ResetZoom)
+		] {
+			commands.push((name.into(), Command::Plot(action)));
+		}
+		#[cfg(feature = "native")]
+		if song::has_plays(&self.schema) {
+			commands.push((
+				"Export listens for ListenBrainz".into(),
+				Command::Plot(PlotAction::ExportListens)
+			));
+		}
+		let presenting = match self.presenting {
+			Some(_) => "Stop presenting",
+			None => "Present"
+		};
+		commands.push((presenting.into(), Command::TogglePresenting));
+		let profiling = match self.profiling {
+			true => "Hide how long rebuilding took",
+			false => "Show how long rebuilding took"
+		};
+		commands.push((profiling.into(), Command::ToggleProfiling));
+
+		#[cfg(feature = "native")]
+		{
+			if self.source.is_some() {
+				commands.push(("Reload the data".into(), Command::Reload));
+			}
+			for file in self.recent.get_or_insert_with(Recent::load).files() {
+				commands.push((
+					format!("Open recent: {}", file.name()),
+					Command::OpenRecent(file.clone())
+				));
+			}
+		}
+		commands
+	}
+
+	fn run_command(&mut self, command: Command, ctx: &egui::Context) {
+		let result = match command {
+			Command::AddKey(key) => {
+				self.add_key(key);
+				Ok(())
+			}
+			Command::RemoveKey(key) => {
+				self.remove_key(&key);
+				Ok(())
+			}
+			Command::GroupBy(key) => self.set_x_axis(vec![key]),
+			Command::SetDrilling(drilling) => {
+				self.set_drilling(drilling);
+				Ok(())
+			}
+			Command::CountRows => self.set_rate(None),
+			Command::ListeningTime => {
+				self.set_listening_time(true);
+				Ok(())
+			}
+			Command::SkipRate => {
+				self.set_skip_rate(true);
+				Ok(())
+			}
+			Command::Sum(key) => self.set_sum(Some(&key)),
+			Command::Aggregate(aggregation) => {
+				self.set_aggregate(aggregation);
+				Ok(())
+			}
+			Command::Rate(key) => self.set_rate(Some(&key)),
+			Command::Chart(kind) => {
+				self.settings.chart = kind;
+				Ok(())
+			}
+			// Exporting the image needs to know where the plot is, which it won't until it's
+			// drawn
+			Command::Plot(PlotAction::ExportImage) => {
+				self.plot_action = Some(PlotAction::ExportImage);
+				Ok(())
+			}
+			Command::Plot(action) => {
+				self.handle_plot_action(action, ctx, Rect::NOTHING);
+				Ok(())
+			}
+			Command::TogglePresenting => {
+				self.toggle_presenting(ctx);
+				Ok(())
+			}
+			Command::ToggleProfiling => {
+				self.profiling = !self.profiling;
+				Ok(())
+			}
+			#[cfg(feature = "native")]
+			Command::Reload => {
+				self.reload();
+				Ok(())
+			}
+			#[cfg(feature = "native")]
+			Command::OpenRecent(file) => {
+				self.open_recent(&file);
+				Ok(())
+			}
+		};
+		if let Err(e) = result {
+			self.status = Some(e.to_string());
+		}
+		self.fit_plot = true;
+	}
+
+	fn handle_plot_action(&mut self, action: PlotAction, ctx: &egui::Context, plot_rect: Rect) {
+		match action {
+			PlotAction::ExportImage => self.export = Some(ImageExport::Requested(plot_rect)),
+			PlotAction::ExportPng => self.png_export = Some(PngExport::default()),
+			PlotAction::CopyData => {
+				let format = self.settings.value_format();
+				let tsv = self
+					.bars
+					.iter()
+					.map(|bar| {
+						let compared = bar
+							.compared
+							.map(|compared| format!("\t{}", format.format(compared)));
+						format!(
+							"{}\t{}{}",
+							bar.short_name(),
+							format.format(bar.value),
+							compared.unwrap_or_default()
+						)
+					})
+					.collect::<Vec<_>>()
+					.join("\n");
+				ctx.copy_text(tsv);
+				self.status = Some(format!("Copied {} bars to the clipboard", self.bars.len()));
+			}
+			PlotAction::ClearFilters => self.clear_filters(),
+			PlotAction::ResetZoom => self.fit_plot = true,
+			#[cfg(feature = "native")]
+			PlotAction::ExportListens => {
+				let rows = self.filtered_rows();
+				let total = rows.len();
+				let result = listenbrainz::export(rows).and_then(|(json, skipped)| {
+					let path = timestamped_path("-listens.json");
+					std::fs::write(&path, json).map_err(|e| e.to_string())?;
+					Ok((path, skipped))
+				});
+				self.status = Some(match result {
+					Ok((path, 0)) => format!("Exported {total} listens to {path}"),
+					Ok((path, skipped)) => format!(
+						"Exported {} listens to {path} (leaving out {skipped} that didn't say \
+						 what was played or when)",
+						total - skipped
+					),
+					Err(e) => format!("Couldn't export the listens: {e}")
+				});
+			}
+		}
+	}
+
+	/// Moves along any image export that's in progress, saving the screenshot once it's arrived
+	fn progress_export(&mut self, ctx: &egui::Context) {
+		match self.export {
+			Some(ImageExport::Requested(rect)) => {
+				ctx.send_viewport_cmd(ViewportCommand::Screenshot);
+				self.export = Some(ImageExport::Waiting(rect));
+			}
+			Some(ImageExport::Waiting(rect)) => {
+				let screenshot = ctx.input(|i| {
+					i.raw.events.iter().find_map(|event| match event {
+						Event::Screenshot { image, .. } => Some(image.clone()),
+						_ => None
+					})
+				});
+
+				if let Some(screenshot) = screenshot {
+					let image = screenshot.region(&rect, Some(ctx.pixels_per_point()));
+					self.status = Some(match save_png(&image) {
+						Ok(path) => format!("Saved the chart to {path}"),
+						Err(e) => format!("Couldn't save the chart: {e}")
+					});
+					self.export = None;
+				}
+			}
+			None => ()
+		}
+
+		if self.export.is_some() {
+			ctx.request_repaint();
+		}
+	}
+}
+
+fn format_duration(duration: Duration) -> String {
+	format!("{:.1} ms", duration.as_secs_f64() * 1000.)
+}
+
+/// A path in the current directory that won't clash with anything else that's been saved
+#[cfg(feature = "native")]
+fn timestamped_path(suffix: &str) -> String {
+	let secs = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+	format!("spart-{secs}{suffix}")
+}
+
+#[cfg(feature = "native")]
+fn save_png(image: &ColorImage) -> Result<String, image::ImageError> {
+	let path = timestamped_path(".png");
+
+	image::save_buffer(
+		&path,
+		image.as_raw(),
+		image.width() as u32,
+		image.height() as u32,
+		image::ExtendedColorType::Rgba8
+	)?;
+	Ok(path)
+}
+
+/// Browsers won't let us write files wherever we like, so there's nowhere to put the image
+#[cfg(not(feature = "native"))]
+fn save_png(_image: &ColorImage) -> Result<String, &'static str> {
+	Err("saving images isn't supported in the browser")
+}
+
+impl eframe::App for App {
+	fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+		// The menu's only for when spart's a window of its own, since whatever it's embedded in
+		// has its own idea of what files are open
+		#[cfg(feature = "native")]
+		{
+			self.autosave(ctx);
+			if self.presenting.is_none() {
+				egui::TopBottomPanel::top("menu").show(ctx, |ui| self.show_menu(ui));
+			}
+		}
+		egui::CentralPanel::default()
+			.frame(egui::Frame::none())
+			.show(ctx, |ui| self.show(ui));
+		self.show_dropped(ctx);
+	}
+
+	#[cfg(feature = "native")]
+	fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+		self.remember_source();
+		// The session's kept even when spart's closed properly, in case it was closed by accident
+		if let Some(session) = self.session(Some(self.view_token())) {
+			let _ = autosave::save(&session);
+		}
+	}
+}
+
+impl App {
+	/// Shows everything - the settings, the plot, and the status bar - in whatever space `ui` has
+	/// left, so that this can be put inside some other app's window as well as being one itself
+	pub fn show(&mut self, ui: &mut egui::Ui) {
+		let ctx = &ui.ctx().clone();
+		self.progress_export(ctx);
+		#[cfg(feature = "native")]
+		{
+			self.check_for_changes(ctx);
+			self.check_feed(ctx);
+		}
+
+		if ctx.input_mut(|i| i.consume_shortcut(&palette::SHORTCUT)) {
+			self.palette = match self.palette {
+				Some(_) => None,
+				None => Some(Palette::default())
+			};
+		}
+		if self.palette.is_some() {
+			let commands = self.commands();
+			if let Some(palette) = &mut self.palette {
+				match palette.show(ctx, commands) {
+					Outcome::Open => (),
+					Outcome::Closed => self.palette = None,
+					Outcome::Chosen(command) => {
+						self.palette = None;
+						self.run_command(command, ctx);
+					}
+				}
+			}
+		}
+		if self.profiling {
+			self.show_profile(ui);
+		}
+		self.show_png_export(ctx);
+		#[cfg(feature = "native")]
+		self.show_session_dialog(ctx);
+
+		self.receive_column();
+		self.receive_derived();
+		if let Some(bars) = self.worker.poll()
+			&& !self.querying()
+		{
+			self.receive_bars(bars);
+		}
+
+		if let Some(due) = self.rebuild_due {
+			match due.checked_duration_since(Instant::now()) {
+				Some(left) => ctx.request_repaint_after(left),
+				None => {
+					self.rebuild_bars();
+					self.fit_plot = true;
+				}
+			}
+		}
+
+		let toggle_presenting = ctx.input(|i| {
+			i.key_pressed(Key::F11) || (self.presenting.is_some() && i.key_pressed(Key::Escape))
+		});
+		if toggle_presenting {
+			self.toggle_presenting(ctx);
+		}
+
+		// Arrow keys only walk through the bars while they're not being used to edit something
+		if ctx.memory(|memory| memory.focused().is_none()) {
+			let (left, right, escape) = ctx.input(|i| {
+				(
+					i.key_pressed(Key::ArrowLeft),
+					i.key_pressed(Key::ArrowRight),
+					i.key_pressed(Key::Escape)
+				)
+			});
+			match (left, right) {
+				(true, false) => self.move_selection(-1),
+				(false, true) => self.move_selection(1),
+				_ => ()
+			}
+			if escape && !toggle_presenting {
+				self.selected = None;
+			}
+		}
+
+		if self.status.is_some() || self.presenting.is_none() {
+			let (mut dismissed, mut compact) = (false, false);
+			egui::TopBottomPanel::bottom(ui.id().with("status")).show_inside(ui, |ui| {
+				ui.horizontal(|ui| {
+					if self.presenting.is_none() {
+						ui.label(format!(
+							"{} rows, about {}B",
+							self.data.len(),
+							NumberFormat::Si.format(self.memory as f64)
+						));
+						compact = ui
+							.add_enabled(
+								!Self::keys_in_use(&self.settings).is_empty(),
+								Button::new("Compact")
+							)
+							.on_hover_text(
+								"Drop every key that isn't being grouped or bounded by, and share \
+								 the strings that show up more than once"
+							)
+							.on_disabled_hover_text("Pick some keys to keep first")
+							.clicked();
+						ui.separator();
+					}
+
+					if let Some(status) = &self.status {
+						ui.label(status);
+						dismissed = ui.small_button("❌").clicked();
+					}
+				});
+			});
+
+			if dismissed {
+				self.status = None;
+			}
+			if compact {
+				self.compact();
+			}
+		}
+
+		if let Some(key) = &self.stats_shown {
+			let loaded = self.loaded_type(key).unwrap_or(ValueType::Null);
+			let key_type = self.settings.key_types.get(key).copied();
+			let splittable = self.key_type(key).is_ok_and(|ty| ty == ValueType::String);
+			let addresses = self.schema.ip_keys.contains(key) && !self.addresses.contains(key);
+			let parts = self.schema.url_keys.contains(key) && !self.urls.contains(key);
+			let agent = self.schema.agent_keys.contains(key) && !self.agents.contains(key);
+			let delimiter = self.settings.delimiters.get(key).cloned();
+			let stats = self.stats.entry(key.clone()).or_insert_with(|| {
+				KeyStats::compute(&self.data, key, self.settings.byte_encoding(key))
+			});
+			let format = self.settings.formats.get(key).copied().unwrap_or_default();
+			let numeric = matches!(stats.summary, Summary::Numeric { .. });
+			let test = self.settings.outliers.get(key).copied();
+			let outliers = test.and_then(|test| match self.outlier_counts.entry(key.clone()) {
+				Entry::Occupied(found) => Some(&*found.into_mut()),
+				Entry::Vacant(entry) => Some(&*entry.insert(test.find(&self.data, key)?))
+			});
+
+			let mut open = true;
+			let mut change = None;
+			let mut retyped = None;
+			let mut split = None;
+			let mut add_fields = false;
+			let mut add_parts = false;
+			let mut add_agent = false;
+			egui::Window::new(format!("Statistics for '{key}'"))
+				.open(&mut open)
+				.collapsible(false)
+				.show(ctx, |ui| {
+					show_stats(ui, stats, format);
+					retyped = show_key_type(ui, loaded, key_type);
+					if splittable {
+						split = show_delimiter(ui, delimiter.as_deref());
+					}
+					if addresses {
+						add_fields = ui
+							.button("Add subnet, version, and private keys")
+							.on_hover_text(
+								"Pull keys out of these IP addresses that are easier to group by"
+							)
+							.clicked();
+					}
+					if parts {
+						add_parts = ui
+							.button("Add host, path, and query keys")
+							.on_hover_text(
+								"Pull the parts of these URLs out into keys that are easier to \
+								 group by"
+							)
+							.clicked();
+					}
+					if agent {
+						add_agent = ui
+							.button("Add browser, OS, and device keys")
+							.on_hover_text("Tell where each of these user agents is from")
+							.clicked();
+					}
+					if numeric {
+						ui.separator();
+						change = show_outliers(ui, test, outliers, format);
+					}
+				});
+
+			let key = key.clone();
+			if !open {
+				self.stats_shown = None;
+			}
+			let result = match (change, retyped, split) {
+				(_, _, Some(delimiter)) => self.set_delimiter(&key, delimiter),
+				(_, Some(ty), None) => self.set_key_type(&key, ty),
+				(Some(OutlierChange::Test(test)), None, None) => self.set_outlier_test(&key, test),
+				(Some(OutlierChange::Exclude), None, None) => self.exclude_outliers(&key),
+				(None, None, None) => Ok(())
+			}
+			.and_then(|()| match add_fields {
+				true => self.add_ip_fields(&key),
+				false => Ok(())
+			})
+			.and_then(|()| match add_parts {
+				true => self.add_url_fields(&key),
+				false => Ok(())
+			})
+			.and_then(|()| match add_agent {
+				true => self.add_agent_fields(&key),
+				false => Ok(())
+			});
+			if let Err(e) = result {
+				self.status = Some(e.to_string());
+			}
+		}
+
+		if let Some((idx, bar)) = self
+			.selected
+			.and_then(|idx| Some((idx, self.bars.get(idx)?)))
+		{
+			let pinned = self.pinned.contains(&bar.labels);
+			let toggle_pin = egui::SidePanel::right(ui.id().with("selected"))
+				.show_inside(ui, |ui| {
+					show_selected(
+						ui,
+						bar,
+						idx,
+						&self.bars,
+						self.settings.value_format(),
+						pinned,
+						self.refreshed.as_ref()
+					)
+				})
+				.inner;
+			if toggle_pin {
+				let labels = bar.labels.clone();
+				self.toggle_pin(labels);
+			}
+		}
+
+		egui::CentralPanel::default().show_inside(ui, |ui| {
+			let (id, rect) = ui.allocate_space(ui.available_size());
+			let builder = UiBuilder::new()
+				.id_salt(id)
+				.max_rect(rect)
+				.layout(Layout::left_to_right(Align::Center));
+
+			let mut ui = ui.new_child(builder);
+
+			if self.presenting.is_none() {
+				ui.vertical(|ui| self.show_settings(ui));
+			}
+
+			self.show_plot(&mut ui, id);
+			if self.png_export.as_ref().is_some_and(|export| export.due) {
+				self.export_png(ctx, id);
+			}
+		});
+	}
+
+	/// Lets the user pick how big of a PNG to export the plot as
+	fn show_png_export(&mut self, ctx: &egui::Context) {
+		let Some(export) = &mut self.png_export else {
+			return;
+		};
+
+		let mut open = true;
+		egui::Window::new("Export PNG")
+			.open(&mut open)
+			.collapsible(false)
+			.resizable(false)
+			.show(ctx, |ui| {
+				egui::Grid::new("png size").num_columns(2).show(ui, |ui| {
+					ui.label("Width");
+					ui.add(DragValue::new(&mut export.width).range(1..=offscreen::MAX_SIDE));
+					ui.end_row();
+					ui.label("Height");
+					ui.add(DragValue::new(&mut export.height).range(1..=offscreen::MAX_SIDE));
+					ui.end_row();
+					ui.label("Scale").on_hover_text(
+						"How many pixels each point of the window's takes up, which makes the \
+						 text and lines bigger without changing what's shown"
+					);
+					ui.add(
+						DragValue::new(&mut export.scale)
+							.range(0.25..=8.)
+							.speed(0.05)
+							.suffix("×")
+					);
+					ui.end_row();
+				});
+				export.due = ui.button("Export").clicked();
+			});
+		if !open {
+			self.png_export = None;
+		}
+	}
+
+	/// Draws the plot again off screen, at the size that was picked for it, and saves that
+	fn export_png(&mut self, ctx: &egui::Context, id: egui::Id) {
+		let Some(export) = self.png_export.take() else {
+			return;
+		};
+
+		// It's zoomed into just the same part of the plot as the one on screen is
+		let mut memory = PlotMemory::load(ctx, id);
+		let fit_plot = self.fit_plot;
+		let image = offscreen::render(ctx, [export.width, export.height], export.scale, |ui| {
+			if let Some(memory) = memory.take() {
+				memory.store(ui.ctx(), id);
+			}
+			self.show_plot(ui, id);
+		});
+		self.fit_plot = fit_plot;
+
+		self.status = Some(match save_png(&image) {
+			Ok(path) => format!(
+				"Saved the chart to {path} ({}×{})",
+				export.width, export.height
+			),
+			Err(e) => format!("Couldn't save the chart: {e}")
+		});
+	}
+}
+
+impl App {
+	fn show_settings(&mut self, ui: &mut egui::Ui) {
+		if ui
+			.button("⛶ Present")
+			.on_hover_text("Show only the plot (F11 or Esc to go back)")
+			.clicked()
+		{
+			self.toggle_presenting(ui.ctx());
+		}
+
+		#[cfg(feature = "native")]
+		if self.source.is_some() {
+			ui.horizontal(|ui| {
+				let mut watching = self.watch.is_some();
+				if ui.checkbox(&mut watching, "Reload on change").changed() {
+					self.set_watching(watching);
+				}
+
+				if ui.button("⟳ Reload").clicked() {
+					self.reload();
+				}
+			});
+		}
+
+		#[cfg(feature = "sql")]
+		self.show_sql(ui);
+
+		let presets = song::Preset::ALL
+			.into_iter()
+			.filter(|preset| preset.applies_to(&self.schema))
+			.collect::<Vec<_>>();
+		if !presets.is_empty() {
+			ui.heading("Presets");
+			ui.horizontal_wrapped(|ui| {
+				for preset in presets {
+					if ui.button(preset.ui_descriptor()).clicked() {
+						// It's only been offered because it applies
+						_ = self.apply_preset(preset);
+					}
+				}
+			});
+		}
+
+		self.show_sharing(ui);
+
+		if self.history.len() > 1 {
+			let chosen = ui
+				.collapsing(format!("History ({})", self.history.len()), |ui| {
+					self.history.show(ui, &self.settings)
+				})
+				.body_returned
+				.flatten();
+			if let Some(token) = chosen
+				&& let Err(e) = self.open_view(&token)
+			{
+				self.status = Some(e.to_string());
+			}
+		}
+
+		ui.heading("Keys");
+
+		let mut drilling = self.settings.drill.is_some();
+		if (drilling || self.settings.x_axis.len() > 1)
+			&& ui
+				.checkbox(&mut drilling, "Drill down")
+				.on_hover_text(
+					"Group by one key at a time, in order, and click on a bar to go down to the \
+					 next key with just its rows"
+				)
+				.changed()
+		{
+			self.set_drilling(drilling);
+		}
+
+		let mut toggled = None;
+		let grouped = match &self.settings.drill {
+			Some(drill) => &drill.keys,
+			None => &self.settings.x_axis
+		};
+		for (key, _) in &self.schema.keys {
+			let selected = grouped.contains(key);
+			let (clicked, info_clicked) = ui
+				.horizontal(|ui| {
+					let clicked = ui.radio(selected, key.deref()).clicked();
+					let info = ui.small_button("ℹ").on_hover_text("Show statistics");
+					(clicked, info.clicked())
+				})
+				.inner;
+
+			if info_clicked {
+				self.stats_shown = Some(key.clone());
+			}
+
+			if clicked {
+				toggled = Some((key.clone(), selected));
+			}
+		}
+
+		if let Some((key, selected)) = toggled {
+			self.fit_plot = true;
+			if selected {
+				self.remove_key(&key);
+			} else {
+				self.add_key(key);
+			}
+		}
+
+		let resp = ui
+			.text_edit_singleline(&mut self.script_input.column)
+			.on_hover_text(
+				"Add a key worked out by a script, e.g. 'minutes=ms_played / 60000', then press \
+				 enter"
+			);
+		if resp.lost_focus()
+			&& ui.input(|i| i.key_pressed(Key::Enter))
+			&& self.adding_column.is_none()
+		{
+			let column = std::mem::take(&mut self.script_input.column);
+			match self.add_column(column.clone()) {
+				Ok(()) => self.script_input.column_err = None,
+				Err(e) => {
+					self.script_input.column_err = Some(e.to_string());
+					self.script_input.column = column;
+				}
+			}
+		}
+		if let Some(adding) = &self.adding_column {
+			ui.horizontal(|ui| {
+				ui.spinner();
+				ui.label(format!("Working out '{}'…", adding.name));
+			});
+		}
+		if let Some(err) = &self.script_input.column_err {
+			ui.colored_label(ui.visuals().error_fg_color, err);
+		}
+
+		if song::has_listening_time(&self.schema) {
+			let mut listening = song::is_listening_time(&self.settings);
+			if ui
+				.checkbox(&mut listening, "Total listening time")
+				.on_hover_text(
+					"Make each bar as tall as how long it was listened to, instead of how many \
+					 times it was played"
+				)
+				.changed()
+			{
+				self.set_listening_time(listening);
+			}
+		}
+
+		if song::has_podcasts(&self.schema) {
+			let current = song::Podcasts::of(&self.settings);
+			let mut podcasts = current;
+			ComboBox::from_label("Podcasts")
+				.selected_text(podcasts.ui_descriptor())
+				.show_ui(ui, |ui| {
+					for choice in song::Podcasts::ALL {
+						ui.selectable_value(&mut podcasts, choice, choice.ui_descriptor());
+					}
+				});
+			if podcasts != current {
+				self.set_podcasts(podcasts);
+			}
+		}
+
+		if song::has_skips(&self.schema) {
+			let mut skip_rate = song::is_skip_rate(&self.settings);
+			if ui
+				.checkbox(&mut skip_rate, "Skip rate")
+				.on_hover_text(
+					"Make each bar as tall as the share of its plays that were skipped, instead of \
+					 how many times it was played"
+				)
+				.changed()
+			{
+				self.set_skip_rate(skip_rate);
+			}
+		}
+
+		let numbers = self
+			.schema
+			.keys
+			.iter()
+			.filter(|(_, ty)| matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float))
+			.map(|(key, _)| key)
+			.collect::<Vec<_>>();
+		if !numbers.is_empty() {
+			let current = match self.settings.y_axis {
+				YAxisKey::Key(key) => Some(key),
+				_ => None
+			};
+			let mut chosen = current;
+			let label = format!("{} of", self.settings.aggregation.ui_descriptor());
+			ComboBox::new("sum", label)
+				.selected_text(current.unwrap_or("Off"))
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut chosen, None, "Off");
+					for key in numbers {
+						ui.selectable_value(&mut chosen, Some(key.as_str()), key.as_str());
+					}
+				})
+				.response
+				.on_hover_text(
+					"Make each bar as tall as the total of a key over its rows, like how many \
+					 bytes were sent, instead of how many rows it has"
+				);
+			if chosen != current {
+				let chosen = chosen.map(str::to_string);
+				if let Err(e) = self.set_sum(chosen.as_deref()) {
+					self.status = Some(e.to_string());
+				}
+			}
+
+			if current.is_some() {
+				let mut aggregation = self.settings.aggregation;
+				ComboBox::from_label("Aggregate")
+					.selected_text(aggregation.ui_descriptor())
+					.show_ui(ui, |ui| {
+						for choice in Aggregation::ALL {
+							ui.selectable_value(&mut aggregation, choice, choice.ui_descriptor());
+						}
+					})
+					.response
+					.on_hover_text(
+						"How each bar's values of the key are combined into its height, like the \
+						 average size of each endpoint's responses instead of their total"
+					);
+				if aggregation != self.settings.aggregation {
+					self.set_aggregate(aggregation);
+				}
+			}
+		}
+
+		let booleans = self
+			.schema
+			.keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::Bool)
+			.map(|(key, _)| key)
+			.collect::<Vec<_>>();
+		if !booleans.is_empty() {
+			let current = match self.settings.y_axis {
+				YAxisKey::Rate(key) => Some(key),
+				_ => None
+			};
+			let mut chosen = current;
+			ComboBox::from_label("% true")
+				.selected_text(current.unwrap_or("Off"))
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut chosen, None, "Off");
+					for key in booleans {
+						ui.selectable_value(&mut chosen, Some(key.as_str()), key.as_str());
+					}
+				})
+				.response
+				.on_hover_text(
+					"Make each bar as tall as the share of its rows where a key is true, like how \
+					 often an endpoint errored"
+				);
+			if chosen != current {
+				let chosen = chosen.map(str::to_string);
+				if let Err(e) = self.set_rate(chosen.as_deref()) {
+					self.status = Some(e.to_string());
+				}
+			}
+		}
+
+		ui.heading("Pages");
+
+		let num_bars = self.bars.len();
+		let page_size = self.settings.page_size() as isize;
+		ui.horizontal(|ui| {
+			let shown = self.settings.shown_range(num_bars);
+
+			if ui.add_enabled(shown.start > 0, Button::new("◀")).clicked() {
+				self.settings.scroll_by(-page_size, num_bars);
+			}
+
+			ui.label(format!(
+				"Bars {}–{} of {num_bars}",
+				(shown.start + 1).min(shown.end),
+				shown.end
+			));
+
+			if ui
+				.add_enabled(shown.end < num_bars, Button::new("▶"))
+				.clicked()
+			{
+				self.settings.scroll_by(page_size, num_bars);
+			}
+		});
+
+		ui.horizontal(|ui| {
+			ui.label("Page size");
+			let mut all = self.settings.shows_all();
+			if ui.checkbox(&mut all, "All").changed() {
+				self.settings.max_shown = if all { usize::MAX } else { num_bars.max(1) };
+			}
+			if !all {
+				ui.add(DragValue::new(&mut self.settings.max_shown).range(1..=num_bars.max(1)));
+			}
+			ui.label(format!(
+				"{} of {num_bars} bars",
+				self.settings.shown_range(num_bars).len()
+			));
+		});
+
+		ui.horizontal(|ui| {
+			let mut merging = self.settings.merge_below.is_some();
+			let toggled = ui
+				.checkbox(&mut merging, "Merge bars under")
+				.on_hover_text(
+					"Put the groups that make up less than this much of the total into one bar"
+				)
+				.changed();
+			if toggled {
+				self.set_merge_below(merging.then_some(DEFAULT_MERGE_BELOW));
+			}
+			if let Some(fraction) = &mut self.settings.merge_below {
+				let mut percent = *fraction * 100.;
+				let resp = ui.add(
+					DragValue::new(&mut percent)
+						.range(0.1..=50.)
+						.speed(0.1)
+						.suffix("%")
+				);
+				if resp.changed() {
+					*fraction = percent / 100.;
+					self.rebuild_due = Some(Instant::now() + REBUILD_DEBOUNCE);
+				}
+			}
+		});
+
+		let mut unpin = None;
+		for labels in &self.pinned {
+			ui.horizontal(|ui| {
+				ui.label(format!("📌 {}", labels.join(", ")));
+				if ui.small_button("❌").on_hover_text("Unpin").clicked() {
+					unpin = Some(labels.clone());
+				}
+			});
+		}
+		if let Some(labels) = unpin {
+			self.toggle_pin(labels);
+		}
+
+		let max_offset = self.settings.max_offset(num_bars);
+		ui.add_enabled(
+			max_offset > 0,
+			Slider::new(&mut self.settings.offset, 0..=max_offset).text("Scroll")
+		);
+
+		ui.heading("Formatting");
+
+		ui.horizontal(|ui| {
+			ComboBox::from_label("Chart")
+				.selected_text(self.settings.chart.ui_descriptor())
+				.show_ui(ui, |ui| {
+					for kind in ChartKind::ALL {
+						if kind == ChartKind::Map && !self.mappable() {
+							continue;
+						}
+						ui.selectable_value(&mut self.settings.chart, kind, kind.ui_descriptor());
+					}
+				});
+			if matches!(self.settings.chart, ChartKind::Line | ChartKind::Points) {
+				ui.checkbox(&mut self.settings.trend, "Trend line")
+					.on_hover_text("Draw the straight line that best fits the points");
+			}
+		});
+
+		if self.settings.chart == ChartKind::Bars {
+			let current = self.settings.stack_by.clone();
+			let mut chosen = current.clone();
+			ComboBox::from_label("Stack by")
+				.selected_text(current.as_deref().unwrap_or("Off"))
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut chosen, None, "Off");
+					for (key, _) in &self.schema.keys {
+						if !self.settings.x_axis.contains(key) {
+							ui.selectable_value(&mut chosen, Some(key.clone()), key.as_str());
+						}
+					}
+				})
+				.response
+				.on_hover_text(
+					"Split each bar into a colored segment for each value of another key, like the \
+					 artists in each month"
+				);
+			if chosen != current
+				&& let Err(e) = self.set_stack_by(chosen.as_deref())
+			{
+				self.status = Some(e.to_string());
+			}
+		}
+
+		// Averaging over bars only makes sense when they're in order of time
+		if self
+			.settings
+			.x_axis
+			.iter()
+			.any(|key| self.settings.date_formats.contains_key(key))
+		{
+			ui.horizontal(|ui| {
+				let mut averaging = self.settings.moving_average.is_some();
+				let mut changed = ui
+					.checkbox(&mut averaging, "Moving average")
+					.on_hover_text(
+						"Draw the average of each bar and the ones before it, to smooth out noise"
+					)
+					.changed();
+				if changed {
+					self.settings.moving_average = averaging.then_some(DEFAULT_AVERAGE_WINDOW);
+				}
+				if let Some(window) = &mut self.settings.moving_average {
+					changed |= ui
+						.add(DragValue::new(window).range(2..=365).suffix(" bars"))
+						.changed();
+				}
+				if changed {
+					self.visible = None;
+				}
+			});
+		}
+
+		let order = self.settings.order;
+		ComboBox::from_label("Order")
+			.selected_text(order.ui_descriptor())
+			.show_ui(ui, |ui| {
+				for order in BarOrder::ALL {
+					ui.selectable_value(&mut self.settings.order, order, order.ui_descriptor());
+				}
+			});
+		let tie_break = self.settings.tie_break;
+		if order == BarOrder::Tallest {
+			ComboBox::from_label("Ties")
+				.selected_text(tie_break.ui_descriptor())
+				.show_ui(ui, |ui| {
+					for tie_break in TieBreak::ALL {
+						ui.selectable_value(
+							&mut self.settings.tie_break,
+							tie_break,
+							tie_break.ui_descriptor()
+						);
+					}
+				})
+				.response
+				.on_hover_text("Which bars come first when they're just as tall");
+		}
+		if self.settings.order != order || self.settings.tie_break != tie_break {
+			self.rebuild_bars();
+			self.fit_plot = true;
+		}
+
+		ui.horizontal(|ui| {
+			ui.label("Label width");
+			ui.add(DragValue::new(&mut self.settings.label_width).range(1..=200))
+				.on_hover_text(
+					"Longer names are cut short along the axis, but are shown in full \
+					 when hovering over their bar"
+				);
+		});
+
+		ui.horizontal(|ui| {
+			ui.label("Label template");
+			let mut template = self.settings.label_template.clone().unwrap_or_default();
+			let resp = ui
+				.text_edit_singleline(&mut template)
+				.on_hover_text("What to name the bars, like '{track} — {artist}'");
+			if resp.changed() {
+				self.settings.label_template = (!template.is_empty()).then_some(template);
+			}
+			// Don't rebuild on every keystroke while it's being typed out
+			if resp.lost_focus() {
+				self.rebuild_bars();
+			}
+		});
+
+		let mut update_bars = false;
+		if matches!(self.settings.y_axis, YAxisKey::Count) {
+			// This only affects how the plot is drawn, not the bars, so there's nothing
+			// to rebuild if it changes
+			let _ = show_format_picker(ui, "Count", &mut self.settings.count_format);
+		}
+		for (key, ty) in &self.schema.keys {
+			if !matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float) {
+				continue;
+			}
+
+			let mut format = self.settings.formats.get(key).copied().unwrap_or_default();
+			if show_format_picker(ui, key, &mut format) {
+				self.settings.formats.insert(key.clone(), format);
+				// Only the bar names depend on the format, so we only need to rebuild if
+				// this key is actually part of those names
+				update_bars |= self.settings.x_axis.contains(key);
+			}
+		}
+
+		let byte_keys = self
+			.schema
+			.keys
+			.iter()
+			.filter(|(_, ty)| *ty == ValueType::Bytes)
+			.map(|(key, _)| key)
+			.collect::<Vec<_>>();
+		if !byte_keys.is_empty() {
+			ui.heading("Bytes");
+
+			for key in byte_keys {
+				let old = self.settings.byte_encoding(key);
+				let mut encoding = old;
+				ComboBox::from_label(key)
+					.selected_text(encoding.ui_descriptor())
+					.show_ui(ui, |ui| {
+						for option in ByteEncoding::ALL {
+							ui.selectable_value(&mut encoding, option, option.ui_descriptor());
+						}
+					});
+				if encoding != old {
+					self.settings.byte_encodings.insert(key.clone(), encoding);
+					// The most common values are counted up by how they look
+					self.stats.remove(key);
+					update_bars |= self.settings.x_axis.contains(key);
+				}
+			}
+		}
+
+		if !self.schema.timestamp_keys.is_empty() || !self.schema.epoch_keys.is_empty() {
+			ui.heading("Dates");
+
+			for key in &self.schema.timestamp_keys {
+				if show_date_format_picker(ui, key, &mut self.settings.date_formats) {
+					update_bars |= self.settings.x_axis.contains(key);
+				}
+			}
+
+			let mut retyped = None;
+			for (key, ty) in &self.schema.epoch_keys {
+				ui.horizontal(|ui| {
+					let unit = match ty {
+						KeyType::EpochMillis => "milliseconds",
+						_ => "seconds"
+					};
+					ui.label(format!("'{key}' looks like Unix timestamps in {unit}"));
+					if ui.button("Treat as dates").clicked() {
+						retyped = Some((key.clone(), *ty));
+					}
+				});
+			}
+			if let Some((key, ty)) = retyped
+				&& let Err(e) = self.set_key_type(&key, Some(ty))
+			{
+				self.status = Some(e.to_string());
+			}
+		}
+
+		self.show_bins(ui);
+		self.show_reference_lines(ui);
+		self.show_color_rules(ui);
+		self.show_annotations(ui);
+
+		ui.heading("Bounds");
+
+		ui.horizontal(|ui| {
+			let mut comparing = self.settings.compare.is_some();
+			if ui
+				.checkbox(&mut comparing, "Compare")
+				.on_hover_text(
+					"Chart the bars for a second set of bounds (B) next to the ones for these (A)"
+				)
+				.changed()
+			{
+				self.set_comparing(comparing);
+			}
+			if comparing {
+				let mut b = self.editing_b;
+				ui.radio_value(&mut b, false, "Editing A");
+				ui.radio_value(&mut b, true, "B");
+				if b != self.editing_b {
+					self.edit_b(b);
+					self.fit_plot = true;
+				}
+			}
+		});
+
+		if !self.schema.timestamp_keys.is_empty() {
+			let current = self
+				.settings
+				.periods
+				.as_ref()
+				.map(|periods| (periods.key.clone(), periods.period));
+			let mut chosen = current.clone();
+			let describe =
+				|(key, period): &(String, Period)| format!("{} of '{key}'", period.ui_descriptor());
+			ComboBox::from_label("Periods")
+				.selected_text(chosen.as_ref().map_or_else(|| "Off".to_string(), describe))
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut chosen, None, "Off");
+					for key in &self.schema.timestamp_keys {
+						for period in Period::ALL {
+							let choice = (key.clone(), period);
+							let text = describe(&choice);
+							ui.selectable_value(&mut chosen, Some(choice), text);
+						}
+					}
+				})
+				.response
+				.on_hover_text(
+					"Chart each bar's rows in the latest period next to its rows in the period \
+					 before it"
+				);
+			if chosen != current {
+				let chosen = chosen.as_ref().map(|(key, period)| (key.as_str(), *period));
+				if let Err(e) = self.set_periods(chosen) {
+					self.status = Some(e.to_string());
+				}
+			}
+		}
+
+		if (self.settings.compare.is_some() || self.settings.periods.is_some())
+			&& ui
+				.checkbox(&mut self.settings.diff, "Show as change")
+				.on_hover_text(
+					"Chart how much each bar changed from A to B, or from one period to the next"
+				)
+				.changed()
+		{
+			self.rebuild_bars();
+			self.fit_plot = true;
+		}
+
+		if !self.settings.brushes.is_empty() {
+			let picked = self
+				.settings
+				.brushes
+				.iter()
+				.map(Brush::describe)
+				.collect::<Vec<_>>();
+			ui.label(format!(
+				"Only rows in the bars picked in the other charts ({})",
+				picked.join("; ")
+			));
+		}
+
+		self.show_sampling(ui);
+
+		let resp = ui
+			.text_edit_singleline(&mut self.filter_input)
+			.on_hover_text("e.g. 'country=US|CA' or 'ms_played>30000', then press enter");
+		if resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+			let filter = std::mem::take(&mut self.filter_input);
+			self.filter_err = self.add_filter(&filter).err();
+			if self.filter_err.is_some() {
+				// Let them fix it instead of making them type it all out again
+				self.filter_input = filter;
+			}
+		}
+		if let Some(err) = &self.filter_err {
+			ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+		}
+
+		let resp = ui
+			.text_edit_singleline(&mut self.script_input.predicate)
+			.on_hover_text(
+				"A script that rows have to be true for, e.g. 'ms_played > 30000 && !skipped', \
+				 then press enter"
+			);
+		if resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+			self.script_input.predicate_err = self
+				.set_predicate(&self.script_input.predicate.clone())
+				.err()
+				.map(|e| e.to_string());
+		}
+		if let Some(err) = &self.script_input.predicate_err {
+			ui.colored_label(ui.visuals().error_fg_color, err);
+		}
 
-use crate::{
-	bars::make_bars,
-	settings::{Bound, Settings, ValueBound},
-	sort::sort_arr
-};
+		for (key, ty) in &self.schema.keys {
+			ComboBox::from_label(&**key)
+				.selected_text(
+					self.settings
+						.bounds
+						.get(key)
+						.map_or("None", ValueBound::ui_descriptor)
+				)
+				.show_ui(ui, |ui| {
+					update_bars |= show_bounds_for_ty(ui, key, *ty, &mut self.settings.bounds)
+				});
 
-pub struct App {
-	// We could try to do zero-copy deserialization, but it'll be much easier to work with if we
-	// just copy it all - plus, the data will normally just be loaded in once and then manipulated
-	// a bunch, so we don't need to optimize the loading-in phase very much. Also it may not
-	// actually be super possible to do `yoke`-like stuff 'cause we don't want to have a set
-	// schema, meaning that we have to use `merde::Value`, and we can't necessarily
-	// `derive(Yokeable)` on that. So.
-	//
-	// Invariant: Each `Map` inside this vec has the same schema, and contains no nested data
-	// structures - no inner `Map`s or `Array`s. It is also not empty.
-	data: Vec<merde::Map<'static>>,
-	keys: Vec<(String, ValueType)>,
-	settings: Settings<'static>,
-	pub bars: Vec<Bar>
-}
-
-#[derive(thiserror::Error, Debug)]
-pub enum AppCreationErr {
-	#[error("The provided data is empty")]
-	NoData,
-	#[error("Differing types were found for the key '{key}': {expected:?} and {found:?}")]
-	DifferentTypes {
-		key: String,
-		expected: ValueType,
-		found: ValueType
-	},
-	#[error(
-		"Nested types (like '{0:?}') are not allowed here (we just can't make a bar graph with them)"
-	)]
-	NestedTypes(ValueType)
-}
+			if let Some(bound) = self.settings.bounds.get_mut(key) {
+				match show_bounds_configurations(bound, ui) {
+					BoundChange::Unchanged => (),
+					// Rebuilding on every frame of a drag would make it stutter with lots of
+					// data, so wait until they let go or stop moving it for a bit
+					BoundChange::Dragging =>
+						self.rebuild_due = Some(Instant::now() + REBUILD_DEBOUNCE),
+					BoundChange::Finished => update_bars = true
+				}
+			}
+		}
 
-impl App {
-	pub fn new(data: Vec<merde::Map<'static>>) -> Result<Self, AppCreationErr> {
-		let Some(first) = data.first() else {
-			return Err(AppCreationErr::NoData);
-		};
-
-		for map in data.iter().skip(1) {
-			for (key, value) in map.iter() {
-				match (first[key].value_type(), value.value_type()) {
-					// we don't want nested types
-					(t @ (ValueType::Map | ValueType::Array), _)
-					| (_, t @ (ValueType::Map | ValueType::Array)) => {
-						return Err(AppCreationErr::NestedTypes(t));
-					}
-					// and we're ok with type differences if one is null and the other is a
-					// different type - everything's Option around here
-					(ValueType::Null, _) | (_, ValueType::Null) => (),
-					// But if they're two different types otherwise, that's an error.
-					(a, b) if a != b => {
-						return Err(AppCreationErr::DifferentTypes {
-							key: key.to_string(),
-							expected: a,
-							found: b
-						});
-					}
-					_ => ()
+		if update_bars {
+			self.rebuild_bars();
+			self.fit_plot = true;
+		}
+	}
+
+	fn show_sharing(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			if ui
+				.button("🔗 Copy view")
+				.on_hover_text(
+					"Copy a link that shows someone else with the same data exactly this chart"
+				)
+				.clicked()
+			{
+				ui.ctx()
+					.copy_text(format!("{}{}", share::URL_PREFIX, self.view_token()));
+				self.status = Some("Copied the view to the clipboard".to_string());
+			}
+
+			let resp = ui
+				.text_edit_singleline(&mut self.view_input)
+				.on_hover_text("Paste a view that was copied from spart, then press enter");
+			if resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+				self.view_err = self.open_view(&self.view_input.clone()).err();
+				if self.view_err.is_none() {
+					self.view_input.clear();
+				}
+			}
+		});
+		if let Some(err) = &self.view_err {
+			ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+		}
+	}
+
+	fn show_color_rules(&mut self, ui: &mut egui::Ui) {
+		let mut removed = None;
+		for (idx, rule) in self.settings.color_rules.iter().enumerate() {
+			ui.horizontal(|ui| {
+				let [r, g, b] = rule.color;
+				ui.colored_label(Color32::from_rgb(r, g, b), "⏹");
+				ui.label(rule.to_string());
+				if ui.small_button("❌").on_hover_text("Remove").clicked() {
+					removed = Some(idx);
+				}
+			});
+		}
+		if let Some(idx) = removed {
+			self.settings.color_rules.remove(idx);
+			self.rebuild_bars();
+		}
+
+		let resp = ui
+			.text_edit_singleline(&mut self.color_rule_input)
+			.on_hover_text(
+				"Color some bars, e.g. 'value > 100 -> red' or 'name contains error -> orange', \
+				 then press enter"
+			);
+		if resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+			match self.color_rule_input.parse() {
+				Ok(rule) => {
+					self.add_color_rule(rule);
+					self.color_rule_input.clear();
+					self.color_rule_err = None;
 				}
+				Err(e) => self.color_rule_err = Some(e)
 			}
 		}
+		if let Some(err) = &self.color_rule_err {
+			ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+		}
+	}
 
-		let mut keys: Vec<(String, _)> = first
+	/// Making histograms of the numeric keys on the x-axis, with however many bins (or bins however
+	/// wide) they should have
+	fn show_bins(&mut self, ui: &mut egui::Ui) {
+		let numeric = self
+			.settings
+			.x_axis
 			.iter()
-			.map(|(k, v)| (k.to_string(), v.value_type()))
-			.collect();
+			.filter(|key| {
+				self.key_type(key).is_ok_and(|ty| {
+					matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float)
+				})
+			})
+			.cloned()
+			.collect::<Vec<_>>();
+		if numeric.is_empty() {
+			return;
+		}
+		ui.heading("Histograms");
 
-		// sort_by_key requires returning a &str that borrows from the passed-in CowStr and the
-		// lifetimes aren't friendly with that.
-		#[allow(clippy::unnecessary_sort_by)]
-		keys.sort_unstable_by(|(a, _), (b, _)| (**a).cmp(&**b));
+		let mut changed = None;
+		for key in numeric {
+			let Some((min, max)) = self.numeric_range(&key) else {
+				continue;
+			};
+			let bins = self.settings.bins.get(&key).copied();
+			ui.horizontal(|ui| {
+				let mut binned = bins.is_some();
+				if ui
+					.checkbox(&mut binned, key.as_str())
+					.on_hover_text(
+						"Put the values into bins that are all as wide, instead of giving each one \
+						 a bar"
+					)
+					.changed()
+				{
+					let bins = binned
+						.then(|| Bins::covering(min, max, DEFAULT_BIN_COUNT))
+						.flatten();
+					changed = Some((key.clone(), bins));
+				}
+				let Some(bins) = bins else {
+					return;
+				};
 
-		Ok(Self {
-			data,
-			keys,
-			settings: Settings::default(),
-			bars: Vec::new()
-		})
+				let mut count = bins.count(min, max);
+				if ui
+					.add(
+						DragValue::new(&mut count)
+							.range(1..=MAX_BIN_COUNT)
+							.suffix(" bins")
+					)
+					.changed()
+				{
+					changed = Bins::covering(min, max, count).map(|bins| (key.clone(), Some(bins)));
+				}
+				let mut width = bins.width;
+				if ui
+					.add(
+						DragValue::new(&mut width)
+							.range(f64::MIN_POSITIVE..=f64::MAX)
+							.speed(bins.width / 100.)
+							.prefix("width ")
+					)
+					.on_hover_text("Bins this wide start at multiples of it")
+					.changed()
+				{
+					changed = Bins::aligned(width).map(|bins| (key.clone(), Some(bins)));
+				}
+			});
+		}
+		if let Some((key, bins)) = changed
+			&& let Err(e) = self.set_bins(&key, bins)
+		{
+			self.status = Some(e.to_string());
+		}
 	}
 
-	pub fn add_key(
-		key: String,
-		bars: &mut Vec<Bar>,
-		data: &mut [merde::Map<'static>],
-		settings: &mut Settings
-	) {
-		settings.x_axis.push(key);
-		Self::rebuild_bars(bars, data, settings);
+	fn show_reference_lines(&mut self, ui: &mut egui::Ui) {
+		let mut removed = None;
+		for (idx, line) in self.settings.reference_lines.iter().enumerate() {
+			ui.horizontal(|ui| {
+				ui.label(match line {
+					ReferenceLine::Value(value) =>
+						format!("― At {}", self.settings.value_format().format(*value)),
+					ReferenceLine::Mean => "― Mean".to_string(),
+					ReferenceLine::Median => "― Median".to_string()
+				});
+				if ui.small_button("❌").on_hover_text("Remove").clicked() {
+					removed = Some(idx);
+				}
+			});
+		}
+		if let Some(idx) = removed {
+			self.settings.reference_lines.remove(idx);
+		}
+
+		ui.horizontal(|ui| {
+			ui.label("Reference line");
+			for (line, name) in [
+				(ReferenceLine::Mean, "Mean"),
+				(ReferenceLine::Median, "Median")
+			] {
+				if ui.button(name).clicked() {
+					self.add_reference_line(line);
+				}
+			}
+			if ui.button("At").clicked() {
+				self.add_reference_line(ReferenceLine::Value(self.reference_input));
+			}
+			ui.add(DragValue::new(&mut self.reference_input));
+		});
 	}
 
-	pub fn remove_key(
-		key: &String,
-		bars: &mut Vec<Bar>,
-		data: &mut [merde::Map<'static>],
-		settings: &mut Settings
-	) {
-		if let Some(idx) = settings.x_axis.iter().position(|k| k == key) {
-			settings.x_axis.remove(idx);
+	fn show_annotations(&mut self, ui: &mut egui::Ui) {
+		ui.heading("Annotations");
+
+		let mut removed = None;
+		for (idx, annotation) in self.settings.annotations.iter().enumerate() {
+			ui.horizontal(|ui| {
+				let place = match &annotation.anchor {
+					Anchor::Bar(labels) => labels.join(", "),
+					Anchor::X(x) => format!("x = {x}")
+				};
+				ui.label(format!("📝 {} ({place})", annotation.text));
+				if ui.small_button("❌").on_hover_text("Remove").clicked() {
+					removed = Some(idx);
+				}
+			});
+		}
+		if let Some(idx) = removed {
+			self.settings.annotations.remove(idx);
+		}
+
+		ui.text_edit_singleline(&mut self.note_input.0)
+			.on_hover_text("e.g. 'moved countries here'");
+		let selected = self.selected.and_then(|idx| self.bars.get(idx));
+		let mut anchor = None;
+		ui.horizontal(|ui| {
+			let writing = !self.note_input.0.trim().is_empty();
+			if ui
+				.add_enabled(
+					writing && selected.is_some(),
+					Button::new("On the selected bar")
+				)
+				.clicked()
+			{
+				anchor = selected.map(|bar| Anchor::Bar(bar.labels.clone()));
+			}
+			if ui.add_enabled(writing, Button::new("At")).clicked() {
+				anchor = Some(Anchor::X(self.note_input.1));
+			}
+			ui.add(DragValue::new(&mut self.note_input.1).speed(0.1));
+		});
+		if let Some(anchor) = anchor {
+			let text = std::mem::take(&mut self.note_input.0);
+			self.annotate(Annotation {
+				text: text.trim().to_string(),
+				anchor
+			});
 		}
-		Self::rebuild_bars(bars, data, settings);
 	}
 
-	fn rebuild_bars(
-		bars: &mut Vec<Bar>,
-		data: &mut [merde::Map<'static>],
-		settings: &mut Settings
-	) {
-		let was_empty = bars.is_empty();
-		sort_arr(data, &*settings);
-		*bars = make_bars(data, &*settings);
+	fn show_sampling(&mut self, ui: &mut egui::Ui) {
+		let mut changed = false;
+		ui.horizontal(|ui| {
+			let mut sampling = self.settings.sample.is_some();
+			if ui
+				.checkbox(&mut sampling, "Sample")
+				.on_hover_text("Only chart some of the rows, for a quicker look at lots of data")
+				.changed()
+			{
+				self.settings.sample = sampling.then(|| {
+					Sample::new(DEFAULT_SAMPLE_ROWS.min(self.data.len()), Sampling::Random)
+				});
+				changed = true;
+			}
+			let Some(sample) = &mut self.settings.sample else {
+				return;
+			};
+
+			changed |= ui
+				.add(
+					DragValue::new(&mut sample.rows)
+						.range(1..=self.data.len().max(1))
+						.suffix(" rows")
+				)
+				.changed();
+
+			// Only strings and bools have few enough values to take the same number of rows from
+			// each of them
+			let strata = self
+				.schema
+				.keys
+				.iter()
+				.filter(|(_, ty)| matches!(ty, ValueType::String | ValueType::Bool))
+				.map(|(key, _)| Sampling::Stratified(key.clone()));
+			let weights = self
+				.schema
+				.keys
+				.iter()
+				.filter(|(_, ty)| matches!(ty, ValueType::I64 | ValueType::U64 | ValueType::Float))
+				.map(|(key, _)| Sampling::Weighted(key.clone()));
+			let method = sample.method.clone();
+			ComboBox::from_id_salt("sampling")
+				.selected_text(sample.method.describe())
+				.show_ui(ui, |ui| {
+					for choice in std::iter::once(Sampling::Random)
+						.chain(strata)
+						.chain(weights)
+					{
+						let text = choice.describe();
+						ui.selectable_value(&mut sample.method, choice, text);
+					}
+				});
+			changed |= sample.method != method;
+
+			if ui
+				.button("🎲")
+				.on_hover_text("Pick a different sample")
+				.clicked()
+			{
+				sample.seed = sample.seed.wrapping_add(1);
+				changed = true;
+			}
+		});
 
-		if was_empty {
-			settings.max_shown = bars.len();
+		if changed {
+			self.rebuild_due = Some(Instant::now() + REBUILD_DEBOUNCE);
 		}
 	}
-}
 
-impl eframe::App for App {
-	fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
-		egui::CentralPanel::default().show(ctx, |ui| {
-			let (id, rect) = ui.allocate_space(ui.available_size());
-			let builder = UiBuilder::new()
-				.id_salt(id)
-				.max_rect(rect)
-				.layout(Layout::left_to_right(Align::Center));
+	#[cfg(feature = "sql")]
+	fn show_sql(&mut self, ui: &mut egui::Ui) {
+		ui.heading("SQL");
 
-			let mut ui = ui.new_child(builder);
+		ui.add(
+			egui::TextEdit::multiline(&mut self.sql.input)
+				.hint_text("SELECT artist, count(*) FROM data GROUP BY 1 ORDER BY 2 DESC")
+				.desired_rows(2)
+		);
+		ui.horizontal(|ui| {
+			if ui.button("▶ Run").clicked() {
+				let sql = self.sql.input.clone();
+				self.sql.err = self.set_query(&sql).err().map(|e| e.to_string());
+			}
+			if self.sql.active.is_some() && ui.button("Clear").clicked() {
+				self.sql.err = self.set_query("").err().map(|e| e.to_string());
+			}
+		});
+
+		if self.sql.active.is_some() {
+			ui.label("The bars are the query's results, so the settings below don't change them");
+		}
+		if let Some(err) = &self.sql.err {
+			ui.colored_label(ui.visuals().error_fg_color, err);
+		}
+	}
+
+	/// What's shown instead of the plot while there's nothing to group by, since it'd otherwise
+	/// just be blank
+	fn show_placeholder(&mut self, ui: &mut egui::Ui) {
+		let (data, schema) = (&self.data, &self.schema);
+		let suggestions = self
+			.suggestions
+			.get_or_insert_with(|| suggest::suggest(data, schema));
 
-			ui.vertical(|ui| {
-				ui.heading("Keys");
-
-				for (key, _) in &self.keys {
-					let selected = self.settings.x_axis.contains(key);
-					if ui.radio(selected, key.deref()).clicked() {
-						if selected {
-							Self::remove_key(
-								key,
-								&mut self.bars,
-								&mut self.data,
-								&mut self.settings
-							);
-						} else {
-							Self::add_key(
-								key.clone(),
-								&mut self.bars,
-								&mut self.data,
-								&mut self.settings
-							);
+		let mut chosen = None;
+		ui.centered_and_justified(|ui| {
+			ui.vertical_centered(|ui| {
+				ui.add_space(ui.available_height() / 3.);
+				ui.label("Select one or more keys to group by");
+				if !suggestions.is_empty() {
+					ui.label("or try one of these:");
+					ui.horizontal_wrapped(|ui| {
+						for suggestion in suggestions.iter() {
+							if ui.button(suggestion.name()).clicked() {
+								chosen = Some(suggestion.clone());
+							}
 						}
+					});
+				}
+			});
+		});
+
+		if let Some(suggestion) = chosen {
+			if let Some(format) = suggestion.date_format {
+				self.settings
+					.date_formats
+					.insert(suggestion.key.clone(), format.to_string());
+			}
+			self.fit_plot = true;
+			self.add_key(suggestion.key);
+		}
+	}
+
+	/// If there's anything in the data that could be put on a map
+	fn mappable(&self) -> bool {
+		!self.schema.country_keys.is_empty() || self.schema.coordinates.is_some()
+	}
+
+	/// Puts the bars on a map if they're of countries, or else the filtered rows if they have
+	/// coordinates
+	fn show_map(&mut self, ui: &mut egui::Ui, id: egui::Id) {
+		let format = self.settings.value_format();
+		let countries = matches!(
+			self.settings.x_axis.as_slice(),
+			[key] if self.schema.country_keys.contains(key)
+		);
+		if countries && !self.querying() {
+			map::show(ui, id, Layer::Countries(&self.bars), format);
+		} else if let Some(coordinates) = &self.schema.coordinates {
+			let cells = match &self.density {
+				Some(cells) => cells,
+				None => {
+					let cells = geo::density(self.filtered_rows(), coordinates);
+					self.density.insert(cells)
+				}
+			};
+			map::show(ui, id, Layer::Density(cells), format);
+		} else {
+			map::show_unplaceable(ui);
+		}
+	}
+
+	fn show_plot(&mut self, ui: &mut egui::Ui, id: egui::Id) {
+		#[cfg(feature = "sql")]
+		let querying = self.sql.active.is_some();
+		#[cfg(not(feature = "sql"))]
+		let querying = false;
+		if self.settings.x_axis.is_empty() && !querying {
+			self.show_placeholder(ui);
+			return;
+		}
+
+		if let Some(drill) = &self.settings.drill {
+			let mut climbed = None;
+			ui.horizontal(|ui| {
+				let path = drill.path.iter().map(String::as_str);
+				for (depth, label) in std::iter::once("All").chain(path).enumerate() {
+					if depth > 0 {
+						ui.label("›");
+					}
+					let crumb = ui
+						.selectable_label(depth == drill.path.len(), label)
+						.on_hover_text(format!("Go back to grouping by {}", drill.keys[depth]));
+					if crumb.clicked() {
+						climbed = Some(depth);
 					}
 				}
+				ui.weak(format!("by {}", drill.key()));
+			});
+			if let Some(depth) = climbed {
+				self.drill_up(depth);
+			}
+		}
 
-				ui.heading("Max shown");
+		if self.bars.is_empty() && self.worker.is_busy() {
+			ui.centered_and_justified(|ui| ui.spinner());
+		}
 
-				let num_bars = self.bars.len();
-				ui.add(egui::Slider::new(
-					&mut self.settings.max_shown,
-					0..=num_bars
-				));
+		if self.settings.chart == ChartKind::Map && !self.bars.is_empty() {
+			self.show_map(ui, id);
+			return;
+		}
 
-				ui.heading("Bounds");
+		if !self.bars.is_empty() {
+			let shown = self.settings.shown_range(self.bars.len());
+			if shown != self.last_shown {
+				self.fit_plot = true;
+				self.last_shown = shown.clone();
+			}
 
-				let mut update_bars = false;
-				for (key, ty) in &self.keys {
-					ComboBox::from_label(&**key)
-						.selected_text(
-							self.settings
-								.bounds
-								.get(key)
-								.map_or("None", ValueBound::ui_descriptor)
-						)
-						.show_ui(ui, |ui| {
-							update_bars |=
-								show_bounds_for_ty(ui, key, *ty, &mut self.settings.bounds)
-						});
+			// Hold off on fitting until the new bars are in, since those are what it should fit to
+			let fit_plot = !self.worker.is_busy() && std::mem::take(&mut self.fit_plot);
+			let value_format = self.settings.value_format();
+			let bars = &self.bars;
+			let label_width = self.settings.label_width;
+			let chart = self.settings.chart;
+			let highlight = ui.visuals().selection.bg_fill;
+			let outlier = ui.visuals().error_fg_color;
+			let faint = ui.visuals().weak_text_color();
+			let trend = self.settings.trend;
+			let moving_average = self.settings.moving_average.unwrap_or_default();
+			let visible = match &mut self.visible {
+				Some(visible) if visible.range == shown => visible,
+				_ => self.visible.insert(VisibleBars::new(
+					&self.bars,
+					shown,
+					&self.pinned,
+					ui.visuals(),
+					&self.settings,
+					self.refreshed.as_ref()
+				))
+			};
+			let selected = self
+				.selected
+				.and_then(|idx| Some((visible.x_of(idx)?, &self.bars[idx])));
+			let diffing = self.settings.diff;
+			let height_of = move |bar: &LabeledBar| match bar.compared {
+				Some(_) if diffing => diff::change(bar),
+				_ => bar.value
+			};
+			let notes = self
+				.settings
+				.annotations
+				.iter()
+				.filter_map(|note| match &note.anchor {
+					Anchor::Bar(labels) => {
+						let idx = self.bars.iter().position(|bar| bar.labels == *labels)?;
+						Some((
+							visible.x_of(idx)?,
+							Some(height_of(&self.bars[idx])),
+							&note.text
+						))
+					}
+					Anchor::X(x) => Some((*x, None, &note.text))
+				})
+				.collect::<Vec<_>>();
+			let heights = self.bars.iter().map(height_of).collect::<Vec<_>>();
+			let references = self
+				.settings
+				.reference_lines
+				.iter()
+				.filter_map(|line| {
+					let height = line.height(&heights)?;
+					Some((height, line.label(height, value_format)))
+				})
+				.collect::<Vec<_>>();
+			let (indices, first) = (visible.indices.clone(), visible.range.start);
+			// While B's bounds are being edited, they're the ones the bars are made from
+			// Periods are named by when they are, oldest first
+			let [a, b] = COMPARE_COLORS;
+			let series = match &self.settings.periods {
+				Some(periods) => [(periods.previous(), a), (periods.latest.clone(), b)],
+				None if self.editing_b => [("B".to_string(), b), ("A".to_string(), a)],
+				None => [("A".to_string(), a), ("B".to_string(), b)]
+			};
+
+			// Scrolling over the plot walks through the bars instead of panning the plot, since
+			// the shown bars are what the user will normally want to move through
+			// The plot's memory is kept under `id` itself, so that it can be found again when
+			// drawing it off screen
+			let mut plot = Plot::new(id).id(id).allow_scroll(false);
+			if visible.pairs.is_some() || visible.stacks.is_some() {
+				plot = plot.legend(Legend::default());
+			}
+			let plot = plot
+				.x_axis_formatter(move |mark, _| {
+					// Each bar sits at an integer argument, so label those with their names
+					if mark.value.fract() != 0. || mark.value < 0. {
+						return String::new();
+					}
+					(mark.value as usize)
+						.checked_sub(first)
+						.and_then(|pos| bars.get(*indices.get(pos)?))
+						.map(|bar| truncate(&bar.short_name(), label_width).into_owned())
+						.unwrap_or_default()
+				})
+				.y_axis_formatter(move |mark, _| value_format.format(mark.value))
+				.show(ui, |ui| {
+					if fit_plot {
+						ui.set_auto_bounds(Vec2b::TRUE);
+					}
+
+					let [left, _] = ui.plot_bounds().min();
+					for (height, label) in &references {
+						ui.hline(
+							HLine::new(*height)
+								.color(REFERENCE_COLOR)
+								.style(LineStyle::dashed_loose())
+								.allow_hover(false)
+						);
+						ui.text(
+							Text::new(PlotPoint::new(left, *height), format!(" {label}"))
+								.color(REFERENCE_COLOR)
+								.anchor(Align2::LEFT_BOTTOM)
+								.allow_hover(false)
+						);
+					}
+
+					// Notes on a bar sit just above it, and ones between bars at the top
+					let top = ui.plot_bounds().max()[1];
+					for (x, height, text) in &notes {
+						ui.vline(
+							VLine::new(*x)
+								.color(outlier)
+								.style(LineStyle::dashed_loose())
+								.allow_hover(false)
+						);
+						let (y, anchor) = match height {
+							Some(height) => (*height, Align2::LEFT_BOTTOM),
+							None => (top, Align2::LEFT_TOP)
+						};
+						ui.text(
+							Text::new(PlotPoint::new(*x, y), format!(" {text}"))
+								.color(outlier)
+								.anchor(anchor)
+								.allow_hover(false)
+						);
+					}
+
+					match chart {
+						ChartKind::Bars => {
+							let names = &visible.names;
+							let Some((bars, compared)) = &visible.pairs else {
+								match &visible.stacks {
+									Some(stacks) =>
+										for (label, color, segments) in stacks {
+											ui.bar_chart(
+												BarChart::new(segments.clone())
+													.name(label)
+													.color(*color)
+													.element_formatter(bar_tooltip(
+														names.clone(),
+														first,
+														Some(label.clone()),
+														value_format
+													))
+											);
+										},
+									None => ui.bar_chart(
+										BarChart::new(visible.bars.clone()).element_formatter(
+											bar_tooltip(names.clone(), first, None, value_format)
+										)
+									)
+								}
+								if !visible.ghosts.is_empty() {
+									ui.bar_chart(
+										BarChart::new(
+											visible
+												.ghosts
+												.iter()
+												.map(|ghost| {
+													ghost
+														.clone()
+														.fill(Color32::TRANSPARENT)
+														.stroke(Stroke::new(1.5, faint))
+												})
+												.collect()
+										)
+										.allow_hover(false)
+									);
+								}
+								if let Some((x, bar)) = selected {
+									// Hovering is left to the bar underneath, which has its name.
+									// Stacked bars are only outlined, so that their segments can
+									// still be told apart.
+									let bar = Bar::new(x, height_of(bar));
+									let bar = match visible.stacks {
+										Some(_) => bar
+											.fill(Color32::TRANSPARENT)
+											.stroke(Stroke::new(2., highlight)),
+										None => bar.fill(highlight)
+									};
+									ui.bar_chart(BarChart::new(vec![bar]).allow_hover(false));
+								}
+								if let Some(average) = &visible.average {
+									ui.line(average_line(average.clone(), moving_average));
+								}
+								return;
+							};
+
+							// Behind the pair, since it'd hide one of them otherwise
+							if let Some((x, bar)) = selected {
+								let height = bar.value.max(bar.compared.unwrap_or(0.));
+								ui.bar_chart(
+									BarChart::new(vec![
+										Bar::new(x, height).width(PAIR_WIDTH * 2.5).fill(highlight),
+									])
+									.allow_hover(false)
+								);
+							}
+							for (bars, (name, color)) in [bars, compared].into_iter().zip(series) {
+								ui.bar_chart(
+									BarChart::new(bars.clone())
+										.name(&name)
+										.color(color)
+										.element_formatter(bar_tooltip(
+											names.clone(),
+											first,
+											Some(name),
+											value_format
+										))
+								);
+							}
+						}
+						// Maps are drawn by `map::show` instead
+						ChartKind::Map => (),
+						ChartKind::Line | ChartKind::Points => {
+							// If the plot's zoomed in, only some of the points are on screen, so
+							// there's room for more of them than there are pixels
+							let shown_width = ui.plot_bounds().width().max(f64::EPSILON);
+							let pixels = f64::from(ui.response().rect.width())
+								* visible.bars.len() as f64
+								/ shown_width;
+							let points = visible.points(pixels.ceil() as usize);
 
-					if let Some(bound) = self.settings.bounds.get_mut(key) {
-						update_bars |= show_bounds_configurations(bound, ui);
+							// Compared points are drawn where the bar would be, not next to it
+							let compared = visible.pairs.as_ref().map(|(_, compared)| {
+								compared
+									.iter()
+									.map(|bar| [bar.argument - PAIR_WIDTH / 2., bar.value])
+									.collect::<Vec<_>>()
+							});
+							let [(first_name, first_color), (second_name, second_color)] = series;
+							if chart == ChartKind::Line {
+								let line = Line::new(points);
+								ui.line(match &compared {
+									Some(_) => line.name(first_name).color(first_color),
+									None => line
+								});
+								if let Some(compared) = compared {
+									ui.line(
+										Line::new(compared).name(second_name).color(second_color)
+									);
+								}
+							} else {
+								let points = Points::new(points).radius(3.);
+								ui.points(match &compared {
+									Some(_) => points.name(first_name).color(first_color),
+									None => points
+								});
+								if let Some(compared) = compared {
+									ui.points(
+										Points::new(compared)
+											.radius(3.)
+											.name(second_name)
+											.color(second_color)
+									);
+								}
+							}
+							if let Some(fit) = visible.trend.filter(|_| trend) {
+								let (start, end) =
+									(first as f64, (first + visible.bars.len() - 1) as f64);
+								// Its name is what's shown when hovering over it
+								ui.line(
+									Line::new(vec![[start, fit.at(start)], [end, fit.at(end)]])
+										.name(format!(
+											"Trend: {} per bar, R² = {:.2}",
+											value_format.format(fit.slope),
+											fit.r_squared
+										))
+										.color(faint)
+										.style(LineStyle::dashed_loose())
+								);
+							}
+							if let Some(average) = &visible.average {
+								ui.line(average_line(average.clone(), moving_average));
+							}
+							if !visible.ghosts.is_empty() {
+								ui.line(
+									Line::new(
+										visible
+											.ghosts
+											.iter()
+											.map(|ghost| [ghost.argument, ghost.value])
+											.collect::<Vec<_>>()
+									)
+									.name("Before the refresh")
+									.color(faint)
+									.style(LineStyle::dotted_dense())
+								);
+							}
+							if !visible.outlying.is_empty() {
+								ui.points(
+									Points::new(visible.outlying.clone())
+										.radius(5.)
+										.color(outlier)
+										.allow_hover(false)
+								);
+							}
+							if let Some((x, bar)) = selected {
+								ui.points(
+									Points::new(vec![[x, height_of(bar)]])
+										.radius(6.)
+										.color(highlight)
+										.allow_hover(false)
+								);
+							}
+						}
+					}
+				});
+
+			#[cfg(feature = "native")]
+			let has_plays = song::has_plays(&self.schema);
+			let mut action = None;
+			plot.response.context_menu(|ui| {
+				for (label, choice) in [
+					("Export image", PlotAction::ExportImage),
+					("Export PNG…", PlotAction::ExportPng),
+					("Copy data", PlotAction::CopyData),
+					("Clear filters", PlotAction::ClearFilters),
+					("Reset zoom", PlotAction::ResetZoom)
+				] {
+					if ui.button(label).clicked() {
+						action = Some(choice);
+						ui.close_menu();
 					}
 				}
 
-				if update_bars {
-					Self::rebuild_bars(&mut self.bars, &mut self.data, &mut self.settings);
+				#[cfg(feature = "native")]
+				if has_plays && ui.button("Export listens for ListenBrainz").clicked() {
+					action = Some(PlotAction::ExportListens);
+					ui.close_menu();
 				}
 			});
 
-			if !self.bars.is_empty() {
-				Plot::new(id).show(&mut ui, |ui| {
-					let bars = self.bars[..self.settings.max_shown.min(self.bars.len())].to_vec();
-					ui.set_auto_bounds(Vec2b::TRUE);
-					ui.bar_chart(BarChart::new(bars))
-				});
+			if let Some(action) = action.or_else(|| self.plot_action.take()) {
+				self.handle_plot_action(action, ui.ctx(), plot.response.rect);
+			}
+
+			// Clicking a bar selects it, the same as walking to it with the arrow keys, unless
+			// there's a key below it to drill down to
+			if plot.response.clicked()
+				&& let Some(pos) = plot.response.interact_pointer_pos()
+			{
+				let x = plot.transform.value_from_position(pos).x.round();
+				if let Some(idx) = self.visible.as_ref().and_then(|visible| visible.bar_at(x)) {
+					match &self.settings.drill {
+						Some(drill) if drill.can_descend() && !self.querying() => {
+							let label = self.bars[idx].labels[0].clone();
+							self.drill_down(label);
+						}
+						_ => self.selected = Some(idx)
+					}
+				}
+			}
+
+			#[cfg(feature = "enrich")]
+			if let Some(pos) = plot.response.hover_pos()
+				&& chart == ChartKind::Bars
+				&& song::has_covers(&self.schema, &self.settings)
+			{
+				let point = plot.transform.value_from_position(pos);
+				let idx = point.x.round();
+				// Only while the pointer's actually on a bar, which is half as wide as the gap
+				// between them
+				if let Some(bar) = ((point.x - idx).abs() <= 0.25)
+					.then(|| self.visible.as_ref()?.bar_at(idx))
+					.flatten()
+					.and_then(|idx| self.bars.get(idx))
+					.filter(|bar| point.y.clamp(bar.value.min(0.), bar.value.max(0.)) == point.y)
+				{
+					plot.response.clone().on_hover_ui_at_pointer(|ui| {
+						self.covers.show(ui, bar, &self.data, &self.settings);
+					});
+				}
+			}
+
+			if plot.response.hovered() {
+				let scroll = ui.input(|i| i.raw_scroll_delta);
+				let delta = if scroll.x == 0. { -scroll.y } else { -scroll.x };
+				if delta != 0. {
+					let bars = delta.signum() as isize * SCROLL_STEP;
+					self.settings.scroll_by(bars, self.bars.len());
+				}
+			}
+
+			if self.worker.is_busy() {
+				let corner = plot.response.rect.right_top() + vec2(-24., 4.);
+				ui.put(
+					Rect::from_min_size(corner, Vec2::splat(20.)),
+					Spinner::new()
+				);
+			}
+		}
+	}
+}
+
+/// The line of a moving average over `window` bars
+fn average_line(points: Vec<[f64; 2]>, window: usize) -> Line {
+	Line::new(points)
+		.name(format!("{window}-bar moving average"))
+		.color(AVERAGE_COLOR)
+		.width(2.)
+}
+
+type BarTooltip = Box<dyn Fn(&Bar, &BarChart) -> String>;
+
+/// What's shown when hovering over a bar drawn at its index (since `first`, which `names` starts
+/// at), along with which of the compared sets of bars it's from if it's one of a pair
+fn bar_tooltip(
+	names: Arc<[String]>,
+	first: usize,
+	series: Option<String>,
+	format: NumberFormat
+) -> BarTooltip {
+	Box::new(move |bar, _| {
+		// Bars that are one of a pair are off to the side of their index
+		let name = (bar.argument.round() as usize)
+			.checked_sub(first)
+			.and_then(|pos| names.get(pos))
+			.map_or("", String::as_str);
+		let value = format.format(bar.value);
+		match &series {
+			Some(series) => format!("{name}\n{series}: {value}"),
+			None => format!("{name}\n{value}")
+		}
+	})
+}
+
+/// Everything about the bar (the `idx`th of `bars`) that's been selected with the arrow keys.
+/// Returns true if it should be pinned (or unpinned, if it already is).
+#[must_use]
+fn show_selected(
+	ui: &mut egui::Ui,
+	bar: &LabeledBar,
+	idx: usize,
+	bars: &[LabeledBar],
+	format: NumberFormat,
+	pinned: bool,
+	refreshed: Option<&FxHashMap<Vec<String>, f64>>
+) -> bool {
+	ui.heading("Selected bar");
+	ui.label(&bar.name);
+	let toggle_pin = ui
+		.selectable_label(pinned, "📌 Pinned")
+		.on_hover_text("Keep showing this bar, even when it's not on the page")
+		.clicked();
+	ui.separator();
+
+	egui::Grid::new("selected_grid")
+		.striped(true)
+		.show(ui, |ui| {
+			ui.label("Value");
+			ui.label(format.format(bar.value));
+			ui.end_row();
+
+			if let Some(compared) = bar.compared {
+				ui.label("Compared");
+				ui.label(format.format(compared));
+				ui.end_row();
+
+				ui.label("Change");
+				ui.label(format!(
+					"{} ({})",
+					format.format(diff::change(bar)),
+					diff::describe(bar)
+				));
+				ui.end_row();
+			}
+
+			for segment in bar.segments.iter().rev() {
+				ui.label(&segment.label);
+				ui.label(format!(
+					"{} ({} rows)",
+					format.format(segment.value),
+					segment.rows
+				));
+				ui.end_row();
+			}
+
+			if bar.outliers > 0 {
+				ui.label("Outlying rows");
+				ui.label(bar.outliers.to_string());
+				ui.end_row();
+			}
+
+			if let Some(refreshed) = refreshed {
+				ui.label("Since the refresh");
+				ui.label(refresh_change(
+					refreshed.get(&bar.labels),
+					bar.value,
+					format
+				));
+				ui.end_row();
+			}
+
+			ui.label("Position");
+			ui.label(format!("{} of {}", idx + 1, bars.len()));
+			ui.end_row();
+
+			// Rates don't add up to anything, so they don't have a share of it either
+			if format != NumberFormat::Percent {
+				let total = bars.iter().map(|bar| bar.value).sum::<f64>();
+				if total != 0. {
+					ui.label("Share");
+					ui.label(NumberFormat::Percent.format(bar.value / total));
+					ui.end_row();
+				}
+			}
+		});
+
+	ui.small("← and → move between bars, Esc deselects");
+	toggle_pin
+}
+
+/// How a bar's height changed when the data was refreshed, given how tall it was before then (if
+/// it was there at all)
+fn refresh_change(before: Option<&f64>, now: f64, format: NumberFormat) -> String {
+	match before {
+		Some(before) if *before == now => "Unchanged since the refresh".to_string(),
+		Some(before) => {
+			let sign = if now > *before { "+" } else { "-" };
+			format!(
+				"{} before the refresh ({sign}{})",
+				format.format(*before),
+				format.format((now - before).abs())
+			)
+		}
+		None => "New since the refresh".to_string()
+	}
+}
+
+/// What's been asked for in [`show_outliers`]
+enum OutlierChange {
+	/// Look for them with a different test (or stop looking)
+	Test(Option<OutlierTest>),
+	/// Bound them out
+	Exclude
+}
+
+/// The outliers of a key whose stats are being shown, if they're being looked for
+#[must_use]
+fn show_outliers(
+	ui: &mut egui::Ui,
+	test: Option<OutlierTest>,
+	outliers: Option<&Outliers>,
+	format: NumberFormat
+) -> Option<OutlierChange> {
+	let mut change = None;
+	let mut chosen = test;
+	ComboBox::from_label("Outliers")
+		.selected_text(chosen.map_or("Not flagged", OutlierTest::ui_descriptor))
+		.show_ui(ui, |ui| {
+			ui.selectable_value(&mut chosen, None, "Not flagged");
+			for test in OutlierTest::ALL {
+				ui.selectable_value(&mut chosen, Some(test), test.ui_descriptor());
 			}
 		});
+	if chosen != test {
+		change = Some(OutlierChange::Test(chosen));
+	}
+
+	if let Some(outliers) = outliers {
+		ui.label(format!(
+			"{} rows are outside {} to {}",
+			outliers.rows,
+			format.format(outliers.fences.low),
+			format.format(outliers.fences.high)
+		));
+		if ui
+			.add_enabled(outliers.rows > 0, Button::new("Exclude them"))
+			.clicked()
+		{
+			change = Some(OutlierChange::Exclude);
+		}
 	}
+	change
+}
+
+/// Lets the user pick a type to treat the key as instead of what it was loaded as, if there's one
+/// it can be treated as
+#[must_use]
+fn show_key_type(
+	ui: &mut egui::Ui,
+	loaded: ValueType,
+	current: Option<KeyType>
+) -> Option<Option<KeyType>> {
+	let options = KeyType::ALL
+		.into_iter()
+		.filter(|ty| ty.applies_to(loaded))
+		.collect::<Vec<_>>();
+	if options.is_empty() {
+		return None;
+	}
+
+	let mut chosen = current;
+	ComboBox::from_label("Treat as")
+		.selected_text(chosen.map_or("As loaded", KeyType::ui_descriptor))
+		.show_ui(ui, |ui| {
+			ui.selectable_value(&mut chosen, None, "As loaded");
+			for ty in options {
+				ui.selectable_value(&mut chosen, Some(ty), ty.ui_descriptor());
+			}
+		});
+	(chosen != current).then_some(chosen)
+}
+
+/// Lets a string key be split up into the things it lists, returning what it's now split by if
+/// that changed
+fn show_delimiter(ui: &mut egui::Ui, current: Option<&str>) -> Option<Option<String>> {
+	let mut splitting = current.is_some();
+	let mut delimiter = current.unwrap_or(DEFAULT_DELIMITER).to_string();
+	ui.horizontal(|ui| {
+		let toggled = ui
+			.checkbox(&mut splitting, "Split on")
+			.on_hover_text(
+				"Count each row towards a bar for every thing that it lists, like both 'rock' and \
+				 'indie' for 'rock, indie'"
+			)
+			.changed();
+		let edited = ui
+			.add_enabled(
+				splitting,
+				egui::TextEdit::singleline(&mut delimiter).desired_width(40.)
+			)
+			.changed();
+		// An empty delimiter would split values into each of their characters, so it's only
+		// taken once something's been typed in its place
+		match (toggled, splitting) {
+			(true, false) => Some(None),
+			(true, true) => Some(Some(delimiter)),
+			(false, true) if edited && !delimiter.is_empty() => Some(Some(delimiter)),
+			_ => None
+		}
+	})
+	.inner
+}
+
+fn show_stats(ui: &mut egui::Ui, stats: &KeyStats, format: NumberFormat) {
+	egui::Grid::new("stats_grid").striped(true).show(ui, |ui| {
+		ui.label("Null");
+		ui.label(format!(
+			"{:.1}% ({} of {})",
+			stats.null_percentage(),
+			stats.nulls,
+			stats.rows
+		));
+		ui.end_row();
+
+		match &stats.summary {
+			Summary::Numeric {
+				min,
+				max,
+				mean,
+				median
+			} =>
+				for (name, val) in [
+					("Min", min),
+					("Max", max),
+					("Mean", mean),
+					("Median", median)
+				] {
+					ui.label(name);
+					ui.label(format.format(*val));
+					ui.end_row();
+				},
+			Summary::TopValues(values) =>
+				for (val, count) in values {
+					ui.label(val);
+					ui.label(count.to_string());
+					ui.end_row();
+				},
+			Summary::Nothing => ()
+		}
+	});
 }
 
 #[must_use]
@@ -248,16 +5041,145 @@ fn show_bounds_for_ty(
 	}
 }
 
-fn show_bounds_configurations(bound: &mut ValueBound, ui: &mut egui::Ui) -> bool {
-	fn show_slider_for_range<N: Numeric>(range: &mut Range<N>, ui: &mut egui::Ui) {
-		ui.add(Slider::new(&mut range.start, N::MIN..=range.end));
-		ui.add(Slider::new(&mut range.end, range.start..=N::MAX));
+#[must_use]
+fn show_format_picker(ui: &mut egui::Ui, label: &str, format: &mut NumberFormat) -> bool {
+	let old = *format;
+
+	ui.horizontal(|ui| {
+		ComboBox::from_label(label)
+			.selected_text(format.ui_descriptor())
+			.show_ui(ui, |ui| {
+				for option in NumberFormat::BASE_OPTIONS {
+					// Don't reset the number of decimals if we're already showing fixed decimals
+					let selected = std::mem::discriminant(format) == std::mem::discriminant(option);
+					if ui
+						.selectable_label(selected, option.ui_descriptor())
+						.clicked() && !selected
+					{
+						*format = *option;
+					}
+				}
+			});
+
+		match format {
+			NumberFormat::Fixed(decimals) => {
+				ui.add(DragValue::new(decimals).range(0..=10).suffix(" decimals"));
+			}
+			NumberFormat::Currency(currency) => {
+				ComboBox::from_id_salt((label, "currency"))
+					.selected_text(currency.symbol())
+					.show_ui(ui, |ui| {
+						for option in Currency::ALL {
+							ui.selectable_value(currency, option, option.symbol());
+						}
+					});
+			}
+			_ => ()
+		}
+	});
+
+	*format != old
+}
+
+#[must_use]
+fn show_date_format_picker(
+	ui: &mut egui::Ui,
+	key: &String,
+	date_formats: &mut FxHashMap<String, String>
+) -> bool {
+	let mut current = date_formats.get(key).cloned();
+	let old = current.clone();
+
+	ComboBox::from_label(&**key)
+		.selected_text(current.as_deref().map_or("Raw", |format| {
+			DATE_FORMAT_PRESETS
+				.iter()
+				.find(|(preset, _)| *preset == format)
+				.map_or("Custom", |(_, name)| name)
+		}))
+		.show_ui(ui, |ui| {
+			ui.selectable_value(&mut current, None, "Raw");
+			for (preset, name) in DATE_FORMAT_PRESETS {
+				ui.selectable_value(&mut current, Some((*preset).to_string()), *name);
+			}
+		});
+
+	let mut editing = false;
+	let mut submitted = false;
+	if let Some(format) = &mut current {
+		let resp = ui
+			.text_edit_singleline(format)
+			.on_hover_text("A strftime-style format");
+		editing = resp.has_focus();
+		submitted = resp.lost_focus();
+	}
+
+	let changed = current != old;
+	match current {
+		Some(format) => date_formats.insert(key.clone(), format),
+		None => date_formats.remove(key)
+	};
+
+	// Don't rebuild on every keystroke while a custom format is being typed out
+	submitted || (changed && !editing)
+}
+
+/// How a bound was changed by the user this frame
+enum BoundChange {
+	Unchanged,
+	/// A slider is in the middle of being dragged, so the user probably isn't done with it yet
+	Dragging,
+	Finished
+}
+
+fn show_bounds_configurations(bound: &mut ValueBound, ui: &mut egui::Ui) -> BoundChange {
+	fn show_slider_for_range<N: Numeric>(range: &mut Range<N>, ui: &mut egui::Ui) -> BoundChange {
+		let start = ui.add(Slider::new(&mut range.start, N::MIN..=range.end));
+		let end = ui.add(Slider::new(&mut range.end, range.start..=N::MAX));
+
+		if start.drag_stopped() || end.drag_stopped() {
+			BoundChange::Finished
+		} else if !start.changed() && !end.changed() {
+			BoundChange::Unchanged
+		} else if start.dragged() || end.dragged() {
+			BoundChange::Dragging
+		} else {
+			// Typed in or nudged with the keyboard
+			BoundChange::Finished
+		}
 	}
 
 	match bound {
 		ValueBound::I64(Bound::Range(range)) => show_slider_for_range(range, ui),
 		ValueBound::U64(Bound::Range(range)) => show_slider_for_range(range, ui),
-		ValueBound::F64(Bound::Range(range)) => show_slider_for_range(range, ui),
+		ValueBound::F64(bound, nans) => {
+			let mut change = match bound {
+				Bound::Range(range) => {
+					let before = range.clone();
+					let change = show_slider_for_range(range, ui);
+					// A NaN or infinite end (which can be typed in) would make a range that either
+					// nothing or everything is in
+					if !range.start.is_finite() || !range.end.is_finite() {
+						*range = before;
+					}
+					change
+				}
+				Bound::Specifics { .. } => BoundChange::Unchanged
+			};
+
+			let before = *nans;
+			ComboBox::from_id_salt(ui.next_auto_id())
+				.selected_text(nans.ui_descriptor())
+				.show_ui(ui, |ui| {
+					for choice in Nans::ALL {
+						ui.selectable_value(nans, choice, choice.ui_descriptor());
+					}
+				});
+			if *nans != before {
+				change = BoundChange::Finished;
+			}
+			change
+		}
 		ValueBound::Str { include: _, values } => {
 			let mut to_remove = None;
 			let mut return_rebuild = false;
@@ -286,10 +5208,22 @@ fn show_bounds_configurations(bound: &mut ValueBound, ui: &mut egui::Ui) -> bool
 				values.push(new_val);
 			}
 
-			return return_rebuild;
+			if return_rebuild {
+				BoundChange::Finished
+			} else {
+				BoundChange::Unchanged
+			}
+		}
+		ValueBound::Bool(bools) => {
+			let changed = ui.checkbox(&mut bools.trues, "true").changed()
+				| ui.checkbox(&mut bools.falses, "false").changed()
+				| ui.checkbox(&mut bools.nulls, "null").changed();
+			if changed {
+				BoundChange::Finished
+			} else {
+				BoundChange::Unchanged
+			}
 		}
-		_ => ()
+		_ => BoundChange::Unchanged
 	}
-
-	false
 }
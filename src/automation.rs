@@ -0,0 +1,281 @@
+//! Driving the app from other programs, so that scripts can set up a chart without anyone
+//! clicking through it and tests can go through the whole pipeline without a window. Requests can
+//! be handed to [`handle`] directly, or sent as JSON-RPC 2.0 (one call per line) through
+//! [`respond`], which is what `spart rpc` and `spart --rpc` do with stdin and stdout.
+
+use std::{error::Error, fmt, path::PathBuf};
+
+use serde::{Deserialize, de};
+use serde_json::{Value, json};
+use spart_core::{
+	render::{self, RenderOptions},
+	schema::KeyType,
+	settings::{Aggregation, Anchor, Annotation, ValueBound},
+	song
+};
+
+use crate::App;
+
+/// Something to do to the app. Each variant's name (in snake_case) is the JSON-RPC method for it,
+/// and its fields are the method's params.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Request {
+	/// Groups the bars by these keys, in order, instead of whichever they were grouped by
+	SetXAxis {
+		keys: Vec<String>
+	},
+	/// Applies a filter like `country=US|CA`, just like `--filter` does
+	AddFilter {
+		filter: String
+	},
+	/// Bounds `key` to `bound` (in the same shape that settings are stored in), or stops bounding
+	/// it if that's missing
+	SetBound {
+		key: String,
+		bound: Option<ValueBound>
+	},
+	ClearFilters {},
+	/// Treats a key's values as `categorical` or `numeric` instead of what they were loaded as,
+	/// or as that again if `treat_as` is missing
+	SetKeyType {
+		key: String,
+		treat_as: Option<KeyType>
+	},
+	/// Only charts rows that this Rhai expression is true for, or all of them if it's empty
+	SetPredicate {
+		expr: String
+	},
+	/// What each bar's height comes from - the number of rows in it if `key` is missing, or else
+	/// the sum of `key`'s values if it's a numeric key (of integers or floats), or the share of
+	/// them that are true if it's a boolean key. A numeric key's values can be combined by
+	/// `function` (`sum`, `mean`, `median`, `min`, or `max`) instead, which stays as it was if
+	/// that's missing.
+	SetAggregation {
+		key: Option<String>,
+		function: Option<Aggregation>
+	},
+	/// The bars, tallest first, once any changes that are still being made are done
+	Bars {
+		limit: Option<usize>
+	},
+	Settings {},
+	/// A token for the current view, which `open_view` (or pasting it into the window) shows
+	/// again
+	View {},
+	/// Replaces all the settings with the ones in a token from `view`
+	OpenView {
+		token: String
+	},
+	/// Adds a note to the chart, anchored to the bar with these labels or to a position along
+	/// the x-axis (in the same shape that settings are stored in)
+	Annotate {
+		text: String,
+		anchor: Anchor
+	},
+	/// Draws the chart to an SVG file, like `spart render` does
+	Export {
+		path: PathBuf,
+		width: Option<u32>,
+		height: Option<u32>,
+		limit: Option<usize>
+	}
+}
+
+/// Does what `request` asks, returning whatever it asked for (or `null` if it just changed
+/// something)
+pub fn handle(app: &mut App, request: Request) -> Result<Value, Box<dyn Error>> {
+	match request {
+		Request::SetXAxis { keys } => app.set_x_axis(keys)?,
+		Request::AddFilter { filter } => app.add_filter(&filter)?,
+		Request::SetBound { key, bound } => app.set_bound(key, bound)?,
+		Request::ClearFilters {} => app.clear_filters(),
+		Request::SetKeyType { key, treat_as } => app.set_key_type(&key, treat_as)?,
+		Request::SetPredicate { expr } => app.set_predicate(&expr)?,
+		Request::SetAggregation { key, function } => {
+			match key {
+				None => app.set_listening_time(false),
+				Some(key) if key == song::MS_PLAYED => app.set_listening_time(true),
+				// Numbers are summed up, and booleans have the share of them that's true
+				Some(key) => app
+					.set_sum(Some(&key))
+					.or_else(|_| app.set_rate(Some(&key)))
+					.map_err(|_| format!("'{key}' is neither a numeric key nor a boolean one"))?
+			}
+			if let Some(function) = function {
+				app.set_aggregate(function);
+			}
+		}
+		Request::Bars { limit } => {
+			app.wait_for_bars();
+			let bars = app
+				.bars
+				.iter()
+				.take(limit.unwrap_or(usize::MAX))
+				.map(|bar| match bar.compared {
+					Some(compared) =>
+						json!({ "labels": bar.labels, "value": bar.value, "compared": compared }),
+					None => json!({ "labels": bar.labels, "value": bar.value })
+				})
+				.collect();
+			return Ok(Value::Array(bars));
+		}
+		Request::Settings {} => return Ok(serde_json::to_value(app.settings())?),
+		Request::View {} => return Ok(Value::String(app.view_token())),
+		Request::OpenView { token } => app.open_view(&token)?,
+		Request::Annotate { text, anchor } => app.annotate(Annotation { text, anchor }),
+		Request::Export {
+			path,
+			width,
+			height,
+			limit
+		} => {
+			app.wait_for_bars();
+			let bars = &app.bars[..limit.unwrap_or(usize::MAX).min(app.bars.len())];
+			let svg = render::to_svg(bars, &RenderOptions {
+				width: width.unwrap_or(1200),
+				height: height.unwrap_or(600),
+				value_format: app.settings().value_format(),
+				label_width: app.settings().label_width,
+				annotations: app.settings().annotations.clone(),
+				reference_lines: app.settings().reference_lines.clone()
+			});
+			std::fs::write(&path, svg)
+				.map_err(|e| format!("Couldn't write to {}: {e}", path.display()))?;
+		}
+	}
+	Ok(Value::Null)
+}
+
+/// The envelope that each request comes in
+#[derive(Deserialize)]
+struct Call {
+	/// Missing for notifications, which don't get a response
+	id: Option<Value>,
+	method: String,
+	params: Option<Value>
+}
+
+// The error codes that JSON-RPC reserves
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// For when the request made sense, but couldn't be done
+const FAILED: i64 = -32000;
+
+/// Answers a single line of JSON-RPC with `handler`, returning the line to send back (if the call
+/// wasn't a notification)
+pub fn respond(
+	line: &str,
+	handler: impl FnOnce(Request) -> Result<Value, Box<dyn Error>>
+) -> Option<String> {
+	let call = match serde_json::from_str::<Call>(line) {
+		Ok(call) => call,
+		Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, e.to_string()))
+	};
+
+	let request = json!({
+		"method": call.method,
+		// Every request takes an object, so that any of its params can be left out
+		"params": call.params.unwrap_or_else(|| json!({}))
+	});
+	// serde's message for unknown methods lists the known ones
+	let result = Request::deserialize(request)
+		.map_err(|e| match is_method(&call.method) {
+			true => (INVALID_PARAMS, e.to_string()),
+			false => (METHOD_NOT_FOUND, e.to_string())
+		})
+		.and_then(|request| handler(request).map_err(|e| (FAILED, e.to_string())));
+
+	let id = call.id?;
+	Some(match result {
+		Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+		Err((code, message)) => error_response(id, code, message)
+	})
+}
+
+/// If `method` is one of [`Request`]'s, whatever its params are
+fn is_method(method: &str) -> bool {
+	/// Just says whether deserializing failed because of which method it was
+	#[derive(Debug)]
+	struct Unknown(bool);
+
+	impl de::Error for Unknown {
+		fn custom<T: fmt::Display>(_: T) -> Self {
+			Self(false)
+		}
+
+		fn unknown_variant(_: &str, _: &'static [&'static str]) -> Self {
+			Self(true)
+		}
+	}
+
+	impl fmt::Display for Unknown {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			f.write_str(if self.0 {
+				"unknown method"
+			} else {
+				"bad params"
+			})
+		}
+	}
+
+	impl Error for Unknown {}
+
+	// Without any params, the method's the only thing that can be unknown
+	let call = de::value::MapDeserializer::<_, Unknown>::new(std::iter::once(("method", method)));
+	!matches!(Request::deserialize(call), Err(Unknown(true)))
+}
+
+fn error_response(id: Value, code: i64, message: String) -> String {
+	json!({
+		"jsonrpc": "2.0",
+		"id": id,
+		"error": { "code": code, "message": message }
+	})
+	.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The code of the error that `line` is answered with, if it is with one
+	fn error_code(line: &str) -> Option<i64> {
+		let response = respond(line, |_| Ok(Value::Null))?;
+		serde_json::from_str::<Value>(&response).ok()?["error"]["code"].as_i64()
+	}
+
+	#[test]
+	fn methods() {
+		assert!(is_method("set_x_axis"));
+		assert!(is_method("clear_filters"));
+		assert!(!is_method("set_y_axis"));
+	}
+
+	#[test]
+	fn error_codes() {
+		let unknown = r#"{"jsonrpc": "2.0", "id": 1, "method": "set_y_axis", "params": {}}"#;
+		assert_eq!(error_code(unknown), Some(METHOD_NOT_FOUND));
+		let bad_params = r#"{"id": 1, "method": "set_x_axis", "params": {"keys": 3}}"#;
+		assert_eq!(error_code(bad_params), Some(INVALID_PARAMS));
+		let missing_params = r#"{"jsonrpc": "2.0", "id": 1, "method": "add_filter"}"#;
+		assert_eq!(error_code(missing_params), Some(INVALID_PARAMS));
+		assert_eq!(error_code("{"), Some(PARSE_ERROR));
+
+		let fine = r#"{"jsonrpc": "2.0", "id": 1, "method": "set_x_axis", "params": {"keys": []}}"#;
+		assert_eq!(error_code(fine), None);
+		let failed = respond(fine, |_| Err("nope".into())).unwrap();
+		assert_eq!(
+			serde_json::from_str::<Value>(&failed).unwrap()["error"]["code"],
+			FAILED
+		);
+	}
+
+	#[test]
+	fn notifications() {
+		// Without an id, nothing's sent back, even if it didn't work
+		let unknown = r#"{"jsonrpc": "2.0", "method": "set_y_axis"}"#;
+		assert_eq!(respond(unknown, |_| Ok(Value::Null)), None);
+	}
+}
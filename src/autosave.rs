@@ -0,0 +1,32 @@
+//! Keeping a copy of the session (which files are being looked at, and the view they're on) every
+//! so often, so that a long session of building up filters isn't lost if spart crashes or is
+//! closed by accident. The next time it's started without any files, it offers to pick the
+//! session back up. It's kept in `session.json` next to the recent files (see [`crate::recent`]).
+
+use std::path::PathBuf;
+
+use web_time::Duration;
+
+use crate::{
+	recent::{RecentFile, state_dir},
+	session::{self, SessionErr}
+};
+
+/// How often the session's saved, if it's changed since it last was
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+fn path() -> Option<PathBuf> {
+	state_dir().map(|dir| dir.join("session.json"))
+}
+
+/// The session that was last saved, if there is one
+pub fn load() -> Option<RecentFile> {
+	session::load(&path()?).ok()
+}
+
+pub fn save(session: &RecentFile) -> Result<(), SessionErr> {
+	match path() {
+		Some(path) => session::save(&path, session),
+		None => Ok(())
+	}
+}
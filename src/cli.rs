@@ -0,0 +1,640 @@
+use std::{io::IsTerminal, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+#[cfg(feature = "enrich")]
+use spart::Config;
+use spart::{config::Theme, demo};
+use spart_core::{
+	bins::Bins,
+	coloring::ColorRule,
+	diff,
+	feed::Address,
+	format::Unit,
+	load::{Format, LoadErr, LoadOptions, Progress, Strings, load_files},
+	memory::Rows,
+	periods::Period,
+	sample::{Sample, Sampling},
+	script::{Expression, ScriptErr, Scripts, parse_column},
+	settings::{Aggregation, Anchor, Annotation, DEFAULT_DELIMITER, ReferenceLine, TieBreak},
+	song::Preset
+};
+#[cfg(feature = "enrich")]
+use spart_core::{enrich::Enrichment, lastfm::LastFm, musicbrainz::MusicBrainz, song};
+
+/// Chart JSON data formatted as an array of flat objects
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+	#[command(subcommand)]
+	pub command: Option<Command>,
+
+	/// Used when no subcommand is given, so that `spart data.json` just opens the GUI
+	#[command(flatten)]
+	pub gui: GuiArgs,
+
+	/// Read defaults from this file instead of ~/.config/spart/config.toml
+	#[arg(long, global = true, value_name = "PATH")]
+	pub config: Option<PathBuf>
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+	/// Explore the data in a window (the default if no subcommand is given)
+	Gui(GuiArgs),
+	/// Draw a chart of the data to an image file, without opening a window
+	Render(RenderArgs),
+	/// Print the bars that would be charted as tab-separated values
+	Query(QueryArgs),
+	/// Print the keys found in the data and what types their values are
+	Schema(DataArgs),
+	/// Write the rows that get through the filters to a file as a ListenBrainz export, which it
+	/// can import
+	Export(ExportArgs),
+	/// Write a year in review of a listening history (its top artists and tracks, how long was
+	/// spent listening, and when) to an HTML page
+	Review(ReviewArgs),
+	/// Answer JSON-RPC requests about the data (one per line) from stdin, without opening a
+	/// window
+	Rpc(RpcArgs)
+}
+
+#[derive(Args)]
+pub struct DataArgs {
+	/// The files to load, or `-` to read from stdin
+	pub paths: Vec<String>,
+
+	/// Parse every file in this format instead of guessing from each one's extension and contents
+	#[arg(long, value_name = "FORMAT")]
+	pub format: Option<Format>,
+
+	/// Use some generated data instead of loading any files
+	#[arg(long, conflicts_with = "paths")]
+	pub demo: bool,
+
+	/// Point into the files' text instead of copying strings out of it. Uses much less memory for
+	/// data that's mostly strings, but each file's text is kept whole for as long as its rows are.
+	#[arg(long, conflicts_with = "demo")]
+	pub zero_copy: bool
+}
+
+#[derive(Args)]
+pub struct ViewArgs {
+	/// Start from a view that was shared from spart (with "Copy view" in the window or
+	/// `query --share`), which the rest of these options then add to
+	#[arg(long, value_name = "TOKEN")]
+	pub view: Option<String>,
+
+	/// Group the bars by this key; can be given multiple times to group by several keys at once
+	#[arg(long = "x", value_name = "KEY")]
+	pub x_axis: Vec<String>,
+
+	/// Split each bar into a segment for each value of this key, stacked on top of each other,
+	/// like `--x month --stack-by artist`
+	#[arg(long, value_name = "KEY")]
+	pub stack_by: Option<String>,
+
+	/// Treat this numeric key as names instead of numbers (like IDs or HTTP status codes), so that
+	/// it's filtered and grouped by its exact values; can be given multiple times
+	#[arg(long, value_name = "KEY")]
+	pub categorical: Vec<String>,
+
+	/// Parse this string key's values as numbers (leaving the ones that aren't as null), so that
+	/// it can be filtered by ranges; can be given multiple times
+	#[arg(long, value_name = "KEY")]
+	pub numeric: Vec<String>,
+
+	/// Only include rows matching a filter like `country=US|CA` or `ms_played>30000`; can be
+	/// given multiple times
+	#[arg(long = "filter", value_name = "FILTER")]
+	pub filters: Vec<String>,
+
+	/// Compare the bars against the ones for another set of filters, which start out the same as
+	/// the `--filter` ones and are narrowed down by these; can be given multiple times
+	#[arg(long = "compare", value_name = "FILTER")]
+	pub compare: Vec<String>,
+
+	/// Chart how much each bar changed between the data and this file (like the same export from
+	/// a year later), with categories that are only in one of them marked as new or gone
+	#[arg(long, value_name = "PATH", conflicts_with = "compare")]
+	pub diff: Option<String>,
+
+	/// Compare each bar's rows in the latest period of a timestamp key against the period before
+	/// it, like `ts=month` for month over month [possible periods: day, week, month, year]
+	#[arg(long, value_name = "KEY=PERIOD", conflicts_with_all = ["compare", "diff"])]
+	pub periods: Option<String>,
+
+	/// Only include rows that this Rhai expression is true for, like `ms_played > 30000`. Every
+	/// key is available as a variable, or through `row["some key"]`.
+	#[arg(long = "where", value_name = "EXPR")]
+	pub predicate: Option<String>,
+
+	/// Leave out the rows whose values of this (numeric) key are outliers, going by the
+	/// interquartile range; can be given multiple times
+	#[arg(long, value_name = "KEY")]
+	pub exclude_outliers: Vec<String>,
+
+	/// Only chart a random sample of this many of the rows that get through the filters
+	#[arg(long, value_name = "ROWS")]
+	pub sample: Option<usize>,
+
+	/// Take the same number of rows for the sample from each value of this key, so that rare
+	/// ones aren't left out
+	#[arg(
+		long,
+		value_name = "KEY",
+		requires = "sample",
+		conflicts_with = "weight"
+	)]
+	pub stratify: Option<String>,
+
+	/// Pick rows for the sample in proportion to their value of this key
+	#[arg(long, value_name = "KEY", requires = "sample")]
+	pub weight: Option<String>,
+
+	/// Add a key whose value in each row comes from a Rhai expression, like
+	/// `minutes=ms_played / 60000`; can be given multiple times
+	#[arg(long = "column", value_name = "NAME=EXPR")]
+	pub columns: Vec<String>,
+
+	/// Add `KEY_subnet`, `KEY_version`, and `KEY_private` keys from the IP addresses in a key, so
+	/// that they can be grouped by; can be given multiple times
+	#[arg(long, value_name = "KEY")]
+	pub ip: Vec<String>,
+
+	/// Add `KEY_host` and `KEY_path` keys from the URLs (or paths) in a key, along with a
+	/// `KEY_query_NAME` key for each of its most common query parameters; can be given multiple
+	/// times
+	#[arg(long, value_name = "KEY")]
+	pub url: Vec<String>,
+
+	/// Add `KEY_browser`, `KEY_os`, and `KEY_device` keys from the user agents in a key; can be
+	/// given multiple times
+	#[arg(long, value_name = "KEY")]
+	pub user_agent: Vec<String>,
+
+	/// Drill down through the `--x` keys one at a time, into the bar with this label of each of
+	/// them in turn; can be given multiple times, to go further down
+	#[arg(long, value_name = "LABEL")]
+	pub drill: Vec<String>,
+
+	/// Start from one of the usual charts of listening history, which `--x` then adds to
+	/// [possible values: top-artists, top-tracks, listens-per-month, hour-of-day]
+	#[arg(long, value_name = "PRESET")]
+	pub preset: Option<Preset>,
+
+	/// Make each bar as tall as the share of its rows where this boolean key is true, like
+	/// `skipped` for a skip rate
+	#[arg(long, value_name = "KEY", conflicts_with = "sum")]
+	pub rate: Option<String>,
+
+	/// Make each bar as tall as the total of this numeric key over its rows, instead of how many
+	/// rows it has
+	#[arg(long, value_name = "KEY")]
+	pub sum: Option<String>,
+
+	/// How the values of `--sum`'s key are combined into each bar's height, like `mean` for how
+	/// big each endpoint's responses were on average
+	#[arg(long, value_name = "sum|mean|median|min|max")]
+	pub aggregate: Option<Aggregation>,
+
+	/// Merge the groups that make up less than this percent of the total into one "Other" bar
+	#[arg(long, value_name = "PERCENT")]
+	pub merge_below: Option<f64>,
+
+	/// How to order bars that are just as tall as each other: alphabetically by label, naturally
+	/// (with numbers ordered by value), or by which was made up of more rows
+	#[arg(long, value_name = "alphabetical|natural|rows")]
+	pub ties: Option<TieBreak>,
+
+	/// Put a note on the chart, like `Mitski=saw them live` (or `@12=text` to put it 12 bars along
+	/// instead of on a particular one), where a bar grouped by several keys is named by its labels
+	/// joined with `, `; can be given multiple times
+	#[arg(long = "annotate", value_name = "BAR=TEXT")]
+	pub annotations: Vec<String>,
+
+	/// Draw a horizontal line across the chart at this value, or at the mean or median of the
+	/// bars; can be given multiple times
+	#[arg(long = "reference", value_name = "VALUE|mean|median")]
+	pub reference_lines: Vec<ReferenceLine>,
+
+	/// Color the bars that a rule like `value > 100 -> red` or `name contains live -> orange`
+	/// matches; can be given multiple times, and the first rule that matches wins
+	#[arg(long = "color", value_name = "RULE")]
+	pub color_rules: Vec<ColorRule>,
+
+	/// Format a key's values as being in a unit, like `size=bytes` or `price=usd` [possible units:
+	/// bytes, ms, usd, eur, gbp, jpy]; can be given multiple times
+	#[arg(long = "unit", value_name = "KEY=UNIT")]
+	pub units: Vec<String>,
+
+	/// Split a key's values into the things they list, like `genres` (on `, `) or `tags=;`, so
+	/// that each row counts towards a bar for every one of them; can be given multiple times
+	#[arg(long = "split", value_name = "KEY[=DELIMITER]")]
+	pub splits: Vec<String>,
+
+	/// Make a histogram of a numeric key, by bucketing its values into this many bins that are
+	/// all as wide, like `price=20`; can be given multiple times
+	#[arg(long = "bins", value_name = "KEY=COUNT")]
+	pub bins: Vec<String>,
+
+	/// Make a histogram of a numeric key with bins this wide, like `price=5` for 0–5, 5–10, and so
+	/// on; can be given multiple times
+	#[arg(long = "bin-width", value_name = "KEY=WIDTH")]
+	pub bin_widths: Vec<String>,
+
+	/// Name the bars like `{track} — {artist}`, where each key in braces is replaced by the bar's
+	/// value of it
+	#[arg(long, value_name = "TEMPLATE")]
+	pub label: Option<String>,
+
+	/// A Rhai script defining functions that `--where` and `--column` can use
+	#[arg(long, value_name = "PATH")]
+	pub script: Option<PathBuf>,
+
+	/// Chart the results of this SQL query (over a table called `data`) instead, where the last
+	/// column is how tall each bar is and the rest are what it's labelled with
+	#[cfg(feature = "sql")]
+	#[arg(long, value_name = "QUERY")]
+	pub sql: Option<String>,
+
+	/// Add keys by looking up each value of KEY (or `artist` if it's left out) online; can be
+	/// given multiple times [possible services: lastfm, musicbrainz]
+	#[cfg(feature = "enrich")]
+	#[arg(long = "enrich", value_name = "SERVICE[=KEY]")]
+	pub enrich: Vec<String>
+}
+
+impl ViewArgs {
+	/// What the expressions in `--where` and `--column` are compiled with
+	pub fn scripts(&self) -> Result<Scripts, Box<dyn std::error::Error>> {
+		let Some(path) = &self.script else {
+			return Ok(Scripts::default());
+		};
+		let library = std::fs::read_to_string(path)
+			.map_err(|e| format!("Couldn't read the script at {}: {e}", path.display()))?;
+		Ok(Scripts::with_library(&library)?)
+	}
+
+	/// Adds the keys that `--enrich` asks for to `data`, returning what it was enriched with so
+	/// that it can be done again if the data's reloaded
+	#[cfg(feature = "enrich")]
+	pub fn enrich(
+		&self,
+		data: &mut [merde::Map<'static>],
+		config: &Config
+	) -> Result<Vec<Enrichment>, Box<dyn std::error::Error>> {
+		let mut enrichments = Vec::new();
+		for requested in &self.enrich {
+			let (service, key) = requested
+				.split_once('=')
+				.unwrap_or((requested, song::ARTIST));
+			let enrichment = match service {
+				"lastfm" => {
+					let lastfm = config.lastfm.as_ref().ok_or(
+						"--enrich lastfm needs an API key, set as `api_key` under `[lastfm]` in \
+						 the config file"
+					)?;
+					Enrichment::LastFm {
+						service: LastFm::new(lastfm.api_key.clone()),
+						key: key.to_string()
+					}
+				}
+				"musicbrainz" => Enrichment::MusicBrainz {
+					service: MusicBrainz::new(),
+					key: key.to_string()
+				},
+				_ =>
+					return Err(format!(
+						"Unknown service '{service}' (expected lastfm or musicbrainz)"
+					)
+					.into()),
+			};
+
+			let mut reported = false;
+			enrichment.apply(data, &mut |done, total| {
+				if std::io::stderr().is_terminal() {
+					eprint!("\r\x1b[KLooking up {key} on {service}: {done}/{total}");
+					reported = true;
+				}
+			})?;
+			if reported {
+				eprint!("\r\x1b[K");
+			}
+			enrichments.push(enrichment);
+		}
+		Ok(enrichments)
+	}
+
+	/// Adds the rows of the file from `--diff` to `data`, marking which of them each row is from,
+	/// returning if there was one
+	pub fn add_diffed(&self, data: &mut Rows, options: &LoadOptions) -> Result<bool, LoadErr> {
+		let Some(path) = &self.diff else {
+			return Ok(false);
+		};
+
+		let mut reported = false;
+		let mut after = load_files(
+			std::slice::from_ref(path),
+			None,
+			Strings::Copy,
+			options,
+			&mut |path, progress| {
+				report_progress(path, progress);
+				reported = true;
+			}
+		)?;
+		if reported && std::io::stderr().is_terminal() {
+			eprint!("\r\x1b[K");
+		}
+
+		diff::mark(data, diff::BEFORE);
+		diff::mark(&mut after, diff::AFTER);
+		data.append(after);
+		Ok(true)
+	}
+
+	/// The notes that `--annotate` asks for
+	pub fn annotations(&self) -> Result<Vec<Annotation>, String> {
+		self.annotations
+			.iter()
+			.map(|given| {
+				let (bar, text) = given
+					.split_once('=')
+					.ok_or_else(|| format!("'{given}' doesn't say what the note is (BAR=TEXT)"))?;
+				let anchor = match bar.strip_prefix('@') {
+					Some(x) => Anchor::X(
+						x.parse()
+							.map_err(|_| format!("'{x}' isn't a position along the x-axis"))?
+					),
+					None => Anchor::Bar(bar.split(", ").map(str::to_string).collect())
+				};
+				Ok(Annotation {
+					text: text.to_string(),
+					anchor
+				})
+			})
+			.collect()
+	}
+
+	/// The units that `--unit` gives keys
+	pub fn units(&self) -> Result<Vec<(String, Unit)>, String> {
+		self.units
+			.iter()
+			.map(|given| {
+				let (key, unit) = given
+					.split_once('=')
+					.ok_or_else(|| format!("'{given}' doesn't say what the unit is (KEY=UNIT)"))?;
+				Ok((key.to_string(), unit.parse()?))
+			})
+			.collect()
+	}
+
+	/// The keys that `--bins` makes histograms of, and how many bins each has
+	pub fn bins(&self) -> Result<Vec<(String, usize)>, String> {
+		self.bins
+			.iter()
+			.map(|given| {
+				let (key, count) = given
+					.split_once('=')
+					.ok_or_else(|| format!("'{given}' doesn't say how many bins (KEY=COUNT)"))?;
+				match count.parse() {
+					Ok(count) if count > 0 => Ok((key.to_string(), count)),
+					_ => Err(format!("'{count}' isn't a number of bins"))
+				}
+			})
+			.collect()
+	}
+
+	/// The keys that `--bin-width` makes histograms of, and how wide each one's bins are
+	pub fn bin_widths(&self) -> Result<Vec<(String, Bins)>, String> {
+		self.bin_widths
+			.iter()
+			.map(|given| {
+				let (key, width) = given.split_once('=').ok_or_else(|| {
+					format!("'{given}' doesn't say how wide the bins are (KEY=WIDTH)")
+				})?;
+				match width.parse().ok().and_then(Bins::aligned) {
+					Some(bins) => Ok((key.to_string(), bins)),
+					None => Err(format!("'{width}' isn't a width that bins can be"))
+				}
+			})
+			.collect()
+	}
+
+	/// The key and period that `--periods` compares, if it's given
+	pub fn periods(&self) -> Result<Option<(&str, Period)>, String> {
+		let Some(given) = &self.periods else {
+			return Ok(None);
+		};
+		let (key, period) = given
+			.split_once('=')
+			.ok_or_else(|| format!("'{given}' doesn't say what the period is (KEY=PERIOD)"))?;
+		Ok(Some((key, period.parse()?)))
+	}
+
+	/// The keys that `--split` splits up, and what by
+	pub fn splits(&self) -> impl Iterator<Item = (String, String)> {
+		self.splits.iter().map(|given| match given.split_once('=') {
+			Some((key, delimiter)) => (key.to_string(), delimiter.to_string()),
+			None => (given.clone(), DEFAULT_DELIMITER.to_string())
+		})
+	}
+
+	/// The sample that `--sample` asks for, if any
+	pub fn sample(&self) -> Option<Sample> {
+		let method = match (&self.stratify, &self.weight) {
+			(Some(key), _) => Sampling::Stratified(key.clone()),
+			(None, Some(key)) => Sampling::Weighted(key.clone()),
+			(None, None) => Sampling::Random
+		};
+		self.sample.map(|rows| Sample::new(rows, method))
+	}
+
+	/// The keys from `--column`, with their compiled expressions
+	pub fn columns(&self, scripts: &Scripts) -> Result<Vec<(String, Expression)>, ScriptErr> {
+		self.columns
+			.iter()
+			.map(|column| {
+				let (name, expr) = parse_column(column)?;
+				Ok((name.to_string(), scripts.compile(expr)?))
+			})
+			.collect()
+	}
+}
+
+#[derive(Args)]
+pub struct GuiArgs {
+	#[command(flatten)]
+	pub data: DataArgs,
+
+	#[command(flatten)]
+	pub view: ViewArgs,
+
+	/// Reload the data whenever any of the files it came from change
+	#[arg(long)]
+	pub watch: bool,
+
+	/// Keep adding rows (JSON objects, one per line) that are sent to this TCP address (like
+	/// 127.0.0.1:9000), Unix socket (like unix:spart.sock, or any path with a / in it), or
+	/// existing FIFO
+	#[arg(long, value_name = "ADDRESS", conflicts_with = "watch")]
+	pub listen: Option<Address>,
+
+	/// Take JSON-RPC requests (one per line) from stdin to change what's charted, answering them
+	/// on stdout
+	#[arg(long)]
+	pub rpc: bool,
+
+	/// Which colors to use, overriding the config file [possible values: system, dark, light]
+	#[arg(long, value_name = "THEME")]
+	pub theme: Option<Theme>,
+
+	/// Open the files of a session that was saved from the window, on the view it was saved with
+	#[arg(long, value_name = "PATH", conflicts_with_all = ["paths", "demo", "view"])]
+	pub session: Option<PathBuf>
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+	#[command(flatten)]
+	pub data: DataArgs,
+
+	#[command(flatten)]
+	pub view: ViewArgs,
+
+	/// Where to write the chart; only SVG files are supported
+	#[arg(short, long, value_name = "PATH")]
+	pub output: PathBuf,
+
+	/// How wide the image should be, in pixels
+	#[arg(long, default_value_t = 1200)]
+	pub width: u32,
+
+	/// How tall the image should be, in pixels
+	#[arg(long, default_value_t = 600)]
+	pub height: u32,
+
+	/// Only draw this many of the largest bars
+	#[arg(long, value_name = "N")]
+	pub limit: Option<usize>,
+
+	/// Count up the rows as they're read instead of loading them all first, so that data bigger
+	/// than memory can be charted. Only works with JSON and JSON lines files.
+	#[arg(long, conflicts_with = "demo")]
+	pub stream: bool
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+	#[command(flatten)]
+	pub data: DataArgs,
+
+	#[command(flatten)]
+	pub view: ViewArgs,
+
+	/// Only print this many of the largest bars
+	#[arg(long, value_name = "N")]
+	pub limit: Option<usize>,
+
+	/// Count up the rows as they're read instead of loading them all first, so that data bigger
+	/// than memory can be charted. Only works with JSON and JSON lines files.
+	#[arg(long, conflicts_with = "demo")]
+	pub stream: bool,
+
+	/// Print a link to the view instead of its bars, which `--view` (or pasting it into the
+	/// window) opens again
+	#[arg(long, conflicts_with = "stream")]
+	pub share: bool
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+	#[command(flatten)]
+	pub data: DataArgs,
+
+	#[command(flatten)]
+	pub view: ViewArgs,
+
+	/// Where to write the listens
+	#[arg(short, long, value_name = "PATH")]
+	pub output: PathBuf
+}
+
+#[derive(Args)]
+pub struct ReviewArgs {
+	#[command(flatten)]
+	pub data: DataArgs,
+
+	#[command(flatten)]
+	pub view: ViewArgs,
+
+	/// Only look at what was played in this year, instead of all of it
+	#[arg(long)]
+	pub year: Option<i32>,
+
+	/// Where to write the page
+	#[arg(short, long, value_name = "PATH")]
+	pub output: PathBuf
+}
+
+#[derive(Args)]
+pub struct RpcArgs {
+	#[command(flatten)]
+	pub data: DataArgs,
+
+	#[command(flatten)]
+	pub view: ViewArgs
+}
+
+impl DataArgs {
+	/// Loads whatever data the user asked for, or `None` if they didn't give us any
+	pub fn load(&self, options: &LoadOptions) -> Result<Option<Rows>, LoadErr> {
+		if self.demo {
+			Ok(Some(demo::generate(demo::DEMO_ROWS).into()))
+		} else if self.paths.is_empty() {
+			Ok(None)
+		} else {
+			let strings = if self.zero_copy {
+				Strings::Borrow
+			} else {
+				Strings::Copy
+			};
+			let mut reported = false;
+			let rows = load_files(
+				&self.paths,
+				self.format,
+				strings,
+				options,
+				&mut |path, progress| {
+					report_progress(path, progress);
+					reported = true;
+				}
+			);
+			if reported && std::io::stderr().is_terminal() {
+				eprint!("\r\x1b[K");
+			}
+			rows.map(Some)
+		}
+	}
+
+	/// If the data is being read from stdin, so nothing else can be
+	pub fn reads_stdin(&self) -> bool {
+		self.paths.iter().any(|path| path == "-")
+	}
+
+	/// Like [`Self::load`], but for modes that have nothing to do without data
+	pub fn load_required(&self, options: &LoadOptions) -> Result<Rows, Box<dyn std::error::Error>> {
+		self.load(options)?
+			.ok_or_else(|| "No data to work with; pass some files or --demo".into())
+	}
+}
+
+/// Shows how far along loading `path` is on stderr, if someone's there to see it
+fn report_progress(path: &str, progress: Progress) {
+	if std::io::stderr().is_terminal() {
+		let percent = (progress.bytes_done * 100)
+			.checked_div(progress.bytes_total)
+			.unwrap_or(100);
+		eprint!("\r\x1b[KLoading {path}: {percent}%");
+	}
+}
@@ -0,0 +1,156 @@
+#[cfg(feature = "native")]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use eframe::egui::ThemePreference;
+use fxhash::FxHashMap;
+use serde::Deserialize;
+use spart_core::{
+	format::Unit,
+	load::{FormatOptions, LoadOptions},
+	settings::{Aggregation, ChartKind, Settings}
+};
+
+/// Defaults that are read from `~/.config/spart/config.toml` (or wherever `--config` points),
+/// and which flags given on the command line take precedence over.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	pub theme: Theme,
+	/// How many bars are shown at once, or all of them if this isn't set
+	pub page_size: Option<usize>,
+	/// How many characters of each bar's name are shown along the x-axis
+	pub label_width: Option<usize>,
+	/// Whether to start off reloading the data whenever the files it came from change
+	pub watch: bool,
+	/// How the bars are drawn to begin with
+	pub chart: Option<ChartKind>,
+	/// How the values of the summed key are combined to begin with
+	pub aggregation: Option<Aggregation>,
+	/// What some keys are measured in, like `size = "bytes"`, so that their values are formatted
+	/// to match
+	pub units: FxHashMap<String, Unit>,
+	/// How many seconds a track has to play for before it's no longer counted as skipped
+	pub skip_threshold: Option<u64>,
+	/// Arrays with at most this many elements are exploded into a key for each of them (like
+	/// `tags.0`), instead of the data being rejected for having them
+	pub explode_arrays: Option<usize>,
+	/// How files in the formats that can be laid out more than one way are read, like
+	/// `[formats.csv]` with `delimiter = ";"` and `header = false`
+	pub formats: FormatOptions,
+	/// What's needed to look artists up with `--enrich lastfm`
+	pub lastfm: Option<LastFm>
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LastFm {
+	/// From https://www.last.fm/api/account/create
+	pub api_key: String
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+	#[default]
+	System,
+	Dark,
+	Light
+}
+
+impl From<Theme> for ThemePreference {
+	fn from(theme: Theme) -> Self {
+		match theme {
+			Theme::System => Self::System,
+			Theme::Dark => Self::Dark,
+			Theme::Light => Self::Light
+		}
+	}
+}
+
+impl std::str::FromStr for Theme {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"system" => Ok(Self::System),
+			"dark" => Ok(Self::Dark),
+			"light" => Ok(Self::Light),
+			_ => Err(format!(
+				"Unknown theme '{s}' (expected system, dark, or light)"
+			))
+		}
+	}
+}
+
+#[cfg(feature = "native")]
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigErr {
+	#[error("Couldn't read the config file at {path}: {source}")]
+	Io {
+		path: PathBuf,
+		source: std::io::Error
+	},
+	#[error("Couldn't parse the config file at {path}: {source}")]
+	Parse {
+		path: PathBuf,
+		source: toml::de::Error
+	}
+}
+
+impl Config {
+	/// The settings that a chart should start out with
+	pub fn settings(&self) -> Settings<'static> {
+		let default = Settings::default();
+		Settings {
+			label_width: self.label_width.unwrap_or(default.label_width),
+			default_page_size: self.page_size,
+			chart: self.chart.unwrap_or(default.chart),
+			aggregation: self.aggregation.unwrap_or(default.aggregation),
+			formats: self
+				.units
+				.iter()
+				.map(|(key, unit)| (key.clone(), unit.number_format()))
+				.collect(),
+			..default
+		}
+	}
+
+	/// How data should be read out of files
+	pub fn load_options(&self) -> LoadOptions {
+		let default = LoadOptions::default();
+		LoadOptions {
+			formats: self.formats.clone(),
+			explode_limit: self.explode_arrays.unwrap_or(default.explode_limit),
+			skip_threshold: self
+				.skip_threshold
+				.map_or(default.skip_threshold, Duration::from_secs)
+		}
+	}
+
+	/// Where the config file lives if the user hasn't said otherwise: `$XDG_CONFIG_HOME/spart`,
+	/// falling back to `~/.config/spart`
+	#[cfg(feature = "native")]
+	pub fn default_path() -> Option<PathBuf> {
+		let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+			.filter(|dir| !dir.is_empty())
+			.map(PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+		Some(config_dir.join("spart").join("config.toml"))
+	}
+
+	/// Reads the config at `path`. If the user didn't ask for a specific file (`required` is
+	/// false), a missing file just means everything should be left at its default.
+	#[cfg(feature = "native")]
+	pub fn load(path: PathBuf, required: bool) -> Result<Self, ConfigErr> {
+		let contents = match std::fs::read_to_string(&path) {
+			Ok(contents) => contents,
+			Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound =>
+				return Ok(Self::default()),
+			Err(source) => return Err(ConfigErr::Io { path, source })
+		};
+
+		toml::from_str(&contents).map_err(|source| ConfigErr::Parse { path, source })
+	}
+}
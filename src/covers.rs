@@ -0,0 +1,153 @@
+//! Album covers for the tooltips of bars that are tracks, albums, or artists. They're fetched on
+//! another thread the first time each bar is hovered over, since that can take a couple seconds.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use fxhash::FxHashMap;
+use merde::{CowStr, Value};
+use spart_core::{bars::LabeledBar, coverart::CoverArt, settings::Settings, song, worker::Data};
+
+/// How big the covers are drawn, in points
+const COVER_SIZE: f32 = 96.;
+
+enum Cover {
+	Loading,
+	Missing,
+	Failed(String),
+	Loaded(TextureHandle)
+}
+
+struct Request {
+	/// What the cover is kept under in [`Covers::shown`]
+	id: String,
+	/// The artist and album of the cover
+	release: (String, String),
+	ctx: egui::Context
+}
+
+pub struct Covers {
+	shown: FxHashMap<String, Cover>,
+	requests: Option<Sender<Request>>,
+	arrived: (Sender<Fetched>, Receiver<Fetched>)
+}
+
+/// What comes back from the thread: the cover's id, and the cover (or why it couldn't be gotten)
+type Fetched = (String, Result<Option<ColorImage>, String>);
+
+impl Covers {
+	pub fn new() -> Self {
+		Self {
+			shown: FxHashMap::default(),
+			requests: None,
+			arrived: mpsc::channel()
+		}
+	}
+
+	/// Shows the cover of the album that `bar` is (or is from), asking for it first if it hasn't
+	/// been yet
+	pub fn show(&mut self, ui: &mut egui::Ui, bar: &LabeledBar, data: &Data, settings: &Settings) {
+		while let Ok((id, cover)) = self.arrived.1.try_recv() {
+			let cover = match cover {
+				Ok(Some(image)) =>
+					Cover::Loaded(ui.ctx().load_texture(&id, image, TextureOptions::LINEAR)),
+				Ok(None) => Cover::Missing,
+				Err(e) => Cover::Failed(e)
+			};
+			self.shown.insert(id, cover);
+		}
+
+		// The same name can mean different things depending on what the bars are grouped by (e.g.
+		// self-titled albums)
+		let id = format!("{}\n{}", settings.x_axis.join("\n"), bar.name);
+		if !self.shown.contains_key(&id) {
+			let cover = match release_of(bar, data, settings) {
+				Some(release) => {
+					let request = Request {
+						id: id.clone(),
+						release,
+						ctx: ui.ctx().clone()
+					};
+					match self.requests().send(request) {
+						Ok(()) => Cover::Loading,
+						Err(_) => Cover::Failed("the cover fetcher stopped".to_string())
+					}
+				}
+				None => Cover::Missing
+			};
+			self.shown.insert(id.clone(), cover);
+		}
+
+		match &self.shown[&id] {
+			Cover::Loading => {
+				ui.horizontal(|ui| {
+					ui.spinner();
+					ui.label("Finding the cover…");
+				});
+			}
+			Cover::Missing => {
+				ui.weak("No cover found");
+			}
+			Cover::Failed(e) => {
+				ui.weak(format!("Couldn't get the cover: {e}"));
+			}
+			Cover::Loaded(texture) => {
+				ui.add(egui::Image::new(texture).max_size(egui::Vec2::splat(COVER_SIZE)));
+			}
+		}
+	}
+
+	fn requests(&mut self) -> &Sender<Request> {
+		self.requests.get_or_insert_with(|| {
+			let (requests, received) = mpsc::channel::<Request>();
+			let arrived = self.arrived.0.clone();
+			std::thread::Builder::new()
+				.name("spart-covers".to_string())
+				.spawn(move || {
+					let art = CoverArt::new();
+					for request in received {
+						let (artist, album) = &request.release;
+						let cover = art
+							.front(artist, album)
+							.map(|bytes| bytes.and_then(|bytes| decode(&bytes)))
+							.map_err(|e| e.to_string());
+						if arrived.send((request.id, cover)).is_err() {
+							break;
+						}
+						request.ctx.request_repaint();
+					}
+				})
+				.expect("Couldn't start the thread that fetches covers");
+			requests
+		})
+	}
+}
+
+impl Default for Covers {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The artist and album of the first row that's part of `bar`
+fn release_of(bar: &LabeledBar, data: &Data, settings: &Settings) -> Option<(String, String)> {
+	let string = |row: &merde::Map, key| match row.get(&CowStr::from(key)) {
+		Some(Value::Str(s)) => Some(s.to_string()),
+		_ => None
+	};
+
+	let row = data.iter().find(|row| {
+		settings.x_axis.iter().zip(&bar.labels).all(|(key, label)| {
+			row.get(&CowStr::from(key.as_str())).is_some_and(|value| {
+				settings.label_for(key, &settings.grouping_value(key, value)) == *label
+			})
+		})
+	})?;
+	Some((string(row, song::ARTIST)?, string(row, song::ALBUM)?))
+}
+
+fn decode(bytes: &[u8]) -> Option<ColorImage> {
+	let image = image::load_from_memory(bytes).ok()?.to_rgba8();
+	let size = [image.width() as usize, image.height() as usize];
+	Some(ColorImage::from_rgba_unmultiplied(size, &image))
+}
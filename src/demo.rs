@@ -0,0 +1,108 @@
+use chrono::DateTime;
+use merde::{Map, Value};
+
+/// How many rows of demo data are generated
+pub const DEMO_ROWS: usize = 5000;
+
+const ARTISTS: &[(&str, &[&str])] = &[
+	("The Lumineers", &["Ho Hey", "Ophelia", "Cleopatra"]),
+	("Phoebe Bridgers", &[
+		"Motion Sickness",
+		"Kyoto",
+		"Garden Song"
+	]),
+	("Khruangbin", &[
+		"Maria También",
+		"Evan Finds the Third Room"
+	]),
+	("Big Thief", &["Not", "Paul", "Masterpiece", "Shark Smile"]),
+	("Car Seat Headrest", &[
+		"Drunk Drivers/Killer Whales",
+		"Bodys"
+	]),
+	("Alvvays", &["Archie, Marry Me", "Dreams Tonite"]),
+	("Japanese Breakfast", &[
+		"Be Sweet",
+		"Everybody Wants to Love You"
+	]),
+	("Mitski", &[
+		"Nobody",
+		"Washing Machine Heart",
+		"First Love / Late Spring"
+	])
+];
+
+const COUNTRIES: &[&str] = &["US", "CA", "GB", "DE", "SE", "JP"];
+const PLATFORMS: &[&str] = &["android", "ios", "web_player", "windows", "osx"];
+
+/// 2019-01-01T00:00:00Z
+const START_TS: i64 = 1_546_300_800;
+/// How far past `START_TS` the generated timestamps go - about four years
+const TS_SPAN: u64 = 4 * 365 * 24 * 60 * 60;
+
+/// Generates a fake listening history that looks a bit like a Spotify export, with a mix of every
+/// kind of value that we support so that every feature has something to chew on.
+pub fn generate(rows: usize) -> Vec<Map<'static>> {
+	let mut rng = Rng(0x5eed_cafe_f00d_d00d);
+
+	(0..rows)
+		.map(|_| {
+			// Skew towards the first few artists so the charts have some shape to them
+			let artist_idx = (rng.next_f64().powi(2) * ARTISTS.len() as f64) as usize;
+			let (artist, tracks) = ARTISTS[artist_idx];
+			let track = rng.pick(tracks);
+
+			let secs = START_TS + (rng.next() % TS_SPAN) as i64;
+			let ts = DateTime::from_timestamp(secs, 0)
+				.unwrap_or_default()
+				.format("%Y-%m-%dT%H:%M:%SZ")
+				.to_string();
+
+			let skipped = rng.next_f64() < 0.2;
+			let ms_played = if skipped {
+				rng.next() % 30_000
+			} else {
+				120_000 + rng.next() % 180_000
+			};
+
+			// Not every row has every piece of info, just like real data
+			let rating = if rng.next_f64() < 0.1 {
+				Value::Null
+			} else {
+				Value::from((rng.next_f64() * 50.).round() / 10.)
+			};
+
+			Map::new()
+				.with("ts", ts)
+				.with("artist", artist)
+				.with("track", track)
+				.with("country", rng.pick(COUNTRIES))
+				.with("platform", rng.pick(PLATFORMS))
+				.with("ms_played", ms_played)
+				.with("skipped", skipped)
+				.with("shuffle", rng.next_f64() < 0.5)
+				.with("rating", rating)
+		})
+		.collect()
+}
+
+/// A tiny xorshift generator - we want the same demo data every time, and don't need anything
+/// cryptographically interesting.
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	fn next_f64(&mut self) -> f64 {
+		(self.next() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	fn pick<'a>(&mut self, options: &[&'a str]) -> &'a str {
+		options[self.next() as usize % options.len()]
+	}
+}
@@ -0,0 +1,55 @@
+//! Loading data that was handed to us through the window rather than the command line, like by
+//! dropping files onto it.
+
+use eframe::egui::DroppedFile;
+use spart_core::load::{Format, LoadErr, LoadOptions, load_text};
+
+/// Loads all the rows in `files`, concatenated together, along with the format that each file
+/// turned out to be in. Depending on the platform, each file comes with either its contents or a
+/// path to read them from.
+pub fn load(
+	files: &[DroppedFile],
+	options: &LoadOptions
+) -> Result<(Vec<merde::Map<'static>>, Vec<Format>), LoadErr> {
+	let (mut rows, mut formats) = (Vec::new(), Vec::new());
+
+	for file in files {
+		let name = file
+			.path
+			.as_ref()
+			.map_or_else(|| file.name.clone(), |path| path.display().to_string());
+
+		let (file_rows, format) = match (&file.bytes, &file.path) {
+			(Some(bytes), _) => load_bytes(&name, bytes, options)?,
+			#[cfg(feature = "native")]
+			(None, Some(path)) => {
+				let bytes = std::fs::read(path).map_err(|source| LoadErr::Io {
+					path: name.clone(),
+					source
+				})?;
+				load_bytes(&name, &bytes, options)?
+			}
+			_ => continue
+		};
+		rows.extend(file_rows);
+		formats.push(format);
+	}
+
+	Ok((rows, formats))
+}
+
+/// Parses the contents of the file called `name`, guessing its format from the name or contents
+/// (which is returned too)
+pub fn load_bytes(
+	name: &str,
+	bytes: &[u8],
+	options: &LoadOptions
+) -> Result<(Vec<merde::Map<'static>>, Format), LoadErr> {
+	let text = std::str::from_utf8(bytes).map_err(|e| LoadErr::Io {
+		path: name.to_string(),
+		source: std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+	})?;
+	let format =
+		Format::detect(name, text).ok_or_else(|| LoadErr::Undetectable(name.to_string()))?;
+	Ok((load_text(name, text, Some(format), options)?, format))
+}
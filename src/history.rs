@@ -0,0 +1,175 @@
+//! A timeline of the different views that have been charted this session, each with a little
+//! picture of its bars, so that any of them can be gone back to with a click. Unlike going back a
+//! step at a time, nothing's lost by going back to an old view and changing it from there.
+
+use eframe::egui::{self, Rect, Sense, pos2, vec2};
+use spart_core::{
+	bars::LabeledBar,
+	settings::{Settings, YAxisKey},
+	share
+};
+
+/// How many views are remembered before the oldest ones are forgotten
+const MAX_VIEWS: usize = 50;
+
+/// How many of a view's first bars are drawn in its thumbnail
+const THUMBNAIL_BARS: usize = 24;
+
+const THUMBNAIL_SIZE: egui::Vec2 = vec2(64., 24.);
+
+#[derive(Default)]
+pub struct History {
+	/// Oldest first
+	views: Vec<View>
+}
+
+struct View {
+	/// What it's of, like `artist (sum of ms_played, 2 filters)`
+	title: String,
+	/// What it's opened from (see [`share`])
+	token: String,
+	/// What makes it a different view from the others (see [`identity`])
+	identity: String,
+	/// The heights of its first bars, out of the tallest of them
+	heights: Vec<f32>
+}
+
+impl History {
+	/// Remembers the view that `settings` made `bars` with, as the newest one. If it's been
+	/// charted before, it's just moved up from where it was.
+	pub fn record(&mut self, settings: &Settings, bars: &[LabeledBar], token: String) {
+		let identity = identity(settings);
+		self.views.retain(|view| view.identity != identity);
+
+		let heights = bars
+			.iter()
+			.take(THUMBNAIL_BARS)
+			.map(|bar| bar.value.abs())
+			.collect::<Vec<_>>();
+		let tallest = heights.iter().copied().fold(0., f64::max);
+		self.views.push(View {
+			title: title(settings),
+			token,
+			identity,
+			heights: heights
+				.into_iter()
+				.map(|height| match tallest {
+					0. => 0.,
+					_ => (height / tallest) as f32
+				})
+				.collect()
+		});
+
+		if self.views.len() > MAX_VIEWS {
+			self.views.remove(0);
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.views.len()
+	}
+
+	/// Lists the views, newest first, returning the token of the one that was clicked on if one
+	/// was. The one that `current` would make is highlighted.
+	pub fn show(&self, ui: &mut egui::Ui, current: &Settings) -> Option<String> {
+		let current = identity(current);
+		let mut chosen = None;
+		for view in self.views.iter().rev() {
+			ui.horizontal(|ui| {
+				let thumbnail = thumbnail(ui, &view.heights);
+				let label = ui.selectable_label(view.identity == current, &view.title);
+				if thumbnail.clicked() || label.clicked() {
+					chosen = Some(view.token.clone());
+				}
+			});
+		}
+		chosen
+	}
+}
+
+/// The parts of a view's settings that decide what its bars are, and so what makes it different
+/// from other views, as opposed to how they're drawn or which of them are being looked at
+fn identity(settings: &Settings) -> String {
+	share::encode(&Settings {
+		bounds: settings.bounds.clone(),
+		compare: settings.compare.clone(),
+		diff: settings.diff,
+		periods: settings.periods.clone(),
+		drill: settings.drill.clone(),
+		predicate: settings.predicate.clone(),
+		sample: settings.sample.clone(),
+		key_types: settings.key_types.clone(),
+		x_axis: settings.x_axis.clone(),
+		stack_by: settings.stack_by.clone(),
+		y_axis: settings.y_axis.clone(),
+		aggregation: settings.aggregation,
+		merge_below: settings.merge_below,
+		date_formats: settings.date_formats.clone(),
+		bins: settings.bins.clone(),
+		delimiters: settings.delimiters.clone(),
+		..Settings::default()
+	})
+}
+
+fn title(settings: &Settings) -> String {
+	let mut details = Vec::new();
+	match settings.y_axis {
+		YAxisKey::Count => (),
+		YAxisKey::Key(key) => details.push(format!(
+			"{} of {key}",
+			settings.aggregation.ui_descriptor().to_lowercase()
+		)),
+		YAxisKey::Rate(key) => details.push(format!("rate of {key}"))
+	}
+	let filters = settings.bounds.len() + usize::from(settings.predicate.is_some());
+	match filters {
+		0 => (),
+		1 => details.push("1 filter".to_string()),
+		filters => details.push(format!("{filters} filters"))
+	}
+	if let Some(key) = &settings.stack_by {
+		details.push(format!("stacked by {key}"));
+	}
+	if settings.compare.is_some() {
+		details.push("compared".to_string());
+	}
+	if let Some(periods) = &settings.periods {
+		details.push(periods.period.ui_descriptor().to_lowercase());
+	}
+	if let Some(drill) = settings
+		.drill
+		.as_ref()
+		.filter(|drill| !drill.path.is_empty())
+	{
+		details.push(format!("in {}", drill.path.join(" › ")));
+	}
+
+	let keys = settings.x_axis.join(", ");
+	match details.is_empty() {
+		true => keys,
+		false => format!("{keys} ({})", details.join(", "))
+	}
+}
+
+/// A tiny bar chart of `heights`, which can be clicked on
+fn thumbnail(ui: &mut egui::Ui, heights: &[f32]) -> egui::Response {
+	let (rect, response) = ui.allocate_exact_size(THUMBNAIL_SIZE, Sense::click());
+	let painter = ui.painter_at(rect);
+	painter.rect_filled(rect, 2., ui.visuals().extreme_bg_color);
+
+	let width = rect.width() / THUMBNAIL_BARS as f32;
+	let color = ui.visuals().selection.bg_fill;
+	for (idx, height) in heights.iter().enumerate() {
+		let left = rect.left() + idx as f32 * width;
+		let top = rect.bottom() - height * rect.height();
+		painter.rect_filled(
+			Rect::from_min_max(
+				pos2(left + width * 0.1, top),
+				pos2(left + width * 0.9, rect.bottom())
+			),
+			0.,
+			color
+		);
+	}
+	response.on_hover_text("Go back to this view")
+}
@@ -0,0 +1,29 @@
+//! spart's GUI, which the `spart` binary runs as its own window, but which can also be embedded in
+//! other eframe apps with [`SpartPanel`].
+
+pub mod app;
+#[cfg(feature = "native")]
+pub mod automation;
+#[cfg(feature = "native")]
+mod autosave;
+pub mod config;
+#[cfg(feature = "enrich")]
+mod covers;
+pub mod demo;
+mod dropped;
+mod history;
+mod map;
+mod offscreen;
+mod palette;
+mod panel;
+#[cfg(feature = "native")]
+pub mod recent;
+pub mod screen;
+#[cfg(feature = "native")]
+pub mod session;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+pub use app::App;
+pub use config::Config;
+pub use panel::{SpartPanel, cross_filter};
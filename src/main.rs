@@ -1,37 +1,30 @@
-use app::App;
-use eframe::egui;
-use merde::{IntoStatic, json::from_str};
+#[cfg(feature = "native")]
+mod cli;
+#[cfg(feature = "native")]
+mod native;
 
-mod app;
-mod bars;
-mod settings;
-mod sort;
+#[cfg(not(any(feature = "native", target_arch = "wasm32")))]
+compile_error!("spart needs the `native` feature unless it's being built for the web");
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-	let args = std::env::args().skip(1);
-
-	let json_data = args
-		.map(std::fs::read_to_string)
-		.collect::<Result<Vec<_>, _>>()?
-		.join("\n");
-
-	let deserialized: Vec<merde::Map<'static>> = from_str::<Vec<merde::Map>>(&json_data)
-		.unwrap()
-		.into_static();
-
-	let options = eframe::NativeOptions {
-		viewport: egui::ViewportBuilder::default().with_inner_size([600., 400.]),
-		..Default::default()
-	};
-	eframe::run_native(
-		"Spart",
-		options,
-		Box::new(move |_| {
-			App::new(deserialized)
-				.map(|a| Box::new(a) as _)
-				.map_err(|e| Box::new(e) as _)
-		})
-	)?;
+#[cfg(feature = "native")]
+fn main() {
+	if let Err(e) = native::run() {
+		// Most errors already say what caused them, but the ones that don't should still say it
+		let mut message = e.to_string();
+		let mut source = e.source();
+		while let Some(cause) = source {
+			let cause_message = cause.to_string();
+			if !message.contains(&cause_message) {
+				message = format!("{message}: {cause_message}");
+			}
+			source = cause.source();
+		}
+		eprintln!("Error: {message}");
+		std::process::exit(1);
+	}
+}
 
-	Ok(())
+#[cfg(all(target_arch = "wasm32", not(feature = "native")))]
+fn main() {
+	spart::web::start();
 }
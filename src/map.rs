@@ -0,0 +1,108 @@
+//! Drawing where the rows are on a map of the world. There aren't any borders to draw, so it's
+//! just the plot with longitudes along it and latitudes up it, with each country (or each cell of
+//! rows with coordinates) marked by a circle that's bigger and stronger the more is there.
+
+use eframe::egui::{self, Align2};
+use egui_plot::{Plot, PlotPoint, Points, Text};
+use spart_core::{bars::LabeledBar, format::NumberFormat, geo};
+
+/// How big the circle of the biggest country is, in points
+const MAX_RADIUS: f32 = 24.;
+
+/// How big the circle of the smallest country is, so that it can still be hovered over
+const MIN_RADIUS: f32 = 3.;
+
+/// How many shades the cells of rows are drawn in, from the emptiest to the densest
+const SHADES: usize = 5;
+
+/// What's on the map
+pub enum Layer<'a> {
+	/// Each bar's country (its only label) with a circle as big as the bar is tall
+	Countries(&'a [LabeledBar]),
+	/// How many rows are around each place (see [`geo::density`])
+	Density(&'a [([f64; 2], usize)])
+}
+
+pub fn show(ui: &mut egui::Ui, id: egui::Id, layer: Layer, format: NumberFormat) {
+	let color = ui.visuals().selection.bg_fill;
+	let text = ui.visuals().text_color();
+	Plot::new(id.with("map"))
+		.data_aspect(1.)
+		.include_x(-180.)
+		.include_x(180.)
+		.include_y(-90.)
+		.include_y(90.)
+		.x_axis_formatter(|mark, _| format!("{}°", mark.value))
+		.y_axis_formatter(|mark, _| format!("{}°", mark.value))
+		.label_formatter(|name, point| match name {
+			"" => format!("{:.1}°, {:.1}°", point.y, point.x),
+			name => name.to_string()
+		})
+		.show(ui, |ui| match layer {
+			Layer::Countries(bars) => {
+				let tallest = bars.iter().map(|bar| bar.value.abs()).fold(0., f64::max);
+				for bar in bars {
+					let Some(place) = bar.labels.first().and_then(|code| geo::centroid(code))
+					else {
+						continue;
+					};
+					let share = match tallest {
+						0. => 0.,
+						_ => (bar.value.abs() / tallest) as f32
+					};
+					// By area, so that a country with twice as much looks twice as big
+					let radius = (MAX_RADIUS * share.sqrt()).max(MIN_RADIUS);
+					ui.points(
+						Points::new(vec![place])
+							.radius(radius)
+							.filled(true)
+							.color(color.gamma_multiply(0.25 + 0.75 * share))
+							.name(format!("{}\n{}", bar.name, format.format(bar.value)))
+					);
+					ui.text(
+						Text::new(PlotPoint::new(place[0], place[1]), &bar.labels[0])
+							.color(text)
+							.anchor(Align2::CENTER_CENTER)
+							.allow_hover(false)
+					);
+				}
+			}
+			Layer::Density(cells) => {
+				let densest = cells.iter().map(|(_, rows)| *rows).max().unwrap_or(1);
+				// Rows tend to bunch up in a few places, so the shades go by orders of magnitude
+				let scale = (densest as f64).ln().max(1.);
+				let shade_of = |rows: usize| {
+					((rows as f64).ln() / scale * (SHADES - 1) as f64).round() as usize
+				};
+				for shade in 0..SHADES {
+					let points = cells
+						.iter()
+						.filter(|(_, rows)| shade_of(*rows) == shade)
+						.map(|(place, _)| *place)
+						.collect::<Vec<_>>();
+					if points.is_empty() {
+						continue;
+					}
+					let strength = (shade + 1) as f32 / SHADES as f32;
+					let most = (scale * (shade as f64 + 0.5) / (SHADES - 1) as f64).exp();
+					ui.points(
+						Points::new(points)
+							.radius(MIN_RADIUS + 2. * shade as f32)
+							.filled(true)
+							.color(color.gamma_multiply(strength))
+							.name(format!("Up to {} rows", (most as usize).min(densest)))
+					);
+				}
+			}
+		});
+}
+
+/// What's said instead of the map when there's nothing to put on one
+pub fn show_unplaceable(ui: &mut egui::Ui) {
+	ui.centered_and_justified(|ui| {
+		ui.label(
+			"Group by a key of country codes (like US or GB), or load rows with a latitude and \
+			 longitude, to see them on a map"
+		);
+	});
+}
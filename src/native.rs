@@ -0,0 +1,630 @@
+//! Running spart as a program on a computer, where it's started from the command line and can
+//! read and write files.
+
+use std::{
+	error::Error,
+	io::BufRead,
+	sync::mpsc::{self, Receiver},
+	thread
+};
+
+use clap::Parser;
+use eframe::egui;
+use spart::{App, Config, automation, recent::Recent, screen::Screen, session};
+use spart_core::{
+	agents,
+	bars::{self, LabeledBar, make_bars},
+	diff,
+	feed::Feed,
+	ip, listenbrainz,
+	load::Source,
+	memory::Rows,
+	render::{self, RenderOptions},
+	review,
+	schema::{self, KeyType, Schema},
+	script,
+	settings::{self, BarOrder, Settings, YAxisKey},
+	share, stream,
+	suggest::{self, Suggestion},
+	urls
+};
+
+use crate::cli::{
+	Cli, Command, DataArgs, ExportArgs, GuiArgs, QueryArgs, RenderArgs, ReviewArgs, RpcArgs,
+	ViewArgs
+};
+
+pub fn run() -> Result<(), Box<dyn Error>> {
+	let cli = Cli::parse();
+
+	let config = match cli.config {
+		Some(path) => Config::load(path, true)?,
+		None => Config::default_path()
+			.map(|path| Config::load(path, false))
+			.transpose()?
+			.unwrap_or_default()
+	};
+
+	match cli.command.unwrap_or(Command::Gui(cli.gui)) {
+		Command::Gui(args) => run_gui(args, config),
+		Command::Render(args) => run_render(args, &config),
+		Command::Query(args) => run_query(args, &config),
+		Command::Schema(args) => run_schema(&args, &config),
+		Command::Export(args) => run_export(args, &config),
+		Command::Review(args) => run_review(args, &config),
+		Command::Rpc(args) => run_rpc(args, &config)
+	}
+}
+
+/// Sets up an app with the columns, grouping, and filters that were asked for on the command line
+fn build_app(mut data: Rows, view: ViewArgs, config: &Config) -> Result<App, Box<dyn Error>> {
+	let diffing = view.add_diffed(&mut data, &config.load_options())?;
+	#[cfg(feature = "enrich")]
+	let enrichments = view.enrich(&mut data, config)?;
+	for key in &view.ip {
+		ip::add_fields(&mut data, key)?;
+	}
+	for key in &view.url {
+		urls::add_fields(&mut data, key)?;
+	}
+	for key in &view.user_agent {
+		agents::add_fields(&mut data, key)?;
+	}
+	let scripts = view.scripts()?;
+	let columns = view.columns(&scripts)?;
+	for (name, expr) in &columns {
+		script::add_column(&mut data, name, expr)?;
+	}
+
+	let mut app = App::new(data, config)?
+		.with_scripts(scripts, columns)
+		.with_addresses(view.ip.clone())
+		.with_urls(view.url.clone())
+		.with_agents(view.user_agent.clone());
+	#[cfg(feature = "enrich")]
+	{
+		app = app.with_enrichments(enrichments);
+	}
+	if let Some(token) = &view.view {
+		app.open_view(token)?;
+	}
+	// Before the filters, since they're parsed differently for each type
+	for key in &view.categorical {
+		app.set_key_type(key, Some(KeyType::Categorical))?;
+	}
+	for key in &view.numeric {
+		app.set_key_type(key, Some(KeyType::Numeric))?;
+	}
+	for filter in &view.filters {
+		app.add_filter(filter)?;
+	}
+	for key in &view.exclude_outliers {
+		app.exclude_outliers(key)?;
+	}
+	for filter in &view.compare {
+		app.add_compare_filter(filter)?;
+	}
+	if diffing {
+		app.diff_datasets();
+	}
+	if let Some(periods) = view.periods()? {
+		app.set_periods(Some(periods))?;
+	}
+	if let Some(predicate) = &view.predicate {
+		app.set_predicate(predicate)?;
+	}
+	if let Some(sample) = view.sample() {
+		app.set_sample(Some(sample))?;
+	}
+	if let Some(percent) = view.merge_below {
+		app.set_merge_below(Some(percent / 100.));
+	}
+	if let Some(tie_break) = view.ties {
+		app.set_tie_break(tie_break);
+	}
+	if let Some(key) = &view.sum {
+		app.set_sum(Some(key))?;
+	}
+	if let Some(aggregation) = view.aggregate {
+		app.set_aggregate(aggregation);
+	}
+	if let Some(key) = &view.rate {
+		app.set_rate(Some(key))?;
+	}
+	if let Some(key) = &view.stack_by {
+		app.set_stack_by(Some(key))?;
+	}
+	for (key, unit) in view.units()? {
+		app.set_unit(&key, unit)?;
+	}
+	for (key, delimiter) in view.splits() {
+		app.set_delimiter(&key, Some(delimiter))?;
+	}
+	for annotation in view.annotations()? {
+		app.annotate(annotation);
+	}
+	for line in &view.reference_lines {
+		app.add_reference_line(*line);
+	}
+	for rule in view.color_rules.iter().cloned() {
+		app.add_color_rule(rule);
+	}
+	if let Some(preset) = view.preset {
+		app.apply_preset(preset)?;
+	}
+	for (key, count) in view.bins()? {
+		app.set_bin_count(&key, count)?;
+	}
+	for (key, bins) in view.bin_widths()? {
+		app.set_bins(&key, Some(bins))?;
+	}
+	app.select_keys(view.x_axis)?;
+	if !view.drill.is_empty() {
+		if view.drill.len() >= app.settings().x_axis.len() {
+			return Err("--drill needs an --x key below each of the bars it drills into".into());
+		}
+		app.set_drilling(true);
+		for label in view.drill {
+			app.drill_down(label);
+		}
+	}
+	if view.label.is_some() {
+		app.set_label_template(view.label);
+	}
+	#[cfg(feature = "sql")]
+	if let Some(sql) = &view.sql {
+		app.set_query(sql)?;
+	}
+	Ok(app)
+}
+
+/// The bars for the modes that only need to make a single chart, along with the settings they
+/// were made with
+fn headless_bars(
+	data: &DataArgs,
+	view: ViewArgs,
+	stream: bool,
+	config: &Config
+) -> Result<(Vec<LabeledBar>, Settings<'static>), Box<dyn Error>> {
+	if stream {
+		if data.paths.is_empty() {
+			return Err("--stream needs some files (or `-` for stdin) to read from".into());
+		}
+		if !view.columns.is_empty() {
+			return Err("--column can't be used with --stream".into());
+		}
+		if !view.ip.is_empty() {
+			return Err("--ip can't be used with --stream".into());
+		}
+		if !view.url.is_empty() {
+			return Err("--url can't be used with --stream".into());
+		}
+		if !view.user_agent.is_empty() {
+			return Err("--user-agent can't be used with --stream".into());
+		}
+		if !view.drill.is_empty() {
+			return Err("--drill can't be used with --stream".into());
+		}
+		if view.preset.is_some() {
+			return Err("--preset can't be used with --stream".into());
+		}
+		if !view.compare.is_empty() {
+			return Err("--compare can't be used with --stream".into());
+		}
+		if view.diff.is_some() {
+			return Err("--diff can't be used with --stream".into());
+		}
+		if view.periods.is_some() {
+			return Err("--periods can't be used with --stream".into());
+		}
+		if view.sample.is_some() {
+			return Err("--sample can't be used with --stream".into());
+		}
+		if view.view.is_some() {
+			return Err("--view can't be used with --stream".into());
+		}
+		if !view.categorical.is_empty() || !view.numeric.is_empty() {
+			return Err("--categorical and --numeric can't be used with --stream".into());
+		}
+		if !view.exclude_outliers.is_empty() {
+			return Err("--exclude-outliers can't be used with --stream".into());
+		}
+		if !view.annotations.is_empty() {
+			return Err("--annotate can't be used with --stream".into());
+		}
+		if !view.reference_lines.is_empty() {
+			return Err("--reference can't be used with --stream".into());
+		}
+		// How far the values go isn't known until they've all been read
+		if !view.bins.is_empty() {
+			return Err("--bins can't be used with --stream (but --bin-width can)".into());
+		}
+		#[cfg(feature = "sql")]
+		if view.sql.is_some() {
+			return Err("--sql can't be used with --stream".into());
+		}
+		#[cfg(feature = "enrich")]
+		if !view.enrich.is_empty() {
+			return Err("--enrich can't be used with --stream".into());
+		}
+
+		let mut settings = config.settings();
+		if let Some(predicate) = &view.predicate {
+			settings.predicate = Some(view.scripts()?.compile(predicate)?);
+		}
+		for (key, unit) in view.units()? {
+			settings.formats.insert(key, unit.number_format());
+		}
+		settings.delimiters.extend(view.splits());
+		let bin_widths = view.bin_widths()?;
+		if !bin_widths.is_empty() {
+			settings.order = BarOrder::Labels;
+		}
+		settings.bins.extend(bin_widths);
+		settings.label_template = view.label;
+		settings.stack_by = view.stack_by;
+		settings.merge_below = view.merge_below.map(|percent| percent / 100.);
+		if let Some(tie_break) = view.ties {
+			settings.tie_break = tie_break;
+		}
+		if let Some(key) = &view.sum {
+			settings.y_axis = YAxisKey::Key(settings::static_key(key));
+		}
+		if let Some(aggregation) = view.aggregate {
+			settings.aggregation = aggregation;
+		}
+		if let Some(key) = &view.rate {
+			settings.y_axis = YAxisKey::Rate(settings::static_key(key));
+		}
+		settings.color_rules.clone_from(&view.color_rules);
+		let (groups, settings) = stream::aggregate(
+			&data.paths,
+			data.format,
+			view.x_axis,
+			&view.filters,
+			settings,
+			&config.load_options()
+		)?;
+		return Ok((make_bars(&groups, &settings), settings));
+	}
+
+	let mut app = build_app(data.load_required(&config.load_options())?, view, config)?;
+	app.wait_for_bars();
+
+	let settings = app.settings().clone();
+	// A query's bars are labelled by its own columns instead of any keys
+	#[cfg(feature = "sql")]
+	let settings = match app.query_labels() {
+		Some(labels) => Settings {
+			x_axis: labels.to_vec(),
+			..settings
+		},
+		None => settings
+	};
+	Ok((std::mem::take(&mut app.bars), settings))
+}
+
+fn run_gui(mut args: GuiArgs, config: Config) -> Result<(), Box<dyn Error>> {
+	if let Some(path) = &args.session {
+		let session = session::load(path)
+			.map_err(|e| format!("Couldn't open the session at {}: {e}", path.display()))?;
+		args.data.format = args.data.format.or(session.format()?);
+		// The keys were added before anything asked for on the command line was
+		let added = session.added;
+		args.view.columns = [added.columns, args.view.columns].concat();
+		args.view.ip = [added.addresses, args.view.ip].concat();
+		args.view.url = [added.urls, args.view.url].concat();
+		args.view.user_agent = [added.agents, args.view.user_agent].concat();
+		args.data.paths = session.paths;
+		args.view.view = session.view;
+	}
+	if args.rpc && args.data.reads_stdin() {
+		return Err("--rpc needs stdin for requests, so the data can't be read from it".into());
+	}
+	let options = config.load_options();
+	let data = args.data.load(&options)?;
+	let theme = args.theme.unwrap_or(config.theme);
+
+	// Reloading the files would lose which of the diffed datasets each row was from
+	let source = (!args.data.demo && args.view.diff.is_none())
+		.then(|| Source::new(args.data.paths, args.data.format, options))
+		.flatten();
+	if args.watch && args.view.diff.is_some() {
+		return Err("--watch can't be used with --diff".into());
+	}
+	if args.watch && source.is_none() {
+		return Err("--watch needs files to watch (and stdin can't be watched)".into());
+	}
+	let watch = (args.watch || config.watch) && args.listen.is_none();
+
+	let feed = args
+		.listen
+		.map(|address| {
+			Feed::listen(address.clone(), config.load_options())
+				.map_err(|e| format!("Couldn't listen for rows on {address}: {e}"))
+		})
+		.transpose()?;
+
+	// Anything that's wrong with what was asked for is reported before a window ever shows up
+	let screen = match (data, feed) {
+		(None, Some(feed)) => Screen::listening(feed, config),
+		(None, None) => Screen::empty(None, config),
+		(Some(data), feed) => {
+			let mut app = build_app(data, args.view, &config)?;
+			if let Some(source) = source {
+				app = app.with_source(source, watch);
+			}
+			if let Some(file) = app.session(None) {
+				Recent::remember(file);
+			}
+			if let Some(feed) = feed {
+				app = app.with_feed(feed);
+			}
+			Screen::Loaded(Box::new(app))
+		}
+	};
+
+	let options = eframe::NativeOptions {
+		viewport: egui::ViewportBuilder::default().with_inner_size([600., 400.]),
+		..Default::default()
+	};
+	eframe::run_native(
+		"Spart",
+		options,
+		Box::new(move |cc| {
+			cc.egui_ctx.set_theme(theme);
+			if !args.rpc {
+				return Ok(Box::new(screen));
+			}
+			Ok(Box::new(Driven {
+				screen,
+				requests: read_requests(cc.egui_ctx.clone())?
+			}))
+		})
+	)?;
+
+	Ok(())
+}
+
+/// The window, along with whoever's sending it requests over stdin
+struct Driven {
+	screen: Screen,
+	requests: Receiver<String>
+}
+
+impl eframe::App for Driven {
+	fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+		for line in self.requests.try_iter() {
+			let response = automation::respond(&line, |request| match &mut self.screen {
+				Screen::Loaded(app) => automation::handle(app, request),
+				_ => Err("There's no data to work with yet".into())
+			});
+			if let Some(response) = response {
+				println!("{response}");
+			}
+		}
+		self.screen.update(ctx, frame);
+	}
+
+	fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+		self.screen.on_exit(gl);
+	}
+}
+
+/// Passes along each line of stdin as it comes in, waking up the window for each one
+fn read_requests(ctx: egui::Context) -> std::io::Result<Receiver<String>> {
+	let (send, recv) = mpsc::channel();
+	thread::Builder::new()
+		.name("spart-rpc".into())
+		.spawn(move || {
+			for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+				if send.send(line).is_err() {
+					break;
+				}
+				ctx.request_repaint();
+			}
+		})?;
+	Ok(recv)
+}
+
+fn run_render(args: RenderArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+	if args.output.extension().is_none_or(|ext| ext != "svg") {
+		return Err("Charts can only be rendered to .svg files".into());
+	}
+
+	if !args.view.compare.is_empty() || args.view.diff.is_some() || args.view.periods.is_some() {
+		return Err(
+			"Compared bars can't be rendered yet, only charted in the window or queried".into()
+		);
+	}
+	let (bars, settings) = headless_bars(&args.data, args.view, args.stream, config)?;
+	let bars = &bars[..args.limit.unwrap_or(usize::MAX).min(bars.len())];
+	let svg = render::to_svg(bars, &RenderOptions {
+		width: args.width,
+		height: args.height,
+		value_format: settings.value_format(),
+		label_width: settings.label_width,
+		annotations: settings.annotations.clone(),
+		reference_lines: settings.reference_lines.clone()
+	});
+
+	std::fs::write(&args.output, svg)?;
+	Ok(())
+}
+
+fn run_query(args: QueryArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+	if args.share {
+		let app = build_app(
+			args.data.load_required(&config.load_options())?,
+			args.view,
+			config
+		)?;
+		println!("{}{}", share::URL_PREFIX, app.view_token());
+		return Ok(());
+	}
+
+	let (bars, settings) = headless_bars(&args.data, args.view, args.stream, config)?;
+	let format = settings.value_format();
+
+	// Periods are named by when they are, since that's what they're compared by
+	let (value, compared) = match &settings.periods {
+		Some(periods) => (periods.previous(), Some(periods.latest.clone())),
+		None => (
+			"value".to_string(),
+			settings.compare.is_some().then(|| "compared".to_string())
+		)
+	};
+	print!("{}\t{value}", settings.x_axis.join("\t"));
+	print!(
+		"{}",
+		compared.map(|name| format!("\t{name}")).unwrap_or_default()
+	);
+	print!("{}", if settings.diff { "\tchange" } else { "" });
+	// Stacked bars have a column for each of their segments
+	let legend = bars::legend(&bars);
+	println!(
+		"{}",
+		legend
+			.iter()
+			.map(|label| format!("\t{label}"))
+			.collect::<String>()
+	);
+	for bar in bars.iter().take(args.limit.unwrap_or(usize::MAX)) {
+		print!("{}\t{}", bar.labels.join("\t"), format.format(bar.value));
+		match bar.compared {
+			Some(compared) if settings.diff =>
+				print!("\t{}\t{}", format.format(compared), diff::describe(bar)),
+			Some(compared) => print!("\t{}", format.format(compared)),
+			None => ()
+		}
+		for label in &legend {
+			let segment = bar.segments.iter().find(|segment| segment.label == *label);
+			print!(
+				"\t{}",
+				format.format(segment.map_or(0., |segment| segment.value))
+			);
+		}
+		println!();
+	}
+	Ok(())
+}
+
+fn run_export(args: ExportArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+	let app = build_app(
+		args.data.load_required(&config.load_options())?,
+		args.view,
+		config
+	)?;
+	let (json, skipped) = listenbrainz::export(app.filtered_rows())?;
+	std::fs::write(&args.output, json)
+		.map_err(|e| format!("Couldn't write to {}: {e}", args.output.display()))?;
+
+	if skipped > 0 {
+		eprintln!("Left out {skipped} rows that didn't say what was played or when");
+	}
+	Ok(())
+}
+
+fn run_review(args: ReviewArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+	let app = build_app(
+		args.data.load_required(&config.load_options())?,
+		args.view,
+		config
+	)?;
+	let html = review::report(app.filtered_rows(), args.year)?;
+	std::fs::write(&args.output, html)
+		.map_err(|e| format!("Couldn't write to {}: {e}", args.output.display()))?;
+	Ok(())
+}
+
+fn run_rpc(args: RpcArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+	if args.data.reads_stdin() {
+		return Err("Requests are read from stdin, so the data can't be".into());
+	}
+
+	let mut app = build_app(
+		args.data.load_required(&config.load_options())?,
+		args.view,
+		config
+	)?;
+	for line in std::io::stdin().lock().lines() {
+		if let Some(response) =
+			automation::respond(&line?, |request| automation::handle(&mut app, request))
+		{
+			println!("{response}");
+		}
+	}
+	Ok(())
+}
+
+fn run_schema(args: &DataArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+	let data = args.load_required(&config.load_options())?;
+	let summaries = schema::describe(&data);
+
+	let key_width = summaries
+		.iter()
+		.map(|s| s.key.chars().count())
+		.chain(["key".len()])
+		.max()
+		.unwrap_or_default();
+
+	println!(
+		"{:key_width$}  {:<12}  {:>8}  {:>8}",
+		"key", "type", "nulls", "distinct"
+	);
+	for summary in &summaries {
+		let types = match summary.types.as_slice() {
+			[] => "Null".to_string(),
+			types => types
+				.iter()
+				.map(|ty| format!("{ty:?}"))
+				.collect::<Vec<_>>()
+				.join("|")
+		};
+		println!(
+			"{:key_width$}  {types:<12}  {:>8}  {:>8}",
+			summary.key, summary.nulls, summary.distinct
+		);
+	}
+	println!("\n{} rows", data.len());
+
+	// The summary above should make it clear what's wrong, but the user will still want to know
+	// that the data can't be loaded as-is
+	let schema = Schema::infer(&data).map_err(|e| format!("This data can't be charted: {e}"))?;
+	for (key, ty) in &schema.epoch_keys {
+		let unit = match ty {
+			KeyType::EpochMillis => "milliseconds",
+			_ => "seconds"
+		};
+		println!("'{key}' looks like Unix timestamps in {unit}, which can be treated as dates");
+	}
+	for key in &schema.country_keys {
+		println!("'{key}' looks like country codes, which can be drawn on a map");
+	}
+	for key in &schema.ip_keys {
+		println!("'{key}' looks like IP addresses, which `--ip {key}` pulls subnets out of");
+	}
+	for key in &schema.url_keys {
+		println!("'{key}' looks like URLs, which `--url {key}` pulls hosts and paths out of");
+	}
+	for key in &schema.agent_keys {
+		println!(
+			"'{key}' looks like user agents, which `--user-agent {key}` pulls browsers out of"
+		);
+	}
+	if let Some(coordinates) = &schema.coordinates {
+		println!(
+			"'{}' and '{}' look like coordinates, which can be drawn on a map",
+			coordinates.latitude, coordinates.longitude
+		);
+	}
+
+	let suggestions = suggest::suggest(&data, &schema)
+		.iter()
+		.map(Suggestion::name)
+		.collect::<Vec<_>>();
+	if !suggestions.is_empty() {
+		println!("Try grouping by: {}", suggestions.join(", "));
+	}
+	Ok(())
+}
@@ -0,0 +1,197 @@
+//! Drawing things off screen, at whatever size they're wanted at, so that charts can be exported
+//! at a resolution that has nothing to do with how big the window is. What's drawn is tessellated
+//! just like it would be for the screen, but the triangles are then filled in here instead of by
+//! the GPU.
+
+use eframe::egui::{
+	self, Color32, ColorImage, Context, ImageData, Pos2, RawInput, Rect, TextureId, ViewportId,
+	epaint::{ClippedPrimitive, ImageDelta, Mesh, Primitive},
+	pos2, vec2
+};
+use fxhash::FxHashMap;
+
+/// How many times everything's drawn before what was drawn is kept, since some widgets (like
+/// plots) size parts of themselves by how they were drawn the time before
+const PASSES: usize = 2;
+
+/// The biggest an image can be on either side, in pixels
+pub const MAX_SIDE: usize = 8192;
+
+/// Draws whatever `draw` puts in a `ui` that fills an image of `size` pixels, with everything
+/// `scale` times as big as it'd be at a pixel per point, and styled like `like` is
+pub fn render(
+	like: &Context,
+	size: [usize; 2],
+	scale: f32,
+	mut draw: impl FnMut(&mut egui::Ui)
+) -> ColorImage {
+	let ctx = Context::default();
+	ctx.set_theme(like.theme());
+	ctx.set_style(like.style());
+
+	let screen = Rect::from_min_size(Pos2::ZERO, vec2(size[0] as f32, size[1] as f32) / scale);
+	let mut textures = FxHashMap::default();
+	let mut output = None;
+	for _ in 0..PASSES {
+		let mut input = RawInput {
+			screen_rect: Some(screen),
+			max_texture_side: Some(MAX_SIDE),
+			..RawInput::default()
+		};
+		input
+			.viewports
+			.entry(ViewportId::ROOT)
+			.or_default()
+			.native_pixels_per_point = Some(scale);
+
+		let full = ctx.run(input, |ctx| {
+			egui::CentralPanel::default().show(ctx, |ui| draw(ui));
+		});
+		for (id, delta) in full.textures_delta.set {
+			update_texture(&mut textures, id, delta);
+		}
+		output = Some((full.shapes, full.pixels_per_point));
+	}
+
+	let mut image = ColorImage::new(size, Color32::TRANSPARENT);
+	let Some((shapes, pixels_per_point)) = output else {
+		return image;
+	};
+	for ClippedPrimitive {
+		clip_rect,
+		primitive
+	} in ctx.tessellate(shapes, pixels_per_point)
+	{
+		// Nothing that's drawn here paints with callbacks
+		if let Primitive::Mesh(mesh) = primitive {
+			let clip = Rect::from_min_max(
+				(clip_rect.min.to_vec2() * pixels_per_point).to_pos2(),
+				(clip_rect.max.to_vec2() * pixels_per_point).to_pos2()
+			);
+			fill(
+				&mut image,
+				&mesh,
+				clip,
+				textures.get(&mesh.texture_id),
+				pixels_per_point
+			);
+		}
+	}
+	image
+}
+
+/// Applies what egui changed about one of its textures since it was last drawn
+fn update_texture(
+	textures: &mut FxHashMap<TextureId, ColorImage>,
+	id: TextureId,
+	delta: ImageDelta
+) {
+	let patch = match delta.image {
+		ImageData::Color(image) => (*image).clone(),
+		ImageData::Font(font) => ColorImage {
+			size: font.size,
+			pixels: font.srgba_pixels(None).collect()
+		}
+	};
+	let Some(([x, y], texture)) = delta.pos.zip(textures.get_mut(&id)) else {
+		textures.insert(id, patch);
+		return;
+	};
+	let [width, _] = texture.size;
+	for (row, pixels) in patch.pixels.chunks_exact(patch.width()).enumerate() {
+		let start = (y + row) * width + x;
+		texture.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+	}
+}
+
+/// Fills in the triangles of `mesh` (whose vertices are in points) that are inside `clip` (which
+/// is in pixels), blending them over what's already in `image`
+fn fill(
+	image: &mut ColorImage,
+	mesh: &Mesh,
+	clip: Rect,
+	texture: Option<&ColorImage>,
+	pixels_per_point: f32
+) {
+	let [width, height] = image.size;
+	let clip = clip.intersect(Rect::from_min_size(
+		Pos2::ZERO,
+		vec2(width as f32, height as f32)
+	));
+
+	for triangle in mesh.indices.chunks_exact(3) {
+		let mut vertices = [0, 1, 2].map(|idx| mesh.vertices[triangle[idx] as usize]);
+		for vertex in &mut vertices {
+			vertex.pos = (vertex.pos.to_vec2() * pixels_per_point).to_pos2();
+		}
+		// Everything below expects the vertices to go around the same way
+		if edge(vertices[0].pos, vertices[1].pos, vertices[2].pos) < 0. {
+			vertices.swap(1, 2);
+		}
+		let [a, b, c] = vertices;
+		let area = edge(a.pos, b.pos, c.pos);
+		let bounds = Rect::from_points(&[a.pos, b.pos, c.pos]).intersect(clip);
+		if area == 0. || !bounds.is_positive() {
+			continue;
+		}
+
+		let edges = [(b.pos, c.pos), (c.pos, a.pos), (a.pos, b.pos)];
+		for y in bounds.top().floor() as usize..bounds.bottom().ceil() as usize {
+			for x in bounds.left().floor() as usize..bounds.right().ceil() as usize {
+				let point = pos2(x as f32 + 0.5, y as f32 + 0.5);
+				if !clip.contains(point) {
+					continue;
+				}
+				let weights = edges.map(|(from, to)| edge(from, to, point));
+				let inside = weights
+					.iter()
+					.zip(edges)
+					.all(|(weight, (from, to))| *weight > 0. || (*weight == 0. && owns(from, to)));
+				if !inside {
+					continue;
+				}
+
+				let [wa, wb, wc] = weights.map(|weight| weight / area);
+				let uv = a.uv.to_vec2() * wa + b.uv.to_vec2() * wb + c.uv.to_vec2() * wc;
+				let texel = texture.map_or(Color32::WHITE, |texture| sample(texture, uv));
+				let colors =
+					[a.color, b.color, c.color].map(|color| color.to_array().map(f32::from));
+				let source = [0, 1, 2, 3].map(|channel| {
+					let vertex =
+						colors[0][channel] * wa + colors[1][channel] * wb + colors[2][channel] * wc;
+					vertex * f32::from(texel.to_array()[channel]) / 255.
+				});
+
+				// Both are premultiplied, so the source just goes over what's there
+				let pixel = &mut image.pixels[y * width + x];
+				let under = pixel.to_array().map(f32::from);
+				let [red, green, blue, alpha] = [0, 1, 2, 3]
+					.map(|channel| source[channel] + under[channel] * (1. - source[3] / 255.))
+					.map(|value| value.round().clamp(0., 255.) as u8);
+				*pixel = Color32::from_rgba_premultiplied(red, green, blue, alpha);
+			}
+		}
+	}
+}
+
+/// Twice the area of the triangle `from`, `to`, `point`, which is positive if `point` is on the
+/// same side of the edge from `from` to `to` as the rest of a triangle that goes around the
+/// usual way
+fn edge(from: Pos2, to: Pos2, point: Pos2) -> f32 {
+	(to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+}
+
+/// If pixels right on the edge from `from` to `to` belong to the triangle it's part of. Two
+/// triangles that share an edge go along it opposite ways, so exactly one of them gets them, and
+/// nothing see-through is drawn twice where they meet.
+fn owns(from: Pos2, to: Pos2) -> bool {
+	to.y > from.y || (to.y == from.y && to.x > from.x)
+}
+
+/// The texel of `texture` at `uv` (from 0 to 1 on both sides)
+fn sample(texture: &ColorImage, uv: egui::Vec2) -> Color32 {
+	let [width, height] = texture.size;
+	let x = ((uv.x * width as f32) as usize).min(width.saturating_sub(1));
+	let y = ((uv.y * height as f32) as usize).min(height.saturating_sub(1));
+	texture.pixels[y * width + x]
+}
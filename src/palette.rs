@@ -0,0 +1,108 @@
+//! A list of everything that can be done right now, narrowed down by typing a few letters of what's
+//! wanted, so that nothing's more than a few keystrokes away however many buttons there are. It's
+//! opened with Ctrl+P (or Cmd+P).
+
+use eframe::egui::{self, Align2, Key, KeyboardShortcut, Modifiers, TextEdit, vec2};
+
+/// How many of the commands that match are listed at once
+const MAX_SHOWN: usize = 12;
+
+pub const SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::P);
+
+#[derive(Default)]
+pub struct Palette {
+	query: String,
+	/// Which of the listed commands pressing enter runs
+	selected: usize
+}
+
+pub enum Outcome<T> {
+	Open,
+	Closed,
+	Chosen(T)
+}
+
+impl Palette {
+	/// Lists whichever of `commands` (each with what it's called) match what's been typed so
+	/// far, best first
+	pub fn show<T>(&mut self, ctx: &egui::Context, commands: Vec<(String, T)>) -> Outcome<T> {
+		let mut matches = commands
+			.into_iter()
+			.filter_map(|(name, command)| Some((score(&self.query, &name)?, name, command)))
+			.collect::<Vec<_>>();
+		// It's a stable sort, so commands that match just as well stay in the order they came in
+		matches.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+		matches.truncate(MAX_SHOWN);
+
+		// The arrow keys and enter are taken before the text box sees them, since they're for
+		// picking a command rather than for editing what's been typed
+		let (up, down, enter, escape) = ctx.input_mut(|i| {
+			(
+				i.consume_key(Modifiers::NONE, Key::ArrowUp),
+				i.consume_key(Modifiers::NONE, Key::ArrowDown),
+				i.consume_key(Modifiers::NONE, Key::Enter),
+				i.consume_key(Modifiers::NONE, Key::Escape)
+			)
+		});
+		if escape {
+			return Outcome::Closed;
+		}
+		let last = matches.len().saturating_sub(1);
+		self.selected = match (up, down) {
+			(true, false) => self.selected.saturating_sub(1),
+			(false, true) => self.selected + 1,
+			_ => self.selected
+		}
+		.min(last);
+
+		let mut chosen = enter.then_some(self.selected);
+		egui::Window::new("Command palette")
+			.title_bar(false)
+			.resizable(false)
+			.anchor(Align2::CENTER_TOP, vec2(0., 40.))
+			.show(ctx, |ui| {
+				let input = ui.add(
+					TextEdit::singleline(&mut self.query)
+						.hint_text("Type to find a command")
+						.desired_width(320.)
+				);
+				input.request_focus();
+				if input.changed() {
+					self.selected = 0;
+				}
+
+				if matches.is_empty() {
+					ui.weak("Nothing matches that");
+				}
+				for (idx, (_, name, _)) in matches.iter().enumerate() {
+					if ui.selectable_label(idx == self.selected, name).clicked() {
+						chosen = Some(idx);
+					}
+				}
+			});
+
+		match chosen.filter(|idx| *idx < matches.len()) {
+			Some(idx) => Outcome::Chosen(matches.swap_remove(idx).2),
+			None => Outcome::Open
+		}
+	}
+}
+
+/// How well `query` matches `name`, if all of its letters show up in it in order (ignoring case).
+/// Letters that start a word, or come right after the last one that matched, count for more.
+fn score(query: &str, name: &str) -> Option<usize> {
+	let name = name.to_lowercase().chars().collect::<Vec<_>>();
+	let (mut score, mut from, mut last) = (0, 0, None);
+	for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+		let idx = from + name[from..].iter().position(|c| *c == wanted)?;
+		score += 1;
+		if last.is_some_and(|last| last + 1 == idx) {
+			score += 2;
+		}
+		if idx == 0 || !name[idx - 1].is_alphanumeric() {
+			score += 3;
+		}
+		(from, last) = (idx + 1, Some(idx));
+	}
+	Some(score)
+}
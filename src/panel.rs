@@ -0,0 +1,44 @@
+use eframe::egui::{self, UiBuilder};
+
+use crate::app::App;
+
+/// The whole explorer (the settings, the plot, and the status bar) as a widget, for putting it
+/// inside some other egui app. Everything it shows lives in an [`App`], which the other app keeps
+/// around between frames.
+pub struct SpartPanel<'a> {
+	state: &'a mut App
+}
+
+impl<'a> SpartPanel<'a> {
+	pub fn new(state: &'a mut App) -> Self {
+		Self { state }
+	}
+
+	/// Shows the explorer in all the space that `ui` has left
+	pub fn show(ui: &mut egui::Ui, state: &mut App) -> egui::Response {
+		ui.add(SpartPanel::new(state))
+	}
+}
+
+/// Links explorers of the same data together, so that selecting a bar in one of them narrows all
+/// the others down to that bar's rows. Call this each frame, after they've all been shown.
+pub fn cross_filter(apps: &mut [App]) {
+	let brushes = apps.iter().map(App::brush).collect::<Vec<_>>();
+	for (idx, app) in apps.iter_mut().enumerate() {
+		let others = brushes
+			.iter()
+			.enumerate()
+			.filter(|(other, _)| *other != idx)
+			.filter_map(|(_, brush)| brush.clone())
+			.collect();
+		app.set_brushes(others);
+	}
+}
+
+impl egui::Widget for SpartPanel<'_> {
+	fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+		let rect = ui.available_rect_before_wrap();
+		ui.allocate_new_ui(UiBuilder::new().max_rect(rect), |ui| self.state.show(ui))
+			.response
+	}
+}
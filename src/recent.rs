@@ -0,0 +1,157 @@
+//! The files that have been opened before, remembered between runs along with the view that they
+//! were last left on, so that they can be opened again (just as they were) from the File menu or
+//! the empty window. They're kept in `$XDG_STATE_HOME/spart/recent.json`, falling back to
+//! `~/.local/state/spart`.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use spart_core::load::{Format, LoadErr};
+
+/// How many files are remembered before the ones that were opened longest ago are forgotten
+const MAX_RECENT: usize = 10;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Recent {
+	/// The most recently opened first
+	files: Vec<RecentFile>
+}
+
+/// Some files that were opened together
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentFile {
+	/// Where they are, made absolute so that they can be opened from anywhere
+	pub paths: Vec<String>,
+	/// The view they were last left on (see [`spart_core::share`]), if they've been closed since
+	pub view: Option<String>,
+	/// The name of the format they were loaded as, if it wasn't left to be detected
+	#[serde(default)]
+	pub format: Option<String>,
+	#[serde(default)]
+	pub added: AddedKeys
+}
+
+/// The keys that were added to some files' data after it was loaded, so that they're still there
+/// when the files are opened again
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AddedKeys {
+	/// Keys worked out by scripts, written like `--column` (`name=expression`)
+	pub columns: Vec<String>,
+	/// Keys of IP addresses that have had their subnets and such pulled out of them
+	pub addresses: Vec<String>,
+	/// Keys of URLs that have had their parts pulled out of them
+	pub urls: Vec<String>,
+	/// Keys of user agents that have had their browsers and such pulled out of them
+	pub agents: Vec<String>
+}
+
+impl RecentFile {
+	pub fn new(
+		paths: &[String],
+		format: Option<Format>,
+		view: Option<String>,
+		added: AddedKeys
+	) -> Self {
+		Self {
+			paths: paths
+				.iter()
+				.map(|path| {
+					std::fs::canonicalize(path)
+						.map_or_else(|_| path.clone(), |path| path.display().to_string())
+				})
+				.collect(),
+			view,
+			format: format.map(|format| format.name().to_string()),
+			added
+		}
+	}
+
+	/// The format they have to be loaded as, if there is one
+	pub fn format(&self) -> Result<Option<Format>, LoadErr> {
+		self.format.as_deref().map(str::parse).transpose()
+	}
+
+	/// What it's called in the list, which is just the files' names
+	pub fn name(&self) -> String {
+		self.paths
+			.iter()
+			.map(|path| {
+				Path::new(path)
+					.file_name()
+					.map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned())
+			})
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+}
+
+impl Recent {
+	/// Reads the files that have been opened before, starting afresh if they can't be read
+	pub fn load() -> Self {
+		state_dir()
+			.map(|dir| dir.join("recent.json"))
+			.and_then(|path| std::fs::read_to_string(path).ok())
+			.and_then(|contents| serde_json::from_str(&contents).ok())
+			.unwrap_or_default()
+	}
+
+	/// Remembers that `file` was opened just now, along with the view it was left on if it's being
+	/// closed. The file's read again first, since another window might have opened something in
+	/// the meantime.
+	pub fn remember(mut file: RecentFile) {
+		let mut recent = Self::load();
+		let idx = recent.files.iter().position(|f| f.paths == file.paths);
+		if let Some(previous) = idx.map(|idx| recent.files.remove(idx)) {
+			file.view = file.view.or(previous.view);
+		}
+		recent.files.insert(0, file);
+		recent.files.truncate(MAX_RECENT);
+		// Forgetting what was opened isn't worth bothering anyone about
+		let _ = recent.save();
+	}
+
+	fn save(&self) -> std::io::Result<()> {
+		let Some(path) = state_dir().map(|dir| dir.join("recent.json")) else {
+			return Ok(());
+		};
+		if let Some(dir) = path.parent() {
+			std::fs::create_dir_all(dir)?;
+		}
+		std::fs::write(path, serde_json::to_string(self)?)
+	}
+
+	pub fn files(&self) -> &[RecentFile] {
+		&self.files
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.files.is_empty()
+	}
+
+	/// Lists the files as buttons, returning the one that was clicked on if one was
+	pub fn show(&self, ui: &mut egui::Ui) -> Option<RecentFile> {
+		let mut chosen = None;
+		for file in &self.files {
+			let button = ui.button(file.name()).on_hover_text(file.paths.join("\n"));
+			if button.clicked() {
+				chosen = Some(file.clone());
+			}
+		}
+		chosen
+	}
+}
+
+/// Where everything that's remembered between runs is kept: `$XDG_STATE_HOME/spart`, falling back
+/// to `~/.local/state/spart`
+pub(crate) fn state_dir() -> Option<PathBuf> {
+	let state_dir = std::env::var_os("XDG_STATE_HOME")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| {
+			std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+		})?;
+
+	Some(state_dir.join("spart"))
+}
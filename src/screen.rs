@@ -0,0 +1,276 @@
+use eframe::egui::{self, Align, Layout};
+use spart_core::memory::Rows;
+#[cfg(feature = "native")]
+use spart_core::{feed::Feed, load::Source};
+
+#[cfg(target_arch = "wasm32")]
+use crate::web::Picker;
+use crate::{
+	app::App,
+	config::Config,
+	demo::{self, DEMO_ROWS},
+	dropped
+};
+#[cfg(feature = "native")]
+use crate::{
+	autosave,
+	recent::{AddedKeys, Recent, RecentFile}
+};
+
+/// What the window is showing - either the app proper, or a screen that helps the user get some
+/// data into it
+pub enum Screen {
+	Empty {
+		/// Why the last attempt to load some data failed, if it did
+		error: Option<String>,
+		config: Config,
+		/// Files that are on their way in from the browser's file picker
+		#[cfg(target_arch = "wasm32")]
+		picker: Picker,
+		/// The files that have been opened before, which can be opened again from here
+		#[cfg(feature = "native")]
+		recent: Recent,
+		/// The session that was autosaved before spart was last closed (or crashed)
+		#[cfg(feature = "native")]
+		session: Option<RecentFile>
+	},
+	/// Waiting for the first rows to come in from a feed, since there's nothing to chart until then
+	#[cfg(feature = "native")]
+	Listening {
+		/// Only `None` once it's been handed off to the app
+		feed: Option<Feed>,
+		config: Config,
+		/// Why the rows that came in couldn't be charted, if they couldn't be
+		error: Option<String>
+	},
+	Loaded(Box<App>)
+}
+
+/// How often we check if the first rows have come in yet
+#[cfg(feature = "native")]
+const LISTEN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How to get data in, which depends on what we're running on
+const HOW_TO_LOAD: &str = if cfg!(target_arch = "wasm32") {
+	"Drop one or more JSON files (each an array of flat objects) or CSV files on this page, or open \
+	 some, to chart them, or try things out with some generated data."
+} else {
+	"Pass one or more JSON files (each an array of flat objects) or CSV files on the command line or \
+	 drop them on this window to chart them, or try things out with some generated data."
+};
+
+impl Screen {
+	pub fn empty(error: Option<String>, config: Config) -> Self {
+		Self::Empty {
+			error,
+			config,
+			#[cfg(target_arch = "wasm32")]
+			picker: Picker::default(),
+			#[cfg(feature = "native")]
+			recent: Recent::load(),
+			#[cfg(feature = "native")]
+			session: autosave::load()
+		}
+	}
+
+	#[cfg(feature = "native")]
+	pub fn listening(feed: Feed, config: Config) -> Self {
+		Self::Listening {
+			feed: Some(feed),
+			config,
+			error: None
+		}
+	}
+
+	/// Shows the app with `data` if it could be loaded, or why it couldn't otherwise
+	fn load(data: Result<impl Into<Rows>, String>, config: &Config) -> Self {
+		match data.and_then(|data| App::new(data, config).map_err(|e| e.to_string())) {
+			Ok(app) => Self::Loaded(Box::new(app)),
+			Err(e) => Self::empty(Some(e), config.clone())
+		}
+	}
+
+	/// Shows the files in `file` the way they were last left, remembering that they were opened
+	#[cfg(feature = "native")]
+	fn reopen(file: &RecentFile, config: &Config) -> Self {
+		let source = match file.format() {
+			Ok(format) => Source::new(file.paths.clone(), format, config.load_options()),
+			Err(e) => return Self::empty(Some(e.to_string()), config.clone())
+		};
+		let Some(source) = source else {
+			return Self::empty(Some("There are no files to open".into()), config.clone());
+		};
+		match Self::load(source.load().map_err(|e| e.to_string()), config) {
+			Self::Loaded(app) => {
+				let mut app = (*app).with_source(source, config.watch);
+				app.restore(file);
+				if let Some(file) = app.session(None) {
+					Recent::remember(file);
+				}
+				Self::Loaded(Box::new(app))
+			}
+			screen => screen
+		}
+	}
+
+	fn show_empty(&mut self, ctx: &egui::Context) -> Option<Self> {
+		let Self::Empty {
+			error,
+			config,
+			#[cfg(target_arch = "wasm32")]
+			picker,
+			#[cfg(feature = "native")]
+			recent,
+			#[cfg(feature = "native")]
+			session
+		} = self
+		else {
+			return None;
+		};
+
+		let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+		// Files that were dropped from somewhere on disk can be reloaded and opened again later,
+		// just like ones from the command line
+		#[cfg(feature = "native")]
+		if let Some(paths) = dropped
+			.iter()
+			.map(|file| Some(file.path.as_ref()?.display().to_string()))
+			.collect::<Option<Vec<_>>>()
+			.filter(|paths| !paths.is_empty())
+		{
+			let file = RecentFile::new(&paths, None, None, AddedKeys::default());
+			return Some(Self::reopen(&file, config));
+		}
+		if !dropped.is_empty() {
+			return Some(Self::load(
+				dropped::load(&dropped, &config.load_options())
+					.map(|(rows, _)| rows)
+					.map_err(|e| e.to_string()),
+				config
+			));
+		}
+
+		#[cfg(target_arch = "wasm32")]
+		if let Some(picked) = picker.take() {
+			return Some(Self::load(picked, config));
+		}
+
+		let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+		let mut next = None;
+
+		egui::CentralPanel::default().show(ctx, |ui| {
+			ui.with_layout(Layout::top_down(Align::Center), |ui| {
+				ui.add_space(ui.available_height() / 3.);
+				if hovering {
+					ui.heading("Drop to load");
+					return;
+				}
+
+				ui.heading("No data loaded");
+				ui.label(HOW_TO_LOAD);
+
+				#[cfg(feature = "native")]
+				if let Some(session) = session {
+					let restore = ui
+						.button(format!("Pick up where you left off ({})", session.name()))
+						.on_hover_text(session.paths.join("\n"));
+					if restore.clicked() {
+						next = Some(Self::reopen(session, config));
+					}
+				}
+
+				#[cfg(target_arch = "wasm32")]
+				if ui.button("Open files…").clicked() {
+					picker.open(ui.ctx(), config.load_options());
+				}
+
+				if ui.button("Load demo data").clicked() {
+					next = Some(Self::load(Ok(demo::generate(DEMO_ROWS)), config));
+				}
+
+				#[cfg(feature = "native")]
+				if !recent.is_empty() {
+					ui.add_space(8.);
+					ui.label("Or open something again:");
+					if let Some(file) = recent.show(ui) {
+						next = Some(Self::reopen(&file, config));
+					}
+				}
+
+				if let Some(error) = error {
+					ui.colored_label(ui.visuals().error_fg_color, error.as_str());
+				}
+			});
+		});
+
+		next
+	}
+}
+
+impl Screen {
+	#[cfg(feature = "native")]
+	fn show_listening(&mut self, ctx: &egui::Context) -> Option<Self> {
+		let Self::Listening {
+			feed,
+			config,
+			error
+		} = self
+		else {
+			return None;
+		};
+
+		ctx.request_repaint_after(LISTEN_INTERVAL);
+		let received = feed.as_ref()?.take();
+		if let Some(err) = received.last_err {
+			*error = Some(err);
+		}
+		if !received.rows.is_empty() {
+			match App::new(received.rows, config) {
+				Ok(app) => return Some(Self::Loaded(Box::new(app.with_feed(feed.take()?)))),
+				Err(e) => *error = Some(e.to_string())
+			}
+		}
+
+		let address = feed.as_ref()?.address.to_string();
+		egui::CentralPanel::default().show(ctx, |ui| {
+			ui.with_layout(Layout::top_down(Align::Center), |ui| {
+				ui.add_space(ui.available_height() / 3.);
+				ui.heading("Waiting for rows");
+				ui.label(format!(
+					"Send JSON objects (one per line) to {address} to start charting them."
+				));
+				ui.spinner();
+
+				if let Some(error) = error {
+					ui.colored_label(ui.visuals().error_fg_color, error.as_str());
+				}
+			});
+		});
+
+		None
+	}
+}
+
+impl eframe::App for Screen {
+	fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+		match self {
+			Self::Loaded(app) => app.update(ctx, frame),
+			Self::Empty { .. } =>
+				if let Some(next) = self.show_empty(ctx) {
+					*self = next;
+				},
+			#[cfg(feature = "native")]
+			Self::Listening { .. } =>
+				if let Some(next) = self.show_listening(ctx) {
+					*self = next;
+				},
+		}
+	}
+
+	#[cfg(feature = "native")]
+	fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+		if let Self::Loaded(app) = self {
+			app.on_exit(gl);
+		}
+	}
+}
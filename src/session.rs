@@ -0,0 +1,35 @@
+//! Saving which files are being looked at and the view they're on to a file of the user's choosing,
+//! so that the exact same chart can be opened again later (with "Load session" or `--session`)
+//! without setting it all up again. A session file is the same JSON as the recent files and the
+//! autosave are kept in (see [`crate::recent`]).
+
+use std::path::Path;
+
+use crate::recent::RecentFile;
+
+/// Where sessions are saved to and loaded from unless the user says otherwise
+pub const DEFAULT_PATH: &str = "spart-session.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionErr {
+	#[error("{0}")]
+	Io(#[from] std::io::Error),
+	#[error("it isn't a session ({0})")]
+	Parse(#[from] serde_json::Error)
+}
+
+pub fn load(path: &Path) -> Result<RecentFile, SessionErr> {
+	let contents = std::fs::read_to_string(path)?;
+	Ok(serde_json::from_str(&contents)?)
+}
+
+pub fn save(path: &Path, session: &RecentFile) -> Result<(), SessionErr> {
+	if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		std::fs::create_dir_all(dir)?;
+	}
+	// Written next to where it's going and moved over, so that crashing partway through writing
+	// it doesn't lose the last one
+	let partial = path.with_extension("json.partial");
+	std::fs::write(&partial, serde_json::to_string(session)?)?;
+	Ok(std::fs::rename(partial, path)?)
+}
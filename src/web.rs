@@ -0,0 +1,74 @@
+//! Running spart in a browser, where there's no command line or filesystem, so data has to come in
+//! through the file picker or by being dropped onto the page.
+
+use std::{cell::RefCell, rc::Rc};
+
+use eframe::{egui, wasm_bindgen::JsCast as _};
+use spart_core::load::{LoadErr, LoadOptions};
+
+use crate::{config::Config, dropped, screen::Screen};
+
+/// The id of the canvas in `index.html` that everything's drawn on
+const CANVAS_ID: &str = "spart_canvas";
+
+type Picked = Result<Vec<merde::Map<'static>>, String>;
+
+pub fn start() {
+	wasm_bindgen_futures::spawn_local(async {
+		let canvas = web_sys::window()
+			.and_then(|window| window.document())
+			.and_then(|document| document.get_element_by_id(CANVAS_ID))
+			.and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+			.expect("Couldn't find the canvas to draw on");
+
+		let started = eframe::WebRunner::new()
+			.start(
+				canvas,
+				eframe::WebOptions::default(),
+				Box::new(|_| Ok(Box::new(Screen::empty(None, Config::default()))))
+			)
+			.await;
+
+		if let Err(e) = started {
+			web_sys::console::error_1(&e);
+		}
+	});
+}
+
+/// Opens the browser's file picker. Reading the files that were picked happens in the
+/// background, so they're left here for the screen to pick up on some later frame.
+#[derive(Default)]
+pub struct Picker(Rc<RefCell<Option<Picked>>>);
+
+impl Picker {
+	pub fn open(&self, ctx: &egui::Context, options: LoadOptions) {
+		let picked = Rc::clone(&self.0);
+		let ctx = ctx.clone();
+
+		wasm_bindgen_futures::spawn_local(async move {
+			let Some(files) = rfd::AsyncFileDialog::new().pick_files().await else {
+				return;
+			};
+
+			*picked.borrow_mut() = Some(read(files, &options).await.map_err(|e| e.to_string()));
+			ctx.request_repaint();
+		});
+	}
+
+	/// The rows from the files that were picked, if they've been read since the last call
+	pub fn take(&self) -> Option<Picked> {
+		self.0.borrow_mut().take()
+	}
+}
+
+async fn read(
+	files: Vec<rfd::FileHandle>,
+	options: &LoadOptions
+) -> Result<Vec<merde::Map<'static>>, LoadErr> {
+	let mut rows = Vec::new();
+	for file in files {
+		let (file_rows, _) = dropped::load_bytes(&file.file_name(), &file.read().await, options)?;
+		rows.extend(file_rows);
+	}
+	Ok(rows)
+}